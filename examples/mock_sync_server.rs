@@ -0,0 +1,873 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Local stand-in for the Furtherance sync server, for end-to-end testing and
+//! self-hosting experiments. Records are stored in memory as the opaque encrypted
+//! blobs clients send.
+//!
+//! Run with `cargo run --example mock_sync_server -- [port]` and set a custom
+//! server of `http://127.0.0.1:<port>` (8787 by default) in the app. The integration tests
+//! in `tests/sync_server.rs` serve the same routes on an ephemeral port.
+//!
+//! Failures are simulated through the `/mock` endpoints:
+//! - `POST /mock/config` with any of `unauthorized_responses`, `refresh_fails`,
+//!   `inactive_subscription`, `delay_ms`, `page_size`, `unavailable_responses`,
+//!   `accept_any_key` and `kdf_unavailable`
+//! - `POST /mock/orphans` with `tasks`, `shortcuts` and `todos` uid lists, which are
+//!   dropped from storage and reported as orphaned on the next sync
+//! - `GET /mock/state` to inspect stored records, `POST /mock/reset` to clear everything
+
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+const DEFAULT_PORT: u16 = 8787;
+const DEFAULT_PAGE_SIZE: usize = 250;
+
+type SharedState = Arc<Mutex<MockServer>>;
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+struct MockConfig {
+    /// Answer this many authenticated requests with 401 before accepting tokens again
+    unauthorized_responses: u32,
+    refresh_fails: bool,
+    inactive_subscription: bool,
+    delay_ms: u64,
+    /// Most records sent per sync response, overriding the client's page size
+    page_size: Option<usize>,
+    /// Answer this many sync requests with 503 and a Retry-After header
+    unavailable_responses: u32,
+    /// Log in with any key, leaving the client's canary check to catch a wrong one
+    accept_any_key: bool,
+    /// Answer `/api/kdf` with 404, like a server that predates passphrases
+    kdf_unavailable: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct EncryptedRecord {
+    encrypted_data: String,
+    nonce: String,
+    uid: String,
+    last_updated: i64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct StoredRecord {
+    record: EncryptedRecord,
+    /// Server time the record was last written, used to select downloads
+    server_updated: i64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+enum RecordKind {
+    Task,
+    Shortcut,
+    Todo,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct KdfInfo {
+    salt: String,
+    version: u32,
+}
+
+/// A known value encrypted with the account's key, opaque to the server
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct KeyCanary {
+    encrypted_data: String,
+    nonce: String,
+}
+
+#[derive(Default, Serialize)]
+struct RecordStore {
+    tasks: HashMap<String, StoredRecord>,
+    shortcuts: HashMap<String, StoredRecord>,
+    todos: HashMap<String, StoredRecord>,
+}
+
+/// Records re-encrypted under the next key generation, kept apart until the rotation finishes
+#[derive(Serialize)]
+struct PendingRotation {
+    key_generation: i64,
+    key_hash: String,
+    kdf: Option<KdfInfo>,
+    canary: KeyCanary,
+    records: RecordStore,
+}
+
+#[derive(Default, Serialize)]
+struct Account {
+    key_hash: String,
+    /// Passphrase salt, set by the first successful login that sends one
+    kdf: Option<KdfInfo>,
+    canary: Option<KeyCanary>,
+    key_generation: i64,
+    records: RecordStore,
+    pending_rotation: Option<PendingRotation>,
+    /// Last server timestamp each device fully synced through
+    devices: HashMap<String, i64>,
+    /// Every signed-in device by id, for the devices list
+    device_info: HashMap<String, DeviceInfo>,
+    orphaned_tasks: Vec<String>,
+    orphaned_shortcuts: Vec<String>,
+    orphaned_todos: Vec<String>,
+}
+
+impl RecordStore {
+    fn records_mut(&mut self, kind: RecordKind) -> &mut HashMap<String, StoredRecord> {
+        match kind {
+            RecordKind::Task => &mut self.tasks,
+            RecordKind::Shortcut => &mut self.shortcuts,
+            RecordKind::Todo => &mut self.todos,
+        }
+    }
+
+    /// Keep whichever copy was edited last
+    fn store(&mut self, kind: RecordKind, record: EncryptedRecord, now: i64) {
+        let records = self.records_mut(kind);
+        let is_newer = records
+            .get(&record.uid)
+            .is_none_or(|stored| record.last_updated > stored.record.last_updated);
+        if is_newer {
+            records.insert(
+                record.uid.clone(),
+                StoredRecord {
+                    record,
+                    server_updated: now,
+                },
+            );
+        }
+    }
+}
+
+#[derive(Clone, Default, Serialize)]
+struct DeviceInfo {
+    device_id: String,
+    name: String,
+    platform: String,
+    last_sync: Option<i64>,
+}
+
+#[derive(Clone)]
+struct Session {
+    email: String,
+    device_id: String,
+}
+
+struct MockServer {
+    config: MockConfig,
+    accounts: HashMap<String, Account>,
+    access_tokens: HashMap<String, Session>,
+    refresh_tokens: HashMap<String, Session>,
+    /// Keys the made-up salts given for unknown accounts
+    kdf_secret: [u8; 32],
+}
+
+impl MockServer {
+    fn new() -> Self {
+        MockServer {
+            config: MockConfig::default(),
+            accounts: HashMap::new(),
+            access_tokens: HashMap::new(),
+            refresh_tokens: HashMap::new(),
+            kdf_secret: rand::random(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct LoginRequest {
+    email: String,
+    encryption_key: String,
+    device_id: String,
+    #[serde(default)]
+    kdf: Option<KdfInfo>,
+}
+
+#[derive(Deserialize)]
+struct KdfRequest {
+    email: String,
+}
+
+#[derive(Deserialize)]
+struct RefreshRequest {
+    refresh_token: String,
+    device_id: String,
+}
+
+#[derive(Deserialize)]
+struct LogoutRequest {
+    device_id: String,
+}
+
+#[derive(Deserialize)]
+struct SyncRequest {
+    last_sync: i64,
+    device_id: String,
+    #[serde(default)]
+    device_name: String,
+    #[serde(default)]
+    platform: String,
+    tasks: Vec<EncryptedRecord>,
+    shortcuts: Vec<EncryptedRecord>,
+    todos: Vec<EncryptedRecord>,
+    #[serde(default)]
+    cursor: Option<String>,
+    #[serde(default)]
+    page_size: usize,
+    #[serde(default)]
+    key_generation: i64,
+}
+
+#[derive(Deserialize)]
+struct RevokeDeviceRequest {
+    revoked_device_id: String,
+}
+
+#[derive(Deserialize)]
+struct RotationStartRequest {
+    key_generation: i64,
+    encryption_key: String,
+    kdf: Option<KdfInfo>,
+    canary: KeyCanary,
+}
+
+#[derive(Deserialize)]
+struct CanaryRequest {
+    canary: KeyCanary,
+}
+
+#[derive(Deserialize)]
+struct RotationUploadRequest {
+    key_generation: i64,
+    tasks: Vec<EncryptedRecord>,
+    shortcuts: Vec<EncryptedRecord>,
+    todos: Vec<EncryptedRecord>,
+}
+
+#[derive(Deserialize)]
+struct RotationRequest {
+    key_generation: i64,
+}
+
+#[derive(Default, Serialize)]
+struct SyncResponse {
+    server_timestamp: i64,
+    tasks: Vec<EncryptedRecord>,
+    shortcuts: Vec<EncryptedRecord>,
+    todos: Vec<EncryptedRecord>,
+    orphaned_tasks: Vec<String>,
+    orphaned_shortcuts: Vec<String>,
+    orphaned_todos: Vec<String>,
+    devices_synced_through: Option<i64>,
+    next_cursor: Option<String>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct OrphansRequest {
+    tasks: Vec<String>,
+    shortcuts: Vec<String>,
+    todos: Vec<String>,
+}
+
+#[tokio::main]
+async fn main() {
+    let port = std::env::args()
+        .nth(1)
+        .and_then(|arg| arg.parse().ok())
+        .unwrap_or(DEFAULT_PORT);
+
+    let address = SocketAddr::from(([127, 0, 0, 1], port));
+    let listener = tokio::net::TcpListener::bind(address)
+        .await
+        .expect("Failed to bind mock sync server");
+    println!("Mock sync server listening on http://{}", address);
+    axum::serve(listener, app())
+        .await
+        .expect("Mock sync server stopped unexpectedly");
+}
+
+/// The mock's routes with empty storage, also served by the sync integration tests
+pub fn app() -> Router {
+    let state: SharedState = Arc::new(Mutex::new(MockServer::new()));
+
+    Router::new()
+        .route("/api/kdf", post(kdf))
+        .route("/api/login", post(login))
+        .route("/api/refresh", post(refresh))
+        .route("/api/logout", post(logout))
+        .route("/api/sync", post(sync))
+        .route("/api/canary", post(store_canary))
+        .route("/api/devices", post(list_devices))
+        .route("/api/devices/revoke", post(revoke_device))
+        .route("/api/rotate-key/start", post(start_rotation))
+        .route("/api/rotate-key/upload", post(upload_rotation))
+        .route("/api/rotate-key/finish", post(finish_rotation))
+        .route("/api/rotate-key/cancel", post(cancel_rotation))
+        .route("/mock/config", get(get_config).post(set_config))
+        .route("/mock/orphans", post(add_orphans))
+        .route("/mock/state", get(get_state))
+        .route("/mock/reset", post(reset))
+        .with_state(state)
+}
+
+async fn login(State(state): State<SharedState>, Json(request): Json<LoginRequest>) -> Response {
+    simulate_delay(&state).await;
+    let mut server = state.lock().unwrap();
+
+    let key_hash = blake3::hash(request.encryption_key.as_bytes())
+        .to_hex()
+        .to_string();
+    let account = server
+        .accounts
+        .entry(request.email.clone())
+        .or_insert_with(|| Account {
+            key_hash: key_hash.clone(),
+            ..Default::default()
+        });
+    if account.key_hash != key_hash && !server.config.accept_any_key {
+        return error_response(StatusCode::UNAUTHORIZED, "invalid_credentials");
+    }
+    let account = server.accounts.get_mut(&request.email).unwrap();
+    if account.kdf.is_none() {
+        account.kdf = request.kdf;
+    }
+    let key_generation = account.key_generation;
+    let canary = account.canary.clone();
+    account
+        .device_info
+        .entry(request.device_id.clone())
+        .or_insert_with(|| DeviceInfo {
+            device_id: request.device_id.clone(),
+            ..Default::default()
+        });
+
+    let session = Session {
+        email: request.email,
+        device_id: request.device_id,
+    };
+    let access_token = uuid::Uuid::new_v4().to_string();
+    let refresh_token = uuid::Uuid::new_v4().to_string();
+    server
+        .access_tokens
+        .insert(access_token.clone(), session.clone());
+    server.refresh_tokens.insert(refresh_token.clone(), session);
+
+    Json(json!({
+        "access_token": access_token,
+        "refresh_token": refresh_token,
+        "key_generation": key_generation,
+        "canary": canary,
+    }))
+    .into_response()
+}
+
+async fn kdf(State(state): State<SharedState>, Json(request): Json<KdfRequest>) -> Response {
+    simulate_delay(&state).await;
+    let server = state.lock().unwrap();
+    if server.config.kdf_unavailable {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    // Every email gets an answer, with a stable made-up salt for accounts without one,
+    // so the response doesn't reveal which accounts exist
+    let kdf = server
+        .accounts
+        .get(&request.email)
+        .and_then(|account| account.kdf.clone())
+        .unwrap_or_else(|| {
+            let hash = blake3::keyed_hash(&server.kdf_secret, request.email.as_bytes());
+            KdfInfo {
+                salt: BASE64.encode(&hash.as_bytes()[..16]),
+                version: 1,
+            }
+        });
+    Json(kdf).into_response()
+}
+
+async fn refresh(
+    State(state): State<SharedState>,
+    Json(request): Json<RefreshRequest>,
+) -> Response {
+    simulate_delay(&state).await;
+    let mut server = state.lock().unwrap();
+
+    if server.config.refresh_fails {
+        return error_response(StatusCode::UNAUTHORIZED, "invalid_refresh_token");
+    }
+
+    let session = match server.refresh_tokens.get(&request.refresh_token) {
+        Some(session) if session.device_id == request.device_id => session.clone(),
+        _ => return error_response(StatusCode::UNAUTHORIZED, "invalid_refresh_token"),
+    };
+
+    let access_token = uuid::Uuid::new_v4().to_string();
+    server.access_tokens.insert(access_token.clone(), session);
+
+    Json(json!({ "access_token": access_token })).into_response()
+}
+
+async fn logout(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Json(request): Json<LogoutRequest>,
+) -> Response {
+    simulate_delay(&state).await;
+    let mut server = state.lock().unwrap();
+
+    let session = match authenticate(&mut server, &headers) {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+
+    server
+        .access_tokens
+        .retain(|_, s| !(s.email == session.email && s.device_id == request.device_id));
+    server
+        .refresh_tokens
+        .retain(|_, s| !(s.email == session.email && s.device_id == request.device_id));
+    if let Some(account) = server.accounts.get_mut(&session.email) {
+        account.devices.remove(&request.device_id);
+        account.device_info.remove(&request.device_id);
+    }
+
+    StatusCode::OK.into_response()
+}
+
+async fn sync(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Json(request): Json<SyncRequest>,
+) -> Response {
+    simulate_delay(&state).await;
+    let mut server = state.lock().unwrap();
+
+    let session = match authenticate(&mut server, &headers) {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+
+    if server.config.inactive_subscription {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(json!({
+                "error": "inactive_subscription",
+                "message": "Subscription inactive",
+            })),
+        )
+            .into_response();
+    }
+
+    if server.config.unavailable_responses > 0 {
+        server.config.unavailable_responses -= 1;
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            [(header::RETRY_AFTER, "5")],
+            Json(json!({ "error": "unavailable" })),
+        )
+            .into_response();
+    }
+
+    let page_size = server
+        .config
+        .page_size
+        .unwrap_or(if request.page_size > 0 {
+            request.page_size
+        } else {
+            DEFAULT_PAGE_SIZE
+        })
+        .max(1);
+
+    let Some(account) = server.accounts.get_mut(&session.email) else {
+        return error_response(StatusCode::UNAUTHORIZED, "unknown_account");
+    };
+
+    // Blobs from an older key generation can't be read by devices using the new key
+    if request.key_generation < account.key_generation {
+        return error_response(StatusCode::CONFLICT, "key_rotated");
+    }
+
+    let now = Utc::now().timestamp();
+    for (kind, records) in [
+        (RecordKind::Task, request.tasks),
+        (RecordKind::Shortcut, request.shortcuts),
+        (RecordKind::Todo, request.todos),
+    ] {
+        for record in records {
+            account.records.store(kind, record, now);
+        }
+    }
+
+    // Everything written since the client's last sync, in a stable order so a cursor can resume it
+    let mut changes: Vec<(i64, RecordKind, String)> = [
+        (RecordKind::Task, &account.records.tasks),
+        (RecordKind::Shortcut, &account.records.shortcuts),
+        (RecordKind::Todo, &account.records.todos),
+    ]
+    .into_iter()
+    .flat_map(|(kind, records)| {
+        records
+            .values()
+            .filter(|stored| stored.server_updated >= request.last_sync)
+            .map(move |stored| (stored.server_updated, kind, stored.record.uid.clone()))
+    })
+    .collect();
+    changes.sort();
+
+    if let Some(cursor) = request.cursor.as_deref().and_then(parse_cursor) {
+        changes.retain(|change| *change > cursor);
+    }
+
+    let has_more = changes.len() > page_size;
+    changes.truncate(page_size);
+
+    let mut response = SyncResponse {
+        server_timestamp: now,
+        next_cursor: if has_more {
+            changes.last().map(format_cursor)
+        } else {
+            None
+        },
+        ..Default::default()
+    };
+
+    for (_, kind, uid) in &changes {
+        let record = account.records.records_mut(*kind)[uid].record.clone();
+        match kind {
+            RecordKind::Task => response.tasks.push(record),
+            RecordKind::Shortcut => response.shortcuts.push(record),
+            RecordKind::Todo => response.todos.push(record),
+        }
+    }
+
+    response.orphaned_tasks = std::mem::take(&mut account.orphaned_tasks);
+    response.orphaned_shortcuts = std::mem::take(&mut account.orphaned_shortcuts);
+    response.orphaned_todos = std::mem::take(&mut account.orphaned_todos);
+
+    let device = account
+        .device_info
+        .entry(request.device_id.clone())
+        .or_default();
+    device.device_id = request.device_id.clone();
+    device.name = request.device_name;
+    device.platform = request.platform;
+    device.last_sync = Some(now);
+
+    if !has_more {
+        account.devices.insert(request.device_id, now);
+    }
+    response.devices_synced_through = account.devices.values().min().copied();
+
+    Json(response).into_response()
+}
+
+async fn list_devices(State(state): State<SharedState>, headers: HeaderMap) -> Response {
+    simulate_delay(&state).await;
+    let mut server = state.lock().unwrap();
+    let account = match authenticated_account(&mut server, &headers) {
+        Ok(account) => account,
+        Err(response) => return response,
+    };
+
+    let devices: Vec<DeviceInfo> = account.device_info.values().cloned().collect();
+    Json(json!({ "devices": devices })).into_response()
+}
+
+/// Sign another device out by invalidating its tokens
+async fn revoke_device(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Json(request): Json<RevokeDeviceRequest>,
+) -> Response {
+    simulate_delay(&state).await;
+    let mut server = state.lock().unwrap();
+    let session = match authenticate(&mut server, &headers) {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+
+    let revoked =
+        |s: &Session| s.email == session.email && s.device_id == request.revoked_device_id;
+    server.access_tokens.retain(|_, s| !revoked(s));
+    server.refresh_tokens.retain(|_, s| !revoked(s));
+    if let Some(account) = server.accounts.get_mut(&session.email) {
+        account.devices.remove(&request.revoked_device_id);
+        account.device_info.remove(&request.revoked_device_id);
+    }
+
+    StatusCode::OK.into_response()
+}
+
+/// Store the canary for an account that doesn't have one yet
+async fn store_canary(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Json(request): Json<CanaryRequest>,
+) -> Response {
+    simulate_delay(&state).await;
+    let mut server = state.lock().unwrap();
+    let account = match authenticated_account(&mut server, &headers) {
+        Ok(account) => account,
+        Err(response) => return response,
+    };
+
+    if account.canary.is_some() {
+        return error_response(StatusCode::CONFLICT, "canary_exists");
+    }
+    account.canary = Some(request.canary);
+
+    StatusCode::OK.into_response()
+}
+
+async fn start_rotation(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Json(request): Json<RotationStartRequest>,
+) -> Response {
+    simulate_delay(&state).await;
+    let mut server = state.lock().unwrap();
+    let account = match authenticated_account(&mut server, &headers) {
+        Ok(account) => account,
+        Err(response) => return response,
+    };
+
+    if request.key_generation != account.key_generation + 1 {
+        return error_response(StatusCode::CONFLICT, "invalid_key_generation");
+    }
+
+    // Starting again discards anything staged by an abandoned attempt
+    account.pending_rotation = Some(PendingRotation {
+        key_generation: request.key_generation,
+        key_hash: blake3::hash(request.encryption_key.as_bytes())
+            .to_hex()
+            .to_string(),
+        kdf: request.kdf,
+        canary: request.canary,
+        records: RecordStore::default(),
+    });
+
+    StatusCode::OK.into_response()
+}
+
+async fn upload_rotation(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Json(request): Json<RotationUploadRequest>,
+) -> Response {
+    simulate_delay(&state).await;
+    let mut server = state.lock().unwrap();
+    let account = match authenticated_account(&mut server, &headers) {
+        Ok(account) => account,
+        Err(response) => return response,
+    };
+
+    let now = Utc::now().timestamp();
+    let records = match account.pending_rotation.as_mut() {
+        Some(pending) if pending.key_generation == request.key_generation => &mut pending.records,
+        // The rotation already finished, e.g. the client stopped before recording that
+        _ if account.key_generation == request.key_generation => &mut account.records,
+        _ => return error_response(StatusCode::CONFLICT, "invalid_key_generation"),
+    };
+
+    for (kind, uploaded) in [
+        (RecordKind::Task, request.tasks),
+        (RecordKind::Shortcut, request.shortcuts),
+        (RecordKind::Todo, request.todos),
+    ] {
+        for record in uploaded {
+            records.store(kind, record, now);
+        }
+    }
+
+    StatusCode::OK.into_response()
+}
+
+async fn finish_rotation(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Json(request): Json<RotationRequest>,
+) -> Response {
+    simulate_delay(&state).await;
+    let mut server = state.lock().unwrap();
+    let account = match authenticated_account(&mut server, &headers) {
+        Ok(account) => account,
+        Err(response) => return response,
+    };
+
+    match account.pending_rotation.take() {
+        Some(pending) if pending.key_generation == request.key_generation => {
+            account.key_generation = pending.key_generation;
+            account.key_hash = pending.key_hash;
+            account.kdf = pending.kdf;
+            account.canary = Some(pending.canary);
+            account.records = pending.records;
+            account.devices.clear();
+            StatusCode::OK.into_response()
+        }
+        pending => {
+            account.pending_rotation = pending;
+            if account.key_generation == request.key_generation {
+                StatusCode::OK.into_response()
+            } else {
+                error_response(StatusCode::CONFLICT, "invalid_key_generation")
+            }
+        }
+    }
+}
+
+async fn cancel_rotation(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Json(request): Json<RotationRequest>,
+) -> Response {
+    simulate_delay(&state).await;
+    let mut server = state.lock().unwrap();
+    let account = match authenticated_account(&mut server, &headers) {
+        Ok(account) => account,
+        Err(response) => return response,
+    };
+
+    match &account.pending_rotation {
+        Some(pending) if pending.key_generation == request.key_generation => {
+            account.pending_rotation = None;
+            StatusCode::OK.into_response()
+        }
+        _ => error_response(StatusCode::CONFLICT, "no_pending_rotation"),
+    }
+}
+
+async fn get_config(State(state): State<SharedState>) -> Json<MockConfig> {
+    Json(state.lock().unwrap().config.clone())
+}
+
+async fn set_config(
+    State(state): State<SharedState>,
+    Json(config): Json<MockConfig>,
+) -> Json<MockConfig> {
+    let mut server = state.lock().unwrap();
+    server.config = config;
+    Json(server.config.clone())
+}
+
+/// Drop records from every account and report them as orphaned on the next sync
+async fn add_orphans(
+    State(state): State<SharedState>,
+    Json(request): Json<OrphansRequest>,
+) -> StatusCode {
+    let mut server = state.lock().unwrap();
+    for account in server.accounts.values_mut() {
+        for uid in &request.tasks {
+            account.records.tasks.remove(uid);
+        }
+        for uid in &request.shortcuts {
+            account.records.shortcuts.remove(uid);
+        }
+        for uid in &request.todos {
+            account.records.todos.remove(uid);
+        }
+        account.orphaned_tasks.extend(request.tasks.iter().cloned());
+        account
+            .orphaned_shortcuts
+            .extend(request.shortcuts.iter().cloned());
+        account.orphaned_todos.extend(request.todos.iter().cloned());
+    }
+    StatusCode::OK
+}
+
+async fn get_state(State(state): State<SharedState>) -> Response {
+    let server = state.lock().unwrap();
+    Json(json!({
+        "config": server.config,
+        "accounts": server.accounts,
+        "sessions": server.access_tokens.len(),
+    }))
+    .into_response()
+}
+
+async fn reset(State(state): State<SharedState>) -> StatusCode {
+    *state.lock().unwrap() = MockServer::new();
+    StatusCode::OK
+}
+
+fn authenticate(server: &mut MockServer, headers: &HeaderMap) -> Result<Session, Response> {
+    if server.config.unauthorized_responses > 0 {
+        server.config.unauthorized_responses -= 1;
+        return Err(error_response(StatusCode::UNAUTHORIZED, "token_expired"));
+    }
+
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .and_then(|token| server.access_tokens.get(token))
+        .cloned()
+        .ok_or_else(|| error_response(StatusCode::UNAUTHORIZED, "invalid_token"))
+}
+
+fn authenticated_account<'a>(
+    server: &'a mut MockServer,
+    headers: &HeaderMap,
+) -> Result<&'a mut Account, Response> {
+    let session = authenticate(server, headers)?;
+    server
+        .accounts
+        .get_mut(&session.email)
+        .ok_or_else(|| error_response(StatusCode::UNAUTHORIZED, "unknown_account"))
+}
+
+async fn simulate_delay(state: &SharedState) {
+    let delay_ms = state.lock().unwrap().config.delay_ms;
+    if delay_ms > 0 {
+        tokio::time::sleep(Duration::from_millis(delay_ms)).await;
+    }
+}
+
+fn error_response(status: StatusCode, error: &str) -> Response {
+    (status, Json(json!({ "error": error }))).into_response()
+}
+
+fn format_cursor(change: &(i64, RecordKind, String)) -> String {
+    format!("{}:{}:{}", change.0, change.1 as u8, change.2)
+}
+
+fn parse_cursor(cursor: &str) -> Option<(i64, RecordKind, String)> {
+    let mut parts = cursor.splitn(3, ':');
+    let server_updated = parts.next()?.parse().ok()?;
+    let kind = match parts.next()? {
+        "0" => RecordKind::Task,
+        "1" => RecordKind::Shortcut,
+        "2" => RecordKind::Todo,
+        _ => return None,
+    };
+    Some((server_updated, kind, parts.next()?.to_string()))
+}
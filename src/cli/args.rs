@@ -0,0 +1,522 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{collections::HashMap, path::PathBuf};
+
+use chrono::{DateTime, Local, LocalResult, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use furtherance_core::services::timesheets::{TimesheetFilter, TimesheetFormat, TimesheetRows};
+
+pub const USAGE: &str = "\
+Usage: furtherance <command> [arguments]
+
+Timer:
+  start <task> [--at TIME]        Start the timer, e.g. start Write report @Work #docs $50
+  stop [--at TIME]                Stop the timer and save the task
+  status                          Show the running timer
+  continue [UID]                  Start the timer with the most recent or a given task
+
+Tasks:
+  tasks [--days N]                List tasks from the last N days (default 7)
+  add <task> --start TIME --stop TIME
+  edit <UID> [--task TASK] [--start TIME] [--stop TIME]
+  delete <UID>...
+
+Todos:
+  todos                           List todos
+  todos add <task> [--date DATE]
+  todos done <UID>
+  todos edit <UID> [--task TASK] [--date DATE]
+  todos delete <UID>
+
+Shortcuts:
+  shortcuts                       List shortcuts
+  shortcuts add <task> [--color #RRGGBB]
+  shortcuts edit <UID> [--task TASK] [--color #RRGGBB]
+  shortcuts delete <UID>
+
+Other:
+  sync                            Sync with the signed-in account
+  export [--from DATE] [--to DATE] [--format FORMAT] [--output FILE]
+                                  Export tasks (to stdout without --output)
+  timesheet [--week DATE] [--rows task|project] [--format csv|markdown|html]
+            [--projects LIST] [--tags LIST] [--output FILE]
+                                  Export a weekly grid of time per task or project
+  import <FILE> [--settings import|keep]
+                                  Merge a JSON archive into this device's data
+  help                            Show this message
+
+TIME is HH:MM for today, or YYYY-MM-DD HH:MM. DATE is YYYY-MM-DD.
+FORMAT is csv (the default), ics, or ics-merged to join back-to-back tasks into one event.
+json exports a full archive of tasks, todos, shortcuts and settings, ignoring --from and --to.
+A timesheet covers the Monday-to-Sunday week containing --week (default today). LIST is
+comma separated, e.g. --projects \"Work, Home\" --tags docs,meetings.
+Set FURTHERANCE_DB_PASSPHRASE to open an encrypted database without a prompt.
+";
+
+const DEFAULT_DAYS: i64 = 7;
+
+pub enum Command {
+    Start {
+        task_input: String,
+        at: Option<DateTime<Local>>,
+    },
+    Stop {
+        at: Option<DateTime<Local>>,
+    },
+    Status,
+    Continue {
+        uid: Option<String>,
+    },
+    Tasks {
+        days: i64,
+    },
+    Add {
+        task_input: String,
+        start: DateTime<Local>,
+        stop: DateTime<Local>,
+    },
+    Edit {
+        uid: String,
+        task_input: Option<String>,
+        start: Option<DateTime<Local>>,
+        stop: Option<DateTime<Local>>,
+    },
+    Delete {
+        uids: Vec<String>,
+    },
+    Todos(TodoCommand),
+    Shortcuts(ShortcutCommand),
+    Sync,
+    Export {
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+        format: ExportFormat,
+        output: Option<PathBuf>,
+    },
+    /// Everything as a JSON archive, from `export --format json`
+    ExportArchive {
+        output: Option<PathBuf>,
+    },
+    Timesheet {
+        week: NaiveDate,
+        rows: TimesheetRows,
+        format: TimesheetFormat,
+        filter: TimesheetFilter,
+        output: Option<PathBuf>,
+    },
+    Import {
+        path: PathBuf,
+        include_settings: bool,
+    },
+    Help,
+}
+
+pub enum ExportFormat {
+    Csv,
+    Ics { merge_contiguous: bool },
+}
+
+pub enum TodoCommand {
+    List,
+    Add {
+        task_input: String,
+        date: NaiveDate,
+    },
+    Done {
+        uid: String,
+    },
+    Edit {
+        uid: String,
+        task_input: Option<String>,
+        date: Option<NaiveDate>,
+    },
+    Delete {
+        uid: String,
+    },
+}
+
+pub enum ShortcutCommand {
+    List,
+    Add {
+        task_input: String,
+        color_hex: Option<String>,
+    },
+    Edit {
+        uid: String,
+        task_input: Option<String>,
+        color_hex: Option<String>,
+    },
+    Delete {
+        uid: String,
+    },
+}
+
+/// Positional words and `--name value` options after the command
+struct Arguments {
+    words: Vec<String>,
+    options: HashMap<String, String>,
+}
+
+impl Arguments {
+    fn split(args: Vec<String>, allowed_options: &[&str]) -> Result<Self, String> {
+        let mut words = Vec::new();
+        let mut options = HashMap::new();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.strip_prefix("--") {
+                Some(option) => {
+                    let (name, value) = match option.split_once('=') {
+                        Some((name, value)) => (name.to_string(), value.to_string()),
+                        None => {
+                            let value = args
+                                .next()
+                                .ok_or_else(|| format!("--{} needs a value", option))?;
+                            (option.to_string(), value)
+                        }
+                    };
+                    if !allowed_options.contains(&name.as_str()) {
+                        return Err(format!("Unknown option --{}", name));
+                    }
+                    options.insert(name, value);
+                }
+                None => words.push(arg),
+            }
+        }
+        Ok(Arguments { words, options })
+    }
+
+    /// All positional words as one task input, e.g. "Write report @Work #docs"
+    fn task_input(&self) -> Result<String, String> {
+        let task_input = self.words.join(" ");
+        if task_input.trim().is_empty() {
+            Err("A task is required".to_string())
+        } else {
+            Ok(task_input)
+        }
+    }
+
+    fn uid(&self) -> Result<String, String> {
+        match self.words.as_slice() {
+            [uid] => Ok(uid.clone()),
+            [] => Err("A UID is required".to_string()),
+            _ => Err("Only one UID can be given".to_string()),
+        }
+    }
+
+    fn no_words(&self) -> Result<(), String> {
+        match self.words.first() {
+            Some(word) => Err(format!("Unexpected argument '{}'", word)),
+            None => Ok(()),
+        }
+    }
+
+    fn time(&self, name: &str) -> Result<Option<DateTime<Local>>, String> {
+        self.options
+            .get(name)
+            .map(|value| parse_time(value))
+            .transpose()
+    }
+
+    fn date(&self, name: &str) -> Result<Option<NaiveDate>, String> {
+        self.options
+            .get(name)
+            .map(|value| parse_date(value))
+            .transpose()
+    }
+
+    fn string(&self, name: &str) -> Option<String> {
+        self.options.get(name).cloned()
+    }
+}
+
+pub fn parse(mut args: Vec<String>) -> Result<Command, String> {
+    if args.is_empty() {
+        return Ok(Command::Help);
+    }
+    let command = args.remove(0);
+    match command.as_str() {
+        "start" => {
+            let args = Arguments::split(args, &["at"])?;
+            Ok(Command::Start {
+                task_input: args.task_input()?,
+                at: args.time("at")?,
+            })
+        }
+        "stop" => {
+            let args = Arguments::split(args, &["at"])?;
+            args.no_words()?;
+            Ok(Command::Stop {
+                at: args.time("at")?,
+            })
+        }
+        "status" => {
+            Arguments::split(args, &[])?.no_words()?;
+            Ok(Command::Status)
+        }
+        "continue" => {
+            let args = Arguments::split(args, &[])?;
+            Ok(Command::Continue {
+                uid: match args.words.len() {
+                    0 => None,
+                    _ => Some(args.uid()?),
+                },
+            })
+        }
+        "tasks" => {
+            let args = Arguments::split(args, &["days"])?;
+            args.no_words()?;
+            let days = match args.string("days") {
+                Some(days) => match days.parse::<i64>() {
+                    Ok(days) if days > 0 => days,
+                    _ => return Err(format!("'{}' is not a number of days", days)),
+                },
+                None => DEFAULT_DAYS,
+            };
+            Ok(Command::Tasks { days })
+        }
+        "add" => {
+            let args = Arguments::split(args, &["start", "stop"])?;
+            Ok(Command::Add {
+                task_input: args.task_input()?,
+                start: args.time("start")?.ok_or("--start is required")?,
+                stop: args.time("stop")?.ok_or("--stop is required")?,
+            })
+        }
+        "edit" => {
+            let args = Arguments::split(args, &["task", "start", "stop"])?;
+            Ok(Command::Edit {
+                uid: args.uid()?,
+                task_input: args.string("task"),
+                start: args.time("start")?,
+                stop: args.time("stop")?,
+            })
+        }
+        "delete" => {
+            let args = Arguments::split(args, &[])?;
+            if args.words.is_empty() {
+                return Err("At least one UID is required".to_string());
+            }
+            Ok(Command::Delete { uids: args.words })
+        }
+        "todos" => parse_todo_command(args).map(Command::Todos),
+        "shortcuts" => parse_shortcut_command(args).map(Command::Shortcuts),
+        "sync" => {
+            Arguments::split(args, &[])?.no_words()?;
+            Ok(Command::Sync)
+        }
+        "export" => {
+            let args = Arguments::split(args, &["from", "to", "format", "output"])?;
+            args.no_words()?;
+            let output = args.string("output").map(PathBuf::from);
+            if args.string("format").as_deref() == Some("json") {
+                return Ok(Command::ExportArchive { output });
+            }
+            Ok(Command::Export {
+                from: args.date("from")?,
+                to: args.date("to")?,
+                format: parse_export_format(args.string("format").as_deref())?,
+                output,
+            })
+        }
+        "timesheet" => {
+            let args = Arguments::split(
+                args,
+                &["week", "rows", "format", "projects", "tags", "output"],
+            )?;
+            args.no_words()?;
+            Ok(Command::Timesheet {
+                week: args
+                    .date("week")?
+                    .unwrap_or_else(|| Local::now().date_naive()),
+                rows: parse_timesheet_rows(args.string("rows").as_deref())?,
+                format: parse_timesheet_format(args.string("format").as_deref())?,
+                filter: TimesheetFilter::parse(
+                    &args.string("projects").unwrap_or_default(),
+                    &args.string("tags").unwrap_or_default(),
+                ),
+                output: args.string("output").map(PathBuf::from),
+            })
+        }
+        "import" => {
+            let args = Arguments::split(args, &["settings"])?;
+            let path = match args.words.as_slice() {
+                [path] => PathBuf::from(path),
+                [] => return Err("A file is required".to_string()),
+                _ => return Err("Only one file can be given".to_string()),
+            };
+            let include_settings = match args.string("settings").as_deref() {
+                None | Some("import") => true,
+                Some("keep") => false,
+                Some(value) => {
+                    return Err(format!(
+                        "'{}' is not a settings choice (import or keep)",
+                        value
+                    ))
+                }
+            };
+            Ok(Command::Import {
+                path,
+                include_settings,
+            })
+        }
+        "help" | "--help" | "-h" => Ok(Command::Help),
+        _ => Err(format!("Unknown command '{}'", command)),
+    }
+}
+
+fn parse_todo_command(mut args: Vec<String>) -> Result<TodoCommand, String> {
+    if args.is_empty() {
+        return Ok(TodoCommand::List);
+    }
+    let command = args.remove(0);
+    match command.as_str() {
+        "list" => {
+            Arguments::split(args, &[])?.no_words()?;
+            Ok(TodoCommand::List)
+        }
+        "add" => {
+            let args = Arguments::split(args, &["date"])?;
+            Ok(TodoCommand::Add {
+                task_input: args.task_input()?,
+                date: args
+                    .date("date")?
+                    .unwrap_or_else(|| Local::now().date_naive()),
+            })
+        }
+        "done" => Ok(TodoCommand::Done {
+            uid: Arguments::split(args, &[])?.uid()?,
+        }),
+        "edit" => {
+            let args = Arguments::split(args, &["task", "date"])?;
+            Ok(TodoCommand::Edit {
+                uid: args.uid()?,
+                task_input: args.string("task"),
+                date: args.date("date")?,
+            })
+        }
+        "delete" => Ok(TodoCommand::Delete {
+            uid: Arguments::split(args, &[])?.uid()?,
+        }),
+        _ => Err(format!("Unknown todos command '{}'", command)),
+    }
+}
+
+fn parse_shortcut_command(mut args: Vec<String>) -> Result<ShortcutCommand, String> {
+    if args.is_empty() {
+        return Ok(ShortcutCommand::List);
+    }
+    let command = args.remove(0);
+    match command.as_str() {
+        "list" => {
+            Arguments::split(args, &[])?.no_words()?;
+            Ok(ShortcutCommand::List)
+        }
+        "add" => {
+            let args = Arguments::split(args, &["color"])?;
+            Ok(ShortcutCommand::Add {
+                task_input: args.task_input()?,
+                color_hex: args.string("color").map(parse_color).transpose()?,
+            })
+        }
+        "edit" => {
+            let args = Arguments::split(args, &["task", "color"])?;
+            Ok(ShortcutCommand::Edit {
+                uid: args.uid()?,
+                task_input: args.string("task"),
+                color_hex: args.string("color").map(parse_color).transpose()?,
+            })
+        }
+        "delete" => Ok(ShortcutCommand::Delete {
+            uid: Arguments::split(args, &[])?.uid()?,
+        }),
+        _ => Err(format!("Unknown shortcuts command '{}'", command)),
+    }
+}
+
+/// HH:MM today, or a full date and time
+fn parse_time(value: &str) -> Result<DateTime<Local>, String> {
+    let naive = if let Ok(time) = NaiveTime::parse_from_str(value, "%H:%M") {
+        Local::now().date_naive().and_time(time)
+    } else if let Ok(date_time) = NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M") {
+        date_time
+    } else if let Ok(date_time) = NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M") {
+        date_time
+    } else if let Ok(date_time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(date_time.with_timezone(&Local));
+    } else {
+        return Err(format!(
+            "'{}' is not a time (HH:MM or YYYY-MM-DD HH:MM)",
+            value
+        ));
+    };
+    match Local.from_local_datetime(&naive) {
+        LocalResult::Single(time) | LocalResult::Ambiguous(time, _) => Ok(time),
+        LocalResult::None => Err(format!("'{}' doesn't exist in the local time zone", value)),
+    }
+}
+
+fn parse_date(value: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| format!("'{}' is not a date (YYYY-MM-DD)", value))
+}
+
+fn parse_timesheet_rows(value: Option<&str>) -> Result<TimesheetRows, String> {
+    match value {
+        None | Some("task") => Ok(TimesheetRows::Task),
+        Some("project") => Ok(TimesheetRows::Project),
+        Some(value) => Err(format!(
+            "'{}' is not a kind of timesheet row (task or project)",
+            value
+        )),
+    }
+}
+
+fn parse_timesheet_format(value: Option<&str>) -> Result<TimesheetFormat, String> {
+    match value {
+        None | Some("csv") => Ok(TimesheetFormat::Csv),
+        Some("markdown") | Some("md") => Ok(TimesheetFormat::Markdown),
+        Some("html") => Ok(TimesheetFormat::Html),
+        Some(value) => Err(format!(
+            "'{}' is not a timesheet format (csv, markdown or html)",
+            value
+        )),
+    }
+}
+
+fn parse_export_format(value: Option<&str>) -> Result<ExportFormat, String> {
+    match value {
+        None | Some("csv") => Ok(ExportFormat::Csv),
+        Some("ics") => Ok(ExportFormat::Ics {
+            merge_contiguous: false,
+        }),
+        Some("ics-merged") => Ok(ExportFormat::Ics {
+            merge_contiguous: true,
+        }),
+        Some(value) => Err(format!(
+            "'{}' is not an export format (csv, ics, ics-merged or json)",
+            value
+        )),
+    }
+}
+
+fn parse_color(value: String) -> Result<String, String> {
+    let hex = value.trim_start_matches('#');
+    if hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        Ok(format!("#{}", hex.to_lowercase()))
+    } else {
+        Err(format!("'{}' is not a color (#RRGGBB)", value))
+    }
+}
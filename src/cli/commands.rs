@@ -0,0 +1,552 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local, LocalResult, NaiveDate, TimeDelta, TimeZone, Utc};
+use furtherance_core::{
+    database,
+    helpers::formatters::{self, seconds_to_formatted_duration},
+    models::{
+        fur_error::FurResult, fur_settings::FurSettings, fur_shortcut::FurShortcut,
+        fur_task::FurTask, fur_todo::FurTodo,
+    },
+    server::encryption::EncryptionError,
+    services::{
+        self,
+        sync::SyncError,
+        timesheets::{TimesheetFilter, TimesheetFormat, TimesheetRows},
+    },
+};
+use rand::Rng;
+
+use crate::{
+    args::{Command, ExportFormat, ShortcutCommand, TodoCommand},
+    describe,
+};
+
+const TIME_FORMAT: &str = "%H:%M";
+
+pub fn run(command: Command, settings: &FurSettings) -> Result<(), String> {
+    match command {
+        Command::Start { task_input, at } => start(&task_input, at, settings),
+        Command::Stop { at } => stop(at, settings),
+        Command::Status => status(settings),
+        Command::Continue { uid } => continue_task(uid, settings),
+        Command::Tasks { days } => list_tasks(days, settings),
+        Command::Add {
+            task_input,
+            start,
+            stop,
+        } => add_task(&task_input, start, stop),
+        Command::Edit {
+            uid,
+            task_input,
+            start,
+            stop,
+        } => edit_task(&uid, task_input, start, stop),
+        Command::Delete { uids } => delete_tasks(uids),
+        Command::Todos(command) => todos(command),
+        Command::Shortcuts(command) => shortcuts(command),
+        Command::Sync => sync(),
+        Command::Export {
+            from,
+            to,
+            format,
+            output,
+        } => export(from, to, format, output),
+        Command::ExportArchive { output } => export_archive(output, settings),
+        Command::Timesheet {
+            week,
+            rows,
+            format,
+            filter,
+            output,
+        } => timesheet(week, rows, format, &filter, output, settings),
+        Command::Import {
+            path,
+            include_settings,
+        } => import(&path, include_settings),
+        Command::Help => Ok(()),
+    }
+}
+
+fn check<T>(result: FurResult<T>) -> Result<T, String> {
+    result.map_err(|e| describe(&e))
+}
+
+fn start(
+    task_input: &str,
+    at: Option<DateTime<Local>>,
+    settings: &FurSettings,
+) -> Result<(), String> {
+    if let Some(timer) = check(services::timer::running())? {
+        return Err(format!(
+            "The timer is already running: {}",
+            timer.task_input
+        ));
+    }
+    let (name, _, _, _) = formatters::split_task_input(task_input);
+    if name.is_empty() {
+        return Err("The task needs a name before @project, #tags or $rate".to_string());
+    }
+    let timer = check(services::timer::start(
+        task_input,
+        at.unwrap_or_else(Local::now),
+        settings,
+    ))?;
+    println!(
+        "Started {} at {}",
+        timer.task_input,
+        timer.start_time.format(TIME_FORMAT)
+    );
+    Ok(())
+}
+
+fn stop(at: Option<DateTime<Local>>, settings: &FurSettings) -> Result<(), String> {
+    let timer = check(services::timer::running())?.ok_or("The timer isn't running")?;
+    let stop_time = at.unwrap_or_else(Local::now);
+    if stop_time <= timer.start_time {
+        return Err("The stop time must be after the start time".to_string());
+    }
+    let task = check(services::timer::stop(&timer, stop_time))?;
+    println!(
+        "Saved {} ({})",
+        task.to_string(),
+        seconds_to_formatted_duration(task.total_time_in_seconds(), settings.show_seconds)
+    );
+    Ok(())
+}
+
+fn status(settings: &FurSettings) -> Result<(), String> {
+    // Announces a pomodoro that ran out while no other front end was open
+    let ended = check(services::timer::check_pomodoro(settings))?;
+    match check(services::timer::running())? {
+        Some(timer) => {
+            println!(
+                "{}\nRunning for {} (since {})",
+                timer.task_input,
+                seconds_to_formatted_duration(
+                    services::timer::elapsed_seconds(&timer),
+                    settings.show_seconds
+                ),
+                timer.start_time.format(TIME_FORMAT)
+            );
+            if settings.pomodoro {
+                let pomodoro = check(services::timer::pomodoro())?;
+                let period = if pomodoro.on_break {
+                    "Break"
+                } else {
+                    "Pomodoro"
+                };
+                if ended.is_some() {
+                    println!("{} is over", period);
+                } else {
+                    println!(
+                        "{} ends in {}",
+                        period,
+                        seconds_to_formatted_duration(
+                            services::timer::pomodoro_seconds_left(
+                                &timer,
+                                &pomodoro,
+                                settings,
+                                Local::now()
+                            ),
+                            true
+                        )
+                    );
+                }
+            }
+        }
+        None => println!("The timer isn't running"),
+    }
+    Ok(())
+}
+
+fn continue_task(uid: Option<String>, settings: &FurSettings) -> Result<(), String> {
+    let task = match uid {
+        Some(uid) => find_task(&uid)?,
+        None => check(services::tasks::most_recent())?.ok_or("There are no tasks to continue")?,
+    };
+    start(&task.to_string(), None, settings)
+}
+
+fn find_task(uid: &str) -> Result<FurTask, String> {
+    check(services::tasks::find(uid))?.ok_or_else(|| format!("No task with UID {}", uid))
+}
+
+fn list_tasks(days: i64, settings: &FurSettings) -> Result<(), String> {
+    let first_day = Local::now().date_naive() - TimeDelta::days(days - 1);
+    let tasks = check(services::tasks::between(
+        start_of_day(first_day)?,
+        Local::now(),
+    ))?;
+    if tasks.is_empty() {
+        println!("No tasks in the last {} days", days);
+        return Ok(());
+    }
+
+    let mut current_date: Option<NaiveDate> = None;
+    for task in tasks {
+        let date = task.start_time.date_naive();
+        if current_date != Some(date) {
+            if current_date.is_some() {
+                println!();
+            }
+            println!("{}", formatters::format_title_date(&date));
+            current_date = Some(date);
+        }
+        println!(
+            "  {}  {}-{}  {:>8}  {}",
+            task.uid,
+            task.start_time.format(TIME_FORMAT),
+            task.stop_time.format(TIME_FORMAT),
+            seconds_to_formatted_duration(task.total_time_in_seconds(), settings.show_seconds),
+            task.to_string()
+        );
+    }
+    Ok(())
+}
+
+fn add_task(task_input: &str, start: DateTime<Local>, stop: DateTime<Local>) -> Result<(), String> {
+    if stop <= start {
+        return Err("The stop time must be after the start time".to_string());
+    }
+    let (name, project, tags, rate) = formatters::split_task_input(task_input);
+    if name.is_empty() {
+        return Err("The task needs a name before @project, #tags or $rate".to_string());
+    }
+    let task = FurTask::new(name, start, stop, tags, project, rate, String::new());
+    check(services::tasks::add(&task))?;
+    println!("Added {} ({})", task.to_string(), task.uid);
+    Ok(())
+}
+
+fn edit_task(
+    uid: &str,
+    task_input: Option<String>,
+    start: Option<DateTime<Local>>,
+    stop: Option<DateTime<Local>>,
+) -> Result<(), String> {
+    let mut task = find_task(uid)?;
+    if let Some(task_input) = task_input {
+        let (name, project, tags, rate) = formatters::split_task_input(&task_input);
+        if name.is_empty() {
+            return Err("The task needs a name before @project, #tags or $rate".to_string());
+        }
+        task.name = name;
+        task.project = project;
+        task.tags = tags;
+        task.rate = rate;
+    }
+    if let Some(start) = start {
+        task.start_time = start;
+    }
+    if let Some(stop) = stop {
+        task.stop_time = stop;
+    }
+    if task.stop_time <= task.start_time {
+        return Err("The stop time must be after the start time".to_string());
+    }
+    task.last_updated = Utc::now().timestamp();
+    check(services::tasks::update(&task))?;
+    println!("Updated {}", task.to_string());
+    Ok(())
+}
+
+fn delete_tasks(uids: Vec<String>) -> Result<(), String> {
+    for uid in &uids {
+        find_task(uid)?;
+    }
+    check(services::tasks::delete(&uids))?;
+    println!("Deleted {} task(s)", uids.len());
+    Ok(())
+}
+
+fn todos(command: TodoCommand) -> Result<(), String> {
+    match command {
+        TodoCommand::List => {
+            let todos = services::todos::all();
+            if todos.is_empty() {
+                println!("No todos");
+            }
+            for (index, (date, todos)) in todos.iter().rev().enumerate() {
+                if index > 0 {
+                    println!();
+                }
+                println!("{}", formatters::format_title_date(date));
+                for todo in todos {
+                    println!(
+                        "  {}  [{}] {}",
+                        todo.uid,
+                        if todo.is_completed { "x" } else { " " },
+                        todo.to_string()
+                    );
+                }
+            }
+        }
+        TodoCommand::Add { task_input, date } => {
+            let (name, project, tags, rate) = formatters::split_task_input(&task_input);
+            if name.is_empty() {
+                return Err("The todo needs a name before @project, #tags or $rate".to_string());
+            }
+            let todo = FurTodo::new(name, project, tags, rate, start_of_day(date)?);
+            check(services::todos::add(&todo))?;
+            println!("Added {} ({})", todo.to_string(), todo.uid);
+        }
+        TodoCommand::Done { uid } => {
+            let todo = find_todo(&uid)?;
+            if !todo.is_completed {
+                check(services::todos::toggle_completed(&uid))?;
+            }
+            println!("Completed {}", todo.to_string());
+        }
+        TodoCommand::Edit {
+            uid,
+            task_input,
+            date,
+        } => {
+            let mut todo = find_todo(&uid)?;
+            if let Some(task_input) = task_input {
+                let (name, project, tags, rate) = formatters::split_task_input(&task_input);
+                if name.is_empty() {
+                    return Err("The todo needs a name before @project, #tags or $rate".to_string());
+                }
+                todo.name = name;
+                todo.project = project;
+                todo.tags = tags;
+                todo.rate = rate;
+            }
+            if let Some(date) = date {
+                todo.date = start_of_day(date)?;
+            }
+            todo.last_updated = Utc::now().timestamp();
+            check(services::todos::update(&todo))?;
+            println!("Updated {}", todo.to_string());
+        }
+        TodoCommand::Delete { uid } => {
+            let todo = find_todo(&uid)?;
+            check(services::todos::delete(&uid))?;
+            println!("Deleted {}", todo.to_string());
+        }
+    }
+    Ok(())
+}
+
+fn find_todo(uid: &str) -> Result<FurTodo, String> {
+    check(services::todos::find(uid))?.ok_or_else(|| format!("No todo with UID {}", uid))
+}
+
+fn shortcuts(command: ShortcutCommand) -> Result<(), String> {
+    match command {
+        ShortcutCommand::List => {
+            let shortcuts = services::shortcuts::all();
+            if shortcuts.is_empty() {
+                println!("No shortcuts");
+            }
+            for shortcut in shortcuts {
+                println!(
+                    "  {}  {}  {}",
+                    shortcut.uid,
+                    shortcut.color_hex,
+                    shortcut.to_string()
+                );
+            }
+        }
+        ShortcutCommand::Add {
+            task_input,
+            color_hex,
+        } => {
+            let (name, project, tags, rate) = formatters::split_task_input(&task_input);
+            if name.is_empty() {
+                return Err("The shortcut needs a name before @project, #tags or $rate".to_string());
+            }
+            let shortcut = FurShortcut::new(
+                name,
+                tags,
+                project,
+                rate,
+                String::new(),
+                color_hex.unwrap_or_else(random_color),
+            );
+            check(services::shortcuts::add(&shortcut))?;
+            println!("Added {} ({})", shortcut.to_string(), shortcut.uid);
+        }
+        ShortcutCommand::Edit {
+            uid,
+            task_input,
+            color_hex,
+        } => {
+            let mut shortcut = find_shortcut(&uid)?;
+            if let Some(task_input) = task_input {
+                let (name, project, tags, rate) = formatters::split_task_input(&task_input);
+                if name.is_empty() {
+                    return Err(
+                        "The shortcut needs a name before @project, #tags or $rate".to_string()
+                    );
+                }
+                shortcut.name = name;
+                shortcut.project = project;
+                shortcut.tags = tags;
+                shortcut.rate = rate;
+            }
+            if let Some(color_hex) = color_hex {
+                shortcut.color_hex = color_hex;
+            }
+            shortcut.last_updated = Utc::now().timestamp();
+            check(services::shortcuts::update(&shortcut))?;
+            println!("Updated {}", shortcut.to_string());
+        }
+        ShortcutCommand::Delete { uid } => {
+            let shortcut = find_shortcut(&uid)?;
+            check(services::shortcuts::delete(&uid))?;
+            println!("Deleted {}", shortcut.to_string());
+        }
+    }
+    Ok(())
+}
+
+fn find_shortcut(uid: &str) -> Result<FurShortcut, String> {
+    check(services::shortcuts::find(uid))?.ok_or_else(|| format!("No shortcut with UID {}", uid))
+}
+
+fn random_color() -> String {
+    let mut rng = rand::thread_rng();
+    format!("#{:06x}", rng.gen::<u32>() & 0xFFFFFF)
+}
+
+fn sync() -> Result<(), String> {
+    let user = database::sync::retrieve_credentials()
+        .map_err(|e| e.to_string())?
+        .ok_or("Sign in to a sync account in the app first")?;
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    match runtime.block_on(services::sync::run(user)) {
+        Ok(log) => {
+            println!("Synced {} record(s)", log.record_count());
+            Ok(())
+        }
+        Err(SyncError::Key(EncryptionError::Locked)) => {
+            Err("The sync key is locked with a PIN. Unlock it in the app first.".to_string())
+        }
+        Err(SyncError::Key(e)) => Err(format!("Couldn't read the sync key: {:?}", e)),
+        Err(SyncError::Api(e)) => Err(format!("Sync failed: {:?}", e)),
+    }
+}
+
+fn export(
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    format: ExportFormat,
+    output: Option<PathBuf>,
+) -> Result<(), String> {
+    let start = match from {
+        Some(from) => start_of_day(from)?,
+        None => DateTime::<Utc>::UNIX_EPOCH.with_timezone(&Local),
+    };
+    let end = match to {
+        Some(to) => start_of_day(to + TimeDelta::days(1))?,
+        None => Local::now(),
+    };
+    let tasks = check(services::tasks::between(start, end))?;
+    let contents = match format {
+        ExportFormat::Csv => services::export::tasks_csv(&tasks),
+        ExportFormat::Ics { merge_contiguous } => {
+            services::calendar::tasks_ics(&tasks, merge_contiguous)
+        }
+    };
+    match output {
+        Some(path) => {
+            check(services::export::write(&path, &contents))?;
+            println!("Exported {} task(s) to {}", tasks.len(), path.display());
+        }
+        None => print!("{}", contents),
+    }
+    Ok(())
+}
+
+fn export_archive(output: Option<PathBuf>, settings: &FurSettings) -> Result<(), String> {
+    let archive = check(services::archive::create(settings))?;
+    let contents = check(services::archive::to_json(&archive))?;
+    match output {
+        Some(path) => {
+            check(services::export::write(&path, &contents))?;
+            println!(
+                "Exported {} task(s), {} todo(s) and {} shortcut(s) to {}",
+                archive.tasks.len(),
+                archive.todos.len(),
+                archive.shortcuts.len(),
+                path.display()
+            );
+        }
+        None => println!("{}", contents),
+    }
+    Ok(())
+}
+
+fn timesheet(
+    week: NaiveDate,
+    rows: TimesheetRows,
+    format: TimesheetFormat,
+    filter: &TimesheetFilter,
+    output: Option<PathBuf>,
+    settings: &FurSettings,
+) -> Result<(), String> {
+    let timesheet = check(services::timesheets::for_week(week, rows, filter))?;
+    let contents = services::timesheets::render(&timesheet, format, settings.show_seconds);
+    match output {
+        Some(path) => {
+            check(services::export::write(&path, &contents))?;
+            println!(
+                "Exported the timesheet for the week of {} to {}",
+                timesheet.first_day,
+                path.display()
+            );
+        }
+        None => print!("{}", contents),
+    }
+    Ok(())
+}
+
+fn import(path: &Path, include_settings: bool) -> Result<(), String> {
+    let archive = check(services::archive::read(path))?;
+    let import = check(services::archive::import(&archive, include_settings))?;
+    for (kind, count) in [
+        ("Tasks", import.tasks),
+        ("Todos", import.todos),
+        ("Shortcuts", import.shortcuts),
+    ] {
+        println!(
+            "{}: {} added, {} updated, {} unchanged",
+            kind, count.added, count.updated, count.unchanged
+        );
+    }
+    if import.settings_imported {
+        println!("Settings imported");
+    }
+    Ok(())
+}
+
+/// Midnight at the start of `date` in the local time zone
+fn start_of_day(date: NaiveDate) -> Result<DateTime<Local>, String> {
+    match Local.from_local_datetime(&date.and_time(Default::default())) {
+        LocalResult::Single(time) | LocalResult::Ambiguous(time, _) => Ok(time),
+        LocalResult::None => Err(format!("{} has no midnight in the local time zone", date)),
+    }
+}
@@ -0,0 +1,122 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! `furtherance`: the timer, tasks, todos, shortcuts and sync from a terminal.
+//! It uses the same database as the app, so a timer started here shows as running there.
+//! Build it without the app's UI with `cargo build --no-default-features --bin furtherance`.
+
+mod args;
+mod commands;
+
+use std::{env, process::ExitCode};
+
+use args::{Command, USAGE};
+use furtherance_core::{
+    database,
+    events::{self, CoreEvent},
+    helpers::logger,
+    hooks,
+    models::{
+        fur_error::{ErrorContext, FurError, FurErrorContext},
+        fur_settings::FurSettings,
+    },
+    services,
+};
+use log::error;
+use tokio::sync::broadcast::Receiver;
+
+/// Read by the CLI instead of prompting when the database is encrypted
+const PASSPHRASE_VARIABLE: &str = "FURTHERANCE_DB_PASSPHRASE";
+
+fn main() -> ExitCode {
+    let command = match args::parse(env::args().skip(1).collect()) {
+        Ok(command) => command,
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    if let Command::Help = command {
+        print!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+
+    let settings = services::settings::load();
+    logger::init(&settings.log_level);
+
+    if let Err(message) = open_database() {
+        eprintln!("{}", message);
+        return ExitCode::FAILURE;
+    }
+
+    let mut events = events::subscribe();
+    let result = commands::run(command, &settings);
+    run_hooks(&mut events, &services::settings::load());
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("{}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Run the user's hooks for what the command did before exiting
+fn run_hooks(events: &mut Receiver<CoreEvent>, settings: &FurSettings) {
+    let mut fired = Vec::new();
+    while let Ok(event) = events.try_recv() {
+        if hooks::payload(&event).is_some() {
+            fired.push(event);
+        }
+    }
+    if fired.is_empty() {
+        return;
+    }
+
+    match tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+    {
+        Ok(runtime) => runtime.block_on(async {
+            for event in fired {
+                hooks::run(event, settings.clone()).await;
+            }
+        }),
+        Err(e) => error!("Error starting the runtime for hooks: {}", e),
+    }
+}
+
+fn open_database() -> Result<(), String> {
+    if database::encryption::is_encrypted() {
+        let passphrase = match env::var(PASSPHRASE_VARIABLE) {
+            Ok(passphrase) => passphrase,
+            Err(_) => rpassword::prompt_password("Database passphrase: ")
+                .map_err(|e| format!("Couldn't read the passphrase: {}", e))?,
+        };
+        if database::encryption::unlock(&passphrase).is_err() {
+            return Err("Wrong database passphrase".to_string());
+        }
+    }
+    database::init::db_init()
+        .in_context(FurErrorContext::OpenDatabase)
+        .map_err(|e| describe(&e))
+}
+
+/// The same title and explanation the app shows in its error alerts
+pub fn describe(error: &FurError) -> String {
+    format!("{}: {}", error.title(), error.localized_message())
+}
@@ -15,9 +15,6 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use dioxus::prelude::{asset, manganis, Asset};
-use once_cell::sync::OnceCell;
-
-use crate::localization::Localization;
 
 pub const ALLOWED_DB_EXTENSIONS: &[&str] =
     &["db", "sqlite", "sqlite3", "db3", "database", "data", "s3db"];
@@ -38,6 +35,3 @@ pub const SHORTCUTS_CSS: Asset = asset!("/assets/css/shortcuts.css");
 pub const SETTINGS_CSS: Asset = asset!("/assets/css/settings.css");
 pub const ALERT_CSS: Asset = asset!("/assets/css/alert.css");
 pub const SHEET_CSS: Asset = asset!("/assets/css/sheet.css");
-
-// Localization
-pub static LOCALIZATION: OnceCell<Localization> = OnceCell::new();
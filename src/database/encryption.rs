@@ -0,0 +1,111 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{fs, io::Read, path::PathBuf, sync::Mutex};
+
+use rusqlite::{params, Connection, Result};
+
+use crate::models::fur_error::{ErrorContext, FurErrorContext, FurResult};
+
+use super::init::{get_directory, open_connection};
+
+/// Every plaintext SQLite file starts with this. SQLCipher files look like random bytes.
+const PLAINTEXT_HEADER: &[u8; 16] = b"SQLite format 3\0";
+
+/// Passphrase for the encrypted database, kept in memory once unlocked
+static DATABASE_KEY: Mutex<Option<String>> = Mutex::new(None);
+
+pub fn is_encrypted() -> bool {
+    let mut header = [0u8; 16];
+    match fs::File::open(get_directory()).and_then(|mut file| file.read_exact(&mut header)) {
+        Ok(_) => &header != PLAINTEXT_HEADER,
+        // A missing or empty database will be created as plaintext
+        Err(_) => false,
+    }
+}
+
+fn set_key(passphrase: Option<&str>) {
+    if let Ok(mut key) = DATABASE_KEY.lock() {
+        *key = passphrase.map(|passphrase| passphrase.to_string());
+    }
+}
+
+pub fn apply_key(conn: &Connection) -> Result<()> {
+    let key = DATABASE_KEY.lock().ok().and_then(|key| key.clone());
+    if let Some(key) = key {
+        conn.pragma_update(None, "key", key)?;
+    }
+    Ok(())
+}
+
+/// Check the passphrase and use it for every connection this session
+pub fn unlock(passphrase: &str) -> Result<()> {
+    let conn = Connection::open(get_directory())?;
+    conn.pragma_update(None, "key", passphrase)?;
+    // SQLCipher only notices a wrong key once a page is read
+    conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |row| {
+        row.get::<_, i64>(0)
+    })?;
+    set_key(Some(passphrase));
+    Ok(())
+}
+
+/// Copy the open database into a new file with the given key ("" for plaintext)
+/// and swap it in place of the current one.
+fn export_database(new_key: &str, context: FurErrorContext) -> FurResult<()> {
+    let path = get_directory();
+    let mut export_path = path.clone().into_os_string();
+    export_path.push(".export");
+    let export_path = PathBuf::from(export_path);
+    if export_path.exists() {
+        fs::remove_file(&export_path).in_context(context)?;
+    }
+
+    {
+        let conn = open_connection().in_context(context)?;
+        conn.execute(
+            "ATTACH DATABASE ?1 AS export KEY ?2",
+            params![export_path.to_string_lossy(), new_key],
+        )
+        .in_context(context)?;
+        conn.query_row("SELECT sqlcipher_export('export')", [], |_| Ok(()))
+            .in_context(context)?;
+        conn.execute("DETACH DATABASE export", [])
+            .in_context(context)?;
+    }
+
+    // Rename is atomic, so a crash leaves either the old or the new database
+    fs::rename(&export_path, &path).in_context(context)?;
+    Ok(())
+}
+
+pub fn encrypt_database(passphrase: &str) -> FurResult<()> {
+    if is_encrypted() {
+        return Ok(());
+    }
+    export_database(passphrase, FurErrorContext::EncryptDatabase)?;
+    set_key(Some(passphrase));
+    Ok(())
+}
+
+pub fn decrypt_database() -> FurResult<()> {
+    if !is_encrypted() {
+        return Ok(());
+    }
+    export_database("", FurErrorContext::DecryptDatabase)?;
+    set_key(None);
+    Ok(())
+}
@@ -20,12 +20,21 @@ use std::path::PathBuf;
 
 use crate::models::fur_settings;
 
+use super::encryption;
+
 pub fn get_directory() -> PathBuf {
     let mut path = fur_settings::get_data_path();
     path.extend(&["furtherance.db"]);
     path
 }
 
+/// Open the database, applying the passphrase if it is encrypted
+pub fn open_connection() -> Result<Connection> {
+    let conn = Connection::open(get_directory())?;
+    encryption::apply_key(&conn)?;
+    Ok(conn)
+}
+
 fn column_exists(conn: &Connection, table: &str, column: &str) -> Result<bool> {
     let mut stmt = conn.prepare(&format!(
         "SELECT COUNT(*) FROM pragma_table_info('{}') WHERE name = ?",
@@ -36,7 +45,7 @@ fn column_exists(conn: &Connection, table: &str, column: &str) -> Result<bool> {
 }
 
 pub fn db_init() -> Result<()> {
-    let conn = Connection::open(get_directory())?;
+    let conn = open_connection()?;
 
     conn.execute(
         "CREATE TABLE IF NOT EXISTS tasks (
@@ -50,7 +59,9 @@ pub fn db_init() -> Result<()> {
             currency TEXT,
             uid TEXT,
             is_deleted BOOLEAN DEFAULT 0,
-            last_updated INTEGER DEFAULT 0
+            last_updated INTEGER DEFAULT 0,
+            tombstone_acked BOOLEAN DEFAULT 0,
+            invoice_number TEXT DEFAULT ''
         );",
         [],
     )?;
@@ -66,7 +77,8 @@ pub fn db_init() -> Result<()> {
             color_hex TEXT,
             uid TEXT,
             is_deleted BOOLEAN DEFAULT 0,
-            last_updated INTEGER DEFAULT 0
+            last_updated INTEGER DEFAULT 0,
+            tombstone_acked BOOLEAN DEFAULT 0
         );",
         [],
     )?;
@@ -78,11 +90,27 @@ pub fn db_init() -> Result<()> {
             key_nonce TEXT NOT NULL,
             access_token TEXT NOT NULL,
             refresh_token TEXT NOT NULL,
-            server TEXT NOT NULL
+            server TEXT NOT NULL,
+            key_generation INTEGER DEFAULT 0,
+            key_storage TEXT DEFAULT 'device',
+            key_storage_salt TEXT
         )",
         [],
     )?;
 
+    for (column, definition) in [
+        ("key_generation", "INTEGER DEFAULT 0"),
+        ("key_storage", "TEXT DEFAULT 'device'"),
+        ("key_storage_salt", "TEXT"),
+    ] {
+        if !column_exists(&conn, "user", column)? {
+            conn.execute(
+                &format!("ALTER TABLE user ADD COLUMN {} {}", column, definition),
+                [],
+            )?;
+        }
+    }
+
     conn.execute(
         "CREATE TABLE IF NOT EXISTS todos (
             id INTEGER PRIMARY KEY,
@@ -95,7 +123,99 @@ pub fn db_init() -> Result<()> {
             uid TEXT,
             is_completed BOOLEAN DEFAULT 0,
             is_deleted BOOLEAN DEFAULT 0,
-            last_updated INTEGER DEFAULT 0
+            last_updated INTEGER DEFAULT 0,
+            tombstone_acked BOOLEAN DEFAULT 0
+        )",
+        [],
+    )?;
+
+    // Track which tombstones the sync server has received
+    for table in ["tasks", "shortcuts", "todos"] {
+        if !column_exists(&conn, table, "tombstone_acked")? {
+            conn.execute(
+                &format!(
+                    "ALTER TABLE {} ADD COLUMN tombstone_acked BOOLEAN DEFAULT 0",
+                    table
+                ),
+                [],
+            )?;
+        }
+    }
+
+    // Invoiced tasks keep the invoice's number so the mark syncs with the task
+    if !column_exists(&conn, "tasks", "invoice_number")? {
+        conn.execute(
+            "ALTER TABLE tasks ADD COLUMN invoice_number TEXT DEFAULT ''",
+            [],
+        )?;
+    }
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS sync_progress (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            full_sync BOOLEAN NOT NULL,
+            started_at INTEGER NOT NULL,
+            last_sync INTEGER NOT NULL,
+            download_since INTEGER NOT NULL,
+            download_started_at INTEGER,
+            download_cursor TEXT,
+            tasks_through_updated INTEGER NOT NULL,
+            tasks_through_uid TEXT NOT NULL,
+            shortcuts_through_updated INTEGER NOT NULL,
+            shortcuts_through_uid TEXT NOT NULL,
+            todos_through_updated INTEGER NOT NULL,
+            todos_through_uid TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS key_rotation (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            encrypted_key TEXT NOT NULL,
+            key_nonce TEXT NOT NULL,
+            key_generation INTEGER NOT NULL,
+            uploaded_count INTEGER NOT NULL,
+            tasks_through_updated INTEGER NOT NULL,
+            tasks_through_uid TEXT NOT NULL,
+            shortcuts_through_updated INTEGER NOT NULL,
+            shortcuts_through_uid TEXT NOT NULL,
+            todos_through_updated INTEGER NOT NULL,
+            todos_through_uid TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS sync_log (
+            id INTEGER PRIMARY KEY,
+            started_at INTEGER NOT NULL,
+            duration_ms INTEGER NOT NULL,
+            full_sync BOOLEAN NOT NULL,
+            direction TEXT NOT NULL,
+            tasks_uploaded INTEGER NOT NULL,
+            shortcuts_uploaded INTEGER NOT NULL,
+            todos_uploaded INTEGER NOT NULL,
+            tasks_downloaded INTEGER NOT NULL,
+            shortcuts_downloaded INTEGER NOT NULL,
+            todos_downloaded INTEGER NOT NULL,
+            orphans_resent INTEGER NOT NULL,
+            decrypt_failures TEXT NOT NULL,
+            error TEXT
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS invoices (
+            number TEXT PRIMARY KEY,
+            project TEXT NOT NULL,
+            client TEXT NOT NULL,
+            first_day TEXT NOT NULL,
+            last_day TEXT NOT NULL,
+            currency TEXT NOT NULL,
+            total REAL NOT NULL,
+            issued_at INTEGER NOT NULL
         )",
         [],
     )?;
@@ -120,5 +240,24 @@ pub fn db_init() -> Result<()> {
         params![false, String::new(), Local::now()],
     )?;
 
+    // Pomodoro progress lives with the timer so the app, CLI and local API share it
+    for (column, definition) in [
+        ("pomodoro_on_break", "BOOLEAN DEFAULT 0"),
+        ("pomodoro_sessions", "INTEGER DEFAULT 0"),
+        ("pomodoro_snoozed", "BOOLEAN DEFAULT 0"),
+        ("pomodoro_snoozed_at", "TIMESTAMP"),
+        ("pomodoro_end_announced", "BOOLEAN DEFAULT 0"),
+    ] {
+        if !column_exists(&conn, "persistence", column)? {
+            conn.execute(
+                &format!(
+                    "ALTER TABLE persistence ADD COLUMN {} {}",
+                    column, definition
+                ),
+                [],
+            )?;
+        }
+    }
+
     Ok(())
 }
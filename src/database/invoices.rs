@@ -0,0 +1,86 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::HashSet;
+
+use chrono::Local;
+use rusqlite::{params, Result};
+
+use crate::models::fur_invoice::FurInvoice;
+
+use super::init::open_connection;
+
+/// Record an invoice and mark the tasks it billed so they aren't billed again. Marking a task
+/// updates it, so the mark syncs with it.
+pub fn insert_invoice(invoice: &FurInvoice) -> Result<()> {
+    let mut conn = open_connection()?;
+    let now = Local::now().timestamp();
+
+    let tx = conn.transaction()?;
+    tx.execute(
+        "INSERT INTO invoices (
+            number,
+            project,
+            client,
+            first_day,
+            last_day,
+            currency,
+            total,
+            issued_at
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            invoice.number,
+            invoice.project,
+            invoice.client,
+            invoice.first_day.to_string(),
+            invoice.last_day.to_string(),
+            invoice.currency,
+            invoice.total,
+            now,
+        ],
+    )?;
+    {
+        let mut stmt =
+            tx.prepare("UPDATE tasks SET invoice_number = ?1, last_updated = ?2 WHERE uid = ?3")?;
+        for uid in &invoice.task_uids {
+            stmt.execute(params![invoice.number, now, uid])?;
+        }
+    }
+    tx.commit()
+}
+
+/// Whether an invoice with this number was issued here, or billed tasks synced or imported
+/// from another device
+pub fn invoice_exists(number: &str) -> Result<bool> {
+    let conn = open_connection()?;
+    let mut stmt = conn.prepare(
+        "SELECT 1 FROM invoices WHERE number = ?1
+        UNION ALL
+        SELECT 1 FROM tasks WHERE invoice_number = ?1
+        LIMIT 1",
+    )?;
+    stmt.exists(params![number])
+}
+
+/// Uids of every task an invoice has billed
+pub fn retrieve_invoiced_task_uids() -> Result<HashSet<String>> {
+    let conn = open_connection()?;
+    let mut stmt = conn.prepare("SELECT uid FROM tasks WHERE invoice_number != ''")?;
+    let uids = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<Result<HashSet<String>>>()?;
+    Ok(uids)
+}
@@ -0,0 +1,91 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use rusqlite::{params, Result};
+
+use super::init::open_connection;
+
+const SYNCED_TABLES: [&str; 3] = ["tasks", "shortcuts", "todos"];
+
+/// Mark every tombstone deleted at or before `timestamp` as received by the server,
+/// so full syncs no longer need to upload it.
+pub fn acknowledge_tombstones(timestamp: i64) -> Result<()> {
+    let mut conn = open_connection()?;
+    let tx = conn.transaction()?;
+
+    for table in SYNCED_TABLES {
+        tx.execute(
+            &format!(
+                "UPDATE {} SET tombstone_acked = 1
+                WHERE is_deleted = 1 AND tombstone_acked = 0 AND last_updated <= ?1",
+                table
+            ),
+            params![timestamp],
+        )?;
+    }
+
+    tx.commit()?;
+
+    Ok(())
+}
+
+/// Forget which tombstones the server has received (e.g. after logging in to a different account)
+pub fn reset_tombstone_acknowledgements() -> Result<()> {
+    let conn = open_connection()?;
+
+    for table in SYNCED_TABLES {
+        conn.execute(
+            &format!(
+                "UPDATE {} SET tombstone_acked = 0 WHERE tombstone_acked = 1",
+                table
+            ),
+            [],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Permanently delete tombstones last updated before `cutoff`.
+/// If `acknowledged_only` is true, tombstones the server hasn't received are kept.
+/// Returns the number of rows removed.
+pub fn purge_tombstones(cutoff: i64, acknowledged_only: bool) -> Result<usize> {
+    let mut conn = open_connection()?;
+    let tx = conn.transaction()?;
+    let mut purged = 0;
+
+    for table in SYNCED_TABLES {
+        purged += tx.execute(
+            &format!(
+                "DELETE FROM {} WHERE is_deleted = 1 AND last_updated < ?1
+                AND (tombstone_acked = 1 OR ?2 = 0)",
+                table
+            ),
+            params![cutoff, acknowledged_only],
+        )?;
+    }
+
+    tx.commit()?;
+
+    Ok(purged)
+}
+
+/// Rebuild the database file to reclaim space left by deleted rows
+pub fn vacuum() -> Result<()> {
+    let conn = open_connection()?;
+    conn.execute("VACUUM", [])?;
+    Ok(())
+}
@@ -14,16 +14,17 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use rusqlite::{params, Connection, Result};
+use chrono::{DateTime, Local};
+use rusqlite::{params, Result, TransactionBehavior};
 
-use crate::models::fur_persist::FurPersist;
+use crate::models::{fur_persist::FurPersist, fur_pomodoro::FurPomodoro};
 
-use super::init::get_directory;
+use super::init::open_connection;
 
 pub fn retrieve_persisting_timer() -> Result<FurPersist, rusqlite::Error> {
-    let conn = Connection::open(get_directory())?;
+    let conn = open_connection()?;
 
-    let mut stmt = conn.prepare("SELECT * FROM persistence")?;
+    let mut stmt = conn.prepare("SELECT * FROM persistence WHERE id = 1")?;
     let mut rows = stmt.query(params![])?;
 
     if let Some(row) = rows.next()? {
@@ -38,7 +39,7 @@ pub fn retrieve_persisting_timer() -> Result<FurPersist, rusqlite::Error> {
 }
 
 pub fn update_persisting_timer(persisting_timer: &FurPersist) -> Result<()> {
-    let conn = Connection::open(get_directory())?;
+    let conn = open_connection()?;
 
     conn.execute(
         "UPDATE persistence SET
@@ -56,8 +57,29 @@ pub fn update_persisting_timer(persisting_timer: &FurPersist) -> Result<()> {
     Ok(())
 }
 
+/// Mark the saved timer stopped if it is still the one started at `start_time`.
+/// Returns false if it was already stopped or restarted, e.g. from the CLI.
+pub fn stop_persisting_timer_started_at(start_time: DateTime<Local>) -> Result<bool> {
+    let mut conn = open_connection()?;
+    // Take the write lock before reading so two processes can't both stop the same timer
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+    let (is_running, saved_start_time): (bool, DateTime<Local>) = tx.query_row(
+        "SELECT is_running, start_time FROM persistence WHERE id = 1",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    if !is_running || saved_start_time != start_time {
+        return Ok(false);
+    }
+    tx.execute("UPDATE persistence SET is_running = 0 WHERE id = 1", [])?;
+    tx.commit()?;
+
+    Ok(true)
+}
+
 pub fn update_persisting_timer_task_input(task_input: &str) -> Result<()> {
-    let conn = Connection::open(get_directory())?;
+    let conn = open_connection()?;
 
     conn.execute(
         "UPDATE persistence SET
@@ -68,3 +90,76 @@ pub fn update_persisting_timer_task_input(task_input: &str) -> Result<()> {
 
     Ok(())
 }
+
+pub fn retrieve_pomodoro() -> Result<FurPomodoro> {
+    let conn = open_connection()?;
+
+    conn.query_row(
+        "SELECT
+            pomodoro_on_break,
+            pomodoro_sessions,
+            pomodoro_snoozed,
+            pomodoro_snoozed_at,
+            pomodoro_end_announced
+        FROM persistence WHERE id = 1",
+        [],
+        |row| {
+            Ok(FurPomodoro {
+                on_break: row.get(0)?,
+                sessions: row.get(1)?,
+                snoozed: row.get(2)?,
+                snoozed_at: row
+                    .get::<_, Option<DateTime<Local>>>(3)?
+                    .unwrap_or_else(Local::now),
+                end_announced: row.get(4)?,
+            })
+        },
+    )
+}
+
+/// Save the timer and pomodoro together so other processes never see one without the other
+pub fn update_persisting_timer_and_pomodoro(
+    persisting_timer: &FurPersist,
+    pomodoro: &FurPomodoro,
+) -> Result<()> {
+    let conn = open_connection()?;
+
+    conn.execute(
+        "UPDATE persistence SET
+            is_running = ?1,
+            task_input = ?2,
+            start_time = ?3,
+            pomodoro_on_break = ?4,
+            pomodoro_sessions = ?5,
+            pomodoro_snoozed = ?6,
+            pomodoro_snoozed_at = ?7,
+            pomodoro_end_announced = ?8
+        WHERE id = 1",
+        params![
+            persisting_timer.is_running,
+            persisting_timer.task_input,
+            persisting_timer.start_time,
+            pomodoro.on_break,
+            pomodoro.sessions,
+            pomodoro.snoozed,
+            pomodoro.snoozed_at,
+            pomodoro.end_announced,
+        ],
+    )?;
+
+    Ok(())
+}
+
+/// Mark the current pomodoro countdown as announced.
+/// Returns false if another process already did.
+pub fn claim_pomodoro_end() -> Result<bool> {
+    let conn = open_connection()?;
+
+    let changed = conn.execute(
+        "UPDATE persistence SET pomodoro_end_announced = 1
+        WHERE id = 1 AND pomodoro_end_announced = 0",
+        [],
+    )?;
+
+    Ok(changed == 1)
+}
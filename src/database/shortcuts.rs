@@ -16,13 +16,18 @@
 
 use rusqlite::{params, Connection, Result};
 
-use crate::models::fur_shortcut::FurShortcut;
+use crate::models::{fur_shortcut::FurShortcut, fur_sync_progress::SyncWatermark};
 
-use super::init::get_directory;
+use super::init::open_connection;
 
 /// Insert a shortcut to the database
 pub fn insert_shortcut(shortcut: &FurShortcut) -> Result<()> {
-    let conn = Connection::open(get_directory())?;
+    let conn = open_connection()?;
+    insert_shortcut_with(&conn, shortcut)
+}
+
+/// Same as insert_shortcut, on a connection the caller already holds
+pub fn insert_shortcut_with(conn: &Connection, shortcut: &FurShortcut) -> Result<()> {
     conn.execute(
         "INSERT INTO shortcuts (
             name,
@@ -53,7 +58,7 @@ pub fn insert_shortcut(shortcut: &FurShortcut) -> Result<()> {
 
 /// Retrieve all shortcuts from the database
 pub fn retrieve_all_shortcuts() -> Result<Vec<FurShortcut>, rusqlite::Error> {
-    let conn = Connection::open(get_directory())?;
+    let conn = open_connection()?;
 
     let mut stmt = conn.prepare("SELECT * FROM shortcuts ORDER BY name")?;
     let mut rows = stmt.query(params![])?;
@@ -80,7 +85,7 @@ pub fn retrieve_all_shortcuts() -> Result<Vec<FurShortcut>, rusqlite::Error> {
 
 /// Retrieve all existing (not deleted) shortcuts from the database
 pub fn retrieve_existing_shortcuts() -> Result<Vec<FurShortcut>, rusqlite::Error> {
-    let conn = Connection::open(get_directory())?;
+    let conn = open_connection()?;
 
     let mut stmt = conn.prepare("SELECT * FROM shortcuts WHERE is_deleted = 0 ORDER BY name")?;
     let mut rows = stmt.query(params![])?;
@@ -105,14 +110,31 @@ pub fn retrieve_existing_shortcuts() -> Result<Vec<FurShortcut>, rusqlite::Error
     Ok(shortcuts)
 }
 
-pub fn retrieve_shortcuts_since_timestamp(
-    timestamp: i64,
+/// Retrieve the next page of shortcuts to upload, ordered by last_updated and uid after the watermark.
+/// A full sync skips tombstones the server already has.
+pub fn retrieve_shortcuts_for_sync(
+    since: i64,
+    full_sync: bool,
+    after: &SyncWatermark,
+    limit: usize,
 ) -> Result<Vec<FurShortcut>, rusqlite::Error> {
-    let conn = Connection::open(get_directory())?;
-
-    let mut stmt =
-        conn.prepare("SELECT * FROM shortcuts WHERE last_updated >= ? ORDER BY last_updated ASC")?;
-    let mut rows = stmt.query(params![timestamp])?;
+    let conn = open_connection()?;
+
+    let mut stmt = conn.prepare(
+        "SELECT * FROM shortcuts
+        WHERE last_updated >= ?1
+        AND (?2 = 0 OR is_deleted = 0 OR tombstone_acked = 0)
+        AND (last_updated > ?3 OR (last_updated = ?3 AND uid > ?4))
+        ORDER BY last_updated ASC, uid ASC
+        LIMIT ?5",
+    )?;
+    let mut rows = stmt.query(params![
+        since,
+        full_sync,
+        after.last_updated,
+        after.uid,
+        limit as i64
+    ])?;
 
     let mut shortcuts_vec: Vec<FurShortcut> = Vec::new();
 
@@ -135,7 +157,7 @@ pub fn retrieve_shortcuts_since_timestamp(
 }
 
 pub fn retrieve_orphaned_shortcuts(shortcut_uids: Vec<String>) -> Result<Vec<FurShortcut>> {
-    let mut conn = Connection::open(get_directory())?;
+    let mut conn = open_connection()?;
     let mut shortcuts = Vec::new();
 
     let tx = conn.transaction()?;
@@ -169,8 +191,12 @@ pub fn retrieve_orphaned_shortcuts(shortcut_uids: Vec<String>) -> Result<Vec<Fur
 }
 
 pub fn update_shortcut(shortcut: &FurShortcut) -> Result<()> {
-    let conn = Connection::open(get_directory())?;
+    let conn = open_connection()?;
+    update_shortcut_with(&conn, shortcut)
+}
 
+/// Same as update_shortcut, on a connection the caller already holds
+pub fn update_shortcut_with(conn: &Connection, shortcut: &FurShortcut) -> Result<()> {
     conn.execute(
         "UPDATE shortcuts SET
             name = (?1),
@@ -199,7 +225,7 @@ pub fn update_shortcut(shortcut: &FurShortcut) -> Result<()> {
 }
 
 pub fn shortcut_exists(shortcut: &FurShortcut) -> Result<bool> {
-    let conn = Connection::open(get_directory())?;
+    let conn = open_connection()?;
 
     let query = "
         SELECT 1 FROM shortcuts
@@ -226,7 +252,15 @@ pub fn shortcut_exists(shortcut: &FurShortcut) -> Result<bool> {
 }
 
 pub fn retrieve_shortcut_by_id(uid: &String) -> Result<Option<FurShortcut>> {
-    let conn = Connection::open(get_directory())?;
+    let conn = open_connection()?;
+    retrieve_shortcut_by_id_with(&conn, uid)
+}
+
+/// Same as retrieve_shortcut_by_id, on a connection the caller already holds
+pub fn retrieve_shortcut_by_id_with(
+    conn: &Connection,
+    uid: &String,
+) -> Result<Option<FurShortcut>> {
     let mut stmt = conn.prepare("SELECT * FROM shortcuts WHERE uid = ?")?;
     let mut rows = stmt.query_map([uid.to_string()], |row| {
         Ok(FurShortcut {
@@ -250,7 +284,7 @@ pub fn retrieve_shortcut_by_id(uid: &String) -> Result<Option<FurShortcut>> {
 }
 
 pub fn delete_shortcut_by_id(uid: &str) -> Result<()> {
-    let conn = Connection::open(get_directory())?;
+    let conn = open_connection()?;
     let now = chrono::Utc::now().timestamp();
 
     conn.execute(
@@ -16,12 +16,20 @@
 
 use rusqlite::{params, Connection, Result};
 
-use crate::models::fur_user::FurUser;
+use crate::{
+    models::{
+        fur_key_rotation::FurKeyRotation,
+        fur_sync_log::{FurSyncLogEntry, RecordCounts},
+        fur_sync_progress::{FurSyncProgress, SyncWatermark},
+        fur_user::FurUser,
+    },
+    server::key_storage::KeyStorageKind,
+};
 
-use super::init::get_directory;
+use super::init::open_connection;
 
 pub fn retrieve_credentials() -> Result<Option<FurUser>> {
-    let conn = Connection::open(get_directory())?;
+    let conn = open_connection()?;
 
     let mut stmt = conn.prepare("SELECT * FROM user LIMIT 1")?;
 
@@ -33,6 +41,11 @@ pub fn retrieve_credentials() -> Result<Option<FurUser>> {
             access_token: row.get(3)?,
             refresh_token: row.get(4)?,
             server: row.get(5)?,
+            key_generation: row.get(6)?,
+            key_storage: KeyStorageKind::from_sqlite(
+                &row.get::<_, Option<String>>(7)?.unwrap_or_default(),
+            ),
+            key_storage_salt: row.get(8)?,
         })
     });
 
@@ -43,27 +56,31 @@ pub fn retrieve_credentials() -> Result<Option<FurUser>> {
     }
 }
 
-pub fn store_credentials(
-    email: &str,
-    encrypted_key: &str,
-    key_nonce: &str,
-    access_token: &str,
-    refresh_token: &str,
-    server: &str,
-) -> Result<()> {
-    let conn = Connection::open(get_directory())?;
+pub fn store_credentials(user: &FurUser) -> Result<()> {
+    let conn = open_connection()?;
 
     conn.execute(
-        "INSERT OR REPLACE INTO user
-        (email, encrypted_key, key_nonce, access_token, refresh_token, server)
-        VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-        params![
+        "INSERT OR REPLACE INTO user (
             email,
             encrypted_key,
             key_nonce,
             access_token,
             refresh_token,
-            server
+            server,
+            key_generation,
+            key_storage,
+            key_storage_salt
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            user.email,
+            user.encrypted_key,
+            user.key_nonce,
+            user.access_token,
+            user.refresh_token,
+            user.server,
+            user.key_generation,
+            user.key_storage.to_sqlite(),
+            user.key_storage_salt,
         ],
     )?;
 
@@ -71,7 +88,7 @@ pub fn store_credentials(
 }
 
 pub fn update_access_token(email: &str, new_token: &str) -> Result<()> {
-    let conn = Connection::open(get_directory())?;
+    let conn = open_connection()?;
 
     conn.execute(
         "UPDATE user
@@ -83,10 +100,312 @@ pub fn update_access_token(email: &str, new_token: &str) -> Result<()> {
     Ok(())
 }
 
+pub fn update_encryption_key(
+    email: &str,
+    encrypted_key: &str,
+    key_nonce: &str,
+    key_generation: i64,
+) -> Result<()> {
+    let conn = open_connection()?;
+
+    conn.execute(
+        "UPDATE user
+         SET encrypted_key = ?1, key_nonce = ?2, key_generation = ?3
+         WHERE email = ?4",
+        params![encrypted_key, key_nonce, key_generation, email],
+    )?;
+
+    Ok(())
+}
+
+/// Store the sync key after it has been re-wrapped by a different key storage
+/// Store a re-wrapped sync key, and the re-wrapped key of any rotation in progress,
+/// in one transaction so the two can't end up wrapped differently
+pub fn update_key_storage(
+    email: &str,
+    encrypted_key: &str,
+    key_nonce: &str,
+    key_storage: &KeyStorageKind,
+    key_storage_salt: Option<&str>,
+    rotation: Option<&FurKeyRotation>,
+) -> Result<()> {
+    let mut conn = open_connection()?;
+    let tx = conn.transaction()?;
+
+    tx.execute(
+        "UPDATE user
+         SET encrypted_key = ?1, key_nonce = ?2, key_storage = ?3, key_storage_salt = ?4
+         WHERE email = ?5",
+        params![
+            encrypted_key,
+            key_nonce,
+            key_storage.to_sqlite(),
+            key_storage_salt,
+            email
+        ],
+    )?;
+    if let Some(rotation) = rotation {
+        write_key_rotation(&tx, rotation)?;
+    }
+
+    tx.commit()?;
+
+    Ok(())
+}
+
 pub fn delete_all_credentials() -> Result<()> {
-    let conn = Connection::open(get_directory())?;
+    let conn = open_connection()?;
 
     conn.execute("DELETE FROM user", [])?;
 
     Ok(())
 }
+
+pub fn retrieve_sync_progress() -> Result<Option<FurSyncProgress>> {
+    let conn = open_connection()?;
+
+    let mut stmt = conn.prepare("SELECT * FROM sync_progress WHERE id = 1")?;
+
+    let result = stmt.query_row([], |row| {
+        Ok(FurSyncProgress {
+            full_sync: row.get(1)?,
+            started_at: row.get(2)?,
+            last_sync: row.get(3)?,
+            download_since: row.get(4)?,
+            download_started_at: row.get(5)?,
+            download_cursor: row.get(6)?,
+            tasks_uploaded_through: SyncWatermark {
+                last_updated: row.get(7)?,
+                uid: row.get(8)?,
+            },
+            shortcuts_uploaded_through: SyncWatermark {
+                last_updated: row.get(9)?,
+                uid: row.get(10)?,
+            },
+            todos_uploaded_through: SyncWatermark {
+                last_updated: row.get(11)?,
+                uid: row.get(12)?,
+            },
+        })
+    });
+
+    match result {
+        Ok(progress) => Ok(Some(progress)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+pub fn store_sync_progress(progress: &FurSyncProgress) -> Result<()> {
+    let conn = open_connection()?;
+
+    conn.execute(
+        "INSERT OR REPLACE INTO sync_progress (
+            id,
+            full_sync,
+            started_at,
+            last_sync,
+            download_since,
+            download_started_at,
+            download_cursor,
+            tasks_through_updated,
+            tasks_through_uid,
+            shortcuts_through_updated,
+            shortcuts_through_uid,
+            todos_through_updated,
+            todos_through_uid
+        ) VALUES (1, ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        params![
+            progress.full_sync,
+            progress.started_at,
+            progress.last_sync,
+            progress.download_since,
+            progress.download_started_at,
+            progress.download_cursor,
+            progress.tasks_uploaded_through.last_updated,
+            progress.tasks_uploaded_through.uid,
+            progress.shortcuts_uploaded_through.last_updated,
+            progress.shortcuts_uploaded_through.uid,
+            progress.todos_uploaded_through.last_updated,
+            progress.todos_uploaded_through.uid,
+        ],
+    )?;
+
+    Ok(())
+}
+
+pub fn delete_sync_progress() -> Result<()> {
+    let conn = open_connection()?;
+
+    conn.execute("DELETE FROM sync_progress", [])?;
+
+    Ok(())
+}
+
+/// Store a sync log entry, keeping only the newest `keep` entries
+pub fn insert_sync_log_entry(entry: &FurSyncLogEntry, keep: usize) -> Result<()> {
+    let conn = open_connection()?;
+
+    conn.execute(
+        "INSERT INTO sync_log (
+            started_at,
+            duration_ms,
+            full_sync,
+            direction,
+            tasks_uploaded,
+            shortcuts_uploaded,
+            todos_uploaded,
+            tasks_downloaded,
+            shortcuts_downloaded,
+            todos_downloaded,
+            orphans_resent,
+            decrypt_failures,
+            error
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+        params![
+            entry.started_at,
+            entry.duration_ms,
+            entry.full_sync,
+            entry.direction().to_sqlite(),
+            entry.uploaded.tasks,
+            entry.uploaded.shortcuts,
+            entry.uploaded.todos,
+            entry.downloaded.tasks,
+            entry.downloaded.shortcuts,
+            entry.downloaded.todos,
+            entry.orphans_resent,
+            entry.decrypt_failures.join(","),
+            entry.error,
+        ],
+    )?;
+
+    conn.execute(
+        "DELETE FROM sync_log WHERE id NOT IN (SELECT id FROM sync_log ORDER BY id DESC LIMIT ?1)",
+        params![keep as i64],
+    )?;
+
+    Ok(())
+}
+
+/// Sync log entries, newest first
+pub fn retrieve_sync_log() -> Result<Vec<FurSyncLogEntry>> {
+    let conn = open_connection()?;
+
+    let mut stmt = conn.prepare("SELECT * FROM sync_log ORDER BY id DESC")?;
+    let mut rows = stmt.query([])?;
+
+    let mut entries: Vec<FurSyncLogEntry> = Vec::new();
+    while let Some(row) = rows.next()? {
+        let decrypt_failures: String = row.get(12)?;
+        entries.push(FurSyncLogEntry {
+            started_at: row.get(1)?,
+            duration_ms: row.get(2)?,
+            full_sync: row.get(3)?,
+            uploaded: RecordCounts {
+                tasks: row.get(5)?,
+                shortcuts: row.get(6)?,
+                todos: row.get(7)?,
+            },
+            downloaded: RecordCounts {
+                tasks: row.get(8)?,
+                shortcuts: row.get(9)?,
+                todos: row.get(10)?,
+            },
+            orphans_resent: row.get(11)?,
+            decrypt_failures: decrypt_failures
+                .split(',')
+                .filter(|uid| !uid.is_empty())
+                .map(|uid| uid.to_string())
+                .collect(),
+            error: row.get(13)?,
+        });
+    }
+
+    Ok(entries)
+}
+
+pub fn delete_sync_log() -> Result<()> {
+    let conn = open_connection()?;
+
+    conn.execute("DELETE FROM sync_log", [])?;
+
+    Ok(())
+}
+
+pub fn retrieve_key_rotation() -> Result<Option<FurKeyRotation>> {
+    let conn = open_connection()?;
+
+    let mut stmt = conn.prepare("SELECT * FROM key_rotation WHERE id = 1")?;
+
+    let result = stmt.query_row([], |row| {
+        Ok(FurKeyRotation {
+            encrypted_key: row.get(1)?,
+            key_nonce: row.get(2)?,
+            key_generation: row.get(3)?,
+            uploaded_count: row.get(4)?,
+            tasks_uploaded_through: SyncWatermark {
+                last_updated: row.get(5)?,
+                uid: row.get(6)?,
+            },
+            shortcuts_uploaded_through: SyncWatermark {
+                last_updated: row.get(7)?,
+                uid: row.get(8)?,
+            },
+            todos_uploaded_through: SyncWatermark {
+                last_updated: row.get(9)?,
+                uid: row.get(10)?,
+            },
+        })
+    });
+
+    match result {
+        Ok(rotation) => Ok(Some(rotation)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+pub fn store_key_rotation(rotation: &FurKeyRotation) -> Result<()> {
+    let conn = open_connection()?;
+    write_key_rotation(&conn, rotation)
+}
+
+fn write_key_rotation(conn: &Connection, rotation: &FurKeyRotation) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO key_rotation (
+            id,
+            encrypted_key,
+            key_nonce,
+            key_generation,
+            uploaded_count,
+            tasks_through_updated,
+            tasks_through_uid,
+            shortcuts_through_updated,
+            shortcuts_through_uid,
+            todos_through_updated,
+            todos_through_uid
+        ) VALUES (1, ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![
+            rotation.encrypted_key,
+            rotation.key_nonce,
+            rotation.key_generation,
+            rotation.uploaded_count,
+            rotation.tasks_uploaded_through.last_updated,
+            rotation.tasks_uploaded_through.uid,
+            rotation.shortcuts_uploaded_through.last_updated,
+            rotation.shortcuts_uploaded_through.uid,
+            rotation.todos_uploaded_through.last_updated,
+            rotation.todos_uploaded_through.uid,
+        ],
+    )?;
+
+    Ok(())
+}
+
+pub fn delete_key_rotation() -> Result<()> {
+    let conn = open_connection()?;
+
+    conn.execute("DELETE FROM key_rotation", [])?;
+
+    Ok(())
+}
@@ -16,9 +16,12 @@
 
 use rusqlite::{params, Connection, Result};
 
-use crate::models::{fur_task::FurTask, fur_task_group::FurTaskGroup};
+use crate::{
+    helpers::uid::generate_uid,
+    models::{fur_sync_progress::SyncWatermark, fur_task::FurTask, fur_task_group::FurTaskGroup},
+};
 
-use super::init::get_directory;
+use super::init::open_connection;
 
 #[derive(Debug)]
 pub enum SortOrder {
@@ -66,8 +69,12 @@ impl SortBy {
 }
 
 pub fn insert_task(task: &FurTask) -> Result<()> {
-    let conn = Connection::open(get_directory())?;
+    let conn = open_connection()?;
+    insert_task_with(&conn, task)
+}
 
+/// Same as insert_task, on a connection the caller already holds
+pub fn insert_task_with(conn: &Connection, task: &FurTask) -> Result<()> {
     conn.execute(
         "INSERT INTO tasks (
             task_name,
@@ -79,8 +86,9 @@ pub fn insert_task(task: &FurTask) -> Result<()> {
             currency,
             uid,
             is_deleted,
-            last_updated
-        ) values (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            last_updated,
+            invoice_number
+        ) values (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
         params![
             task.name,
             task.start_time.to_rfc3339(),
@@ -91,19 +99,23 @@ pub fn insert_task(task: &FurTask) -> Result<()> {
             task.currency,
             task.uid,
             task.is_deleted,
-            task.last_updated
+            task.last_updated,
+            task.invoice_number
         ],
     )?;
 
     Ok(())
 }
 
+/// Insert a batch of imported tasks.
+/// A task whose uid is already used by another row is given a new uid so it can't collide with it.
 pub fn insert_tasks(tasks: &[FurTask]) -> Result<()> {
-    let mut conn = Connection::open(get_directory())?;
+    let mut conn = open_connection()?;
 
     let tx = conn.transaction()?;
 
     {
+        let mut uid_stmt = tx.prepare("SELECT 1 FROM tasks WHERE uid = ?1 LIMIT 1")?;
         let mut stmt = tx.prepare(
             "INSERT INTO tasks (
                 task_name,
@@ -115,11 +127,18 @@ pub fn insert_tasks(tasks: &[FurTask]) -> Result<()> {
                 currency,
                 uid,
                 is_deleted,
-                last_updated
-            ) values (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                last_updated,
+                invoice_number
+            ) values (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
         )?;
 
         for task in tasks {
+            let uid = if uid_stmt.exists(params![task.uid])? {
+                generate_uid()
+            } else {
+                task.uid.clone()
+            };
+
             stmt.execute(params![
                 task.name,
                 task.start_time.to_rfc3339(),
@@ -128,9 +147,10 @@ pub fn insert_tasks(tasks: &[FurTask]) -> Result<()> {
                 task.project,
                 task.rate,
                 task.currency,
-                task.uid,
+                uid,
                 task.is_deleted,
-                task.last_updated
+                task.last_updated,
+                task.invoice_number
             ])?;
         }
     }
@@ -142,7 +162,7 @@ pub fn insert_tasks(tasks: &[FurTask]) -> Result<()> {
 
 pub fn retrieve_all_tasks(sort: SortBy, order: SortOrder) -> Result<Vec<FurTask>, rusqlite::Error> {
     // Retrieve all tasks from the database
-    let conn = Connection::open(get_directory())?;
+    let conn = open_connection()?;
 
     let mut stmt = conn.prepare(
         format!(
@@ -168,6 +188,7 @@ pub fn retrieve_all_tasks(sort: SortBy, order: SortOrder) -> Result<Vec<FurTask>
             uid: row.get(8)?,
             is_deleted: row.get(9)?,
             last_updated: row.get(10)?,
+            invoice_number: row.get(12)?,
         };
         tasks_vec.push(fur_task);
     }
@@ -180,7 +201,7 @@ pub fn retrieve_all_existing_tasks(
     order: SortOrder,
 ) -> Result<Vec<FurTask>, rusqlite::Error> {
     // Retrieve all tasks from the database
-    let conn = Connection::open(get_directory())?;
+    let conn = open_connection()?;
 
     let mut stmt = conn.prepare(
         format!(
@@ -206,6 +227,7 @@ pub fn retrieve_all_existing_tasks(
             uid: row.get(8)?,
             is_deleted: row.get(9)?,
             last_updated: row.get(10)?,
+            invoice_number: row.get(12)?,
         };
         tasks_vec.push(fur_task);
     }
@@ -214,7 +236,7 @@ pub fn retrieve_all_existing_tasks(
 }
 
 pub fn retrieve_tasks_by_date_range(start_date: String, end_date: String) -> Result<Vec<FurTask>> {
-    let conn = Connection::open(get_directory())?;
+    let conn = open_connection()?;
     let mut stmt = conn.prepare(
         "SELECT * FROM tasks WHERE start_time BETWEEN ?1 AND ?2 AND is_deleted = 0 ORDER BY start_time ASC",
     )?;
@@ -234,6 +256,7 @@ pub fn retrieve_tasks_by_date_range(start_date: String, end_date: String) -> Res
             uid: row.get(8)?,
             is_deleted: row.get(9)?,
             last_updated: row.get(10)?,
+            invoice_number: row.get(12)?,
         };
         tasks_vec.push(fur_task);
     }
@@ -247,7 +270,7 @@ pub fn retrieve_tasks_with_day_limit(
     sort: SortBy,
     order: SortOrder,
 ) -> Result<Vec<FurTask>> {
-    let conn = Connection::open(get_directory())?;
+    let conn = open_connection()?;
 
     // Construct the query string dynamically
     let query = format!(
@@ -273,6 +296,7 @@ pub fn retrieve_tasks_with_day_limit(
             uid: row.get(8)?,
             is_deleted: row.get(9)?,
             last_updated: row.get(10)?,
+            invoice_number: row.get(12)?,
         };
         tasks_vec.push(fur_task);
     }
@@ -281,7 +305,12 @@ pub fn retrieve_tasks_with_day_limit(
 }
 
 pub fn retrieve_task_by_id(uid: &String) -> Result<Option<FurTask>> {
-    let conn = Connection::open(get_directory())?;
+    let conn = open_connection()?;
+    retrieve_task_by_id_with(&conn, uid)
+}
+
+/// Same as retrieve_task_by_id, on a connection the caller already holds
+pub fn retrieve_task_by_id_with(conn: &Connection, uid: &String) -> Result<Option<FurTask>> {
     let mut stmt = conn.prepare("SELECT * FROM tasks WHERE uid = ?")?;
     let mut rows = stmt.query_map([uid.to_string()], |row| {
         Ok(FurTask {
@@ -295,6 +324,7 @@ pub fn retrieve_task_by_id(uid: &String) -> Result<Option<FurTask>> {
             uid: row.get(8)?,
             is_deleted: row.get(9)?,
             last_updated: row.get(10)?,
+            invoice_number: row.get(12)?,
         })
     })?;
 
@@ -305,12 +335,31 @@ pub fn retrieve_task_by_id(uid: &String) -> Result<Option<FurTask>> {
     }
 }
 
-pub fn retrieve_tasks_since_timestamp(timestamp: i64) -> Result<Vec<FurTask>, rusqlite::Error> {
-    let conn = Connection::open(get_directory())?;
+/// Retrieve the next page of tasks to upload, ordered by last_updated and uid after the watermark.
+/// A full sync skips tombstones the server already has.
+pub fn retrieve_tasks_for_sync(
+    since: i64,
+    full_sync: bool,
+    after: &SyncWatermark,
+    limit: usize,
+) -> Result<Vec<FurTask>, rusqlite::Error> {
+    let conn = open_connection()?;
 
-    let mut stmt =
-        conn.prepare("SELECT * FROM tasks WHERE last_updated >= ? ORDER BY last_updated ASC")?;
-    let mut rows = stmt.query(params![timestamp])?;
+    let mut stmt = conn.prepare(
+        "SELECT * FROM tasks
+        WHERE last_updated >= ?1
+        AND (?2 = 0 OR is_deleted = 0 OR tombstone_acked = 0)
+        AND (last_updated > ?3 OR (last_updated = ?3 AND uid > ?4))
+        ORDER BY last_updated ASC, uid ASC
+        LIMIT ?5",
+    )?;
+    let mut rows = stmt.query(params![
+        since,
+        full_sync,
+        after.last_updated,
+        after.uid,
+        limit as i64
+    ])?;
 
     let mut tasks_vec: Vec<FurTask> = Vec::new();
 
@@ -326,6 +375,7 @@ pub fn retrieve_tasks_since_timestamp(timestamp: i64) -> Result<Vec<FurTask>, ru
             uid: row.get(8)?,
             is_deleted: row.get(9)?,
             last_updated: row.get(10)?,
+            invoice_number: row.get(12)?,
         };
         tasks_vec.push(fur_task);
     }
@@ -334,7 +384,7 @@ pub fn retrieve_tasks_since_timestamp(timestamp: i64) -> Result<Vec<FurTask>, ru
 }
 
 pub fn retrieve_orphaned_tasks(task_uids: Vec<String>) -> Result<Vec<FurTask>> {
-    let mut conn = Connection::open(get_directory())?;
+    let mut conn = open_connection()?;
     let mut tasks = Vec::new();
 
     let tx = conn.transaction()?;
@@ -354,6 +404,7 @@ pub fn retrieve_orphaned_tasks(task_uids: Vec<String>) -> Result<Vec<FurTask>> {
                     uid: row.get(8)?,
                     is_deleted: row.get(9)?,
                     last_updated: row.get(10)?,
+                    invoice_number: row.get(12)?,
                 })
             })?;
 
@@ -369,8 +420,12 @@ pub fn retrieve_orphaned_tasks(task_uids: Vec<String>) -> Result<Vec<FurTask>> {
 }
 
 pub fn update_task(task: &FurTask) -> Result<()> {
-    let conn = Connection::open(get_directory())?;
+    let conn = open_connection()?;
+    update_task_with(&conn, task)
+}
 
+/// Same as update_task, on a connection the caller already holds
+pub fn update_task_with(conn: &Connection, task: &FurTask) -> Result<()> {
     conn.execute(
         "UPDATE tasks SET
             task_name = ?1,
@@ -381,8 +436,9 @@ pub fn update_task(task: &FurTask) -> Result<()> {
             rate = ?6,
             currency = ?7,
             is_deleted = ?8,
-            last_updated = ?9
-        WHERE uid = ?10",
+            last_updated = ?9,
+            invoice_number = ?10
+        WHERE uid = ?11",
         params![
             task.name,
             task.start_time.to_rfc3339(),
@@ -393,6 +449,7 @@ pub fn update_task(task: &FurTask) -> Result<()> {
             task.currency,
             task.is_deleted,
             task.last_updated,
+            task.invoice_number,
             task.uid,
         ],
     )?;
@@ -401,7 +458,7 @@ pub fn update_task(task: &FurTask) -> Result<()> {
 }
 
 pub fn task_exists(task: &FurTask) -> Result<bool> {
-    let conn = Connection::open(get_directory())?;
+    let conn = open_connection()?;
 
     let query = "
         SELECT 1 FROM tasks
@@ -433,7 +490,7 @@ pub fn task_exists(task: &FurTask) -> Result<bool> {
 }
 
 pub fn delete_tasks_by_ids(id_list: &[String]) -> Result<()> {
-    let conn = Connection::open(get_directory())?;
+    let conn = open_connection()?;
     let now = chrono::Utc::now().timestamp();
 
     for id in id_list {
@@ -447,7 +504,7 @@ pub fn delete_tasks_by_ids(id_list: &[String]) -> Result<()> {
 }
 
 pub fn update_group_of_tasks(group: &FurTaskGroup) -> Result<()> {
-    let mut conn = Connection::open(get_directory())?;
+    let mut conn = open_connection()?;
     let tx = conn.transaction()?;
 
     {
@@ -16,12 +16,12 @@
 
 use rusqlite::{params, Connection, Result};
 
-use crate::models::fur_todo::FurTodo;
+use crate::models::{fur_sync_progress::SyncWatermark, fur_todo::FurTodo};
 
-use super::init::get_directory;
+use super::init::open_connection;
 
 pub fn retrieve_all_todos() -> Result<Vec<FurTodo>, rusqlite::Error> {
-    let conn = Connection::open(get_directory())?;
+    let conn = open_connection()?;
 
     let mut stmt = conn.prepare("SELECT * FROM todos ORDER BY name")?;
     let mut rows = stmt.query(params![])?;
@@ -48,7 +48,7 @@ pub fn retrieve_all_todos() -> Result<Vec<FurTodo>, rusqlite::Error> {
 }
 
 pub fn retrieve_todos_between_dates(start_date: String, end_date: String) -> Result<Vec<FurTodo>> {
-    let conn = Connection::open(get_directory())?;
+    let conn = open_connection()?;
 
     let mut stmt =
         conn.prepare("SELECT * FROM todos WHERE date BETWEEN ?1 AND ?2 AND is_deleted = 0")?;
@@ -76,12 +76,31 @@ pub fn retrieve_todos_between_dates(start_date: String, end_date: String) -> Res
     Ok(todo_vec)
 }
 
-pub fn retrieve_todos_since_timestamp(timestamp: i64) -> Result<Vec<FurTodo>, rusqlite::Error> {
-    let conn = Connection::open(get_directory())?;
-
-    let mut stmt =
-        conn.prepare("SELECT * FROM todos WHERE last_updated >= ? ORDER BY last_updated ASC")?;
-    let mut rows = stmt.query(params![timestamp])?;
+/// Retrieve the next page of todos to upload, ordered by last_updated and uid after the watermark.
+/// A full sync skips tombstones the server already has.
+pub fn retrieve_todos_for_sync(
+    since: i64,
+    full_sync: bool,
+    after: &SyncWatermark,
+    limit: usize,
+) -> Result<Vec<FurTodo>, rusqlite::Error> {
+    let conn = open_connection()?;
+
+    let mut stmt = conn.prepare(
+        "SELECT * FROM todos
+        WHERE last_updated >= ?1
+        AND (?2 = 0 OR is_deleted = 0 OR tombstone_acked = 0)
+        AND (last_updated > ?3 OR (last_updated = ?3 AND uid > ?4))
+        ORDER BY last_updated ASC, uid ASC
+        LIMIT ?5",
+    )?;
+    let mut rows = stmt.query(params![
+        since,
+        full_sync,
+        after.last_updated,
+        after.uid,
+        limit as i64
+    ])?;
 
     let mut todos: Vec<FurTodo> = Vec::new();
 
@@ -105,7 +124,12 @@ pub fn retrieve_todos_since_timestamp(timestamp: i64) -> Result<Vec<FurTodo>, ru
 }
 
 pub fn retrieve_todo_by_id(uid: &String) -> Result<Option<FurTodo>> {
-    let conn = Connection::open(get_directory())?;
+    let conn = open_connection()?;
+    retrieve_todo_by_id_with(&conn, uid)
+}
+
+/// Same as retrieve_todo_by_id, on a connection the caller already holds
+pub fn retrieve_todo_by_id_with(conn: &Connection, uid: &String) -> Result<Option<FurTodo>> {
     let mut stmt = conn.prepare("SELECT * FROM todos WHERE uid = ?")?;
     let mut rows = stmt.query_map([uid.to_string()], |row| {
         Ok(FurTodo {
@@ -130,7 +154,7 @@ pub fn retrieve_todo_by_id(uid: &String) -> Result<Option<FurTodo>> {
 }
 
 pub fn retrieve_orphaned_todos(todo_uids: Vec<String>) -> Result<Vec<FurTodo>> {
-    let mut conn = Connection::open(get_directory())?;
+    let mut conn = open_connection()?;
     let mut todos = Vec::new();
 
     let tx = conn.transaction()?;
@@ -166,8 +190,12 @@ pub fn retrieve_orphaned_todos(todo_uids: Vec<String>) -> Result<Vec<FurTodo>> {
 
 // TODO: Test if last_updated needs to be set to the current time here
 pub fn update_todo(todo: &FurTodo) -> Result<()> {
-    let conn = Connection::open(get_directory())?;
+    let conn = open_connection()?;
+    update_todo_with(&conn, todo)
+}
 
+/// Same as update_todo, on a connection the caller already holds
+pub fn update_todo_with(conn: &Connection, todo: &FurTodo) -> Result<()> {
     conn.execute(
         "UPDATE todos SET
             name = ?1,
@@ -198,8 +226,12 @@ pub fn update_todo(todo: &FurTodo) -> Result<()> {
 }
 
 pub fn insert_todo(todo: &FurTodo) -> Result<()> {
-    let conn = Connection::open(get_directory())?;
+    let conn = open_connection()?;
+    insert_todo_with(&conn, todo)
+}
 
+/// Same as insert_todo, on a connection the caller already holds
+pub fn insert_todo_with(conn: &Connection, todo: &FurTodo) -> Result<()> {
     conn.execute(
         "INSERT INTO todos (
             name,
@@ -231,7 +263,7 @@ pub fn insert_todo(todo: &FurTodo) -> Result<()> {
 }
 
 pub fn toggle_todo_completed(uid: &str) -> Result<()> {
-    let conn = Connection::open(get_directory())?;
+    let conn = open_connection()?;
     let now = chrono::Utc::now().timestamp();
 
     conn.execute(
@@ -246,7 +278,7 @@ pub fn toggle_todo_completed(uid: &str) -> Result<()> {
 }
 
 pub fn set_todo_completed(uid: &str) -> Result<()> {
-    let conn = Connection::open(get_directory())?;
+    let conn = open_connection()?;
     let now = chrono::Utc::now().timestamp();
 
     conn.execute(
@@ -261,7 +293,7 @@ pub fn set_todo_completed(uid: &str) -> Result<()> {
 }
 
 pub fn delete_todo_by_id(uid: &str) -> Result<()> {
-    let conn = Connection::open(get_directory())?;
+    let conn = open_connection()?;
     let now = chrono::Utc::now().timestamp();
 
     conn.execute(
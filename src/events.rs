@@ -0,0 +1,61 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::sync::LazyLock;
+
+use tokio::sync::broadcast;
+
+use crate::models::{
+    fur_persist::FurPersist, fur_settings::FurSettings, fur_sync_log::FurSyncLogEntry,
+    fur_task::FurTask, fur_todo::FurTodo,
+};
+
+/// Events sent to subscribers that buffer more than this many are dropped for them
+const EVENT_BUFFER: usize = 256;
+
+static EVENTS: LazyLock<broadcast::Sender<CoreEvent>> =
+    LazyLock::new(|| broadcast::channel(EVENT_BUFFER).0);
+
+/// Something that changed in the core, for front ends to react to
+#[derive(Clone, Debug)]
+pub enum CoreEvent {
+    TimerStarted(FurPersist),
+    /// The running timer was saved as a task
+    TimerStopped(FurTask),
+    /// The running timer was stopped without saving a task
+    TimerCancelled,
+    /// A pomodoro or its break counted down to zero
+    PomodoroEnded {
+        on_break: bool,
+    },
+    TasksChanged,
+    TodosChanged,
+    TodoCompleted(FurTodo),
+    ShortcutsChanged,
+    SettingsChanged(Box<FurSettings>),
+    SyncStarted,
+    /// A sync ended, successfully or not. The entry's error is set when it failed.
+    SyncFinished(FurSyncLogEntry),
+}
+
+pub fn subscribe() -> broadcast::Receiver<CoreEvent> {
+    EVENTS.subscribe()
+}
+
+pub fn emit(event: CoreEvent) {
+    // Sending only fails when nobody is listening, which is fine for a CLI
+    let _ = EVENTS.send(event);
+}
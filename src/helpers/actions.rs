@@ -18,37 +18,18 @@ use chrono::Local;
 use dioxus::signals::Readable;
 
 use crate::{
-    helpers::views::timer::{reset_timer, stop_timer},
+    helpers::views::timer::{cancel_timer, stop_timer},
     state, NavTab,
 };
 
-use super::{
-    server::sync::sync_after_change,
-    views::{task_history, timer},
-};
+use super::views::{alerts, timer};
 
 pub fn start_stop_pressed() {
     if state::TIMER_IS_RUNNING.cloned() {
-        let mut pomodoro = state::POMODORO.cloned();
-        // Do not move declarations to after if else
-        // They are needed in this position to properly initiate timer on reset
-        if pomodoro.on_break {
-            *state::TIMER_IS_RUNNING.write() = false;
-            pomodoro.on_break = false;
-            pomodoro.snoozed = false;
-            pomodoro.sessions = 0;
-            *state::POMODORO.write() = pomodoro.clone();
-            reset_timer();
-            task_history::update_task_history(state::SETTINGS.read().days_to_show);
-        } else {
-            pomodoro.on_break = false;
-            pomodoro.snoozed = false;
-            pomodoro.sessions = 0;
-            *state::POMODORO.write() = pomodoro.clone();
-            stop_timer(Local::now());
-            task_history::update_task_history(state::SETTINGS.read().days_to_show);
-
-            sync_after_change();
+        if state::POMODORO.read().on_break {
+            cancel_timer();
+        } else if let Err(e) = stop_timer(Local::now()) {
+            alerts::show_error(&e);
         }
     } else {
         timer::start_timer();
@@ -0,0 +1,88 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::sync::Once;
+
+use dioxus::{prelude::spawn_forever, signals::Readable};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::{
+    events::{self, CoreEvent},
+    helpers::{
+        server::{sync::sync_after_change, sync_log},
+        views::{shortcuts::update_all_shortcuts, task_history, todos::update_all_todos},
+    },
+    hooks, state,
+};
+
+static LISTEN_INIT: Once = Once::new();
+
+/// Keep the UI's signals in step with changes made through the core services and run the user's hooks.
+/// Timer events don't change signals because the timer helpers update the UI as they go.
+pub fn listen_for_core_events() {
+    LISTEN_INIT.call_once(|| {
+        let mut receiver = events::subscribe();
+        spawn_forever(async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => handle_event(event),
+                    // Some events were missed, so reload everything they could have changed
+                    Err(RecvError::Lagged(_)) => refresh_all(),
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+    });
+}
+
+fn handle_event(event: CoreEvent) {
+    // Hooks run on their own so a slow one doesn't hold up the UI
+    spawn_forever(hooks::run(event.clone(), state::SETTINGS.cloned()));
+
+    match event {
+        CoreEvent::TasksChanged => {
+            task_history::update_task_history(state::SETTINGS.read().days_to_show);
+            sync_after_change();
+        }
+        CoreEvent::TodosChanged => {
+            update_all_todos();
+            sync_after_change();
+        }
+        CoreEvent::ShortcutsChanged => {
+            update_all_shortcuts();
+            sync_after_change();
+        }
+        CoreEvent::SettingsChanged(settings) => *state::SETTINGS.write() = *settings,
+        CoreEvent::SyncFinished(entry) => {
+            *state::SYNC_LOG.write() = sync_log::get_sync_log();
+            if entry.error.is_none() {
+                refresh_all();
+            }
+        }
+        CoreEvent::TimerStarted(_)
+        | CoreEvent::TimerStopped(_)
+        | CoreEvent::TimerCancelled
+        | CoreEvent::PomodoroEnded { .. }
+        | CoreEvent::TodoCompleted(_)
+        | CoreEvent::SyncStarted => {}
+    }
+}
+
+fn refresh_all() {
+    update_all_todos();
+    task_history::update_task_history(state::SETTINGS.read().days_to_show);
+    update_all_shortcuts();
+}
@@ -15,14 +15,13 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use chrono::{Datelike, Local, NaiveDate, TimeDelta};
-use dioxus::signals::Readable;
 use itertools::Itertools;
 use regex::Regex;
 
-use crate::{loc, localization::Localization, state};
+use crate::{loc, localization::Localization};
 
-pub fn seconds_to_formatted_duration(total_seconds: i64) -> String {
-    if state::SETTINGS.cloned().show_seconds {
+pub fn seconds_to_formatted_duration(total_seconds: i64, show_seconds: bool) -> String {
+    if show_seconds {
         seconds_to_hms(total_seconds)
     } else {
         seconds_to_hm(total_seconds)
@@ -48,6 +47,15 @@ pub fn format_title_date(date: &NaiveDate) -> String {
     }
 }
 
+/// Text safe to place in HTML, inside elements or quoted attributes
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
 pub fn split_task_input(input: &str) -> (String, String, String, f32) {
     let re_name = Regex::new(r"^[^@#$]+").unwrap();
     let re_project = Regex::new(r"@([^#\$]+)").unwrap();
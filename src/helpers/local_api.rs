@@ -0,0 +1,93 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::sync::{Mutex, Once};
+
+use dioxus::{prelude::spawn_forever, signals::Readable};
+use furtherance_core::local_api::{self, TimerRequest};
+use log::error;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::{
+    helpers::{actions, views::timer},
+    state,
+};
+
+/// Stops the running local API server when sent to or dropped
+static LOCAL_API_SHUTDOWN: Mutex<Option<oneshot::Sender<()>>> = Mutex::new(None);
+static LOCAL_API_INIT: Once = Once::new();
+
+pub fn start_local_api() {
+    LOCAL_API_INIT.call_once(restart_local_api);
+}
+
+/// Stop the local API if it is running and start it again if it is enabled in settings
+pub fn restart_local_api() {
+    if let Ok(mut shutdown) = LOCAL_API_SHUTDOWN.lock() {
+        if let Some(shutdown) = shutdown.take() {
+            let _ = shutdown.send(());
+        }
+    }
+
+    let settings = state::SETTINGS.cloned();
+    if !settings.local_api_enabled || settings.local_api_token.is_empty() {
+        return;
+    }
+    let Ok(port) = u16::try_from(settings.local_api_port) else {
+        error!("Invalid local API port: {}", settings.local_api_port);
+        return;
+    };
+
+    let (shutdown_sender, shutdown_receiver) = oneshot::channel::<()>();
+    if let Ok(mut shutdown) = LOCAL_API_SHUTDOWN.lock() {
+        *shutdown = Some(shutdown_sender);
+    }
+    let (timer_requests, mut timer_request_receiver) = mpsc::unbounded_channel();
+
+    // Timer changes run here, on the UI's executor, so they can update its signals
+    spawn_forever(async move {
+        while let Some(request) = timer_request_receiver.recv().await {
+            handle_timer_request(request);
+        }
+    });
+    spawn_forever(async move {
+        let token = settings.local_api_token;
+        let shutdown = async {
+            let _ = shutdown_receiver.await;
+        };
+        if let Err(e) = local_api::serve(port, &token, Some(timer_requests), shutdown).await {
+            error!("Local API stopped: {}", e);
+        }
+    });
+}
+
+fn handle_timer_request(request: TimerRequest) {
+    match request {
+        TimerRequest::Start(task_input, done) => {
+            if !state::TIMER_IS_RUNNING.cloned() {
+                *state::TASK_INPUT.write() = task_input;
+                timer::start_timer();
+            }
+            let _ = done.send(());
+        }
+        TimerRequest::Stop(done) => {
+            if state::TIMER_IS_RUNNING.cloned() {
+                actions::start_stop_pressed();
+            }
+            let _ = done.send(());
+        }
+    }
+}
@@ -0,0 +1,231 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Just enough PDF to lay out text and rules on A4 pages with the standard Helvetica fonts,
+//! which every PDF reader has built in, so nothing needs to be embedded.
+
+/// A4 in points
+pub const PAGE_WIDTH: f32 = 595.0;
+pub const PAGE_HEIGHT: f32 = 842.0;
+
+/// Widths of the printable ASCII characters in Helvetica, in thousandths of the font size
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556,
+    556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722, 722, 667,
+    611, 778, 722, 278, 500, 667, 556, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667,
+    667, 611, 278, 278, 278, 469, 556, 333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500,
+    222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+/// Bold characters are close enough to this much wider for aligning text
+const BOLD_WIDTH_FACTOR: f32 = 1.06;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PdfFont {
+    Regular,
+    Bold,
+}
+
+impl PdfFont {
+    fn resource_name(&self) -> &'static str {
+        match self {
+            PdfFont::Regular => "F1",
+            PdfFont::Bold => "F2",
+        }
+    }
+}
+
+/// Pages of drawing commands, turned into a PDF file by `finish`
+pub struct PdfDocument {
+    pages: Vec<Vec<u8>>,
+}
+
+impl PdfDocument {
+    pub fn new() -> Self {
+        PdfDocument {
+            pages: vec![Vec::new()],
+        }
+    }
+
+    pub fn new_page(&mut self) {
+        self.pages.push(Vec::new());
+    }
+
+    /// Draw text with its baseline starting at `x`, `y`, measured from the bottom left
+    pub fn text(&mut self, x: f32, y: f32, size: f32, font: PdfFont, text: &str) {
+        let mut command = format!(
+            "BT /{} {} Tf {:.2} {:.2} Td (",
+            font.resource_name(),
+            size,
+            x,
+            y
+        )
+        .into_bytes();
+        for character in text.chars() {
+            match win_ansi_byte(character) {
+                byte @ (b'(' | b')' | b'\\') => command.extend([b'\\', byte]),
+                byte => command.push(byte),
+            }
+        }
+        command.extend(b") Tj ET\n");
+        self.current_page().extend(command);
+    }
+
+    /// Draw text so that it ends at `right`
+    pub fn text_right(&mut self, right: f32, y: f32, size: f32, font: PdfFont, text: &str) {
+        let width = text_width(text, size, font);
+        self.text(right - width, y, size, font, text);
+    }
+
+    pub fn line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32) {
+        let command = format!("0.5 w {:.2} {:.2} m {:.2} {:.2} l S\n", x1, y1, x2, y2);
+        self.current_page().extend(command.into_bytes());
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        let page_count = self.pages.len();
+        let mut objects: Vec<Vec<u8>> = Vec::new();
+
+        // 1 is the catalog, 2 the page tree, 3 and 4 the fonts, then each page and its contents
+        let kids = (0..page_count)
+            .map(|index| format!("{} 0 R", 5 + index * 2))
+            .collect::<Vec<String>>()
+            .join(" ");
+        objects.push(b"<< /Type /Catalog /Pages 2 0 R >>".to_vec());
+        objects.push(
+            format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids, page_count).into_bytes(),
+        );
+        for font in ["Helvetica", "Helvetica-Bold"] {
+            objects.push(
+                format!(
+                    "<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>",
+                    font
+                )
+                .into_bytes(),
+            );
+        }
+        for (index, contents) in self.pages.into_iter().enumerate() {
+            objects.push(
+                format!(
+                    "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+                     /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+                    PAGE_WIDTH,
+                    PAGE_HEIGHT,
+                    6 + index * 2
+                )
+                .into_bytes(),
+            );
+            let mut stream = format!("<< /Length {} >>\nstream\n", contents.len()).into_bytes();
+            stream.extend(contents);
+            stream.extend(b"\nendstream");
+            objects.push(stream);
+        }
+
+        let mut pdf = b"%PDF-1.4\n".to_vec();
+        let mut offsets = Vec::new();
+        for (index, object) in objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            pdf.extend(format!("{} 0 obj\n", index + 1).into_bytes());
+            pdf.extend(object);
+            pdf.extend(b"\nendobj\n");
+        }
+        let xref_offset = pdf.len();
+        pdf.extend(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).into_bytes());
+        for offset in offsets {
+            pdf.extend(format!("{:010} 00000 n \n", offset).into_bytes());
+        }
+        pdf.extend(
+            format!(
+                "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+                objects.len() + 1,
+                xref_offset
+            )
+            .into_bytes(),
+        );
+        pdf
+    }
+
+    fn current_page(&mut self) -> &mut Vec<u8> {
+        if self.pages.is_empty() {
+            self.pages.push(Vec::new());
+        }
+        let last = self.pages.len() - 1;
+        &mut self.pages[last]
+    }
+}
+
+impl Default for PdfDocument {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// How wide `text` is when drawn, in points
+pub fn text_width(text: &str, size: f32, font: PdfFont) -> f32 {
+    let thousandths: u32 = text
+        .chars()
+        .map(|character| match character {
+            ' '..='~' => HELVETICA_WIDTHS[character as usize - ' ' as usize] as u32,
+            _ => 556,
+        })
+        .sum();
+    let width = thousandths as f32 * size / 1000.0;
+    match font {
+        PdfFont::Regular => width,
+        PdfFont::Bold => width * BOLD_WIDTH_FACTOR,
+    }
+}
+
+/// Split text into lines no wider than `max_width`, breaking between words where possible
+pub fn wrap(text: &str, size: f32, font: PdfFont, max_width: f32) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let candidate = if line.is_empty() {
+                word.to_string()
+            } else {
+                format!("{} {}", line, word)
+            };
+            if line.is_empty() || text_width(&candidate, size, font) <= max_width {
+                line = candidate;
+            } else {
+                lines.push(std::mem::replace(&mut line, word.to_string()));
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// The character's byte in WinAnsiEncoding, or "?" if the standard fonts can't draw it
+fn win_ansi_byte(character: char) -> u8 {
+    match character {
+        ' '..='~' | '\u{a0}'..='\u{ff}' => character as u8,
+        '€' => 0x80,
+        '‚' => 0x82,
+        '„' => 0x84,
+        '…' => 0x85,
+        '‘' => 0x91,
+        '’' => 0x92,
+        '“' => 0x93,
+        '”' => 0x94,
+        '•' => 0x95,
+        '–' => 0x96,
+        '—' => 0x97,
+        '™' => 0x99,
+        _ => b'?',
+    }
+}
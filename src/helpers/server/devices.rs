@@ -0,0 +1,165 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use chrono::{Local, TimeZone};
+use dioxus::{prelude::spawn, signals::Readable};
+use log::error;
+use serde::{Deserialize, Serialize};
+
+use crate::{loc, localization::Localization, models::fur_device::FurDevice, state};
+
+use super::{
+    api::{post_authorized, response_error, ApiError},
+    encryption::generate_device_id,
+    sync::{set_negative_sync_message, set_positive_sync_messsage},
+};
+
+#[derive(Serialize)]
+struct DevicesRequest {
+    device_id: String,
+}
+
+#[derive(Deserialize)]
+struct DevicesResponse {
+    devices: Vec<FurDevice>,
+}
+
+#[derive(Serialize)]
+struct RevokeDeviceRequest {
+    device_id: String,
+    /// The device whose tokens the server should invalidate
+    revoked_device_id: String,
+}
+
+fn device_id() -> Result<String, ApiError> {
+    generate_device_id().map_err(|_| ApiError::Device("Failed to generate device ID".into()))
+}
+
+pub fn is_this_device(device: &FurDevice) -> bool {
+    generate_device_id().is_ok_and(|id| id == device.device_id)
+}
+
+/// Platform and last sync time for the devices list
+pub fn device_details(device: &FurDevice) -> String {
+    let last_sync = match device
+        .last_sync
+        .map(|timestamp| Local.timestamp_opt(timestamp, 0))
+    {
+        Some(chrono::LocalResult::Single(time)) => time.format("%Y-%m-%d %H:%M").to_string(),
+        _ => loc!("never-synced"),
+    };
+    if device.platform.is_empty() {
+        last_sync
+    } else {
+        format!("{} · {}", device.platform, last_sync)
+    }
+}
+
+pub fn load_devices() {
+    let Some(mut user) = state::USER.cloned() else {
+        return;
+    };
+
+    spawn(async move {
+        let result = async {
+            let response = post_authorized(
+                &mut user,
+                "/api/devices",
+                &DevicesRequest {
+                    device_id: device_id()?,
+                },
+            )
+            .await?;
+            if !response.status().is_success() {
+                return Err(response_error(response, "Failed to retrieve devices").await);
+            }
+            response
+                .json::<DevicesResponse>()
+                .await
+                .map_err(|e| ApiError::Server(e.to_string()))
+        }
+        .await;
+
+        match result {
+            Ok(response) => *state::DEVICES.write() = Some(response.devices),
+            Err(e) => {
+                error!("Error retrieving devices: {:?}", e);
+                set_negative_sync_message(loc!("error-loading-devices"));
+            }
+        }
+    });
+}
+
+pub fn revoke_device_pressed(device: &FurDevice) {
+    *state::DEVICE_TO_REVOKE.write() = Some(device.device_id.clone());
+
+    let mut alert = state::ALERT.cloned();
+    alert.is_shown = true;
+    alert.title = loc!("revoke-device-question");
+    alert.message = loc!("revoke-device-description");
+    alert.confirm_button = (loc!("revoke"), || revoke_device());
+    alert.cancel_button = Some((loc!("cancel"), || close_alert()));
+    *state::ALERT.write() = alert;
+}
+
+fn close_alert() {
+    *state::DEVICE_TO_REVOKE.write() = None;
+    let mut alert = state::ALERT.cloned();
+    alert.close();
+    *state::ALERT.write() = alert;
+}
+
+fn revoke_device() {
+    let revoked_device_id = state::DEVICE_TO_REVOKE.cloned();
+    close_alert();
+    let (Some(mut user), Some(revoked_device_id)) = (state::USER.cloned(), revoked_device_id)
+    else {
+        return;
+    };
+
+    spawn(async move {
+        let result = async {
+            let response = post_authorized(
+                &mut user,
+                "/api/devices/revoke",
+                &RevokeDeviceRequest {
+                    device_id: device_id()?,
+                    revoked_device_id: revoked_device_id.clone(),
+                },
+            )
+            .await?;
+            if response.status().is_success() {
+                Ok(())
+            } else {
+                Err(response_error(response, "Failed to revoke device").await)
+            }
+        }
+        .await;
+
+        match result {
+            Ok(_) => {
+                if let Some(devices) = state::DEVICES.write().as_mut() {
+                    devices.retain(|device| device.device_id != revoked_device_id);
+                }
+                set_positive_sync_messsage(loc!("device-revoked"));
+            }
+            Err(e) => {
+                error!("Error revoking device: {:?}", e);
+                set_negative_sync_message(loc!("error-revoking-device"));
+            }
+        }
+    });
+}
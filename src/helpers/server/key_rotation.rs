@@ -0,0 +1,394 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use dioxus::{prelude::spawn, signals::Readable};
+use log::error;
+use serde::Serialize;
+
+use crate::{
+    database, loc,
+    localization::Localization,
+    models::{
+        fur_key_rotation::FurKeyRotation, fur_shortcut::EncryptedShortcut,
+        fur_sync_log::FurSyncLogEntry, fur_sync_progress::SyncWatermark, fur_task::EncryptedTask,
+        fur_todo::EncryptedTodo, fur_user::FurUser,
+    },
+    services::{self, sync::SyncError},
+    state,
+};
+
+use super::{
+    api::{post_authorized, response_error, ApiError},
+    encryption::{self, generate_device_id, KeyCanary},
+    kdf::{self, KdfInfo},
+    sync::{set_negative_sync_message, set_positive_sync_messsage},
+    sync_engine::{self, SYNC_PAGE_SIZE},
+    sync_scheduler,
+};
+
+#[derive(Serialize)]
+struct RotationStartRequest {
+    device_id: String,
+    key_generation: i64,
+    /// The new sync key, used by the server to check future logins
+    encryption_key: String,
+    /// Salt for the new key when it is derived from a passphrase
+    kdf: Option<KdfInfo>,
+    /// Replaces the account's canary when the rotation finishes
+    canary: KeyCanary,
+}
+
+#[derive(Serialize)]
+struct RotationUploadRequest {
+    device_id: String,
+    key_generation: i64,
+    tasks: Vec<EncryptedTask>,
+    shortcuts: Vec<EncryptedShortcut>,
+    todos: Vec<EncryptedTodo>,
+}
+
+#[derive(Serialize)]
+struct RotationRequest {
+    device_id: String,
+    key_generation: i64,
+}
+
+pub fn get_key_rotation() -> Option<FurKeyRotation> {
+    match database::sync::retrieve_key_rotation() {
+        Ok(rotation) => rotation,
+        Err(e) => {
+            error!("Error retrieving key rotation: {}", e);
+            None
+        }
+    }
+}
+
+pub fn rotate_key_button_pressed() {
+    if state::USER_FIELDS.read().new_encryption_key.is_empty() {
+        return;
+    }
+
+    let mut alert = state::ALERT.cloned();
+    alert.is_shown = true;
+    alert.title = loc!("rotate-key-question");
+    alert.message = loc!("rotate-key-description");
+    alert.confirm_button = (loc!("rotate-key"), || start_key_rotation());
+    alert.cancel_button = Some((loc!("cancel"), || close_alert()));
+    *state::ALERT.write() = alert;
+}
+
+fn close_alert() {
+    let mut alert = state::ALERT.cloned();
+    alert.close();
+    *state::ALERT.write() = alert;
+}
+
+fn start_key_rotation() {
+    close_alert();
+
+    let Some(user) = state::USER.cloned() else {
+        return;
+    };
+    if state::KEY_ROTATION.read().is_some() {
+        return;
+    }
+    let new_key_input = state::USER_FIELDS.read().new_encryption_key.clone();
+
+    // The full sync below must not run alongside a regular one
+    if !sync_scheduler::begin_sync() {
+        set_negative_sync_message(loc!("key-rotation-wait-for-sync"));
+        return;
+    }
+
+    set_positive_sync_messsage(loc!("rotating-key"));
+
+    spawn(async move {
+        let mut user = user;
+        let started = match download_everything(&mut user).await {
+            Ok(_) => begin_rotation(&mut user, new_key_input).await,
+            Err(e) => Err(e),
+        };
+        // Regular syncs now wait for the stored rotation to finish
+        sync_scheduler::sync_stopped(None);
+
+        let result = match started {
+            Ok(rotation) => upload_with_new_key(&mut user, rotation).await,
+            Err(e) => Err(e),
+        };
+        complete_key_rotation(result);
+    });
+}
+
+pub fn resume_key_rotation() {
+    let (Some(user), Some(rotation)) = (state::USER.cloned(), state::KEY_ROTATION.cloned()) else {
+        return;
+    };
+
+    set_positive_sync_messsage(loc!("rotating-key"));
+
+    spawn(async move {
+        let mut user = user;
+        let result = match download_everything(&mut user).await {
+            Ok(log) if log.downloaded.total() > 0 => {
+                // Records that arrived since the rotation started need the new key too
+                let rotation = FurKeyRotation {
+                    uploaded_count: 0,
+                    tasks_uploaded_through: SyncWatermark::start(),
+                    shortcuts_uploaded_through: SyncWatermark::start(),
+                    todos_uploaded_through: SyncWatermark::start(),
+                    ..rotation
+                };
+                store_key_rotation(&rotation);
+                upload_with_new_key(&mut user, rotation).await
+            }
+            // The rotation already finished on the server, so the upload only needs recording
+            Ok(_) | Err(ApiError::KeyRotated) => upload_with_new_key(&mut user, rotation).await,
+            Err(e) => Err(e),
+        };
+        complete_key_rotation(result);
+    });
+}
+
+/// Discard what was uploaded under the new key so the current key stays in use
+pub fn cancel_key_rotation() {
+    let (Some(user), Some(rotation)) = (state::USER.cloned(), state::KEY_ROTATION.cloned()) else {
+        return;
+    };
+
+    spawn(async move {
+        let mut user = user;
+        match send_rotation_request(&mut user, "/api/rotate-key/cancel", &rotation).await {
+            Ok(_) => {
+                clear_key_rotation();
+                set_positive_sync_messsage(loc!("key-rotation-cancelled"));
+            }
+            Err(e) => {
+                error!("Error cancelling key rotation: {:?}", e);
+                set_negative_sync_message(loc!("key-rotation-failed"));
+            }
+        }
+    });
+}
+
+/// Download every record with the current key before re-encrypting, so records only
+/// the server has, or that other devices changed, aren't left under the old key
+async fn download_everything(user: &mut FurUser) -> Result<FurSyncLogEntry, ApiError> {
+    services::sync::run_full(user).await.map_err(|e| match e {
+        SyncError::Api(e) => e,
+        SyncError::Key(e) => {
+            error!("Failed to decrypt encryption key (KeyRotation): {:?}", e);
+            ApiError::KeyDerivation
+        }
+    })
+}
+
+/// Ask the server to start collecting records under the next key generation
+async fn begin_rotation(
+    user: &mut FurUser,
+    new_key_input: String,
+) -> Result<FurKeyRotation, ApiError> {
+    let (new_key, kdf) = if kdf::is_raw_key(&new_key_input) {
+        (new_key_input, None)
+    } else {
+        let kdf = KdfInfo::generate();
+        let kdf_clone = kdf.clone();
+        let new_key =
+            tokio::task::spawn_blocking(move || kdf::derive_key(&new_key_input, &kdf_clone))
+                .await
+                .map_err(|_| ApiError::KeyDerivation)?
+                .map_err(|_| ApiError::KeyDerivation)?;
+        (new_key, Some(kdf))
+    };
+
+    let (encrypted_key, key_nonce) =
+        encryption::encrypt_encryption_key(&new_key, &user.key_storage)
+            .map_err(|_| ApiError::KeyDerivation)?;
+    let canary = encryption::decode_sync_key(&new_key)
+        .and_then(|key| encryption::create_canary(&key))
+        .map_err(|_| ApiError::KeyDerivation)?;
+    let rotation = FurKeyRotation::new(encrypted_key, key_nonce, user.key_generation + 1);
+
+    let response = post_authorized(
+        user,
+        "/api/rotate-key/start",
+        &RotationStartRequest {
+            device_id: device_id()?,
+            key_generation: rotation.key_generation,
+            encryption_key: new_key,
+            kdf,
+            canary,
+        },
+    )
+    .await?;
+    if !response.status().is_success() {
+        return Err(response_error(response, "Failed to start key rotation").await);
+    }
+
+    store_key_rotation(&rotation);
+    Ok(rotation)
+}
+
+/// Upload every local record, including deleted ones, encrypted with the new key.
+/// Progress is stored after each page so the upload can resume after an interruption.
+async fn upload_with_new_key(
+    user: &mut FurUser,
+    mut rotation: FurKeyRotation,
+) -> Result<FurKeyRotation, ApiError> {
+    let new_key = encryption::decrypt_encryption_key(
+        &rotation.encrypted_key,
+        &rotation.key_nonce,
+        &user.key_storage,
+    )
+    .map_err(|_| ApiError::KeyDerivation)?;
+
+    loop {
+        let page = sync_engine::retrieve_upload_page(
+            i64::MIN,
+            false,
+            &rotation.tasks_uploaded_through,
+            &rotation.shortcuts_uploaded_through,
+            &rotation.todos_uploaded_through,
+        );
+        let page_len = page.len();
+
+        let tasks_through = page.tasks.last().map(|task| SyncWatermark {
+            last_updated: task.last_updated,
+            uid: task.uid.clone(),
+        });
+        let shortcuts_through = page.shortcuts.last().map(|shortcut| SyncWatermark {
+            last_updated: shortcut.last_updated,
+            uid: shortcut.uid.clone(),
+        });
+        let todos_through = page.todos.last().map(|todo| SyncWatermark {
+            last_updated: todo.last_updated,
+            uid: todo.uid.clone(),
+        });
+
+        if page_len > 0 {
+            let response = post_authorized(
+                user,
+                "/api/rotate-key/upload",
+                &RotationUploadRequest {
+                    device_id: device_id()?,
+                    key_generation: rotation.key_generation,
+                    tasks: sync_engine::encrypt_tasks(page.tasks, &new_key),
+                    shortcuts: sync_engine::encrypt_shortcuts(page.shortcuts, &new_key),
+                    todos: sync_engine::encrypt_todos(page.todos, &new_key),
+                },
+            )
+            .await?;
+            if !response.status().is_success() {
+                return Err(
+                    response_error(response, "Failed to upload re-encrypted records").await,
+                );
+            }
+        }
+
+        if let Some(watermark) = tasks_through {
+            rotation.tasks_uploaded_through = watermark;
+        }
+        if let Some(watermark) = shortcuts_through {
+            rotation.shortcuts_uploaded_through = watermark;
+        }
+        if let Some(watermark) = todos_through {
+            rotation.todos_uploaded_through = watermark;
+        }
+        rotation.uploaded_count += page_len as i64;
+        store_key_rotation(&rotation);
+
+        if page_len < SYNC_PAGE_SIZE {
+            break;
+        }
+    }
+
+    send_rotation_request(user, "/api/rotate-key/finish", &rotation).await?;
+    Ok(rotation)
+}
+
+async fn send_rotation_request(
+    user: &mut FurUser,
+    path: &str,
+    rotation: &FurKeyRotation,
+) -> Result<(), ApiError> {
+    let response = post_authorized(
+        user,
+        path,
+        &RotationRequest {
+            device_id: device_id()?,
+            key_generation: rotation.key_generation,
+        },
+    )
+    .await?;
+
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        Err(response_error(response, "Key rotation request failed").await)
+    }
+}
+
+fn complete_key_rotation(result: Result<FurKeyRotation, ApiError>) {
+    match result {
+        Ok(rotation) => {
+            let Some(user) = state::USER.cloned() else {
+                return;
+            };
+            if let Err(e) = database::sync::update_encryption_key(
+                &user.email,
+                &rotation.encrypted_key,
+                &rotation.key_nonce,
+                rotation.key_generation,
+            ) {
+                error!("Error storing new encryption key: {}", e);
+                set_negative_sync_message(loc!("key-rotation-failed"));
+                return;
+            }
+            clear_key_rotation();
+
+            match database::sync::retrieve_credentials() {
+                Ok(optional_user) => *state::USER.write() = optional_user,
+                Err(e) => error!("Error retrieving user credentials from database: {}", e),
+            }
+            let mut user_fields = state::USER_FIELDS.cloned();
+            user_fields.new_encryption_key = String::new();
+            *state::USER_FIELDS.write() = user_fields;
+            set_positive_sync_messsage(loc!("key-rotated"));
+        }
+        Err(e) => {
+            // Whatever was uploaded stays staged on the server until resumed or cancelled
+            error!("Error rotating encryption key: {:?}", e);
+            set_negative_sync_message(loc!("key-rotation-failed"));
+        }
+    }
+}
+
+fn store_key_rotation(rotation: &FurKeyRotation) {
+    if let Err(e) = database::sync::store_key_rotation(rotation) {
+        error!("Error storing key rotation progress: {}", e);
+    }
+    *state::KEY_ROTATION.write() = Some(rotation.clone());
+}
+
+fn clear_key_rotation() {
+    if let Err(e) = database::sync::delete_key_rotation() {
+        error!("Error clearing key rotation: {}", e);
+    }
+    *state::KEY_ROTATION.write() = None;
+}
+
+fn device_id() -> Result<String, ApiError> {
+    generate_device_id().map_err(|_| ApiError::Device("Failed to generate device ID".into()))
+}
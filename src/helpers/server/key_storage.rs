@@ -0,0 +1,132 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use dioxus::{prelude::spawn, signals::Readable};
+use log::error;
+
+use crate::{loc, localization::Localization, state};
+
+pub use furtherance_core::server::key_storage::*;
+
+use super::{
+    encryption::{self, EncryptionError},
+    sync::{self, set_negative_sync_message, set_positive_sync_messsage},
+};
+
+const MIN_PIN_LENGTH: usize = 4;
+
+fn reload_user() {
+    *state::USER.write() = sync::get_user();
+    *state::KEY_ROTATION.write() = super::key_rotation::get_key_rotation();
+}
+
+fn clear_pin_field() {
+    let mut user_fields = state::USER_FIELDS.cloned();
+    user_fields.pin = String::new();
+    *state::USER_FIELDS.write() = user_fields;
+}
+
+pub fn set_pin_button_pressed() {
+    let Some(user) = state::USER.cloned() else {
+        return;
+    };
+    let pin = state::USER_FIELDS.read().pin.clone();
+    if pin.chars().count() < MIN_PIN_LENGTH {
+        set_negative_sync_message(loc!("pin-too-short"));
+        return;
+    }
+
+    let salt = generate_pin_salt();
+    clear_pin_field();
+
+    spawn(async move {
+        // Argon2 with a high memory cost takes a while, so keep it off the UI thread
+        let result = tokio::task::spawn_blocking(move || set_pin(&user, &pin, &salt))
+            .await
+            .unwrap_or(Err(EncryptionError::KeyDerivation));
+
+        match result {
+            Ok(_) => {
+                reload_user();
+                set_positive_sync_messsage(loc!("pin-set"));
+            }
+            Err(e) => {
+                error!("Failed to protect sync key with PIN: {:?}", e);
+                set_negative_sync_message(loc!("error-setting-pin"));
+            }
+        }
+    });
+}
+
+pub fn unlock_button_pressed() {
+    let Some(user) = state::USER.cloned() else {
+        return;
+    };
+    let Some(salt) = user.key_storage_salt.clone() else {
+        return;
+    };
+    let pin = state::USER_FIELDS.read().pin.clone();
+    clear_pin_field();
+
+    spawn(async move {
+        // A wrong PIN derives a key that fails to decrypt the stored sync key
+        let unlocked = tokio::task::spawn_blocking(move || {
+            unlock_with_pin(&pin, &salt).and_then(|_| {
+                encryption::decrypt_encryption_key(
+                    &user.encrypted_key,
+                    &user.key_nonce,
+                    &user.key_storage,
+                )
+            })
+        })
+        .await
+        .unwrap_or(Err(EncryptionError::KeyDerivation));
+
+        match unlocked {
+            Ok(_) => {
+                reload_user();
+                set_positive_sync_messsage(loc!("sync-key-unlocked"));
+                sync::request_sync();
+            }
+            Err(_) => {
+                lock();
+                set_negative_sync_message(loc!("incorrect-pin"));
+            }
+        }
+    });
+}
+
+pub fn remove_pin_button_pressed() {
+    let Some(user) = state::USER.cloned() else {
+        return;
+    };
+    if !is_unlocked() {
+        set_negative_sync_message(loc!("sync-key-locked"));
+        return;
+    }
+
+    match change_key_storage(&user, &default_kind(), None) {
+        Ok(_) => {
+            lock();
+            reload_user();
+            set_positive_sync_messsage(loc!("pin-removed"));
+        }
+        Err(e) => {
+            error!("Failed to remove PIN protection: {:?}", e);
+            set_negative_sync_message(loc!("error-setting-pin"));
+        }
+    }
+}
@@ -17,54 +17,44 @@
 use std::sync::Arc;
 
 use dioxus::{prelude::spawn, signals::Readable};
+use log::error;
 use reqwest::Client;
-use serde::{Deserialize, Serialize};
+use serde::Serialize;
 
 use crate::{
     database,
-    helpers::server::sync::{reset_user, set_negative_sync_message},
+    helpers::{
+        server::sync::{reset_user, set_negative_sync_message},
+        views::alerts,
+    },
     loc,
     localization::Localization,
+    models::fur_user::FurUser,
     state,
 };
 
 use super::{
-    encryption::{self, generate_device_id},
+    api::{login, post_authorized, ApiError, LoginResponse},
+    encryption::{self, generate_device_id, KeyCanary},
+    kdf::{self, KdfInfo},
+    key_storage::{self, KeyStorageKind},
+    logout,
     sync::set_positive_sync_messsage,
+    sync_engine,
 };
 
-#[derive(Clone, Debug)]
-pub enum ApiError {
-    Auth(String),
-    Device(String),
-    InactiveSubscription(String),
-    Network(Arc<reqwest::Error>),
-    Server(String),
-    TokenRefresh(String),
-}
+/// Login error for a passphrase on a server that can't store its salt
+const PASSPHRASES_UNSUPPORTED: &str = "Server doesn't support passphrases";
 
 #[derive(Serialize)]
-pub struct LoginRequest {
-    pub email: String,
-    pub encryption_key: String,
-    pub device_id: String,
-}
-
-#[derive(Clone, Debug, Deserialize)]
-pub struct LoginResponse {
-    pub access_token: String,
-    pub refresh_token: String,
+struct KdfRequest {
+    email: String,
 }
 
 #[derive(Serialize)]
-struct RefreshRequest {
-    refresh_token: String,
+struct CanaryRequest {
     device_id: String,
-}
-
-#[derive(Deserialize)]
-struct RefreshResponse {
-    access_token: String,
+    canary: KeyCanary,
 }
 
 pub fn login_button_pressed() {
@@ -79,57 +69,141 @@ pub fn login_button_pressed() {
 
     set_positive_sync_messsage(loc!("logging-in"));
 
-    spawn(async {
-        let login_response = login(email, encryption_key, server).await;
-        complete_login(login_response);
+    spawn(async move {
+        let (sync_key, kdf) = match resolve_sync_key(&email, encryption_key, &server).await {
+            Ok(result) => result,
+            Err(e) => {
+                complete_login(Err(e), String::new(), RecordCheck::Unknown);
+                return;
+            }
+        };
+        let login_response = login(email.clone(), sync_key.clone(), kdf, server.clone()).await;
+        // Without a canary, the account's records are the only way to check the key
+        let record_check = match &login_response {
+            Ok(response) if response.canary.is_none() => {
+                check_key_against_records(response, &email, &server, &sync_key).await
+            }
+            _ => RecordCheck::Unknown,
+        };
+        complete_login(login_response, sync_key, record_check);
     });
 }
 
-pub async fn login(
-    email: String,
-    encryption_key: String,
-    server: String,
-) -> Result<LoginResponse, ApiError> {
-    let client = Client::new();
-    let device_id = match generate_device_id() {
-        Ok(id) => id,
-        Err(_) => return Err(ApiError::Device("Failed to generate device ID".into())),
+/// Turn what the user typed into the sync key. A raw key is used as is, so servers without
+/// `/api/kdf` keep working; anything else is a passphrase, derived with the salt the server
+/// gives for the account. That salt is sent with the login so a new account registers it.
+async fn resolve_sync_key(
+    email: &str,
+    entered_key: String,
+    server: &str,
+) -> Result<(String, Option<KdfInfo>), ApiError> {
+    if kdf::is_raw_key(&entered_key) {
+        return Ok((entered_key, None));
+    }
+    let Some(kdf) = fetch_kdf_info(email, server).await? else {
+        return Err(ApiError::Server(PASSPHRASES_UNSUPPORTED.into()));
     };
 
+    // Argon2 is deliberately slow, so keep it off the async runtime
+    let kdf_clone = kdf.clone();
+    let sync_key = tokio::task::spawn_blocking(move || kdf::derive_key(&entered_key, &kdf_clone))
+        .await
+        .map_err(|_| ApiError::KeyDerivation)?
+        .map_err(|_| ApiError::KeyDerivation)?;
+
+    Ok((sync_key, Some(kdf)))
+}
+
+/// Retrieve the passphrase salt for an account, or None if the server doesn't offer one.
+/// Servers answer every email the same way, with made-up parameters for unknown accounts,
+/// so this can't be used to find out who has an account.
+async fn fetch_kdf_info(email: &str, server: &str) -> Result<Option<KdfInfo>, ApiError> {
+    let client = Client::new();
+
     let response = client
-        .post(format!("{}/api/login", server))
-        .json(&LoginRequest {
-            email,
-            encryption_key,
-            device_id,
+        .post(format!("{}/api/kdf", server))
+        .json(&KdfRequest {
+            email: email.to_string(),
         })
         .send()
         .await
         .map_err(|e| ApiError::Network(Arc::new(e)))?;
 
-    if response.status().is_success() {
-        response
-            .json()
-            .await
-            .map_err(|e| ApiError::Network(Arc::new(e)))
-    } else {
-        Err(ApiError::Auth("Invalid credentials".into()))
+    if !response.status().is_success() {
+        return Ok(None);
     }
+    response
+        .json()
+        .await
+        .map(Some)
+        .map_err(|e| ApiError::Network(Arc::new(e)))
 }
 
-fn complete_login(response_result: Result<LoginResponse, ApiError>) {
+/// What an account's existing records say about the entered key
+#[derive(PartialEq)]
+enum RecordCheck {
+    Opened,
+    NotOpened,
+    NoRecords,
+    Unknown,
+}
+
+async fn check_key_against_records(
+    response: &LoginResponse,
+    email: &str,
+    server: &str,
+    sync_key: &str,
+) -> RecordCheck {
+    let Ok(key) = encryption::decode_sync_key(sync_key) else {
+        return RecordCheck::Unknown;
+    };
+    let mut user = FurUser {
+        email: email.to_string(),
+        encrypted_key: String::new(),
+        key_nonce: String::new(),
+        access_token: response.access_token.clone(),
+        refresh_token: response.refresh_token.clone(),
+        server: server.to_string(),
+        key_generation: response.key_generation,
+        key_storage: KeyStorageKind::DeviceId,
+        key_storage_salt: None,
+    };
+    let device_name = state::SETTINGS.read().device_name.clone();
+
+    match sync_engine::key_opens_records(&mut user, &device_name, &key).await {
+        Ok(Some(true)) => RecordCheck::Opened,
+        Ok(Some(false)) => RecordCheck::NotOpened,
+        Ok(None) => RecordCheck::NoRecords,
+        Err(e) => {
+            error!("Error checking key against server records: {:?}", e);
+            RecordCheck::Unknown
+        }
+    }
+}
+
+fn complete_login(
+    response_result: Result<LoginResponse, ApiError>,
+    sync_key: String,
+    record_check: RecordCheck,
+) {
     match response_result {
         Ok(response) => {
+            let Ok(key_bytes) = encryption::decode_sync_key(&sync_key) else {
+                reset_user();
+                set_negative_sync_message(loc!("error-deriving-key"));
+                return;
+            };
+
             // Encrypt encryption key with device-specific key
+            let key_storage = key_storage::default_kind();
             let (encrypted_key, key_nonce) =
-                match encryption::encrypt_encryption_key(&state::USER_FIELDS.read().encryption_key)
-                {
+                match encryption::encrypt_encryption_key(&sync_key, &key_storage) {
                     Ok(result) => result,
                     Err(e) => {
-                        eprintln!("Error encrypting key: {:?}", e);
+                        error!("Error encrypting key: {:?}", e);
                         match database::sync::delete_all_credentials() {
                             Ok(_) => {}
-                            Err(e) => eprintln!("Error deleting user credentials: {}", e),
+                            Err(e) => error!("Error deleting user credentials: {}", e),
                         };
                         *state::USER.write() = None;
                         set_negative_sync_message(loc!("error-storing-credentials"));
@@ -137,27 +211,69 @@ fn complete_login(response_result: Result<LoginResponse, ApiError>) {
                     }
                 };
 
-            // Store credentials
             let user_fields_clone = state::USER_FIELDS.cloned();
-            if let Err(e) = database::sync::store_credentials(
-                &user_fields_clone.email,
-                &encrypted_key,
-                &key_nonce,
-                &response.access_token,
-                &response.refresh_token,
-                &user_fields_clone.server,
-            ) {
-                eprintln!("Error storing user credentials: {}", e);
+            let user = FurUser {
+                email: user_fields_clone.email,
+                encrypted_key,
+                key_nonce,
+                access_token: response.access_token,
+                refresh_token: response.refresh_token,
+                server: user_fields_clone.server,
+                key_generation: response.key_generation,
+                key_storage,
+                key_storage_salt: None,
+            };
+
+            // Refuse a key that can't open the account's canary, or its records if it has no
+            // canary yet, before syncing anything with it
+            let key_matches = match &response.canary {
+                Some(canary) => encryption::key_matches_canary(&key_bytes, canary),
+                None => record_check != RecordCheck::NotOpened,
+            };
+            if !key_matches {
+                let user = user.clone();
+                spawn(async move { logout::server_logout(&user).await });
+                reset_user();
+                set_negative_sync_message(loc!("key-does-not-match-account"));
+                show_key_mismatch_alert();
+                return;
+            }
+
+            // Store credentials
+            if let Err(e) = database::sync::store_credentials(&user) {
+                error!("Error storing user credentials: {}", e);
                 reset_user();
                 set_negative_sync_message(loc!("error-storing-credentials"));
                 return;
             }
 
+            // Only a key confirmed by the account's records, or the first key of an empty
+            // account, becomes the reference other devices are checked against
+            if response.canary.is_none()
+                && matches!(record_check, RecordCheck::Opened | RecordCheck::NoRecords)
+            {
+                upload_canary(user, key_bytes);
+            }
+
             // Always do a full sync after login
             let mut settings_clone = state::SETTINGS.cloned();
             match settings_clone.change_needs_full_sync(&true) {
                 Ok(_) => *state::SETTINGS.write() = settings_clone,
-                Err(e) => eprintln!("Error changing needs_full_sync: {}", e),
+                Err(e) => error!("Error changing needs_full_sync: {}", e),
+            };
+
+            // This server may not have received earlier deletions
+            if let Err(e) = database::maintenance::reset_tombstone_acknowledgements() {
+                error!("Error resetting tombstone acknowledgements: {}", e);
+            }
+            // A sync interrupted under earlier credentials must not be resumed
+            if let Err(e) = database::sync::delete_sync_progress() {
+                error!("Error clearing sync progress: {}", e);
+            }
+            let mut settings_clone = state::SETTINGS.cloned();
+            match settings_clone.change_devices_synced_through(&0) {
+                Ok(_) => *state::SETTINGS.write() = settings_clone,
+                Err(e) => error!("Error changing devices_synced_through: {}", e),
             };
 
             let key_length = state::USER_FIELDS.read().encryption_key.len();
@@ -166,7 +282,7 @@ fn complete_login(response_result: Result<LoginResponse, ApiError>) {
             match database::sync::retrieve_credentials() {
                 Ok(optional_user) => *state::USER.write() = optional_user,
                 Err(e) => {
-                    eprintln!("Error retrieving user credentials from database: {}", e);
+                    error!("Error retrieving user credentials from database: {}", e);
                     reset_user();
                     set_negative_sync_message(loc!("error-storing-credentials"));
                     return;
@@ -184,13 +300,21 @@ fn complete_login(response_result: Result<LoginResponse, ApiError>) {
             }
         }
         Err(e) => {
-            eprintln!("Error logging in: {:?}", e);
+            error!("Error logging in: {:?}", e);
             reset_user();
             match e {
                 ApiError::Network(e) if e.to_string() == "builder error" => {
                     set_negative_sync_message(loc!("server-must-contain-protocol"));
                     return;
                 }
+                ApiError::KeyDerivation => {
+                    set_negative_sync_message(loc!("error-deriving-key"));
+                    return;
+                }
+                ApiError::Server(message) if message == PASSPHRASES_UNSUPPORTED => {
+                    set_negative_sync_message(loc!("passphrases-not-supported"));
+                    return;
+                }
                 _ => {
                     set_negative_sync_message(loc!("login-failed"));
                     return;
@@ -200,30 +324,34 @@ fn complete_login(response_result: Result<LoginResponse, ApiError>) {
     }
 }
 
-pub async fn refresh_auth_token(refresh_token: String, server: &str) -> Result<String, ApiError> {
-    let client = Client::new();
-    let device_id = match generate_device_id() {
-        Ok(id) => id,
-        Err(_) => return Err(ApiError::Device("Failed to generate device ID".into())),
+fn show_key_mismatch_alert() {
+    alerts::show_message(loc!("login-failed"), loc!("key-does-not-match-account"));
+}
+
+/// Store a canary for accounts that don't have one, so other devices can check their key
+fn upload_canary(mut user: FurUser, key: [u8; 32]) {
+    let canary = match encryption::create_canary(&key) {
+        Ok(canary) => canary,
+        Err(e) => {
+            error!("Error creating key canary: {:?}", e);
+            return;
+        }
     };
 
-    let response = client
-        .post(format!("{}/api/refresh", server))
-        .json(&RefreshRequest {
-            refresh_token,
-            device_id,
-        })
-        .send()
+    spawn(async move {
+        let Ok(device_id) = generate_device_id() else {
+            return;
+        };
+        match post_authorized(
+            &mut user,
+            "/api/canary",
+            &CanaryRequest { device_id, canary },
+        )
         .await
-        .map_err(|e| ApiError::Network(Arc::new(e)))?;
-
-    if response.status().is_success() {
-        let refresh_response = response
-            .json::<RefreshResponse>()
-            .await
-            .map_err(|e| ApiError::Network(Arc::new(e)))?;
-        Ok(refresh_response.access_token)
-    } else {
-        Err(ApiError::TokenRefresh("Failed to refresh token".into()))
-    }
+        {
+            Ok(response) if response.status().is_success() => {}
+            Ok(response) => error!("Failed to store key canary: {}", response.status()),
+            Err(e) => error!("Failed to store key canary: {:?}", e),
+        }
+    });
 }
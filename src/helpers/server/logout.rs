@@ -15,6 +15,7 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use dioxus::{prelude::spawn, signals::Readable};
+use log::error;
 use reqwest::Client;
 use serde::Serialize;
 
@@ -22,7 +23,7 @@ use crate::{
     helpers::server::encryption,
     loc,
     localization::Localization,
-    models::fur_user::{FurUser, FurUserFields},
+    models::{fur_user::FurUser, fur_user_fields::FurUserFields},
     state,
 };
 
@@ -48,7 +49,7 @@ pub async fn server_logout(user: &FurUser) {
     let device_id = match encryption::generate_device_id() {
         Ok(id) => id,
         Err(e) => {
-            eprintln!("Failed to create device id for logout: {:?}", e);
+            error!("Failed to create device id for logout: {:?}", e);
             return;
         }
     };
@@ -60,7 +61,7 @@ pub async fn server_logout(user: &FurUser) {
         .send()
         .await
     {
-        eprintln!("Failed to send logout request: {}", e);
+        error!("Failed to send logout request: {}", e);
     }
 }
 
@@ -0,0 +1,184 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{collections::HashMap, fs, path::PathBuf};
+
+use chrono::{Local, LocalResult, TimeZone};
+use dioxus::signals::Readable;
+use fluent::FluentValue;
+use log::error;
+use serde::Serialize;
+
+use crate::{
+    constants::OFFICIAL_SERVER,
+    database,
+    helpers::views::alerts,
+    loc,
+    localization::Localization,
+    models::{
+        fur_error::{ErrorContext, FurErrorContext, FurResult},
+        fur_settings::get_data_path,
+        fur_sync_log::FurSyncLogEntry,
+        fur_user::FurUser,
+    },
+    state,
+};
+
+const DIAGNOSTICS_FORMAT_VERSION: u32 = 1;
+
+/// Sync history and state for bug reports, with identifying details removed
+#[derive(Serialize)]
+struct DiagnosticsBundle {
+    format_version: u32,
+    app_version: &'static str,
+    platform: &'static str,
+    exported_at: i64,
+    signed_in: bool,
+    custom_server: bool,
+    needs_full_sync: bool,
+    last_sync: i64,
+    devices_synced_through: i64,
+    sync_log: Vec<FurSyncLogEntry>,
+}
+
+pub fn get_sync_log() -> Vec<FurSyncLogEntry> {
+    match database::sync::retrieve_sync_log() {
+        Ok(entries) => entries,
+        Err(e) => {
+            error!("Error retrieving sync log: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+pub fn clear_sync_log() {
+    if let Err(e) = database::sync::delete_sync_log() {
+        error!("Error clearing sync log: {}", e);
+    }
+    *state::SYNC_LOG.write() = Vec::new();
+}
+
+pub fn entry_time(entry: &FurSyncLogEntry) -> String {
+    match Local.timestamp_opt(entry.started_at, 0) {
+        LocalResult::Single(time) => time.format("%Y-%m-%d %H:%M:%S").to_string(),
+        _ => entry.started_at.to_string(),
+    }
+}
+
+/// One-line description of a sync for the settings view
+pub fn entry_summary(entry: &FurSyncLogEntry) -> String {
+    let duration = format!("{:.1}", entry.duration_ms as f64 / 1000.0);
+    let mut parts = vec![if entry.error.is_some() {
+        loc!(
+            "sync-log-failed",
+            &HashMap::from([("duration", FluentValue::from(duration))])
+        )
+    } else {
+        loc!(
+            "sync-log-summary",
+            &HashMap::from([
+                (
+                    "uploaded",
+                    FluentValue::from(entry.uploaded.total() + entry.orphans_resent)
+                ),
+                ("downloaded", FluentValue::from(entry.downloaded.total())),
+                ("duration", FluentValue::from(duration)),
+            ])
+        )
+    }];
+    if !entry.decrypt_failures.is_empty() {
+        parts.push(loc!(
+            "sync-log-decrypt-failures",
+            &HashMap::from([("count", FluentValue::from(entry.decrypt_failures.len()))])
+        ));
+    }
+    parts.join(" · ")
+}
+
+pub fn export_diagnostics_pressed() {
+    match export_diagnostics() {
+        Ok(path) => alerts::show_message(
+            loc!("sync-history"),
+            loc!(
+                "diagnostics-exported",
+                &HashMap::from([("path", FluentValue::from(path.display().to_string()))])
+            ),
+        ),
+        Err(e) => alerts::show_error(&e),
+    }
+}
+
+fn export_diagnostics() -> FurResult<PathBuf> {
+    let user = state::USER.cloned();
+    let settings = state::SETTINGS.cloned();
+
+    let bundle = DiagnosticsBundle {
+        format_version: DIAGNOSTICS_FORMAT_VERSION,
+        app_version: env!("CARGO_PKG_VERSION"),
+        platform: std::env::consts::OS,
+        exported_at: Local::now().timestamp(),
+        signed_in: user.is_some(),
+        custom_server: user
+            .as_ref()
+            .is_some_and(|user| user.server != OFFICIAL_SERVER.to_string()),
+        needs_full_sync: settings.needs_full_sync,
+        last_sync: settings.last_sync,
+        devices_synced_through: settings.devices_synced_through,
+        sync_log: get_sync_log()
+            .into_iter()
+            .map(|entry| redact_entry(entry, user.as_ref()))
+            .collect(),
+    };
+
+    let mut path = get_data_path();
+    path.push(format!(
+        "furtherance-sync-diagnostics-{}.json",
+        Local::now().format("%Y%m%d-%H%M%S")
+    ));
+    let json =
+        serde_json::to_string_pretty(&bundle).in_context(FurErrorContext::ExportDiagnostics)?;
+    fs::write(&path, json).in_context(FurErrorContext::ExportDiagnostics)?;
+
+    Ok(path)
+}
+
+/// Replace uids with short one-way hashes and remove account details from errors
+fn redact_entry(mut entry: FurSyncLogEntry, user: Option<&FurUser>) -> FurSyncLogEntry {
+    entry.decrypt_failures = entry
+        .decrypt_failures
+        .iter()
+        .map(|failure| match failure.split_once(':') {
+            Some((record_type, uid)) => format!("{}:{}", record_type, redact_uid(uid)),
+            None => redact_uid(failure),
+        })
+        .collect();
+
+    if let (Some(error), Some(user)) = (entry.error.as_mut(), user) {
+        if !user.server.is_empty() {
+            *error = error.replace(&user.server, "[server]");
+        }
+        if !user.email.is_empty() {
+            *error = error.replace(&user.email, "[email]");
+        }
+    }
+
+    entry
+}
+
+/// Still lets the same record be spotted across entries
+fn redact_uid(uid: &str) -> String {
+    blake3::hash(uid.as_bytes()).to_hex()[..12].to_string()
+}
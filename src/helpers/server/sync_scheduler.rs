@@ -0,0 +1,152 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
+    time::Duration,
+};
+
+use chrono::{Local, TimeDelta};
+use dioxus::{prelude::spawn_forever, signals::Readable};
+use rand::{thread_rng, Rng};
+
+use crate::{
+    models::fur_sync_status::{FurSyncStatus, SyncState},
+    state,
+};
+
+use super::{api::ApiError, sync};
+
+/// Quiet period after a change before syncing, so a burst of edits shares one sync
+const DEBOUNCE_DELAY: Duration = Duration::from_secs(2);
+const FIRST_RETRY_DELAY: u64 = 5;
+const MAX_RETRY_DELAY: u64 = 1800;
+
+static SYNC_RUNNING: AtomicBool = AtomicBool::new(false);
+/// Set when a change comes in during a sync, so another one runs afterwards
+static SYNC_REQUESTED_AGAIN: AtomicBool = AtomicBool::new(false);
+static FAILED_ATTEMPTS: AtomicU32 = AtomicU32::new(0);
+/// Bumped whenever a scheduled sync is replaced, so the stale timer does nothing
+static TIMER_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Sync once changes stop coming in. While offline the changes wait for the next retry.
+pub fn request_sync_soon() {
+    if SYNC_RUNNING.load(Ordering::SeqCst) {
+        SYNC_REQUESTED_AGAIN.store(true, Ordering::SeqCst);
+        return;
+    }
+    if matches!(state::SYNC_STATUS.read().state, SyncState::Retrying { .. }) {
+        return;
+    }
+
+    set_state(SyncState::Pending);
+    sync_after(DEBOUNCE_DELAY);
+}
+
+/// Mark a sync as started. Returns false if one is already running.
+pub fn begin_sync() -> bool {
+    if SYNC_RUNNING.swap(true, Ordering::SeqCst) {
+        SYNC_REQUESTED_AGAIN.store(true, Ordering::SeqCst);
+        return false;
+    }
+
+    TIMER_GENERATION.fetch_add(1, Ordering::SeqCst);
+    set_state(SyncState::InFlight);
+    true
+}
+
+pub fn sync_succeeded() {
+    FAILED_ATTEMPTS.store(0, Ordering::SeqCst);
+    *state::SYNC_STATUS.write() = FurSyncStatus::new();
+    SYNC_RUNNING.store(false, Ordering::SeqCst);
+
+    if SYNC_REQUESTED_AGAIN.swap(false, Ordering::SeqCst) {
+        request_sync_soon();
+    }
+}
+
+/// Record a failed sync and schedule a retry if the error is temporary
+pub fn sync_failed(error: &ApiError, message: String) {
+    if !error.is_temporary() {
+        sync_stopped(Some(message));
+        return;
+    }
+
+    let attempt = FAILED_ATTEMPTS.fetch_add(1, Ordering::SeqCst);
+    let delay = retry_delay(attempt, error.retry_after());
+    *state::SYNC_STATUS.write() = FurSyncStatus {
+        state: SyncState::Retrying {
+            at: Local::now() + TimeDelta::from_std(delay).unwrap_or(TimeDelta::zero()),
+        },
+        last_error: Some(message),
+    };
+    SYNC_REQUESTED_AGAIN.store(false, Ordering::SeqCst);
+    SYNC_RUNNING.store(false, Ordering::SeqCst);
+
+    sync_after(delay);
+}
+
+/// End a sync that can't succeed by retrying
+pub fn sync_stopped(error_message: Option<String>) {
+    FAILED_ATTEMPTS.store(0, Ordering::SeqCst);
+    *state::SYNC_STATUS.write() = FurSyncStatus {
+        state: SyncState::Idle,
+        last_error: error_message,
+    };
+    SYNC_REQUESTED_AGAIN.store(false, Ordering::SeqCst);
+    SYNC_RUNNING.store(false, Ordering::SeqCst);
+}
+
+/// Cancel any scheduled sync or retry, e.g. after logging out
+pub fn reset() {
+    TIMER_GENERATION.fetch_add(1, Ordering::SeqCst);
+    FAILED_ATTEMPTS.store(0, Ordering::SeqCst);
+    SYNC_REQUESTED_AGAIN.store(false, Ordering::SeqCst);
+    *state::SYNC_STATUS.write() = FurSyncStatus::new();
+}
+
+fn sync_after(delay: Duration) {
+    let generation = TIMER_GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    spawn_forever(async move {
+        tokio::time::sleep(delay).await;
+        if TIMER_GENERATION.load(Ordering::SeqCst) == generation && state::USER.read().is_some() {
+            sync::request_sync();
+        }
+    });
+}
+
+/// Exponential backoff with jitter, unless the server said when to come back.
+/// A server's wait is kept within the backoff bounds so it can't cause a busy loop or stop sync.
+fn retry_delay(attempt: u32, retry_after: Option<u64>) -> Duration {
+    if let Some(seconds) = retry_after {
+        return Duration::from_secs(seconds.clamp(FIRST_RETRY_DELAY, MAX_RETRY_DELAY));
+    }
+
+    let max_delay = FIRST_RETRY_DELAY
+        .saturating_mul(2u64.saturating_pow(attempt))
+        .min(MAX_RETRY_DELAY);
+    // Wait between half and all of the delay so devices that failed together don't retry together
+    let jittered = thread_rng().gen_range(max_delay / 2..=max_delay);
+    Duration::from_secs(jittered)
+}
+
+fn set_state(sync_state: SyncState) {
+    let last_error = state::SYNC_STATUS.read().last_error.clone();
+    *state::SYNC_STATUS.write() = FurSyncStatus {
+        state: sync_state,
+        last_error,
+    };
+}
@@ -0,0 +1,46 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! A throwaway data folder for tests that use the database or settings
+
+use std::{
+    env, fs,
+    path::PathBuf,
+    process,
+    sync::{Mutex, MutexGuard},
+};
+
+use crate::database;
+
+static DATA_LOCK: Mutex<()> = Mutex::new(());
+
+/// Stands in for the user's data folder while testing
+pub fn data_path() -> PathBuf {
+    let path = env::temp_dir().join(format!("furtherance-tests-{}", process::id()));
+    fs::create_dir_all(&path).expect("Couldn't create the test data folder");
+    path
+}
+
+/// Start from an empty database. Tests share one data folder,
+/// so the returned guard keeps other tests out until it's dropped.
+pub fn fresh_database() -> MutexGuard<'static, ()> {
+    let guard = DATA_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let path = data_path();
+    fs::remove_dir_all(&path).expect("Couldn't clear the test data folder");
+    fs::create_dir_all(&path).expect("Couldn't create the test data folder");
+    database::init::db_init().expect("Couldn't create the test database");
+    guard
+}
@@ -0,0 +1,32 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use uuid::Uuid;
+
+/// Generate a new random identifier for a task, todo, or shortcut.
+///
+/// Uids are pure identity and never derived from a record's content, so editing
+/// a record never changes its uid. Records created by older versions carry a
+/// blake3 content hash instead; those are kept as-is and treated the same way.
+pub fn generate_uid() -> String {
+    Uuid::now_v7().to_string()
+}
+
+/// A uid for a record imported from another app, always the same for the same `source`,
+/// e.g. "ics:<event uid>". Importing the item again finds the record it created before.
+pub fn imported_uid(source: &str) -> String {
+    blake3::hash(source.as_bytes()).to_hex().to_string()
+}
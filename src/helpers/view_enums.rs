@@ -38,6 +38,38 @@ impl FurView {
     ];
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum FurLogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+impl FurLogLevel {
+    pub const ALL: [FurLogLevel; 4] = [
+        FurLogLevel::Error,
+        FurLogLevel::Warn,
+        FurLogLevel::Info,
+        FurLogLevel::Debug,
+    ];
+}
+
+impl std::fmt::Display for FurLogLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                FurLogLevel::Error => loc!("log-level-error"),
+                FurLogLevel::Warn => loc!("log-level-warn"),
+                FurLogLevel::Info => loc!("log-level-info"),
+                FurLogLevel::Debug => loc!("log-level-debug"),
+            }
+        )
+    }
+}
+
 impl std::fmt::Display for FurView {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
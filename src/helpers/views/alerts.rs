@@ -0,0 +1,43 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use dioxus::signals::Readable;
+use log::error;
+
+use crate::{loc, localization::Localization, models::fur_error::FurError, state};
+
+/// Show an alert with a single OK button
+pub fn show_message(title: String, message: String) {
+    let mut alert = state::ALERT.cloned();
+    alert.is_shown = true;
+    alert.title = title;
+    alert.message = message;
+    alert.confirm_button = (loc!("ok"), || close_alert());
+    alert.cancel_button = None;
+    *state::ALERT.write() = alert;
+}
+
+/// Log an error and tell the user about it instead of crashing
+pub fn show_error(error: &FurError) {
+    error!("{}", error);
+    show_message(error.title(), error.localized_message());
+}
+
+pub fn close_alert() {
+    let mut alert = state::ALERT.cloned();
+    alert.close();
+    *state::ALERT.write() = alert;
+}
@@ -0,0 +1,183 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+
+use dioxus::signals::Readable;
+use fluent::FluentValue;
+use log::error;
+
+use crate::{
+    helpers::views::alerts,
+    loc,
+    localization::Localization,
+    models::{
+        fur_invoice::{FurInvoice, FurTaxLine, InvoiceGrouping},
+        fur_settings::FurSettings,
+    },
+    services::{self, invoices::InvoiceOptions},
+    state,
+};
+
+/// Minutes line items can be rounded up to, with 0 for no rounding
+pub const ROUNDING_CHOICES: [i64; 5] = [0, 6, 15, 30, 60];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InvoiceFormat {
+    Pdf,
+    Html,
+    PdfAndHtml,
+}
+
+impl InvoiceFormat {
+    pub const ALL: [InvoiceFormat; 3] = [
+        InvoiceFormat::Pdf,
+        InvoiceFormat::Html,
+        InvoiceFormat::PdfAndHtml,
+    ];
+
+    pub fn label(&self) -> String {
+        match self {
+            InvoiceFormat::Pdf => loc!("format-pdf"),
+            InvoiceFormat::Html => loc!("format-html"),
+            InvoiceFormat::PdfAndHtml => loc!("format-pdf-and-html"),
+        }
+    }
+}
+
+pub fn grouping_label(grouping: InvoiceGrouping) -> String {
+    match grouping {
+        InvoiceGrouping::TaskName => loc!("group-by-task-name"),
+        InvoiceGrouping::Tag => loc!("group-by-tag"),
+    }
+}
+
+pub fn rounding_label(minutes: i64) -> String {
+    if minutes == 0 {
+        loc!("no-rounding")
+    } else {
+        loc!(
+            "round-up-to-minutes",
+            &HashMap::from([("minutes", FluentValue::from(minutes))])
+        )
+    }
+}
+
+pub fn show_invoice_sheet() {
+    let mut sheets = state::SHEETS.cloned();
+    sheets.new_invoice_is_shown = true;
+    *state::SHEETS.write() = sheets;
+}
+
+pub fn close_invoice_sheet() {
+    let mut sheets = state::SHEETS.cloned();
+    sheets.new_invoice_is_shown = false;
+    *state::SHEETS.write() = sheets;
+}
+
+/// The invoice the options would make, or nothing if the tasks can't be read
+pub fn preview_invoice(options: &InvoiceOptions) -> Option<FurInvoice> {
+    services::invoices::build(options, &state::SETTINGS.cloned())
+        .inspect_err(|e| error!("Error building invoice preview: {}", e))
+        .ok()
+}
+
+/// Issue the invoice, marking its tasks as invoiced, and save it in the export folder
+pub fn create_invoice(options: &InvoiceOptions, format: InvoiceFormat) {
+    let invoice = match services::invoices::build(options, &state::SETTINGS.cloned()) {
+        Ok(invoice) => invoice,
+        Err(e) => {
+            alerts::show_error(&e);
+            return;
+        }
+    };
+    if invoice.lines.is_empty() {
+        alerts::show_message(loc!("create-invoice"), loc!("no-billable-tasks"));
+        return;
+    }
+
+    let mut files: Vec<(String, Vec<u8>)> = Vec::new();
+    if format != InvoiceFormat::Html {
+        files.push((
+            services::invoices::file_name(&invoice, "pdf"),
+            services::invoices::to_pdf(&invoice),
+        ));
+    }
+    if format != InvoiceFormat::Pdf {
+        files.push((
+            services::invoices::file_name(&invoice, "html"),
+            services::invoices::to_html(&invoice).into_bytes(),
+        ));
+    }
+
+    let mut paths = Vec::new();
+    for (file_name, contents) in files {
+        match services::export::save(&file_name, contents) {
+            Ok(path) => paths.push(path.display().to_string()),
+            Err(e) => {
+                alerts::show_error(&e);
+                return;
+            }
+        }
+    }
+    // Only count the invoice as issued once it has been saved
+    if let Err(e) = services::invoices::issue(&invoice) {
+        alerts::show_error(&e);
+        return;
+    }
+
+    close_invoice_sheet();
+    alerts::show_message(
+        loc!("create-invoice"),
+        loc!(
+            "invoice-created",
+            &HashMap::from([
+                ("number", FluentValue::from(invoice.number)),
+                ("path", FluentValue::from(paths.join("\n"))),
+            ])
+        ),
+    );
+}
+
+pub fn set_invoice_sender(value: &str) {
+    change_invoice_settings(|settings| settings.change_invoice_sender(value));
+}
+
+pub fn set_invoice_number_prefix(value: &str) {
+    change_invoice_settings(|settings| settings.change_invoice_number_prefix(value));
+}
+
+pub fn change_invoice_next_number(delta: i64) {
+    change_invoice_settings(|settings| {
+        let next_number = settings.invoice_next_number + delta;
+        settings.change_invoice_next_number(&next_number)
+    });
+}
+
+/// Save the default taxes once what's typed can be read, e.g. "VAT 20%, City tax 1.5%"
+pub fn set_default_tax_lines(text: &str) {
+    if let Some(tax_lines) = FurTaxLine::parse_list(text) {
+        change_invoice_settings(|settings| settings.change_invoice_tax_lines(&tax_lines));
+    }
+}
+
+fn change_invoice_settings(change: impl FnOnce(&mut FurSettings) -> Result<(), std::io::Error>) {
+    let mut settings_clone = state::SETTINGS.cloned();
+    match change(&mut settings_clone) {
+        Ok(_) => *state::SETTINGS.write() = settings_clone,
+        Err(e) => error!("Error changing invoice settings: {}", e),
+    }
+}
@@ -14,7 +14,39 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::{loc, localization::Localization};
+use std::{collections::HashMap, path::Path};
+
+use chrono::{Local, NaiveDate};
+use dioxus::{prelude::spawn, signals::Readable};
+use fluent::FluentValue;
+use furtherance_core::local_api;
+use log::error;
+
+use crate::{
+    database,
+    helpers::{
+        local_api::restart_local_api,
+        server::{
+            encryption::{self, EncryptionError},
+            sync_engine::purge_expired_tombstones,
+        },
+        views::alerts,
+    },
+    loc,
+    localization::Localization,
+    models::{
+        fur_calendar_event::FurCalendarEvent,
+        fur_hook::FurHookEvent,
+        fur_sync_status::{FurSyncStatus, SyncState},
+        fur_user::FurUser,
+    },
+    services::{
+        self,
+        importers::{self, ColumnMapping, ImportField, ImportSource, ImportTable, PreviewRow},
+        timesheets::{TimesheetFilter, TimesheetFormat, TimesheetRows},
+    },
+    state,
+};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ServerChoices {
@@ -43,3 +75,424 @@ impl std::fmt::Display for ServerChoices {
         )
     }
 }
+
+/// Purge expired tombstones and reclaim the space they used
+pub fn compact_database() {
+    let settings = state::SETTINGS.cloned();
+    let signed_in = state::USER.read().is_some();
+    spawn(async move {
+        // VACUUM rewrites the whole file, so keep it off the UI thread
+        let result = tokio::task::spawn_blocking(move || {
+            purge_expired_tombstones(&settings, signed_in);
+            database::maintenance::vacuum()
+        })
+        .await;
+        match result {
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => error!("Error compacting database: {}", e),
+            Err(e) => error!("Error compacting database: {}", e),
+        }
+    });
+}
+
+/// Encrypt the database with a passphrase. Returns false if nothing changed.
+pub fn encrypt_database(passphrase: &str, confirmation: &str) -> bool {
+    if passphrase.is_empty() {
+        return false;
+    }
+    if passphrase != confirmation {
+        show_database_alert(loc!("passphrases-do-not-match"));
+        return false;
+    }
+
+    match database::encryption::encrypt_database(passphrase) {
+        Ok(_) => {
+            *state::DATABASE_ENCRYPTED.write() = true;
+            show_database_alert(loc!("database-encrypted"));
+            true
+        }
+        Err(e) => {
+            alerts::show_error(&e);
+            false
+        }
+    }
+}
+
+pub fn decrypt_database() {
+    match database::encryption::decrypt_database() {
+        Ok(_) => {
+            *state::DATABASE_ENCRYPTED.write() = false;
+            show_database_alert(loc!("database-decrypted"));
+        }
+        Err(e) => alerts::show_error(&e),
+    }
+}
+
+fn show_database_alert(message: String) {
+    alerts::show_message(loc!("database-encryption"), message);
+}
+
+/// Fingerprint of the sync key, for checking that two devices use the same one
+pub fn key_fingerprint_text(user: &FurUser) -> String {
+    match encryption::decrypt_encryption_key(
+        &user.encrypted_key,
+        &user.key_nonce,
+        &user.key_storage,
+    ) {
+        Ok(key) => encryption::key_fingerprint(&key),
+        Err(EncryptionError::Locked) => loc!("sync-key-locked"),
+        Err(_) => loc!("error-decrypting-key"),
+    }
+}
+
+pub fn sync_status_text(status: &FurSyncStatus) -> String {
+    match &status.state {
+        SyncState::Idle => match &status.last_error {
+            Some(error) => error.clone(),
+            None => loc!("sync-idle"),
+        },
+        SyncState::Pending => loc!("sync-pending"),
+        SyncState::InFlight => loc!("syncing"),
+        SyncState::Retrying { at } => loc!(
+            "sync-retrying",
+            &HashMap::from([
+                (
+                    "error",
+                    FluentValue::from(status.last_error.clone().unwrap_or_default())
+                ),
+                ("time", FluentValue::from(at.format("%H:%M:%S").to_string())),
+            ])
+        ),
+    }
+}
+
+pub fn set_local_api_enabled(enabled: bool) {
+    let mut settings_clone = state::SETTINGS.cloned();
+    let mut result = settings_clone.change_local_api_enabled(&enabled);
+    if result.is_ok() && enabled && settings_clone.local_api_token.is_empty() {
+        result = settings_clone.change_local_api_token(&local_api::generate_token());
+    }
+    match result {
+        Ok(_) => {
+            *state::SETTINGS.write() = settings_clone;
+            restart_local_api();
+        }
+        Err(e) => error!("Error changing local_api_enabled: {}", e),
+    }
+}
+
+/// Save a finished port edit and restart the server on it.
+/// Anything that isn't a usable port leaves the current one in place.
+pub fn set_local_api_port(value: &str) {
+    let Ok(port) = value.trim().parse::<i64>() else {
+        return;
+    };
+    let mut settings_clone = state::SETTINGS.cloned();
+    if port == settings_clone.local_api_port {
+        return;
+    }
+    match settings_clone.change_local_api_port(&port) {
+        Ok(_) => {
+            *state::SETTINGS.write() = settings_clone;
+            restart_local_api();
+        }
+        Err(e) => error!("Error changing local_api_port: {}", e),
+    }
+}
+
+/// Replace the token so anything using the old one loses access
+pub fn regenerate_local_api_token() {
+    let mut settings_clone = state::SETTINGS.cloned();
+    match settings_clone.change_local_api_token(&local_api::generate_token()) {
+        Ok(_) => {
+            *state::SETTINGS.write() = settings_clone;
+            restart_local_api();
+        }
+        Err(e) => error!("Error changing local_api_token: {}", e),
+    }
+}
+
+pub fn hook_label(event: FurHookEvent) -> String {
+    match event {
+        FurHookEvent::TimerStarted => loc!("hook-timer-started"),
+        FurHookEvent::TimerStopped => loc!("hook-timer-stopped"),
+        FurHookEvent::PomodoroEnded => loc!("hook-pomodoro-ended"),
+        FurHookEvent::TodoCompleted => loc!("hook-todo-completed"),
+        FurHookEvent::SyncFinished => loc!("hook-sync-finished"),
+    }
+}
+
+pub fn set_hook(event: FurHookEvent, value: &str) {
+    let mut settings_clone = state::SETTINGS.cloned();
+    match settings_clone.change_hook(event, value) {
+        Ok(_) => *state::SETTINGS.write() = settings_clone,
+        Err(e) => error!("Error changing {} hook: {}", event.name(), e),
+    }
+}
+
+/// Save the tasks from `first_day` through `last_day`, both YYYY-MM-DD, as an .ics file
+pub fn export_calendar(first_day: &str, last_day: &str, merge_contiguous: bool) {
+    let Some((first_day, last_day)) = parse_date_range(first_day, last_day) else {
+        alerts::show_message(loc!("export-calendar"), loc!("invalid-date-range"));
+        return;
+    };
+
+    let result = services::tasks::on_dates(first_day, last_day).and_then(|tasks| {
+        services::export::save(
+            &format!("furtherance-{}-{}.ics", first_day, last_day),
+            &services::calendar::tasks_ics(&tasks, merge_contiguous),
+        )
+    });
+    match result {
+        Ok(path) => alerts::show_message(
+            loc!("export-calendar"),
+            loc!(
+                "exported-to",
+                &HashMap::from([("path", FluentValue::from(path.display().to_string()))])
+            ),
+        ),
+        Err(e) => alerts::show_error(&e),
+    }
+}
+
+/// Save the Monday-to-Sunday grid of time for the week containing `week`
+pub fn export_timesheet(
+    week: &str,
+    rows: TimesheetRows,
+    format: TimesheetFormat,
+    projects: &str,
+    tags: &str,
+) {
+    let Ok(week) = NaiveDate::parse_from_str(week.trim(), "%Y-%m-%d") else {
+        alerts::show_message(loc!("export-timesheet"), loc!("invalid-date"));
+        return;
+    };
+
+    let filter = TimesheetFilter::parse(projects, tags);
+    let show_seconds = state::SETTINGS.read().show_seconds;
+    let result = services::timesheets::for_week(week, rows, &filter).and_then(|timesheet| {
+        services::export::save(
+            &services::timesheets::file_name(&timesheet, format),
+            services::timesheets::render(&timesheet, format, show_seconds),
+        )
+    });
+    match result {
+        Ok(path) => alerts::show_message(
+            loc!("export-timesheet"),
+            loc!(
+                "exported-to",
+                &HashMap::from([("path", FluentValue::from(path.display().to_string()))])
+            ),
+        ),
+        Err(e) => alerts::show_error(&e),
+    }
+}
+
+pub fn timesheet_rows_label(rows: TimesheetRows) -> String {
+    match rows {
+        TimesheetRows::Task => loc!("rows-by-task"),
+        TimesheetRows::Project => loc!("rows-by-project"),
+    }
+}
+
+/// Save every task, todo, shortcut and the settings as a JSON archive
+pub fn export_archive() {
+    let result = services::archive::create(&state::SETTINGS.cloned())
+        .and_then(|archive| services::archive::to_json(&archive))
+        .and_then(|json| {
+            services::export::save(
+                &format!("furtherance-{}.json", Local::now().format("%Y-%m-%d")),
+                &json,
+            )
+        });
+    match result {
+        Ok(path) => alerts::show_message(
+            loc!("export-archive"),
+            loc!(
+                "exported-to",
+                &HashMap::from([("path", FluentValue::from(path.display().to_string()))])
+            ),
+        ),
+        Err(e) => alerts::show_error(&e),
+    }
+}
+
+/// Merge a JSON archive into the database, keeping whichever copy of a record is newer
+pub fn import_archive(path: &str, include_settings: bool) {
+    let result = services::archive::read(Path::new(path.trim()))
+        .and_then(|archive| services::archive::import(&archive, include_settings));
+    match result {
+        Ok(import) => {
+            let total = import.total();
+            alerts::show_message(
+                loc!("import-archive"),
+                loc!(
+                    "archive-imported",
+                    &HashMap::from([
+                        ("added", FluentValue::from(total.added)),
+                        ("updated", FluentValue::from(total.updated)),
+                        ("unchanged", FluentValue::from(total.unchanged)),
+                    ])
+                ),
+            );
+        }
+        Err(e) => alerts::show_error(&e),
+    }
+}
+
+pub fn parse_date_range(first_day: &str, last_day: &str) -> Option<(NaiveDate, NaiveDate)> {
+    let first_day = NaiveDate::parse_from_str(first_day, "%Y-%m-%d").ok()?;
+    let last_day = NaiveDate::parse_from_str(last_day, "%Y-%m-%d").ok()?;
+    (first_day <= last_day).then_some((first_day, last_day))
+}
+
+/// Read the events in an .ics file and show them for picking which to import
+pub fn load_calendar_file(path: &str) {
+    match services::calendar::read_ics(Path::new(path.trim())) {
+        Ok(events) if events.is_empty() => {
+            alerts::show_message(loc!("import-calendar"), loc!("no-events-found"))
+        }
+        Ok(events) => {
+            let mut sheets = state::SHEETS.cloned();
+            sheets.import_calendar_sheet = Some(events);
+            *state::SHEETS.write() = sheets;
+        }
+        Err(e) => alerts::show_error(&e),
+    }
+}
+
+pub fn import_calendar_events(events: &[FurCalendarEvent], as_todos: bool) {
+    let settings = state::SETTINGS.cloned();
+    let result = if as_todos {
+        services::calendar::import_as_todos(events, &settings)
+    } else {
+        services::calendar::import_as_tasks(events, &settings)
+    };
+    match result {
+        Ok(count) => {
+            close_import_calendar_sheet();
+            alerts::show_message(
+                loc!("import-calendar"),
+                loc!(
+                    "events-imported",
+                    &HashMap::from([
+                        ("imported", FluentValue::from(count.imported)),
+                        ("skipped", FluentValue::from(count.skipped)),
+                    ])
+                ),
+            );
+        }
+        Err(e) => alerts::show_error(&e),
+    }
+}
+
+pub fn close_import_calendar_sheet() {
+    let mut sheets = state::SHEETS.cloned();
+    sheets.import_calendar_sheet = None;
+    *state::SHEETS.write() = sheets;
+}
+
+pub fn set_category_mapping(category: &str, task_input: &str) {
+    let mut settings_clone = state::SETTINGS.cloned();
+    match settings_clone.change_category_mapping(category, task_input) {
+        Ok(_) => *state::SETTINGS.write() = settings_clone,
+        Err(e) => error!("Error changing calendar category mapping: {}", e),
+    }
+}
+
+/// When a calendar event happens, as shown in the import sheet
+pub fn calendar_event_time(event: &FurCalendarEvent) -> String {
+    if event.all_day {
+        return event.start.format("%Y-%m-%d").to_string();
+    }
+    let time = format!(
+        "{} – {}",
+        event.start.format("%Y-%m-%d %H:%M"),
+        event.end.format("%H:%M")
+    );
+    match &event.unknown_time_zone {
+        Some(zone) => format!(
+            "{} ({})",
+            time,
+            loc!(
+                "calendar-unknown-time-zone",
+                &HashMap::from([("zone", FluentValue::from(zone.clone()))])
+            )
+        ),
+        None => time,
+    }
+}
+
+/// Read another tracker's export and show how its entries would be imported
+pub fn load_tracker_file(path: &str, source: ImportSource) {
+    match importers::read(Path::new(path.trim()), source) {
+        Ok(table) if table.rows.is_empty() => {
+            alerts::show_message(import_tracker_title(source), loc!("no-entries-found"))
+        }
+        Ok(table) => {
+            let mut sheets = state::SHEETS.cloned();
+            sheets.import_tasks_sheet = Some(table);
+            *state::SHEETS.write() = sheets;
+        }
+        Err(e) => alerts::show_error(&e),
+    }
+}
+
+/// What each row of the table would become, or nothing if the database can't be read
+pub fn preview_import(table: &ImportTable, mapping: &ColumnMapping) -> Vec<PreviewRow> {
+    importers::preview(table, mapping).unwrap_or_else(|e| {
+        error!("Error previewing import: {}", e);
+        Vec::new()
+    })
+}
+
+pub fn import_tracker_rows(source: ImportSource, rows: &[PreviewRow]) {
+    match importers::import(rows) {
+        Ok(count) => {
+            close_import_tasks_sheet();
+            alerts::show_message(
+                import_tracker_title(source),
+                loc!(
+                    "tasks-imported",
+                    &HashMap::from([
+                        ("imported", FluentValue::from(count.imported)),
+                        ("skipped", FluentValue::from(count.skipped)),
+                    ])
+                ),
+            );
+        }
+        Err(e) => alerts::show_error(&e),
+    }
+}
+
+pub fn close_import_tasks_sheet() {
+    let mut sheets = state::SHEETS.cloned();
+    sheets.import_tasks_sheet = None;
+    *state::SHEETS.write() = sheets;
+}
+
+pub fn import_tracker_title(source: ImportSource) -> String {
+    loc!(
+        "import-from-tracker",
+        &HashMap::from([("tracker", FluentValue::from(source.name()))])
+    )
+}
+
+pub fn import_field_label(field: ImportField) -> String {
+    match field {
+        ImportField::Description => loc!("import-field-description"),
+        ImportField::Client => loc!("import-field-client"),
+        ImportField::Project => loc!("import-field-project"),
+        ImportField::Task => loc!("import-field-task"),
+        ImportField::Tags => loc!("import-field-tags"),
+        ImportField::Billable => loc!("import-field-billable"),
+        ImportField::Rate => loc!("import-field-rate"),
+        ImportField::Amount => loc!("import-field-amount"),
+        ImportField::Currency => loc!("import-field-currency"),
+        ImportField::StartDate => loc!("import-field-start-date"),
+        ImportField::StartTime => loc!("import-field-start-time"),
+        ImportField::EndDate => loc!("import-field-end-date"),
+        ImportField::EndTime => loc!("import-field-end-time"),
+        ImportField::Duration => loc!("import-field-duration"),
+    }
+}
@@ -14,18 +14,8 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::{database, models::fur_shortcut::FurShortcut, state};
+use crate::{services, state};
 
 pub fn update_all_shortcuts() {
-    *state::SHORTCUTS.write() = get_all_shortcuts();
-}
-
-pub fn get_all_shortcuts() -> Vec<FurShortcut> {
-    match database::shortcuts::retrieve_existing_shortcuts() {
-        Ok(shortcuts) => shortcuts,
-        Err(e) => {
-            eprintln!("Error reading shortcuts from database: {}", e);
-            vec![]
-        }
-    }
+    *state::SHORTCUTS.write() = services::shortcuts::all();
 }
@@ -16,89 +16,53 @@
 
 use std::time::Duration;
 
-use chrono::{DateTime, Local, TimeDelta};
-use dioxus::{
-    prelude::{spawn, spawn_forever},
-    signals::Readable,
-};
+use chrono::{DateTime, Local};
+use dioxus::{prelude::spawn, signals::Readable};
+use log::error;
 use std::sync::Once;
 
 use crate::{
-    database, formatters,
-    helpers::{
-        server::sync::{request_sync, sync_after_change},
-        views::task_history::update_task_history,
-    },
+    helpers::views::alerts,
     loc,
     localization::Localization,
     models::{
-        fur_persist::{reset_persisting_timer, FurPersist},
-        fur_task::FurTask,
+        fur_error::FurResult, fur_persist::FurPersist, fur_pomodoro::FurPomodoro,
+        fur_settings::FurSettings,
     },
-    state,
+    services, state,
 };
 
 static TIMER_INIT: Once = Once::new();
+/// How often the saved timer is read back, so a timer started or stopped from the CLI shows here
+const SAVED_TIMER_POLL_SECONDS: u64 = 5;
 
 pub fn ensure_timer_running() {
     TIMER_INIT.call_once(|| {
         spawn(async move {
+            let mut seconds_until_poll = SAVED_TIMER_POLL_SECONDS;
             loop {
                 tokio::time::sleep(Duration::from_secs(1)).await;
+                seconds_until_poll -= 1;
+                if seconds_until_poll == 0 {
+                    seconds_until_poll = SAVED_TIMER_POLL_SECONDS;
+                    show_saved_timer().await;
+                }
                 if state::TIMER_IS_RUNNING.cloned() {
                     let duration =
                         Local::now().signed_duration_since(state::TIMER_START_TIME.cloned());
-                    let seconds_elapsed = duration.num_seconds();
-                    *state::TIMER_TEXT.write() = get_timer_text(seconds_elapsed);
+                    *state::TIMER_TEXT.write() = get_timer_text(duration.num_seconds());
 
                     let settings = state::SETTINGS.cloned();
-                    let mut alert = state::ALERT.cloned();
-                    let pomodoro = state::POMODORO.cloned();
-
+                    // Check if other alert is being displayed so as not to replace it
                     if settings.pomodoro
-                        && state::TIMER_TEXT.cloned() == "0:00:00".to_string()
-                        && seconds_elapsed > 2
+                        && state::TIMER_TEXT.cloned() == "0:00:00"
+                        && !state::ALERT.read().is_shown
                     {
-                        // Check if other alert is being displayed so as not to replace it
-                        if !alert.is_shown {
-                            if pomodoro.on_break {
-                                // TODO: Show notification
-                                // show_notification(
-                                //     NotificationType::BreakOver,
-                                //     &self.localization,
-                                //     self.fur_settings.pomodoro_notification_alarm_sound,
-                                // );
-                                alert.is_shown = true;
-                                alert.title = loc!("break-over-title");
-                                alert.message = loc!("break-over-description");
-                                alert.confirm_button =
-                                    (loc!("continue"), || continue_after_break());
-                                alert.cancel_button = Some((loc!("stop"), || stop_after_break()));
-                                *state::ALERT.write() = alert;
-                            } else {
-                                // TODO: Show notification
-                                // show_notification(
-                                //     NotificationType::PomodoroOver,
-                                //     &self.localization,
-                                //     self.fur_settings.pomodoro_notification_alarm_sound,
-                                // );
-                                alert.is_shown = true;
-                                alert.title = loc!("pomodoro-over-title");
-                                alert.message = loc!("pomodoro-over-description");
-                                alert.confirm_button = if settings.pomodoro_extended_breaks
-                                    && pomodoro.sessions % settings.pomodoro_extended_break_interval
-                                        == 0
-                                {
-                                    (loc!("long-break"), || start_break())
-                                } else {
-                                    (loc!("break"), || start_break())
-                                };
-                                alert.cancel_button =
-                                    Some((loc!("stop"), || stop_pomodoro_timer()));
-                                *state::ALERT.write() = alert;
-                            }
+                        match services::timer::check_pomodoro(&settings) {
+                            Ok(Some(pomodoro)) => show_pomodoro_alert(&pomodoro, &settings),
+                            Ok(None) => {}
+                            Err(e) => error!("{}", e),
                         }
-                        continue;
                     }
                 }
             }
@@ -106,50 +70,35 @@ pub fn ensure_timer_running() {
     });
 }
 
-pub fn stop_timer(stop_time: DateTime<Local>) {
-    *state::TIMER_IS_RUNNING.write() = false;
+/// Save the running task and stop the timer. The timer keeps running if the task can't be saved.
+pub fn stop_timer(stop_time: DateTime<Local>) -> FurResult<()> {
+    services::timer::stop(&current_timer(), stop_time)?;
 
-    let (name, project, tags, rate) = formatters::split_task_input(&state::TASK_INPUT.cloned());
-    database::tasks::insert_task(&FurTask::new(
-        name,
-        state::TIMER_START_TIME.cloned(),
-        stop_time,
-        tags,
-        project,
-        rate,
-        String::new(),
-    ))
-    .expect("Couldn't write task to database.");
+    *state::TIMER_IS_RUNNING.write() = false;
+    refresh_pomodoro();
+    reset_timer();
+    Ok(())
+}
 
+/// Stop the timer without saving a task, e.g. at the end of a pomodoro break
+pub fn cancel_timer() {
+    services::timer::cancel();
+    *state::TIMER_IS_RUNNING.write() = false;
+    refresh_pomodoro();
     reset_timer();
 }
 
 pub fn start_timer() {
-    *state::TIMER_START_TIME.write() = Local::now();
-    *state::TIMER_IS_RUNNING.write() = true;
     let settings = state::SETTINGS.cloned();
-    let mut pomodoro = state::POMODORO.cloned();
-
-    if settings.pomodoro && !pomodoro.on_break {
-        pomodoro.sessions += 1;
-        *state::POMODORO.write() = pomodoro;
-    }
-
-    ensure_timer_running();
-
-    if let Err(e) = database::persistence::update_persisting_timer(&FurPersist {
-        is_running: true,
-        task_input: state::TASK_INPUT.cloned(),
-        start_time: state::TIMER_START_TIME.cloned(),
-    }) {
-        eprintln!("Error updating persisting timer: {}", e);
+    match services::timer::start(&state::TASK_INPUT.cloned(), Local::now(), &settings) {
+        Ok(timer) => show_running(timer),
+        Err(e) => alerts::show_error(&e),
     }
 }
 
 pub fn reset_timer() {
     *state::TASK_INPUT.write() = String::new();
     *state::TIMER_TEXT.write() = get_timer_text(0);
-    reset_persisting_timer();
 }
 
 pub fn get_timer_text(seconds_elapsed: i64) -> String {
@@ -162,34 +111,15 @@ pub fn get_timer_text(seconds_elapsed: i64) -> String {
 
 fn get_running_timer_text(seconds_elapsed: i64) -> String {
     let settings = state::SETTINGS.cloned();
-    let pomodoro = state::POMODORO.cloned();
 
     if settings.pomodoro {
-        let stop_time = if pomodoro.on_break {
-            if settings.pomodoro_extended_breaks
-                && pomodoro.sessions % settings.pomodoro_extended_break_interval == 0
-            {
-                state::TIMER_START_TIME.cloned()
-                    + TimeDelta::minutes(settings.pomodoro_extended_break_length)
-            } else {
-                state::TIMER_START_TIME.cloned()
-                    + TimeDelta::minutes(settings.pomodoro_break_length)
-            }
-        } else {
-            if pomodoro.snoozed {
-                pomodoro.snoozed_at + TimeDelta::minutes(settings.pomodoro_snooze_length)
-            } else {
-                state::TIMER_START_TIME.cloned() + TimeDelta::minutes(settings.pomodoro_length)
-            }
-        };
-
-        let seconds_until_end =
-            (stop_time - state::TIMER_START_TIME.cloned()).num_seconds() - seconds_elapsed;
-        if seconds_until_end > 0 {
-            seconds_to_formatted_duration(seconds_until_end, true)
-        } else {
-            "0:00:00".to_string()
-        }
+        let seconds_left = services::timer::pomodoro_seconds_left(
+            &current_timer(),
+            &state::POMODORO.cloned(),
+            &settings,
+            Local::now(),
+        );
+        seconds_to_formatted_duration(seconds_left, true)
     } else {
         seconds_to_formatted_duration(seconds_elapsed, true)
     }
@@ -197,22 +127,10 @@ fn get_running_timer_text(seconds_elapsed: i64) -> String {
 
 pub fn get_stopped_timer_text() -> String {
     let settings = state::SETTINGS.cloned();
-    let pomodoro = state::POMODORO.cloned();
 
     if settings.pomodoro {
-        if pomodoro.on_break {
-            if settings.pomodoro_extended_breaks
-                && pomodoro.sessions % settings.pomodoro_extended_break_interval == 0
-            {
-                seconds_to_formatted_duration(settings.pomodoro_extended_break_length * 60, true)
-            } else {
-                seconds_to_formatted_duration(settings.pomodoro_break_length * 60, true)
-            }
-        } else if pomodoro.snoozed {
-            seconds_to_formatted_duration(settings.pomodoro_snooze_length * 60, true)
-        } else {
-            seconds_to_formatted_duration(settings.pomodoro_length * 60, true)
-        }
+        let length = services::timer::pomodoro_length_seconds(&state::POMODORO.cloned(), &settings);
+        seconds_to_formatted_duration(length, true)
     } else {
         "0:00:00".to_string()
     }
@@ -239,73 +157,122 @@ fn seconds_to_hm(total_seconds: i64) -> String {
     format!("{}:{:02}", h, m)
 }
 
-fn stop_pomodoro_timer() {
-    let settings = state::SETTINGS.cloned();
-    let mut alert = state::ALERT.cloned();
-    let mut pomodoro = state::POMODORO.cloned();
+fn current_timer() -> FurPersist {
+    FurPersist {
+        is_running: state::TIMER_IS_RUNNING.cloned(),
+        task_input: state::TASK_INPUT.cloned(),
+        start_time: state::TIMER_START_TIME.cloned(),
+    }
+}
 
-    pomodoro.snoozed = false;
-    stop_timer(Local::now());
-    alert.is_shown = false;
-    *state::ALERT.write() = alert;
-    pomodoro.sessions = 0;
-    *state::POMODORO.write() = pomodoro;
-    update_task_history(settings.days_to_show);
-    spawn_forever(async move {
-        request_sync();
-    });
+fn show_running(timer: FurPersist) {
+    *state::TASK_INPUT.write() = timer.task_input;
+    *state::TIMER_START_TIME.write() = timer.start_time;
+    *state::TIMER_IS_RUNNING.write() = true;
+    refresh_pomodoro();
+    ensure_timer_running();
 }
 
-fn start_break() {
-    let settings = state::SETTINGS.cloned();
-    let mut alert = state::ALERT.cloned();
-    let mut pomodoro = state::POMODORO.cloned();
+/// Show the timer as saved, picking up changes made elsewhere such as the CLI
+async fn show_saved_timer() {
+    let shown = current_timer();
+    let saved = tokio::task::spawn_blocking(|| -> FurResult<_> {
+        Ok((services::timer::running()?, services::timer::pomodoro()?))
+    })
+    .await;
+    let (timer, pomodoro) = match saved {
+        Ok(Ok(saved)) => saved,
+        Ok(Err(e)) => {
+            error!("{}", e);
+            return;
+        }
+        Err(e) => {
+            error!("Error reading the saved timer: {}", e);
+            return;
+        }
+    };
+    // The timer changed here while it was being read, so what was read may be stale
+    if current_timer() != shown {
+        return;
+    }
 
-    let original_task_input = state::TASK_INPUT.cloned();
-    pomodoro.on_break = true;
-    pomodoro.snoozed = false;
-    *state::POMODORO.write() = pomodoro;
-    stop_timer(Local::now());
-    *state::TASK_INPUT.write() = original_task_input;
-    alert.is_shown = false;
-    *state::ALERT.write() = alert;
-    start_timer();
-    update_task_history(settings.days_to_show);
-    spawn_forever(async move {
-        request_sync();
-    });
+    if *state::POMODORO.read() != pomodoro {
+        *state::POMODORO.write() = pomodoro;
+        if !shown.is_running {
+            *state::TIMER_TEXT.write() = get_stopped_timer_text();
+        }
+    }
+    match timer {
+        Some(timer) if !shown.is_running || timer.start_time != shown.start_time => {
+            show_running(timer)
+        }
+        None if shown.is_running => {
+            *state::TIMER_IS_RUNNING.write() = false;
+            reset_timer();
+        }
+        _ => {}
+    }
 }
 
-fn stop_after_break() {
-    let settings = state::SETTINGS.cloned();
+fn refresh_pomodoro() {
+    match services::timer::pomodoro() {
+        Ok(pomodoro) => *state::POMODORO.write() = pomodoro,
+        Err(e) => error!("{}", e),
+    }
+}
+
+fn show_pomodoro_alert(pomodoro: &FurPomodoro, settings: &FurSettings) {
     let mut alert = state::ALERT.cloned();
-    let mut pomodoro = state::POMODORO.cloned();
-    *state::TIMER_IS_RUNNING.write() = false;
-    pomodoro.on_break = false;
-    pomodoro.snoozed = false;
-    pomodoro.sessions = 0;
-    *state::POMODORO.write() = pomodoro;
-    reset_timer();
-    alert.is_shown = false;
+    alert.is_shown = true;
+    if pomodoro.on_break {
+        // TODO: Show notification
+        alert.title = loc!("break-over-title");
+        alert.message = loc!("break-over-description");
+        alert.confirm_button = (loc!("continue"), || continue_after_break());
+        alert.cancel_button = Some((loc!("stop"), || stop_after_break()));
+    } else {
+        // TODO: Show notification
+        alert.title = loc!("pomodoro-over-title");
+        alert.message = loc!("pomodoro-over-description");
+        alert.confirm_button = if services::timer::is_long_break(pomodoro, settings) {
+            (loc!("long-break"), || start_break())
+        } else {
+            (loc!("break"), || start_break())
+        };
+        alert.cancel_button = Some((loc!("stop"), || stop_pomodoro_timer()));
+    }
     *state::ALERT.write() = alert;
-    update_task_history(settings.days_to_show);
 }
 
-fn continue_after_break() {
-    let settings = state::SETTINGS.cloned();
-    let mut alert = state::ALERT.cloned();
-    let mut pomodoro = state::POMODORO.cloned();
+fn stop_pomodoro_timer() {
+    if let Err(e) = stop_timer(Local::now()) {
+        alerts::show_error(&e);
+        return;
+    }
+    alerts::close_alert();
+}
 
-    *state::TIMER_IS_RUNNING.write() = false;
-    let original_task_input = state::TASK_INPUT.cloned();
-    pomodoro.on_break = false;
-    pomodoro.snoozed = false;
-    *state::POMODORO.write() = pomodoro;
-    reset_timer();
-    *state::TASK_INPUT.write() = original_task_input;
-    alert.is_shown = false;
-    *state::ALERT.write() = alert;
-    start_timer();
-    update_task_history(settings.days_to_show);
-    sync_after_change();
+fn start_break() {
+    match services::timer::start_break(&current_timer(), &state::SETTINGS.cloned()) {
+        Ok(timer) => {
+            alerts::close_alert();
+            show_running(timer);
+        }
+        Err(e) => alerts::show_error(&e),
+    }
+}
+
+fn stop_after_break() {
+    cancel_timer();
+    alerts::close_alert();
+}
+
+fn continue_after_break() {
+    match services::timer::continue_after_break(&current_timer(), &state::SETTINGS.cloned()) {
+        Ok(timer) => {
+            alerts::close_alert();
+            show_running(timer);
+        }
+        Err(e) => alerts::show_error(&e),
+    }
 }
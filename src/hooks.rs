@@ -0,0 +1,157 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! User hooks: a command or webhook run when the timer, a todo or a sync does something.
+//! A failing hook is logged and otherwise ignored, so it can't get in the way of tracking.
+
+use std::{process::Stdio, time::Duration};
+
+use chrono::Local;
+use log::{debug, info, warn};
+use serde_json::{json, Map, Value};
+use tokio::{io::AsyncWriteExt, process::Command};
+
+use crate::{
+    events::CoreEvent,
+    helpers::formatters,
+    models::{
+        fur_hook::{FurHookAction, FurHookEvent},
+        fur_settings::FurSettings,
+    },
+};
+
+/// The hook event for `event` and the JSON its hook receives, or None if hooks don't run for it
+pub fn payload(event: &CoreEvent) -> Option<(FurHookEvent, Value)> {
+    let (hook_event, key, data) = match event {
+        CoreEvent::TimerStarted(timer) => {
+            let (name, project, tags, rate) = formatters::split_task_input(&timer.task_input);
+            let data = json!({
+                "task_input": timer.task_input,
+                "name": name,
+                "project": project,
+                "tags": tags,
+                "rate": rate,
+                "start_time": timer.start_time.to_rfc3339(),
+            });
+            (FurHookEvent::TimerStarted, "timer", data)
+        }
+        CoreEvent::TimerStopped(task) => (FurHookEvent::TimerStopped, "task", json!(task)),
+        CoreEvent::PomodoroEnded { on_break } => (
+            FurHookEvent::PomodoroEnded,
+            "pomodoro",
+            json!({ "on_break": on_break }),
+        ),
+        CoreEvent::TodoCompleted(todo) => (FurHookEvent::TodoCompleted, "todo", json!(todo)),
+        CoreEvent::SyncFinished(entry) => (FurHookEvent::SyncFinished, "sync", json!(entry)),
+        _ => return None,
+    };
+
+    let mut payload = Map::new();
+    payload.insert("event".to_string(), json!(hook_event.name()));
+    payload.insert("time".to_string(), json!(Local::now().to_rfc3339()));
+    payload.insert(key.to_string(), data);
+    Some((hook_event, Value::Object(payload)))
+}
+
+/// Run the hook `settings` has for `event`, if there is one, and log how it went
+pub async fn run(event: CoreEvent, settings: FurSettings) {
+    let Some((hook_event, payload)) = payload(&event) else {
+        return;
+    };
+    let Some(action) = FurHookAction::parse(settings.hook(hook_event)) else {
+        return;
+    };
+    let timeout = Duration::from_secs(settings.hook_timeout_seconds.max(1) as u64);
+
+    // The command or URL itself isn't logged since it may hold a token
+    let result = match action {
+        FurHookAction::Command(command) => run_command(&command, &payload, timeout).await,
+        FurHookAction::Webhook(url) => post(&url, &payload, timeout).await,
+    };
+    match result {
+        Ok(()) => info!("Ran the {} hook", hook_event.name()),
+        Err(message) => warn!("The {} hook failed: {}", hook_event.name(), message),
+    }
+}
+
+async fn run_command(command: &str, payload: &Value, timeout: Duration) -> Result<(), String> {
+    let mut child = shell(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        // Dropping the child when the timeout elapses stops it
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| e.to_string())?;
+
+    let input = payload.to_string();
+    let stdin = child.stdin.take();
+    let finished = async move {
+        if let Some(mut stdin) = stdin {
+            // A hook that doesn't read its input may exit before it's written
+            let _ = stdin.write_all(input.as_bytes()).await;
+        }
+        child.wait_with_output().await
+    };
+    let output = tokio::time::timeout(timeout, finished)
+        .await
+        .map_err(|_| format!("timed out after {}s", timeout.as_secs()))?
+        .map_err(|e| e.to_string())?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if !stdout.trim().is_empty() {
+        debug!("Hook output: {}", stdout.trim());
+    }
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "{}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+#[cfg(not(windows))]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+async fn post(url: &str, payload: &Value, timeout: Duration) -> Result<(), String> {
+    let response = reqwest::Client::new()
+        .post(url)
+        .timeout(timeout)
+        .json(payload)
+        .send()
+        .await
+        .map_err(|e| e.without_url().to_string())?;
+    let status = response.status();
+    if status.is_success() {
+        Ok(())
+    } else {
+        Err(format!("the server responded {}", status))
+    }
+}
@@ -0,0 +1,84 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Furtherance's data, sync and timer logic without any UI.
+//! Front ends call the services and subscribe to events to learn what changed.
+
+pub mod database {
+    pub mod encryption;
+    pub mod init;
+    pub mod invoices;
+    pub mod maintenance;
+    pub mod persistence;
+    pub mod shortcuts;
+    pub mod sync;
+    pub mod tasks;
+    pub mod todos;
+}
+pub mod events;
+pub mod hooks;
+pub mod helpers {
+    pub mod formatters;
+    pub mod logger;
+    pub mod pdf;
+    #[cfg(test)]
+    pub mod test_data;
+    pub mod uid;
+    pub mod view_enums;
+}
+pub mod local_api;
+pub mod localization;
+pub mod models {
+    pub mod fur_archive;
+    pub mod fur_calendar_event;
+    pub mod fur_device;
+    pub mod fur_error;
+    pub mod fur_hook;
+    pub mod fur_invoice;
+    pub mod fur_key_rotation;
+    pub mod fur_persist;
+    pub mod fur_pomodoro;
+    pub mod fur_settings;
+    pub mod fur_shortcut;
+    pub mod fur_sync_log;
+    pub mod fur_sync_progress;
+    pub mod fur_task;
+    pub mod fur_task_group;
+    pub mod fur_todo;
+    pub mod fur_user;
+}
+pub mod server {
+    pub mod api;
+    pub mod encryption;
+    pub mod kdf;
+    pub mod key_storage;
+    pub mod sync;
+}
+pub mod services {
+    pub mod archive;
+    pub mod calendar;
+    pub mod export;
+    pub mod importers;
+    pub mod invoices;
+    pub mod reports;
+    pub mod settings;
+    pub mod shortcuts;
+    pub mod sync;
+    pub mod tasks;
+    pub mod timer;
+    pub mod timesheets;
+    pub mod todos;
+}
@@ -0,0 +1,583 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Opt-in HTTP/JSON API on 127.0.0.1 so scripts, editor plugins and status bars can
+//! read and control the timer and edit records. Every request needs the bearer token
+//! from settings.
+
+use std::{future::Future, net::Ipv4Addr};
+
+use axum::{
+    extract::{Path, Query, Request, State},
+    http::{header, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::{DateTime, Local, NaiveDate, TimeDelta, Utc};
+use log::{error, info};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::{
+    net::TcpListener,
+    sync::{mpsc, oneshot},
+};
+
+use crate::{
+    helpers::formatters,
+    models::{
+        fur_error::{FurError, FurErrorKind},
+        fur_shortcut::FurShortcut,
+        fur_task::FurTask,
+        fur_todo::FurTodo,
+    },
+    services::{
+        self,
+        reports::{Summary, SummaryGrouping},
+    },
+};
+
+/// Days listed when a request doesn't give a date range
+const DEFAULT_DAYS: i64 = 7;
+const TOKEN_BYTES: usize = 32;
+
+/// Asks a front end to start or stop its own timer, so pomodoro breaks and the timer
+/// display behave as if its start/stop button was pressed. Answered once it's done.
+pub enum TimerRequest {
+    Start(String, oneshot::Sender<()>),
+    Stop(oneshot::Sender<()>),
+}
+
+#[derive(Clone)]
+struct ApiState {
+    /// Compared as hashes so a wrong token takes as long to reject as a nearly right one
+    token_hash: blake3::Hash,
+    /// Without a front end the timer is started and stopped through the services
+    timer_requests: Option<mpsc::UnboundedSender<TimerRequest>>,
+}
+
+enum LocalApiError {
+    BadRequest(String),
+    NotFound,
+    Conflict(String),
+    /// The front end that owns the timer has stopped answering
+    Unavailable,
+    Internal(FurError),
+}
+
+impl From<FurError> for LocalApiError {
+    fn from(error: FurError) -> Self {
+        match error.kind {
+            FurErrorKind::TimerChanged => LocalApiError::Conflict(error.localized_message()),
+            _ => LocalApiError::Internal(error),
+        }
+    }
+}
+
+impl IntoResponse for LocalApiError {
+    fn into_response(self) -> Response {
+        let (status, message) = match self {
+            LocalApiError::BadRequest(message) => (StatusCode::BAD_REQUEST, message),
+            LocalApiError::NotFound => (StatusCode::NOT_FOUND, "Not found".to_string()),
+            LocalApiError::Conflict(message) => (StatusCode::CONFLICT, message),
+            LocalApiError::Unavailable => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "The app isn't accepting timer changes".to_string(),
+            ),
+            LocalApiError::Internal(e) => {
+                error!("Local API error: {}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("{}: {}", e.title(), e.localized_message()),
+                )
+            }
+        };
+        (status, Json(json!({ "error": message }))).into_response()
+    }
+}
+
+type ApiResult<T> = Result<Json<T>, LocalApiError>;
+
+#[derive(Serialize)]
+struct TimerState {
+    is_running: bool,
+    task_input: String,
+    start_time: Option<DateTime<Local>>,
+    elapsed_seconds: i64,
+}
+
+#[derive(Deserialize)]
+struct StartTimer {
+    task_input: String,
+}
+
+#[derive(Deserialize)]
+struct DateRange {
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+}
+
+#[derive(Deserialize)]
+struct SummaryQuery {
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    #[serde(default)]
+    group_by: SummaryGrouping,
+}
+
+#[derive(Deserialize)]
+struct NewTask {
+    task_input: String,
+    start_time: DateTime<Local>,
+    stop_time: DateTime<Local>,
+}
+
+#[derive(Deserialize)]
+struct TaskChanges {
+    task_input: Option<String>,
+    start_time: Option<DateTime<Local>>,
+    stop_time: Option<DateTime<Local>>,
+}
+
+#[derive(Deserialize)]
+struct NewTodo {
+    task_input: String,
+    date: Option<NaiveDate>,
+}
+
+#[derive(Deserialize)]
+struct TodoChanges {
+    task_input: Option<String>,
+    date: Option<NaiveDate>,
+    is_completed: Option<bool>,
+}
+
+#[derive(Deserialize)]
+struct NewShortcut {
+    task_input: String,
+    color_hex: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ShortcutChanges {
+    task_input: Option<String>,
+    color_hex: Option<String>,
+}
+
+/// A new random token for the local API
+pub fn generate_token() -> String {
+    let bytes: [u8; TOKEN_BYTES] = rand::thread_rng().gen();
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Serve the API on 127.0.0.1:`port` until `shutdown` completes
+pub async fn serve(
+    port: u16,
+    token: &str,
+    timer_requests: Option<mpsc::UnboundedSender<TimerRequest>>,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> std::io::Result<()> {
+    let listener = listen(port).await?;
+    info!("Local API listening on {}", listener.local_addr()?);
+    axum::serve(listener, router(token, timer_requests))
+        .with_graceful_shutdown(shutdown)
+        .await
+}
+
+/// Only this computer can connect, never other machines on the network
+async fn listen(port: u16) -> std::io::Result<TcpListener> {
+    TcpListener::bind((Ipv4Addr::LOCALHOST, port)).await
+}
+
+fn router(token: &str, timer_requests: Option<mpsc::UnboundedSender<TimerRequest>>) -> Router {
+    let state = ApiState {
+        token_hash: blake3::hash(token.as_bytes()),
+        timer_requests,
+    };
+    Router::new()
+        .route("/timer", get(timer_state))
+        .route("/timer/start", post(start_timer))
+        .route("/timer/stop", post(stop_timer))
+        .route("/tasks", get(list_tasks).post(add_task))
+        .route(
+            "/tasks/{uid}",
+            get(get_task).put(update_task).delete(delete_task),
+        )
+        .route("/todos", get(list_todos).post(add_todo))
+        .route(
+            "/todos/{uid}",
+            get(get_todo).put(update_todo).delete(delete_todo),
+        )
+        .route("/shortcuts", get(list_shortcuts).post(add_shortcut))
+        .route(
+            "/shortcuts/{uid}",
+            get(get_shortcut)
+                .put(update_shortcut)
+                .delete(delete_shortcut),
+        )
+        .route("/reports/summary", get(summary))
+        .layer(middleware::from_fn_with_state(state.clone(), authorize))
+        .with_state(state)
+}
+
+async fn authorize(State(state): State<ApiState>, request: Request, next: Next) -> Response {
+    let token = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match token {
+        Some(token) if !token.is_empty() && blake3::hash(token.as_bytes()) == state.token_hash => {
+            next.run(request).await
+        }
+        _ => (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({ "error": "Missing or invalid token" })),
+        )
+            .into_response(),
+    }
+}
+
+/// Name, project, tags and rate from "name @project #tags $rate"
+fn split_task_input(task_input: &str) -> Result<(String, String, String, f32), LocalApiError> {
+    let parts = formatters::split_task_input(task_input);
+    if parts.0.is_empty() {
+        Err(LocalApiError::BadRequest(
+            "task_input needs a name before @project, #tags or $rate".to_string(),
+        ))
+    } else {
+        Ok(parts)
+    }
+}
+
+fn date_range(from: Option<NaiveDate>, to: Option<NaiveDate>) -> (NaiveDate, NaiveDate) {
+    let to = to.unwrap_or_else(|| Local::now().date_naive());
+    let from = from.unwrap_or(to - TimeDelta::days(DEFAULT_DAYS - 1));
+    (from, to)
+}
+
+fn start_of_day(date: NaiveDate) -> Result<DateTime<Local>, LocalApiError> {
+    date.and_hms_opt(0, 0, 0)
+        .and_then(|midnight| midnight.and_local_timezone(Local).earliest())
+        .ok_or_else(|| LocalApiError::BadRequest(format!("{} has no local midnight", date)))
+}
+
+async fn timer_state() -> ApiResult<TimerState> {
+    Ok(Json(match services::timer::running()? {
+        Some(timer) => TimerState {
+            is_running: true,
+            elapsed_seconds: services::timer::elapsed_seconds(&timer),
+            task_input: timer.task_input,
+            start_time: Some(timer.start_time),
+        },
+        None => TimerState {
+            is_running: false,
+            task_input: String::new(),
+            start_time: None,
+            elapsed_seconds: 0,
+        },
+    }))
+}
+
+async fn start_timer(
+    State(state): State<ApiState>,
+    Json(body): Json<StartTimer>,
+) -> ApiResult<TimerState> {
+    if services::timer::running()?.is_some() {
+        return Err(LocalApiError::Conflict(
+            "The timer is already running".to_string(),
+        ));
+    }
+    split_task_input(&body.task_input)?;
+    match &state.timer_requests {
+        Some(timer_requests) => {
+            let (reply, done) = oneshot::channel();
+            request_timer_change(
+                timer_requests,
+                TimerRequest::Start(body.task_input, reply),
+                done,
+            )
+            .await?;
+        }
+        None => {
+            services::timer::start(&body.task_input, Local::now(), &services::settings::load())?;
+        }
+    }
+    timer_state().await
+}
+
+async fn stop_timer(State(state): State<ApiState>) -> ApiResult<TimerState> {
+    let timer = services::timer::running()?
+        .ok_or_else(|| LocalApiError::Conflict("The timer isn't running".to_string()))?;
+    match &state.timer_requests {
+        Some(timer_requests) => {
+            let (reply, done) = oneshot::channel();
+            request_timer_change(timer_requests, TimerRequest::Stop(reply), done).await?;
+        }
+        None => {
+            services::timer::stop(&timer, Local::now())?;
+        }
+    }
+    timer_state().await
+}
+
+async fn request_timer_change(
+    timer_requests: &mpsc::UnboundedSender<TimerRequest>,
+    request: TimerRequest,
+    done: oneshot::Receiver<()>,
+) -> Result<(), LocalApiError> {
+    timer_requests
+        .send(request)
+        .map_err(|_| LocalApiError::Unavailable)?;
+    done.await.map_err(|_| LocalApiError::Unavailable)
+}
+
+async fn list_tasks(Query(range): Query<DateRange>) -> ApiResult<Vec<FurTask>> {
+    let (from, to) = date_range(range.from, range.to);
+    Ok(Json(services::tasks::on_dates(from, to)?))
+}
+
+async fn get_task(Path(uid): Path<String>) -> ApiResult<FurTask> {
+    services::tasks::find(&uid)?
+        .map(Json)
+        .ok_or(LocalApiError::NotFound)
+}
+
+async fn add_task(Json(body): Json<NewTask>) -> Result<(StatusCode, Json<FurTask>), LocalApiError> {
+    if body.stop_time <= body.start_time {
+        return Err(LocalApiError::BadRequest(
+            "stop_time must be after start_time".to_string(),
+        ));
+    }
+    let (name, project, tags, rate) = split_task_input(&body.task_input)?;
+    let task = FurTask::new(
+        name,
+        body.start_time,
+        body.stop_time,
+        tags,
+        project,
+        rate,
+        String::new(),
+    );
+    services::tasks::add(&task)?;
+    Ok((StatusCode::CREATED, Json(task)))
+}
+
+async fn update_task(
+    Path(uid): Path<String>,
+    Json(changes): Json<TaskChanges>,
+) -> ApiResult<FurTask> {
+    let mut task = services::tasks::find(&uid)?.ok_or(LocalApiError::NotFound)?;
+    if let Some(task_input) = changes.task_input {
+        let (name, project, tags, rate) = split_task_input(&task_input)?;
+        task.name = name;
+        task.project = project;
+        task.tags = tags;
+        task.rate = rate;
+    }
+    if let Some(start_time) = changes.start_time {
+        task.start_time = start_time;
+    }
+    if let Some(stop_time) = changes.stop_time {
+        task.stop_time = stop_time;
+    }
+    if task.stop_time <= task.start_time {
+        return Err(LocalApiError::BadRequest(
+            "stop_time must be after start_time".to_string(),
+        ));
+    }
+    task.last_updated = Utc::now().timestamp();
+    services::tasks::update(&task)?;
+    Ok(Json(task))
+}
+
+async fn delete_task(Path(uid): Path<String>) -> Result<StatusCode, LocalApiError> {
+    services::tasks::find(&uid)?.ok_or(LocalApiError::NotFound)?;
+    services::tasks::delete(&[uid])?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn list_todos() -> ApiResult<Vec<FurTodo>> {
+    Ok(Json(
+        services::todos::all().into_values().flatten().collect(),
+    ))
+}
+
+async fn get_todo(Path(uid): Path<String>) -> ApiResult<FurTodo> {
+    services::todos::find(&uid)?
+        .map(Json)
+        .ok_or(LocalApiError::NotFound)
+}
+
+async fn add_todo(Json(body): Json<NewTodo>) -> Result<(StatusCode, Json<FurTodo>), LocalApiError> {
+    let (name, project, tags, rate) = split_task_input(&body.task_input)?;
+    let date = start_of_day(body.date.unwrap_or_else(|| Local::now().date_naive()))?;
+    let todo = FurTodo::new(name, project, tags, rate, date);
+    services::todos::add(&todo)?;
+    Ok((StatusCode::CREATED, Json(todo)))
+}
+
+async fn update_todo(
+    Path(uid): Path<String>,
+    Json(changes): Json<TodoChanges>,
+) -> ApiResult<FurTodo> {
+    let mut todo = services::todos::find(&uid)?.ok_or(LocalApiError::NotFound)?;
+    let was_completed = todo.is_completed;
+    if let Some(task_input) = changes.task_input {
+        let (name, project, tags, rate) = split_task_input(&task_input)?;
+        todo.name = name;
+        todo.project = project;
+        todo.tags = tags;
+        todo.rate = rate;
+    }
+    if let Some(date) = changes.date {
+        todo.date = start_of_day(date)?;
+    }
+    todo.last_updated = Utc::now().timestamp();
+    services::todos::update(&todo)?;
+    // Toggled separately so completing a todo notifies like it does in the app
+    if changes
+        .is_completed
+        .is_some_and(|completed| completed != was_completed)
+    {
+        services::todos::toggle_completed(&uid)?;
+        todo.is_completed = !was_completed;
+    }
+    Ok(Json(todo))
+}
+
+async fn delete_todo(Path(uid): Path<String>) -> Result<StatusCode, LocalApiError> {
+    services::todos::find(&uid)?.ok_or(LocalApiError::NotFound)?;
+    services::todos::delete(&uid)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn list_shortcuts() -> ApiResult<Vec<FurShortcut>> {
+    Ok(Json(services::shortcuts::all()))
+}
+
+async fn get_shortcut(Path(uid): Path<String>) -> ApiResult<FurShortcut> {
+    services::shortcuts::find(&uid)?
+        .map(Json)
+        .ok_or(LocalApiError::NotFound)
+}
+
+fn color_hex(value: &str) -> Result<String, LocalApiError> {
+    let hex = value.trim_start_matches('#');
+    if hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        Ok(format!("#{}", hex.to_lowercase()))
+    } else {
+        Err(LocalApiError::BadRequest(
+            "color_hex must look like #RRGGBB".to_string(),
+        ))
+    }
+}
+
+async fn add_shortcut(
+    Json(body): Json<NewShortcut>,
+) -> Result<(StatusCode, Json<FurShortcut>), LocalApiError> {
+    let (name, project, tags, rate) = split_task_input(&body.task_input)?;
+    let color_hex = match body.color_hex {
+        Some(value) => color_hex(&value)?,
+        None => format!("#{:06x}", rand::thread_rng().gen::<u32>() & 0xFFFFFF),
+    };
+    let shortcut = FurShortcut::new(name, tags, project, rate, String::new(), color_hex);
+    services::shortcuts::add(&shortcut)?;
+    Ok((StatusCode::CREATED, Json(shortcut)))
+}
+
+async fn update_shortcut(
+    Path(uid): Path<String>,
+    Json(changes): Json<ShortcutChanges>,
+) -> ApiResult<FurShortcut> {
+    let mut shortcut = services::shortcuts::find(&uid)?.ok_or(LocalApiError::NotFound)?;
+    if let Some(task_input) = changes.task_input {
+        let (name, project, tags, rate) = split_task_input(&task_input)?;
+        shortcut.name = name;
+        shortcut.project = project;
+        shortcut.tags = tags;
+        shortcut.rate = rate;
+    }
+    if let Some(value) = changes.color_hex {
+        shortcut.color_hex = color_hex(&value)?;
+    }
+    shortcut.last_updated = Utc::now().timestamp();
+    services::shortcuts::update(&shortcut)?;
+    Ok(Json(shortcut))
+}
+
+async fn delete_shortcut(Path(uid): Path<String>) -> Result<StatusCode, LocalApiError> {
+    services::shortcuts::find(&uid)?.ok_or(LocalApiError::NotFound)?;
+    services::shortcuts::delete(&uid)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn summary(Query(query): Query<SummaryQuery>) -> ApiResult<Summary> {
+    let (from, to) = date_range(query.from, query.to);
+    let tasks = services::tasks::on_dates(from, to)?;
+    Ok(Json(services::reports::summarize(&tasks, query.group_by)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOKEN: &str = "test-token";
+
+    async fn start_api() -> String {
+        let listener = listen(0).await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router(TOKEN, None)).await });
+        format!("http://{}", address)
+    }
+
+    #[tokio::test]
+    async fn listens_on_loopback_only() {
+        let listener = listen(0).await.unwrap();
+        assert_eq!(listener.local_addr().unwrap().ip(), Ipv4Addr::LOCALHOST);
+    }
+
+    #[tokio::test]
+    async fn requests_need_the_bearer_token() {
+        let api = start_api().await;
+        let client = reqwest::Client::new();
+        let start_timer = |authorization: Option<&str>| {
+            let mut request = client.post(format!("{}/timer/start", api)).body("{}");
+            if let Some(authorization) = authorization {
+                request = request.header(header::AUTHORIZATION, authorization);
+            }
+            request.send()
+        };
+
+        for authorization in [
+            None,
+            Some("Bearer wrong-token"),
+            Some("Bearer "),
+            Some(TOKEN),
+            Some("Basic test-token"),
+        ] {
+            let response = start_timer(authorization).await.unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+
+        // The right token gets past authorization to the missing task_input
+        let response = start_timer(Some("Bearer test-token")).await.unwrap();
+        assert_ne!(response.status(), StatusCode::UNAUTHORIZED);
+        assert!(response.status().is_client_error());
+    }
+}
@@ -147,6 +147,7 @@ email = Email
 email-placeholder = email@example.com
 encryption-key = Encryption key
 key = Key
+key-or-passphrase = Key or passphrase
 log-in = Log in
 logging-in = Logging in...
 log-in-first = Please log in first
@@ -167,9 +168,226 @@ sync-successful = {$count ->
     *[other] {$count} items synced
 }
 sync-failed = Sync failed
+sync-idle = Idle
+sync-pending = Waiting to sync
+sync-retrying = {$error}. Retrying at {$time}
+sync-status = Status
 error-decrypting-key = Failed to decrypt encryption key
+error-deriving-key = Failed to derive a key from the passphrase
+passphrases-not-supported = This server doesn't support passphrases. Log in with your encryption key instead.
+new-encryption-key = New encryption key
+rotate-key = Rotate key
+rotate-key-question = Rotate encryption key?
+rotate-key-description = All of your data will be re-encrypted with the new key and uploaded. Your other devices will need to log in again with the new key.
+rotating-key = Rotating key...
+key-rotated = Encryption key rotated
+key-rotation-failed = Key rotation failed
+key-rotation-cancelled = Key rotation cancelled
+key-rotation-wait-for-sync = Wait for the current sync to finish before rotating the key
+key-rotation = Key rotation
+records-reencrypted = {$count ->
+    [one] {$count} item re-encrypted
+    *[other] {$count} items re-encrypted
+}
+resume-key-rotation = Resume key rotation
+cancel-key-rotation = Cancel key rotation
+key-rotated-elsewhere = The encryption key was changed on another device. Log in with the new key.
+key-fingerprint = Key fingerprint
+devices = Devices
+device-name = Device name
+show-devices = Show devices
+refresh-devices = Refresh devices
+this-device = this device
+never-synced = Never synced
+revoke = Revoke
+revoke-device-question = Revoke device?
+revoke-device-description = The device will be signed out and will need to log in again to sync.
+device-revoked = Device revoked
+error-loading-devices = Failed to load devices
+error-revoking-device = Failed to revoke device
+sync-history = Sync history
+sync-log-summary = ↑{$uploaded} ↓{$downloaded} in {$duration}s
+sync-log-failed = Failed after {$duration}s
+sync-log-decrypt-failures = {$count ->
+    [one] {$count} item couldn't be decrypted
+    *[other] {$count} items couldn't be decrypted
+}
+export-diagnostics = Export diagnostics
+clear-sync-history = Clear sync history
+diagnostics-exported = Diagnostics saved to {$path}
+error-exporting-diagnostics = Failed to export diagnostics
+calendar-file = Calendar file
+calendar-file-placeholder = Path to an .ics file
+import-calendar = Import calendar (.ics)
+calendar-categories = Calendar categories
+project-and-tags-placeholder = @Project #tags
+import-as-tasks = Import as tasks
+import-as-todos = Import as todos
+already-imported = Already imported
+no-events-found = No events were found in that file.
+events-imported = Imported {$imported} {$imported ->
+    [one] event
+    *[other] events
+}. {$skipped} skipped.
+calendar-event = Calendar event
+calendar-unknown-time-zone = Unknown time zone { $zone }, shown as local time
+export = Export
+from = From
+to = To
+merge-contiguous-tasks = Merge back-to-back tasks
+export-calendar = Export calendar (.ics)
+tracker-file = Time tracker export
+tracker-file-placeholder = Path to a .csv or .json file
+tracker = Tracker
+preview-import = Preview import
+import-from-tracker = Import from {$tracker}
+import-columns = Columns
+column-not-used = Not used
+import-field-description = Description
+import-field-client = Client
+import-field-project = Project
+import-field-task = Task
+import-field-tags = Tags
+import-field-billable = Billable
+import-field-rate = Rate
+import-field-amount = Amount
+import-field-currency = Currency
+import-field-start-date = Start date
+import-field-start-time = Start time
+import-field-end-date = End date
+import-field-end-time = End time
+import-field-duration = Duration
+import-preview-summary = {$new} new, {$duplicates} {$duplicates ->
+    [one] duplicate
+    *[other] duplicates
+}, {$invalid} invalid
+import-preview-more = …and {$count} more
+duplicate = Duplicate
+invalid-entry = Invalid
+no-entries-found = No entries were found in that file.
+tasks-imported = Imported {$imported} {$imported ->
+    [one] task
+    *[other] tasks
+}. {$skipped} skipped.
+imported-task = Imported task
+export-archive = Export all data (.json)
+archive-file = Archive file
+archive-file-placeholder = Path to a Furtherance .json archive
+import-settings = Import settings
+import-archive = Import all data (.json)
+archive-imported = Added {$added}, updated {$updated}. {$unchanged} already up to date.
+invoices = Invoices
+invoice = Invoice
+invoice-sender = Your details
+invoice-sender-placeholder = Name and address
+invoice-number-prefix = Invoice number prefix
+next-invoice-number = Next invoice number
+default-taxes = Default taxes
+taxes = Taxes
+taxes-placeholder = VAT 20%, City tax 1.5%
+create-invoice = Create invoice
+invoice-date = Date
+invoice-period = Period
+bill-to = Bill to
+client-details-placeholder = Client name and address
+description = Description
+hours = Hours
+amount = Amount
+subtotal = Subtotal
+total = Total
+untagged = Untagged
+group-lines-by = Group lines by
+group-by-task-name = Task name
+group-by-tag = Tag
+rounding = Rounding
+no-rounding = No rounding
+round-up-to-minutes = Round up to {$minutes} minutes
+currency = Currency
+currency-automatic = Automatic
+invoice-format = Format
+format-pdf = PDF
+format-html = HTML
+format-pdf-and-html = PDF and HTML
+no-billable-tasks = There are no billable tasks for this project in that period.
+tasks-already-invoiced = {$count} {$count ->
+    [one] task was
+    *[other] tasks were
+} left out because {$count ->
+    [one] it was
+    *[other] they were
+} already invoiced.
+invoice-created = Invoice {$number} saved to {$path}
+timesheet = Timesheet
+timesheet-week-of = Timesheet for the week of {$date}
+week = Week
+timesheet-rows = Rows
+rows-by-task = Tasks
+rows-by-project = Projects
+format = Format
+only-projects = Only projects
+only-projects-placeholder = Work, Home
+only-tags = Only tags
+only-tags-placeholder = #docs #meetings
+export-timesheet = Export timesheet
+no-project = No project
+exported-to = Saved to {$path}
+invalid-date = Enter a date as YYYY-MM-DD.
+invalid-date-range = Choose a start date on or before the end date.
+hooks = Hooks
+hook-timer-started = Timer started
+hook-timer-stopped = Timer stopped
+hook-pomodoro-ended = Pomodoro ended
+hook-todo-completed = Todo completed
+hook-sync-finished = Sync finished
+command-or-url = Command or URL
+hook-timeout = Timeout (seconds)
+local-api = Local API
+enable-local-api = Enable local API
+port = Port
+address = Address
+access-token = Access token
+regenerate-token = Regenerate token
+logs = Logs
+log-level = Log level
+log-level-error = Errors
+log-level-warn = Warnings
+log-level-info = Info
+log-level-debug = Debug
+log-file = Log file
+show-log = Show log
+hide-log = Hide log
+key-does-not-match-account = This encryption key doesn't match the one used by this account. Check that it's the same key or passphrase you use on your other devices.
+pin = PIN
+new-pin = New PIN
+protect-with-pin = Protect key with PIN
+remove-pin = Remove PIN
+unlock-sync-key = Unlock
+pin-set = Sync key protected with PIN
+pin-removed = PIN removed
+pin-too-short = PIN must be at least 4 characters
+incorrect-pin = Incorrect PIN
+error-setting-pin = Failed to change key protection
+sync-key-locked = Enter your PIN to unlock syncing
+sync-key-unlocked = Sync key unlocked
 sign-up = Sign up
 local-database = Local Database
+days-to-keep-deleted-items = Days to keep deleted items
+compact-database = Compact database
+database-encryption = Database encryption
+database-passphrase = Database passphrase
+confirm-passphrase = Confirm passphrase
+passphrase = Passphrase
+encrypt-database = Encrypt database
+decrypt-database = Remove database encryption
+database-encrypted = The database is now encrypted. You will need the passphrase every time Furtherance starts. If you lose it, your local data cannot be recovered.
+database-decrypted = The database is no longer encrypted.
+passphrases-do-not-match = The passphrases do not match.
+error-encrypting-database = Failed to encrypt the database.
+error-decrypting-database = Failed to remove database encryption.
+database-locked = Database locked
+unlock = Unlock
+incorrect-database-passphrase = Incorrect passphrase
+error-opening-database = Failed to open the database
 database-location = Database location
 create-new = Create New
 open-existing = Open Existing
@@ -278,3 +496,31 @@ tags-cannot-contain = Tags cannot contain @ or $.
 tags-must-start = Tags must start with a #.
 no-symbol-in-rate = Do not include a $ in the rate.
 rate-invalid = Rate must be a valid dollar amount.
+error-saving-task = Failed to save the task
+error-saving-todo = Failed to save the todo
+error-saving-shortcut = Failed to save the shortcut
+error-deleting-task = Failed to delete the task
+error-deleting-todo = Failed to delete the todo
+error-deleting-shortcut = Failed to delete the shortcut
+error-loading-timer = Failed to load the running timer
+error-saving-timer = Failed to save the running timer
+error-saving-settings = Failed to save settings
+error-loading-tasks = Failed to load tasks
+error-loading-todos = Failed to load todos
+error-loading-shortcuts = Failed to load shortcuts
+error-exporting-data = Failed to export data
+error-importing-data = Failed to import data
+error-disk-full = The disk is full. Free up some space and try again.
+error-database-locked = The database is being used by another program. Try again in a moment.
+error-database-not-writable = Furtherance doesn't have permission to write to the database.
+error-database-corrupt = The database file is damaged or isn't a Furtherance database.
+error-database = Something went wrong while accessing the database.
+error-file-not-writable = Furtherance doesn't have permission to write to that location.
+error-file = Something went wrong while writing the file.
+error-file-not-found = The file couldn't be found.
+error-file-unreadable = The file isn't in a format Furtherance can read.
+error-settings-file = The settings file couldn't be read.
+error-timer-changed = The timer was already stopped or restarted somewhere else, such as the command line.
+error-encryption = Something went wrong with encryption.
+error-server = The server couldn't be reached.
+try-again = Try Again
@@ -17,6 +17,8 @@
 use std::{borrow::Cow, collections::HashMap};
 
 use fluent::{concurrent::FluentBundle, FluentArgs, FluentResource, FluentValue};
+use log::error;
+use once_cell::sync::OnceCell;
 use rust_embed::RustEmbed;
 use sys_locale::get_locale;
 
@@ -44,6 +46,8 @@ fn create_bundle(lang: &str) -> FluentBundle<FluentResource> {
     bundle
 }
 
+pub static LOCALIZATION: OnceCell<Localization> = OnceCell::new();
+
 pub struct Localization {
     bundles: HashMap<String, FluentBundle<FluentResource>>,
     current_lang: String,
@@ -110,7 +114,7 @@ impl Localization {
         };
 
         if !errors.is_empty() {
-            eprintln!("Errors occurred during formatting: {:?}", errors);
+            error!("Errors occurred during formatting: {:?}", errors);
         }
 
         // Prevent odd symbols in iced
@@ -121,12 +125,12 @@ impl Localization {
 #[macro_export]
 macro_rules! loc {
     ($key:expr) => {{
-        let localization = crate::constants::LOCALIZATION.get_or_init(Localization::new);
+        let localization = $crate::localization::LOCALIZATION.get_or_init(Localization::new);
         localization.get_message($key, None)
     }};
 
     ($key:expr, $args:expr) => {{
-        let localization = crate::constants::LOCALIZATION.get_or_init(Localization::new);
+        let localization = $crate::localization::LOCALIZATION.get_or_init(Localization::new);
         localization.get_message($key, Some($args))
     }};
 }
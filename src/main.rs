@@ -15,32 +15,22 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 mod models {
+    pub use furtherance_core::models::*;
     pub mod fur_alert;
-    pub mod fur_persist;
-    pub mod fur_pomodoro;
-    pub mod fur_settings;
     pub mod fur_sheet;
-    pub mod fur_shortcut;
-    pub mod fur_task;
-    pub mod fur_task_group;
-    pub mod fur_todo;
-    pub mod fur_user;
-}
-pub mod database {
-    pub mod init;
-    pub mod persistence;
-    pub mod shortcuts;
-    pub mod sync;
-    pub mod tasks;
-    pub mod todos;
+    pub mod fur_sync_status;
+    pub mod fur_user_fields;
 }
 mod helpers {
+    pub use furtherance_core::helpers::*;
     pub mod actions;
     pub mod color_utils;
-    pub mod formatters;
+    pub mod core_events;
     pub mod icons;
-    pub mod view_enums;
+    pub mod local_api;
     pub mod views {
+        pub mod alerts;
+        pub mod invoices;
         pub mod settings;
         pub mod shortcuts;
         pub mod task_history;
@@ -49,35 +39,52 @@ mod helpers {
         pub mod todos;
     }
     pub mod server {
-        pub mod encryption;
+        pub use furtherance_core::server::{api, encryption, kdf, sync as sync_engine};
+        pub mod devices;
+        pub mod key_rotation;
+        pub mod key_storage;
         pub mod login;
         pub mod logout;
         pub mod sync;
+        pub mod sync_log;
+        pub mod sync_scheduler;
     }
 }
 mod views {
+    pub mod database_error_view;
     pub mod settings_view;
     pub mod shortcuts_view;
     pub mod timer_view;
     pub mod todos_view;
+    pub mod unlock_view;
 }
 mod constants;
-mod localization;
 mod state;
 
+use furtherance_core::{database, events, hooks, loc, localization, services};
+
 use constants::{ALERT_CSS, MAIN_CSS, TIMER_CSS};
 use database::init::db_init;
 use dioxus::prelude::*;
 use helpers::{
-    formatters,
+    core_events::listen_for_core_events,
     icons::{BsBookmark, BsCheck2Circle, BsGear, BsHourglassSplit, Icon, IconShape},
-    server::sync::schedule_sync,
+    local_api::start_local_api,
+    logger,
+    server::{key_storage, sync::schedule_sync, sync_engine::purge_expired_tombstones},
+    view_enums::FurLogLevel,
     views::timer::ensure_timer_running,
 };
+use log::error;
+use models::{
+    fur_error::{ErrorContext, FurErrorContext, FurResult},
+    fur_settings::FurSettings,
+};
 use state::ACTIVE_TAB;
 use views::{
-    settings_view::SettingsView, shortcuts_view::ShortcutsView, timer_view::TimerView,
-    todos_view::TodosView,
+    database_error_view::DatabaseErrorView, settings_view::SettingsView,
+    shortcuts_view::ShortcutsView, timer_view::TimerView, todos_view::TodosView,
+    unlock_view::UnlockView,
 };
 
 #[derive(PartialEq, Copy, Clone)]
@@ -89,24 +96,60 @@ pub enum NavTab {
 }
 
 fn main() {
-    db_init().expect("Failed to read or create database");
+    let log_level = FurSettings::new()
+        .map(|settings| settings.log_level)
+        .unwrap_or(FurLogLevel::Info);
+    logger::init(&log_level);
+
     dioxus::launch(App);
 }
 
+pub fn prepare_database() -> FurResult<()> {
+    db_init().in_context(FurErrorContext::OpenDatabase)?;
+    purge_tombstones_on_launch();
+    key_storage::migrate_stored_key();
+    Ok(())
+}
+
+fn purge_tombstones_on_launch() {
+    // Signed-in users purge after each successful sync instead
+    if let (Ok(settings), Ok(None)) = (FurSettings::new(), database::sync::retrieve_credentials()) {
+        purge_expired_tombstones(&settings, false);
+    }
+}
+
 #[component]
 fn App() -> Element {
-    match database::persistence::retrieve_persisting_timer() {
-        Ok(persisting_timer) => {
-            if persisting_timer.is_running {
-                *state::TIMER_IS_RUNNING.write() = persisting_timer.is_running;
-                *state::TASK_INPUT.write() = persisting_timer.task_input;
-                *state::TIMER_START_TIME.write() = persisting_timer.start_time;
-            }
+    if state::DATABASE_LOCKED.cloned() {
+        return rsx! {
+            document::Stylesheet { href: MAIN_CSS }
+            UnlockView {}
+        };
+    }
+    if state::STARTUP_ERROR.read().is_some() {
+        return rsx! {
+            document::Stylesheet { href: MAIN_CSS }
+            DatabaseErrorView {}
+        };
+    }
+
+    match services::timer::running() {
+        Ok(Some(persisting_timer)) => {
+            *state::TIMER_IS_RUNNING.write() = persisting_timer.is_running;
+            *state::TASK_INPUT.write() = persisting_timer.task_input;
+            *state::TIMER_START_TIME.write() = persisting_timer.start_time;
         }
-        Err(e) => eprintln!("Error retrieving persisting timer: {}", e),
+        Ok(None) => {}
+        Err(e) => error!("{}", e),
+    }
+    match services::timer::pomodoro() {
+        Ok(pomodoro) => *state::POMODORO.write() = pomodoro,
+        Err(e) => error!("{}", e),
     }
     ensure_timer_running();
     schedule_sync();
+    listen_for_core_events();
+    start_local_api();
 
     let alert = state::ALERT.cloned();
     let sheets = state::SHEETS.cloned();
@@ -137,7 +180,8 @@ fn App() -> Element {
         if !sheets.new_task_is_shown && !sheets.new_shortcut_is_shown
             && !sheets.new_todo_is_shown && sheets.task_edit_sheet.is_none()
             && sheets.group_details_sheet.is_none() && sheets.edit_todo_sheet.is_none()
-            && sheets.edit_shortcut_sheet.is_none()
+            && sheets.edit_shortcut_sheet.is_none() && sheets.import_calendar_sheet.is_none()
+            && sheets.import_tasks_sheet.is_none() && !sheets.new_invoice_is_shown
         {
             BottomNav {}
         }
@@ -0,0 +1,41 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    fur_settings::FurSettings, fur_shortcut::FurShortcut, fur_task::FurTask, fur_todo::FurTodo,
+};
+
+/// Format of archives written by this version. Raise it when a change would stop older
+/// versions from reading an archive correctly.
+pub const ARCHIVE_FORMAT_VERSION: u32 = 1;
+
+/// Everything Furtherance stores, including deleted records, for moving to another device
+/// without a sync server or for keeping as a backup
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct FurArchive {
+    pub format_version: u32,
+    /// Version of Furtherance that wrote the archive
+    pub app_version: String,
+    pub exported_at: DateTime<Local>,
+    pub tasks: Vec<FurTask>,
+    pub todos: Vec<FurTodo>,
+    pub shortcuts: Vec<FurShortcut>,
+    #[serde(default)]
+    pub settings: Option<FurSettings>,
+}
@@ -0,0 +1,41 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+/// An event read from an .ics file
+#[derive(Clone, Debug, PartialEq)]
+pub struct FurCalendarEvent {
+    /// The event's UID, plus its RECURRENCE-ID for a changed occurrence of a recurring event
+    pub uid: String,
+    pub summary: String,
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+    /// All-day events have dates but no times, so they only import as todos
+    pub all_day: bool,
+    pub categories: Vec<String>,
+    /// A TZID that isn't a known time zone, so the event's times were read as local time
+    pub unknown_time_zone: Option<String>,
+}
+
+/// Project and tags given to events in a calendar category, remembered between imports
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct FurCategoryMapping {
+    pub category: String,
+    /// Task input such as "@Project #tag"
+    pub task_input: String,
+}
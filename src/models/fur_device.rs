@@ -0,0 +1,40 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use serde::Deserialize;
+
+/// A device signed in to the sync account, as reported by the server
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct FurDevice {
+    pub device_id: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub platform: String,
+    /// Server timestamp of the device's last sync, if it has synced
+    #[serde(default)]
+    pub last_sync: Option<i64>,
+}
+
+impl FurDevice {
+    pub fn display_name(&self) -> String {
+        if self.name.is_empty() {
+            self.device_id.chars().take(8).collect()
+        } else {
+            self.name.clone()
+        }
+    }
+}
@@ -0,0 +1,201 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{fmt, io::ErrorKind};
+
+use config::ConfigError;
+use rusqlite::ErrorCode;
+
+use crate::{
+    loc,
+    localization::Localization,
+    server::{api::ApiError, encryption::EncryptionError},
+};
+
+pub type FurResult<T> = Result<T, FurError>;
+
+/// What the app was doing when an error happened
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FurErrorContext {
+    OpenDatabase,
+    LoadTasks,
+    SaveTask,
+    DeleteTask,
+    LoadTodos,
+    SaveTodo,
+    DeleteTodo,
+    LoadShortcuts,
+    SaveShortcut,
+    DeleteShortcut,
+    LoadTimer,
+    SaveTimer,
+    SaveSettings,
+    EncryptDatabase,
+    DecryptDatabase,
+    ExportDiagnostics,
+    ExportData,
+    ImportData,
+}
+
+#[derive(Debug)]
+pub enum FurErrorKind {
+    Database(rusqlite::Error),
+    Io(std::io::Error),
+    Settings(ConfigError),
+    Encryption(EncryptionError),
+    Api(ApiError),
+    Serialization(serde_json::Error),
+    /// The timer being stopped was already stopped or restarted somewhere else
+    TimerChanged,
+}
+
+/// An error with what the app was doing when it happened
+#[derive(Debug)]
+pub struct FurError {
+    pub context: FurErrorContext,
+    pub kind: FurErrorKind,
+}
+
+impl FurError {
+    pub fn new(context: FurErrorContext, kind: impl Into<FurErrorKind>) -> Self {
+        FurError {
+            context,
+            kind: kind.into(),
+        }
+    }
+
+    /// Short title for an alert, e.g. "Couldn't save task"
+    pub fn title(&self) -> String {
+        match self.context {
+            FurErrorContext::OpenDatabase => loc!("error-opening-database"),
+            FurErrorContext::LoadTasks => loc!("error-loading-tasks"),
+            FurErrorContext::SaveTask => loc!("error-saving-task"),
+            FurErrorContext::DeleteTask => loc!("error-deleting-task"),
+            FurErrorContext::LoadTodos => loc!("error-loading-todos"),
+            FurErrorContext::SaveTodo => loc!("error-saving-todo"),
+            FurErrorContext::DeleteTodo => loc!("error-deleting-todo"),
+            FurErrorContext::LoadShortcuts => loc!("error-loading-shortcuts"),
+            FurErrorContext::SaveShortcut => loc!("error-saving-shortcut"),
+            FurErrorContext::DeleteShortcut => loc!("error-deleting-shortcut"),
+            FurErrorContext::LoadTimer => loc!("error-loading-timer"),
+            FurErrorContext::SaveTimer => loc!("error-saving-timer"),
+            FurErrorContext::SaveSettings => loc!("error-saving-settings"),
+            FurErrorContext::EncryptDatabase => loc!("error-encrypting-database"),
+            FurErrorContext::DecryptDatabase => loc!("error-decrypting-database"),
+            FurErrorContext::ExportDiagnostics => loc!("error-exporting-diagnostics"),
+            FurErrorContext::ExportData => loc!("error-exporting-data"),
+            FurErrorContext::ImportData => loc!("error-importing-data"),
+        }
+    }
+
+    /// Why it failed, in terms the user can act on
+    pub fn localized_message(&self) -> String {
+        match &self.kind {
+            FurErrorKind::Database(rusqlite::Error::SqliteFailure(error, _)) => match error.code {
+                ErrorCode::DiskFull => loc!("error-disk-full"),
+                ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked => {
+                    loc!("error-database-locked")
+                }
+                ErrorCode::ReadOnly | ErrorCode::CannotOpen | ErrorCode::PermissionDenied => {
+                    loc!("error-database-not-writable")
+                }
+                ErrorCode::NotADatabase | ErrorCode::DatabaseCorrupt => {
+                    loc!("error-database-corrupt")
+                }
+                _ => loc!("error-database"),
+            },
+            FurErrorKind::Database(_) => loc!("error-database"),
+            FurErrorKind::Io(error) => match error.kind() {
+                ErrorKind::StorageFull => loc!("error-disk-full"),
+                ErrorKind::NotFound => loc!("error-file-not-found"),
+                ErrorKind::InvalidData => loc!("error-file-unreadable"),
+                ErrorKind::PermissionDenied | ErrorKind::ReadOnlyFilesystem => {
+                    loc!("error-file-not-writable")
+                }
+                _ => loc!("error-file"),
+            },
+            FurErrorKind::Settings(_) => loc!("error-settings-file"),
+            FurErrorKind::Encryption(_) => loc!("error-encryption"),
+            FurErrorKind::Api(_) => loc!("error-server"),
+            FurErrorKind::Serialization(_) => loc!("error-file"),
+            FurErrorKind::TimerChanged => loc!("error-timer-changed"),
+        }
+    }
+}
+
+impl fmt::Display for FurError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}: ", self.context)?;
+        match &self.kind {
+            FurErrorKind::Database(e) => write!(f, "{}", e),
+            FurErrorKind::Io(e) => write!(f, "{}", e),
+            FurErrorKind::Settings(e) => write!(f, "{}", e),
+            FurErrorKind::Encryption(e) => write!(f, "{:?}", e),
+            FurErrorKind::Api(e) => write!(f, "{:?}", e),
+            FurErrorKind::Serialization(e) => write!(f, "{}", e),
+            FurErrorKind::TimerChanged => write!(f, "the timer was stopped elsewhere"),
+        }
+    }
+}
+
+impl std::error::Error for FurError {}
+
+impl From<rusqlite::Error> for FurErrorKind {
+    fn from(error: rusqlite::Error) -> Self {
+        FurErrorKind::Database(error)
+    }
+}
+
+impl From<std::io::Error> for FurErrorKind {
+    fn from(error: std::io::Error) -> Self {
+        FurErrorKind::Io(error)
+    }
+}
+
+impl From<ConfigError> for FurErrorKind {
+    fn from(error: ConfigError) -> Self {
+        FurErrorKind::Settings(error)
+    }
+}
+
+impl From<EncryptionError> for FurErrorKind {
+    fn from(error: EncryptionError) -> Self {
+        FurErrorKind::Encryption(error)
+    }
+}
+
+impl From<ApiError> for FurErrorKind {
+    fn from(error: ApiError) -> Self {
+        FurErrorKind::Api(error)
+    }
+}
+
+impl From<serde_json::Error> for FurErrorKind {
+    fn from(error: serde_json::Error) -> Self {
+        FurErrorKind::Serialization(error)
+    }
+}
+
+/// Attach what the app was doing to any error that has a FurErrorKind
+pub trait ErrorContext<T> {
+    fn in_context(self, context: FurErrorContext) -> FurResult<T>;
+}
+
+impl<T, E: Into<FurErrorKind>> ErrorContext<T> for Result<T, E> {
+    fn in_context(self, context: FurErrorContext) -> FurResult<T> {
+        self.map_err(|error| FurError::new(context, error))
+    }
+}
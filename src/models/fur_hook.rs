@@ -0,0 +1,69 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+/// Core events a user can attach a hook to
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FurHookEvent {
+    TimerStarted,
+    TimerStopped,
+    PomodoroEnded,
+    TodoCompleted,
+    SyncFinished,
+}
+
+impl FurHookEvent {
+    pub const ALL: [FurHookEvent; 5] = [
+        FurHookEvent::TimerStarted,
+        FurHookEvent::TimerStopped,
+        FurHookEvent::PomodoroEnded,
+        FurHookEvent::TodoCompleted,
+        FurHookEvent::SyncFinished,
+    ];
+
+    /// Name sent as `event` in the hook's JSON
+    pub fn name(&self) -> &'static str {
+        match self {
+            FurHookEvent::TimerStarted => "timer_started",
+            FurHookEvent::TimerStopped => "timer_stopped",
+            FurHookEvent::PomodoroEnded => "pomodoro_ended",
+            FurHookEvent::TodoCompleted => "todo_completed",
+            FurHookEvent::SyncFinished => "sync_finished",
+        }
+    }
+}
+
+/// What a hook does, parsed from the command or URL saved in settings
+#[derive(Clone, Debug, PartialEq)]
+pub enum FurHookAction {
+    /// Run with the system shell, with the event's JSON on stdin
+    Command(String),
+    /// POST the event's JSON
+    Webhook(String),
+}
+
+impl FurHookAction {
+    /// None when the hook is empty, so nothing should run
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        if value.is_empty() {
+            None
+        } else if value.starts_with("http://") || value.starts_with("https://") {
+            Some(FurHookAction::Webhook(value.to_string()))
+        } else {
+            Some(FurHookAction::Command(value.to_string()))
+        }
+    }
+}
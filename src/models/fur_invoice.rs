@@ -0,0 +1,113 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+/// Tax added to an invoice's subtotal, e.g. VAT 20%
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct FurTaxLine {
+    pub name: String,
+    pub percent: f32,
+}
+
+impl FurTaxLine {
+    /// Tax lines typed as "VAT 20%, City tax 1.5%". None if any of them has no percentage.
+    pub fn parse_list(text: &str) -> Option<Vec<FurTaxLine>> {
+        // A comma only separates taxes when a space follows, so "1,5%" keeps its decimal comma
+        text.replace(", ", ";")
+            .split(';')
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                let (name, percent) = line.rsplit_once(' ').unwrap_or(("", line));
+                let percent = percent.trim_end_matches('%').replace(',', ".");
+                Some(FurTaxLine {
+                    name: name.trim().to_string(),
+                    percent: percent.parse().ok()?,
+                })
+            })
+            .collect()
+    }
+
+    pub fn format_list(tax_lines: &[FurTaxLine]) -> String {
+        tax_lines
+            .iter()
+            .map(|tax| format!("{} {}%", tax.name, tax.percent).trim().to_string())
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+}
+
+/// Billing details remembered for a project's client
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct FurInvoiceClient {
+    pub project: String,
+    /// Name and address, one item per line
+    pub details: String,
+}
+
+/// What an invoice's line items are made from
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum InvoiceGrouping {
+    #[default]
+    TaskName,
+    /// Tasks are billed under their first tag, so a task with several tags is only billed once
+    Tag,
+}
+
+impl InvoiceGrouping {
+    pub const ALL: [InvoiceGrouping; 2] = [InvoiceGrouping::TaskName, InvoiceGrouping::Tag];
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FurInvoiceLine {
+    pub description: String,
+    /// Hours billed, after rounding
+    pub hours: f64,
+    pub rate: f32,
+    pub amount: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FurInvoiceTax {
+    pub name: String,
+    pub percent: f32,
+    pub amount: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FurInvoice {
+    /// Formatted number, e.g. INV-0042
+    pub number: String,
+    /// Position in the invoice number sequence
+    pub sequence: i64,
+    pub issued: NaiveDate,
+    pub sender: String,
+    pub client: String,
+    pub project: String,
+    pub first_day: NaiveDate,
+    pub last_day: NaiveDate,
+    pub currency: String,
+    pub lines: Vec<FurInvoiceLine>,
+    pub subtotal: f64,
+    pub taxes: Vec<FurInvoiceTax>,
+    pub total: f64,
+    /// Tasks billed by this invoice
+    pub task_uids: Vec<String>,
+    /// Matching tasks left out because an earlier invoice already billed them
+    pub already_invoiced: usize,
+}
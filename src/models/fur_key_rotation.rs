@@ -0,0 +1,44 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::fur_sync_progress::SyncWatermark;
+
+/// A key rotation that has started on the server and can be resumed or cancelled
+#[derive(Clone, Debug, PartialEq)]
+pub struct FurKeyRotation {
+    /// The new key, encrypted with the device key like the current one
+    pub encrypted_key: String,
+    pub key_nonce: String,
+    pub key_generation: i64,
+    pub uploaded_count: i64,
+    pub tasks_uploaded_through: SyncWatermark,
+    pub shortcuts_uploaded_through: SyncWatermark,
+    pub todos_uploaded_through: SyncWatermark,
+}
+
+impl FurKeyRotation {
+    pub fn new(encrypted_key: String, key_nonce: String, key_generation: i64) -> Self {
+        FurKeyRotation {
+            encrypted_key,
+            key_nonce,
+            key_generation,
+            uploaded_count: 0,
+            tasks_uploaded_through: SyncWatermark::start(),
+            shortcuts_uploaded_through: SyncWatermark::start(),
+            todos_uploaded_through: SyncWatermark::start(),
+        }
+    }
+}
@@ -16,21 +16,9 @@
 
 use chrono::{DateTime, Local};
 
-use crate::database;
-
 #[derive(Clone, Debug, PartialEq)]
 pub struct FurPersist {
     pub is_running: bool,
     pub task_input: String,
     pub start_time: DateTime<Local>,
 }
-
-pub fn reset_persisting_timer() {
-    if let Err(e) = database::persistence::update_persisting_timer(&FurPersist {
-        is_running: false,
-        task_input: String::new(),
-        start_time: Local::now(),
-    }) {
-        eprintln!("Error updating persisting timer: {}", e);
-    }
-}
@@ -16,12 +16,15 @@
 
 use chrono::{DateTime, Local};
 
-#[derive(Clone, Debug)]
+/// Pomodoro progress, saved with the timer so every front end counts the same sessions
+#[derive(Clone, Debug, PartialEq)]
 pub struct FurPomodoro {
     pub on_break: bool,
     pub sessions: i64,
     pub snoozed: bool,
     pub snoozed_at: DateTime<Local>,
+    /// Whether PomodoroEnded has been emitted for the current countdown
+    pub end_announced: bool,
 }
 
 impl FurPomodoro {
@@ -31,6 +34,13 @@ impl FurPomodoro {
             sessions: 0,
             snoozed: false,
             snoozed_at: Local::now(),
+            end_announced: false,
         }
     }
 }
+
+impl Default for FurPomodoro {
+    fn default() -> Self {
+        Self::new()
+    }
+}
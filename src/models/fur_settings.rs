@@ -14,23 +14,78 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::helpers::view_enums::FurView;
+use crate::{
+    helpers::view_enums::{FurLogLevel, FurView},
+    models::{
+        fur_calendar_event::FurCategoryMapping,
+        fur_hook::FurHookEvent,
+        fur_invoice::{FurInvoiceClient, FurTaxLine},
+    },
+};
 
 use config::{Config, ConfigError, File};
-use directories::BaseDirs;
+use directories::UserDirs;
+use log::error;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct FurSettings {
+    /// Project and tags for calendar categories, used when importing .ics files
+    #[serde(default)]
+    pub calendar_category_mappings: Vec<FurCategoryMapping>,
     pub chosen_idle_time: i64,
     pub days_to_show: i64,
     pub default_view: FurView,
+    /// Name shown for this device in the devices list of other devices
+    /// Shown to the account's other devices. Empty until the user names this device.
+    #[serde(default)]
+    pub device_name: String,
+    #[serde(default)]
+    pub devices_synced_through: i64,
     pub dynamic_total: bool,
     #[serde(default)]
     pub first_run: bool,
+    /// Command or URL run when a pomodoro or break ends. Empty means no hook.
+    #[serde(default)]
+    pub hook_pomodoro_ended: String,
+    #[serde(default)]
+    pub hook_sync_finished: String,
+    /// Hooks still running after this long are stopped
+    #[serde(default = "default_hook_timeout_seconds")]
+    pub hook_timeout_seconds: i64,
+    #[serde(default)]
+    pub hook_timer_started: String,
+    #[serde(default)]
+    pub hook_timer_stopped: String,
+    #[serde(default)]
+    pub hook_todo_completed: String,
+    /// Client details remembered per project for the next invoice
+    #[serde(default)]
+    pub invoice_clients: Vec<FurInvoiceClient>,
+    /// Sequence number of the next invoice, shown after the prefix
+    #[serde(default = "default_invoice_next_number")]
+    pub invoice_next_number: i64,
+    #[serde(default = "default_invoice_number_prefix")]
+    pub invoice_number_prefix: String,
+    /// Your name and address, shown as who the invoice is from
+    #[serde(default)]
+    pub invoice_sender: String,
+    /// Taxes added to new invoices
+    #[serde(default)]
+    pub invoice_tax_lines: Vec<FurTaxLine>,
     pub last_sync: i64,
+    /// Serve the HTTP API on 127.0.0.1 for scripts and editor plugins
+    #[serde(default)]
+    pub local_api_enabled: bool,
+    #[serde(default = "default_local_api_port")]
+    pub local_api_port: i64,
+    /// Bearer token every local API request must send
+    #[serde(default)]
+    pub local_api_token: String,
+    #[serde(default = "default_log_level")]
+    pub log_level: FurLogLevel,
     pub needs_full_sync: bool,
     pub notify_of_sync: bool,
     pub notify_on_idle: bool,
@@ -62,17 +117,37 @@ pub struct FurSettings {
     pub show_todo_project: bool,
     pub show_todo_rate: bool,
     pub show_todo_tags: bool,
+    #[serde(default = "default_tombstone_retention_days")]
+    pub tombstone_retention_days: i64,
 }
 
 impl Default for FurSettings {
     fn default() -> Self {
         FurSettings {
+            calendar_category_mappings: Vec::new(),
             chosen_idle_time: 6,
             days_to_show: 365,
             default_view: FurView::Timer,
+            device_name: String::new(),
+            devices_synced_through: 0,
             dynamic_total: false,
             first_run: true,
+            hook_pomodoro_ended: String::new(),
+            hook_sync_finished: String::new(),
+            hook_timeout_seconds: default_hook_timeout_seconds(),
+            hook_timer_started: String::new(),
+            hook_timer_stopped: String::new(),
+            hook_todo_completed: String::new(),
+            invoice_clients: Vec::new(),
+            invoice_next_number: default_invoice_next_number(),
+            invoice_number_prefix: default_invoice_number_prefix(),
+            invoice_sender: String::new(),
+            invoice_tax_lines: Vec::new(),
             last_sync: 0,
+            local_api_enabled: false,
+            local_api_port: default_local_api_port(),
+            local_api_token: String::new(),
+            log_level: default_log_level(),
             needs_full_sync: true,
             notify_of_sync: true,
             notify_on_idle: true,
@@ -104,6 +179,7 @@ impl Default for FurSettings {
             show_todo_project: true,
             show_todo_rate: true,
             show_todo_tags: true,
+            tombstone_retention_days: default_tombstone_retention_days(),
         }
     }
 }
@@ -120,9 +196,9 @@ impl FurSettings {
         } else {
             // Create the default configuration file
             let default_settings = FurSettings::default();
-            let toml =
-                toml::to_string(&default_settings).expect("Failed to serialize default settings");
-            fs::write(&config_path, &toml).expect("Failed to write default config file");
+            let toml = toml::to_string(&default_settings)
+                .map_err(|e| ConfigError::Foreign(Box::new(e)))?;
+            fs::write(&config_path, &toml).map_err(|e| ConfigError::Foreign(Box::new(e)))?;
 
             builder = builder.add_source(File::from_str(&toml, config::FileFormat::Toml));
         }
@@ -133,7 +209,7 @@ impl FurSettings {
         // If the configuration file existed and we added a new setting, save it
         if config_path.exists() {
             if let Err(e) = settings.save() {
-                eprintln!("Error saving updated settings: {e}");
+                error!("Error saving updated settings: {e}");
             }
         }
 
@@ -141,10 +217,35 @@ impl FurSettings {
     }
 
     pub fn save(&self) -> Result<(), std::io::Error> {
-        let toml = toml::to_string(self).expect("Failed to serialize settings");
+        let toml = toml::to_string(self).map_err(std::io::Error::other)?;
         fs::write(get_settings_path(), toml)
     }
 
+    /// The task input remembered for a calendar category, ignoring case
+    pub fn category_mapping(&self, category: &str) -> Option<&str> {
+        self.calendar_category_mappings
+            .iter()
+            .find(|mapping| mapping.category.eq_ignore_ascii_case(category))
+            .map(|mapping| mapping.task_input.as_str())
+    }
+
+    /// Remember `task_input` for a calendar category, or forget the category if it's empty
+    pub fn change_category_mapping(
+        &mut self,
+        category: &str,
+        task_input: &str,
+    ) -> Result<(), std::io::Error> {
+        self.calendar_category_mappings
+            .retain(|mapping| !mapping.category.eq_ignore_ascii_case(category));
+        if !task_input.trim().is_empty() {
+            self.calendar_category_mappings.push(FurCategoryMapping {
+                category: category.to_string(),
+                task_input: task_input.to_string(),
+            });
+        }
+        self.save()
+    }
+
     pub fn change_chosen_idle_time(&mut self, value: &i64) -> Result<(), std::io::Error> {
         self.chosen_idle_time = value.to_owned();
         self.save()
@@ -167,6 +268,130 @@ impl FurSettings {
         self.save()
     }
 
+    pub fn change_device_name(&mut self, value: &str) -> Result<(), std::io::Error> {
+        self.device_name = value.trim().to_string();
+        self.save()
+    }
+
+    /// The command or URL saved for `event`
+    pub fn hook(&self, event: FurHookEvent) -> &str {
+        match event {
+            FurHookEvent::TimerStarted => &self.hook_timer_started,
+            FurHookEvent::TimerStopped => &self.hook_timer_stopped,
+            FurHookEvent::PomodoroEnded => &self.hook_pomodoro_ended,
+            FurHookEvent::TodoCompleted => &self.hook_todo_completed,
+            FurHookEvent::SyncFinished => &self.hook_sync_finished,
+        }
+    }
+
+    pub fn change_hook(&mut self, event: FurHookEvent, value: &str) -> Result<(), std::io::Error> {
+        let hook = match event {
+            FurHookEvent::TimerStarted => &mut self.hook_timer_started,
+            FurHookEvent::TimerStopped => &mut self.hook_timer_stopped,
+            FurHookEvent::PomodoroEnded => &mut self.hook_pomodoro_ended,
+            FurHookEvent::TodoCompleted => &mut self.hook_todo_completed,
+            FurHookEvent::SyncFinished => &mut self.hook_sync_finished,
+        };
+        *hook = value.to_string();
+        self.save()
+    }
+
+    pub fn change_hook_timeout_seconds(&mut self, value: &i64) -> Result<(), std::io::Error> {
+        if (1..=600).contains(value) {
+            self.hook_timeout_seconds = value.to_owned();
+            self.save()
+        } else {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Hook timeout must be between 1 and 600 seconds",
+            ))
+        }
+    }
+
+    /// The remembered client details for a project, ignoring case
+    pub fn invoice_client(&self, project: &str) -> Option<&str> {
+        self.invoice_clients
+            .iter()
+            .find(|client| client.project.eq_ignore_ascii_case(project))
+            .map(|client| client.details.as_str())
+    }
+
+    /// Remember a project's client details, or forget them if they're empty
+    pub fn change_invoice_client(
+        &mut self,
+        project: &str,
+        details: &str,
+    ) -> Result<(), std::io::Error> {
+        self.invoice_clients
+            .retain(|client| !client.project.eq_ignore_ascii_case(project));
+        if !details.trim().is_empty() {
+            self.invoice_clients.push(FurInvoiceClient {
+                project: project.to_string(),
+                details: details.to_string(),
+            });
+        }
+        self.save()
+    }
+
+    pub fn change_invoice_next_number(&mut self, value: &i64) -> Result<(), std::io::Error> {
+        if value >= &1 {
+            self.invoice_next_number = value.to_owned();
+            self.save()
+        } else {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Invoice numbers must be at least 1",
+            ))
+        }
+    }
+
+    pub fn change_invoice_number_prefix(&mut self, value: &str) -> Result<(), std::io::Error> {
+        self.invoice_number_prefix = value.to_string();
+        self.save()
+    }
+
+    pub fn change_invoice_sender(&mut self, value: &str) -> Result<(), std::io::Error> {
+        self.invoice_sender = value.to_string();
+        self.save()
+    }
+
+    pub fn change_invoice_tax_lines(&mut self, value: &[FurTaxLine]) -> Result<(), std::io::Error> {
+        self.invoice_tax_lines = value.to_vec();
+        self.save()
+    }
+
+    pub fn change_local_api_enabled(&mut self, value: &bool) -> Result<(), std::io::Error> {
+        self.local_api_enabled = value.to_owned();
+        self.save()
+    }
+
+    pub fn change_local_api_port(&mut self, value: &i64) -> Result<(), std::io::Error> {
+        if (1024..=65535).contains(value) {
+            self.local_api_port = value.to_owned();
+            self.save()
+        } else {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "The local API port must be between 1024 and 65535",
+            ))
+        }
+    }
+
+    pub fn change_local_api_token(&mut self, value: &str) -> Result<(), std::io::Error> {
+        self.local_api_token = value.to_string();
+        self.save()
+    }
+
+    pub fn change_log_level(&mut self, value: &FurLogLevel) -> Result<(), std::io::Error> {
+        self.log_level = value.to_owned();
+        self.save()
+    }
+
+    pub fn change_devices_synced_through(&mut self, value: &i64) -> Result<(), std::io::Error> {
+        self.devices_synced_through = value.to_owned();
+        self.save()
+    }
+
     pub fn change_first_run(&mut self, value: bool) -> Result<(), std::io::Error> {
         self.first_run = value;
         self.save()
@@ -392,13 +617,63 @@ impl FurSettings {
         self.show_todo_tags = value.to_owned();
         self.save()
     }
+
+    pub fn change_tombstone_retention_days(&mut self, value: &i64) -> Result<(), std::io::Error> {
+        if value >= &1 {
+            self.tombstone_retention_days = value.to_owned();
+            self.save()
+        } else {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Deleted items must be kept for at least 1 day",
+            ))
+        }
+    }
+}
+
+fn default_tombstone_retention_days() -> i64 {
+    90
+}
+
+fn default_hook_timeout_seconds() -> i64 {
+    10
+}
+
+fn default_invoice_next_number() -> i64 {
+    1
 }
 
+fn default_invoice_number_prefix() -> String {
+    "INV-".to_string()
+}
+
+fn default_local_api_port() -> i64 {
+    47811
+}
+
+fn default_log_level() -> FurLogLevel {
+    FurLogLevel::Info
+}
+
+/// Environment variable that moves the data folder
+pub const DATA_DIR_VARIABLE: &str = "FURTHERANCE_DATA_DIR";
+
+/// Tests keep their databases and settings out of the user's data folder
+#[cfg(test)]
 pub fn get_data_path() -> PathBuf {
-    if let Some(base_dirs) = BaseDirs::new() {
-        // DEBUG println!("BaseDirs data_dir: {:?}", base_dirs.data_dir());
-        let path = PathBuf::from(base_dirs.data_dir());
-        std::fs::create_dir_all(&path).expect("Could not create data directory");
+    crate::helpers::test_data::data_path()
+}
+
+/// The user's data folder, or FURTHERANCE_DATA_DIR when it is set, e.g. by integration tests
+#[cfg(not(test))]
+pub fn get_data_path() -> PathBuf {
+    let data_dir = std::env::var_os(DATA_DIR_VARIABLE)
+        .map(PathBuf::from)
+        .or_else(|| directories::BaseDirs::new().map(|dirs| PathBuf::from(dirs.data_dir())));
+    if let Some(path) = data_dir {
+        if let Err(e) = std::fs::create_dir_all(&path) {
+            error!("Could not create data directory: {}", e);
+        }
         return path;
     } else {
         let fallback = PathBuf::from(".");
@@ -407,6 +682,14 @@ pub fn get_data_path() -> PathBuf {
     }
 }
 
+/// Where exported files are saved: the Downloads folder, or the data folder without one
+pub fn get_export_path() -> PathBuf {
+    match UserDirs::new().and_then(|dirs| dirs.download_dir().map(PathBuf::from)) {
+        Some(path) => path,
+        None => get_data_path(),
+    }
+}
+
 fn get_settings_path() -> PathBuf {
     let mut path = get_data_path();
     path.extend(&["settings.toml"]);
@@ -14,8 +14,11 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use crate::services::importers::ImportTable;
+
 use super::{
-    fur_shortcut::FurShortcut, fur_task::FurTask, fur_task_group::FurTaskGroup, fur_todo::FurTodo,
+    fur_calendar_event::FurCalendarEvent, fur_shortcut::FurShortcut, fur_task::FurTask,
+    fur_task_group::FurTaskGroup, fur_todo::FurTodo,
 };
 
 #[derive(Clone, Debug)]
@@ -23,12 +26,17 @@ pub struct FurSheet {
     pub new_task_is_shown: bool,
     pub new_shortcut_is_shown: bool,
     pub new_todo_is_shown: bool,
+    pub new_invoice_is_shown: bool,
     pub group_details_sheet: Option<FurTaskGroup>,
     pub task_edit_sheet: Option<FurTask>,
     pub add_to_group_sheet: Option<FurTaskGroup>,
     pub edit_group_sheet: Option<FurTaskGroup>,
     pub edit_todo_sheet: Option<FurTodo>,
     pub edit_shortcut_sheet: Option<FurShortcut>,
+    /// Events read from an .ics file, waiting to be picked for import
+    pub import_calendar_sheet: Option<Vec<FurCalendarEvent>>,
+    /// Entries read from another tracker's export, waiting for their columns to be checked
+    pub import_tasks_sheet: Option<ImportTable>,
 }
 
 impl FurSheet {
@@ -37,12 +45,15 @@ impl FurSheet {
             new_task_is_shown: false,
             new_shortcut_is_shown: false,
             new_todo_is_shown: false,
+            new_invoice_is_shown: false,
             group_details_sheet: None,
             task_edit_sheet: None,
             add_to_group_sheet: None,
             edit_group_sheet: None,
             edit_todo_sheet: None,
             edit_shortcut_sheet: None,
+            import_calendar_sheet: None,
+            import_tasks_sheet: None,
         }
     }
 }
@@ -17,6 +17,8 @@
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
 
+use crate::helpers::uid::generate_uid;
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct FurShortcut {
     pub name: String,
@@ -39,8 +41,6 @@ impl FurShortcut {
         currency: String,
         color_hex: String,
     ) -> Self {
-        let uid = generate_shortcut_uid(&name, &tags, &project, &rate, &currency);
-
         FurShortcut {
             name,
             tags,
@@ -48,7 +48,7 @@ impl FurShortcut {
             rate,
             currency,
             color_hex,
-            uid,
+            uid: generate_uid(),
             is_deleted: false,
             last_updated: Utc::now().timestamp(),
         }
@@ -73,18 +73,6 @@ impl ToString for FurShortcut {
     }
 }
 
-pub fn generate_shortcut_uid(
-    name: &str,
-    tags: &str,
-    project: &str,
-    rate: &f32,
-    currency: &str,
-) -> String {
-    let input = format!("{}{}{}{}{}", name, tags, project, rate, currency);
-
-    blake3::hash(input.as_bytes()).to_hex().to_string()
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EncryptedShortcut {
     pub encrypted_data: String,
@@ -0,0 +1,105 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use serde::Serialize;
+
+/// Records of each type sent or applied during a sync
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct RecordCounts {
+    pub tasks: i64,
+    pub shortcuts: i64,
+    pub todos: i64,
+}
+
+impl RecordCounts {
+    pub fn total(&self) -> i64 {
+        self.tasks + self.shortcuts + self.todos
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub enum SyncDirection {
+    None,
+    Upload,
+    Download,
+    Both,
+}
+
+impl SyncDirection {
+    pub fn to_sqlite(&self) -> &str {
+        match self {
+            SyncDirection::None => "none",
+            SyncDirection::Upload => "upload",
+            SyncDirection::Download => "download",
+            SyncDirection::Both => "both",
+        }
+    }
+
+    pub fn from_sqlite(value: &str) -> Self {
+        match value {
+            "upload" => SyncDirection::Upload,
+            "download" => SyncDirection::Download,
+            "both" => SyncDirection::Both,
+            _ => SyncDirection::None,
+        }
+    }
+}
+
+/// The outcome of one sync, kept for diagnosing sync problems
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct FurSyncLogEntry {
+    /// Local time the sync started
+    pub started_at: i64,
+    pub duration_ms: i64,
+    pub full_sync: bool,
+    pub uploaded: RecordCounts,
+    pub downloaded: RecordCounts,
+    pub orphans_resent: i64,
+    /// "type:uid" of each server record that couldn't be decrypted or didn't match its uid
+    pub decrypt_failures: Vec<String>,
+    pub error: Option<String>,
+}
+
+impl FurSyncLogEntry {
+    pub fn new(started_at: i64, full_sync: bool) -> Self {
+        FurSyncLogEntry {
+            started_at,
+            duration_ms: 0,
+            full_sync,
+            uploaded: RecordCounts::default(),
+            downloaded: RecordCounts::default(),
+            orphans_resent: 0,
+            decrypt_failures: Vec::new(),
+            error: None,
+        }
+    }
+
+    pub fn direction(&self) -> SyncDirection {
+        let uploaded = self.uploaded.total() + self.orphans_resent > 0;
+        let downloaded = self.downloaded.total() > 0;
+        match (uploaded, downloaded) {
+            (true, true) => SyncDirection::Both,
+            (true, false) => SyncDirection::Upload,
+            (false, true) => SyncDirection::Download,
+            (false, false) => SyncDirection::None,
+        }
+    }
+
+    /// Every record sent or applied, as reported after a successful sync
+    pub fn record_count(&self) -> i64 {
+        self.uploaded.total() + self.downloaded.total() + self.orphans_resent
+    }
+}
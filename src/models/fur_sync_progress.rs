@@ -0,0 +1,74 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+/// The last record uploaded for one table, ordered by (last_updated, uid)
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyncWatermark {
+    pub last_updated: i64,
+    pub uid: String,
+}
+
+impl SyncWatermark {
+    pub fn start() -> Self {
+        SyncWatermark {
+            last_updated: i64::MIN,
+            uid: String::new(),
+        }
+    }
+}
+
+/// Persisted state of a sync that is split into pages, so an interrupted sync can resume
+#[derive(Clone, Debug, PartialEq)]
+pub struct FurSyncProgress {
+    pub full_sync: bool,
+    /// Local time the sync started, used to acknowledge uploaded tombstones
+    pub started_at: i64,
+    /// Last sync time before this sync started; local changes since then are uploaded
+    pub last_sync: i64,
+    /// Server time that downloads are requested from
+    pub download_since: i64,
+    /// Server time of the first response since downloads last caught up
+    pub download_started_at: Option<i64>,
+    pub download_cursor: Option<String>,
+    pub tasks_uploaded_through: SyncWatermark,
+    pub shortcuts_uploaded_through: SyncWatermark,
+    pub todos_uploaded_through: SyncWatermark,
+}
+
+impl FurSyncProgress {
+    pub fn new(last_sync: i64, full_sync: bool, started_at: i64) -> Self {
+        FurSyncProgress {
+            full_sync,
+            started_at,
+            last_sync,
+            download_since: last_sync,
+            download_started_at: None,
+            download_cursor: None,
+            tasks_uploaded_through: SyncWatermark::start(),
+            shortcuts_uploaded_through: SyncWatermark::start(),
+            todos_uploaded_through: SyncWatermark::start(),
+        }
+    }
+
+    /// The earliest last_updated a record can have and still need uploading
+    pub fn upload_since(&self) -> i64 {
+        if self.full_sync {
+            i64::MIN
+        } else {
+            self.last_sync
+        }
+    }
+}
@@ -17,6 +17,8 @@
 use chrono::{DateTime, Local, Utc};
 use serde::{Deserialize, Serialize};
 
+use crate::helpers::uid::generate_uid;
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct FurTask {
     pub name: String,
@@ -29,6 +31,9 @@ pub struct FurTask {
     pub uid: String,
     pub is_deleted: bool,
     pub last_updated: i64,
+    /// Number of the invoice that billed this task, empty until it is invoiced
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub invoice_number: String,
 }
 
 impl ToString for FurTask {
@@ -59,8 +64,6 @@ impl FurTask {
         rate: f32,
         currency: String,
     ) -> Self {
-        let uid = generate_task_uid(&name, &start_time, &stop_time);
-
         FurTask {
             name,
             start_time,
@@ -69,9 +72,10 @@ impl FurTask {
             project,
             rate,
             currency,
-            uid,
+            uid: generate_uid(),
             is_deleted: false,
             last_updated: Utc::now().timestamp(),
+            invoice_number: String::new(),
         }
     }
 
@@ -85,8 +89,6 @@ impl FurTask {
         currency: String,
         last_updated: i64,
     ) -> Self {
-        let uid = generate_task_uid(&name, &start_time, &stop_time);
-
         FurTask {
             name,
             start_time,
@@ -95,9 +97,10 @@ impl FurTask {
             project,
             rate,
             currency,
-            uid,
+            uid: generate_uid(),
             is_deleted: false,
             last_updated,
+            invoice_number: String::new(),
         }
     }
 
@@ -110,21 +113,6 @@ impl FurTask {
     }
 }
 
-pub fn generate_task_uid(
-    name: &str,
-    start_time: &DateTime<Local>,
-    stop_time: &DateTime<Local>,
-) -> String {
-    let input = format!(
-        "{}{}{}",
-        name,
-        start_time.timestamp(),
-        stop_time.timestamp()
-    );
-
-    blake3::hash(input.as_bytes()).to_hex().to_string()
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EncryptedTask {
     pub encrypted_data: String,
@@ -17,6 +17,8 @@
 use chrono::{DateTime, Local, Utc};
 use serde::{Deserialize, Serialize};
 
+use crate::helpers::uid::generate_uid;
+
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub struct FurTodo {
     pub name: String,
@@ -39,8 +41,6 @@ impl FurTodo {
         rate: f32,
         date: DateTime<Local>,
     ) -> Self {
-        let uid = generate_todo_uid(&name, &date);
-
         FurTodo {
             name,
             project,
@@ -48,7 +48,7 @@ impl FurTodo {
             rate,
             currency: String::new(),
             date,
-            uid,
+            uid: generate_uid(),
             is_completed: false,
             is_deleted: false,
             last_updated: Utc::now().timestamp(),
@@ -74,11 +74,6 @@ impl ToString for FurTodo {
     }
 }
 
-pub fn generate_todo_uid(name: &str, date: &DateTime<Local>) -> String {
-    let input = format!("{}{}", name, date.timestamp());
-    blake3::hash(input.as_bytes()).to_hex().to_string()
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EncryptedTodo {
     pub encrypted_data: String,
@@ -14,7 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::{constants::OFFICIAL_SERVER, helpers::views::settings::ServerChoices};
+use crate::server::key_storage::KeyStorageKind;
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct FurUser {
@@ -24,23 +24,10 @@ pub struct FurUser {
     pub access_token: String,
     pub refresh_token: String,
     pub server: String,
-}
-
-#[derive(Debug, Clone)]
-pub struct FurUserFields {
-    pub email: String,
-    pub encryption_key: String,
-    pub server: String,
-    pub server_selection: ServerChoices,
-}
-
-impl Default for FurUserFields {
-    fn default() -> Self {
-        FurUserFields {
-            email: String::new(),
-            encryption_key: String::new(),
-            server: OFFICIAL_SERVER.to_string(),
-            server_selection: ServerChoices::Official,
-        }
-    }
+    /// Incremented each time the account's encryption key is rotated
+    pub key_generation: i64,
+    /// What wraps encrypted_key on this device
+    pub key_storage: KeyStorageKind,
+    /// Salt for deriving the wrapping key from a PIN
+    pub key_storage_salt: Option<String>,
 }
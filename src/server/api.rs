@@ -0,0 +1,229 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use reqwest::{header::HeaderMap, Client, StatusCode};
+use serde::{Deserialize, Serialize};
+
+use crate::{database, models::fur_user::FurUser};
+
+use super::{
+    encryption::{generate_device_id, KeyCanary},
+    kdf::KdfInfo,
+};
+
+#[derive(Clone, Debug)]
+pub enum ApiError {
+    Auth(String),
+    Device(String),
+    InactiveSubscription(String),
+    KeyDerivation,
+    /// The account's key was rotated on another device
+    KeyRotated,
+    Network(Arc<reqwest::Error>),
+    Server(String),
+    TokenRefresh(String),
+    /// The server is overloaded or down, with the seconds it asked clients to wait
+    Unavailable(Option<u64>),
+}
+
+impl ApiError {
+    /// Whether trying again later could succeed
+    pub fn is_temporary(&self) -> bool {
+        matches!(self, ApiError::Network(_) | ApiError::Unavailable(_))
+    }
+
+    pub fn retry_after(&self) -> Option<u64> {
+        match self {
+            ApiError::Unavailable(retry_after) => *retry_after,
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct RefreshRequest {
+    refresh_token: String,
+    device_id: String,
+}
+
+#[derive(Deserialize)]
+struct RefreshResponse {
+    access_token: String,
+}
+
+#[derive(Serialize)]
+pub struct LoginRequest {
+    pub email: String,
+    pub encryption_key: String,
+    pub device_id: String,
+    /// Passphrase salt, stored by the server if the account doesn't have one yet
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kdf: Option<KdfInfo>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct LoginResponse {
+    pub access_token: String,
+    pub refresh_token: String,
+    #[serde(default)]
+    pub key_generation: i64,
+    /// Missing for accounts that haven't stored one yet
+    #[serde(default)]
+    pub canary: Option<KeyCanary>,
+}
+
+/// POST to the sync server, refreshing the access token once if it has expired
+pub async fn post_authorized<T: Serialize>(
+    user: &mut FurUser,
+    path: &str,
+    body: &T,
+) -> Result<reqwest::Response, ApiError> {
+    let client = Client::new();
+
+    let mut response = client
+        .post(format!("{}{}", user.server, path))
+        .header("Authorization", format!("Bearer {}", user.access_token))
+        .json(body)
+        .send()
+        .await
+        .map_err(|e| ApiError::Network(Arc::new(e)))?;
+
+    if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+        // Try token refresh
+        let new_access_token =
+            refresh_auth_token(user.refresh_token.to_string(), &user.server).await?;
+        if let Err(e) = database::sync::update_access_token(&user.email, &new_access_token) {
+            return Err(ApiError::TokenRefresh(e.to_string()));
+        }
+        user.access_token = new_access_token.clone();
+
+        // Retry with new token
+        response = client
+            .post(format!("{}{}", user.server, path))
+            .header("Authorization", format!("Bearer {}", new_access_token))
+            .json(body)
+            .send()
+            .await
+            .map_err(|e| ApiError::Network(Arc::new(e)))?;
+    }
+
+    Ok(response)
+}
+
+/// Map an unsuccessful response to an error, falling back to a server error with the message
+pub async fn response_error(response: reqwest::Response, message: &str) -> ApiError {
+    let status = response.status();
+    if status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+        return ApiError::Unavailable(retry_after_seconds(response.headers()));
+    }
+
+    if let Ok(error) = response.json::<serde_json::Value>().await {
+        match error.get("error").and_then(|e| e.as_str()) {
+            Some("inactive_subscription") => {
+                return ApiError::InactiveSubscription(
+                    error
+                        .get("message")
+                        .and_then(|m| m.as_str())
+                        .unwrap_or("Subscription inactive")
+                        .to_string(),
+                );
+            }
+            Some("key_rotated") => return ApiError::KeyRotated,
+            _ => {}
+        }
+    }
+
+    ApiError::Server(message.into())
+}
+
+/// Read a Retry-After header given either as seconds or as an HTTP date
+fn retry_after_seconds(headers: &HeaderMap) -> Option<u64> {
+    let value = headers.get(reqwest::header::RETRY_AFTER)?.to_str().ok()?;
+
+    if let Ok(seconds) = value.trim().parse::<u64>() {
+        return Some(seconds);
+    }
+
+    DateTime::parse_from_rfc2822(value.trim())
+        .ok()
+        .map(|date| (date.timestamp() - Utc::now().timestamp()).max(0) as u64)
+}
+
+/// Sign in to `server`, which registers the account on first use
+pub async fn login(
+    email: String,
+    encryption_key: String,
+    kdf: Option<KdfInfo>,
+    server: String,
+) -> Result<LoginResponse, ApiError> {
+    let client = Client::new();
+    let device_id = match generate_device_id() {
+        Ok(id) => id,
+        Err(_) => return Err(ApiError::Device("Failed to generate device ID".into())),
+    };
+
+    let response = client
+        .post(format!("{}/api/login", server))
+        .json(&LoginRequest {
+            email,
+            encryption_key,
+            device_id,
+            kdf,
+        })
+        .send()
+        .await
+        .map_err(|e| ApiError::Network(Arc::new(e)))?;
+
+    if response.status().is_success() {
+        response
+            .json()
+            .await
+            .map_err(|e| ApiError::Network(Arc::new(e)))
+    } else {
+        Err(ApiError::Auth("Invalid credentials".into()))
+    }
+}
+
+pub async fn refresh_auth_token(refresh_token: String, server: &str) -> Result<String, ApiError> {
+    let client = Client::new();
+    let device_id = match generate_device_id() {
+        Ok(id) => id,
+        Err(_) => return Err(ApiError::Device("Failed to generate device ID".into())),
+    };
+
+    let response = client
+        .post(format!("{}/api/refresh", server))
+        .json(&RefreshRequest {
+            refresh_token,
+            device_id,
+        })
+        .send()
+        .await
+        .map_err(|e| ApiError::Network(Arc::new(e)))?;
+
+    if response.status().is_success() {
+        let refresh_response = response
+            .json::<RefreshResponse>()
+            .await
+            .map_err(|e| ApiError::Network(Arc::new(e)))?;
+        Ok(refresh_response.access_token)
+    } else {
+        Err(ApiError::TokenRefresh("Failed to refresh token".into()))
+    }
+}
@@ -0,0 +1,364 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use aes_gcm::{
+    aead::{Aead, KeyInit, Payload},
+    Aes256Gcm, Key, Nonce,
+};
+use base64::{
+    engine::general_purpose::{STANDARD as BASE64, URL_SAFE_NO_PAD},
+    Engine,
+};
+use rand::{thread_rng, RngCore};
+use serde::{Deserialize, Serialize};
+
+use crate::models::fur_settings::get_data_path;
+
+use super::key_storage::{storage_for, KeyStorageKind};
+
+#[derive(Debug)]
+pub enum EncryptionError {
+    Encryption,
+    Decryption,
+    Serialization,
+    DeviceId,
+    KeyDerivation,
+    KeyStorage,
+    /// The stored key is protected by a PIN that hasn't been entered yet
+    Locked,
+}
+
+/// Marks record ciphertext sealed with associated data. Older ciphertext has no prefix.
+const RECORD_PAYLOAD_V1_PREFIX: &str = "v1.";
+const RECORD_PAYLOAD_VERSION: u32 = 1;
+/// Known value the server stores encrypted so a key can be checked before syncing with it
+const CANARY_PLAINTEXT: &str = "furtherance-key-canary";
+const CANARY_ASSOCIATED_DATA: &[u8] = b"furtherance:canary:1";
+const FINGERPRINT_CONTEXT: &str = "furtherance 2025 sync key fingerprint v1";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct KeyCanary {
+    pub encrypted_data: String,
+    pub nonce: String,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum RecordType {
+    Task,
+    Shortcut,
+    Todo,
+}
+
+impl RecordType {
+    fn as_str(&self) -> &str {
+        match self {
+            RecordType::Task => "task",
+            RecordType::Shortcut => "shortcut",
+            RecordType::Todo => "todo",
+        }
+    }
+}
+
+/// The unencrypted fields sent with a record, which its ciphertext is bound to
+pub struct RecordIdentity<'a> {
+    pub record_type: RecordType,
+    pub uid: &'a str,
+    pub last_updated: i64,
+}
+
+impl RecordIdentity<'_> {
+    fn associated_data(&self, payload_version: u32) -> Vec<u8> {
+        format!(
+            "furtherance:{}:{}:{}:{}",
+            self.record_type.as_str(),
+            self.uid,
+            self.last_updated,
+            payload_version
+        )
+        .into_bytes()
+    }
+}
+
+pub fn encrypt<T: Serialize>(
+    data: &T,
+    key: &[u8; 32],
+) -> Result<(String, String), EncryptionError> {
+    encrypt_with_associated_data(data, &[], key)
+}
+
+/// Encrypt a synced record so the server can't move it to another uid or timestamp
+pub fn encrypt_record<T: Serialize>(
+    data: &T,
+    identity: &RecordIdentity,
+    key: &[u8; 32],
+) -> Result<(String, String), EncryptionError> {
+    let (ciphertext, nonce) =
+        encrypt_with_associated_data(data, &identity.associated_data(RECORD_PAYLOAD_VERSION), key)?;
+    Ok((format!("{}{}", RECORD_PAYLOAD_V1_PREFIX, ciphertext), nonce))
+}
+
+fn encrypt_with_associated_data<T: Serialize>(
+    data: &T,
+    associated_data: &[u8],
+    key: &[u8; 32],
+) -> Result<(String, String), EncryptionError> {
+    let mut nonce_bytes = [0u8; 12];
+    thread_rng().fill_bytes(&mut nonce_bytes);
+    let nonce = Nonce::from_slice(&nonce_bytes);
+
+    let json = serde_json::to_string(data).map_err(|_| EncryptionError::Serialization)?;
+
+    let key = Key::<Aes256Gcm>::from_slice(key);
+    let cipher = Aes256Gcm::new(key);
+
+    let ciphertext = cipher
+        .encrypt(
+            nonce,
+            Payload {
+                msg: json.as_bytes(),
+                aad: associated_data,
+            },
+        )
+        .map_err(|_| EncryptionError::Encryption)?;
+
+    Ok((BASE64.encode(ciphertext), BASE64.encode(nonce_bytes)))
+}
+
+pub fn decrypt<T: for<'de> Deserialize<'de>>(
+    encrypted_data: &str,
+    nonce_b64: &str,
+    key: &[u8; 32],
+) -> Result<T, EncryptionError> {
+    decrypt_with_associated_data(encrypted_data, nonce_b64, &[], key)
+}
+
+/// Decrypt a synced record, accepting records written before associated data was added
+pub fn decrypt_record<T: for<'de> Deserialize<'de>>(
+    encrypted_data: &str,
+    nonce_b64: &str,
+    identity: &RecordIdentity,
+    key: &[u8; 32],
+) -> Result<T, EncryptionError> {
+    match encrypted_data.strip_prefix(RECORD_PAYLOAD_V1_PREFIX) {
+        Some(ciphertext) => {
+            decrypt_with_associated_data(ciphertext, nonce_b64, &identity.associated_data(1), key)
+        }
+        None => decrypt(encrypted_data, nonce_b64, key),
+    }
+}
+
+fn decrypt_with_associated_data<T: for<'de> Deserialize<'de>>(
+    encrypted_data: &str,
+    nonce_b64: &str,
+    associated_data: &[u8],
+    key: &[u8; 32],
+) -> Result<T, EncryptionError> {
+    let ciphertext = BASE64
+        .decode(encrypted_data)
+        .map_err(|_| EncryptionError::Decryption)?;
+    let nonce_bytes = BASE64
+        .decode(nonce_b64)
+        .map_err(|_| EncryptionError::Decryption)?;
+
+    // Ensure nonce is correct size
+    if nonce_bytes.len() != 12 {
+        return Err(EncryptionError::Decryption);
+    }
+    let nonce = Nonce::from_slice(&nonce_bytes);
+
+    let key = Key::<Aes256Gcm>::from_slice(key);
+    let cipher = Aes256Gcm::new(key);
+
+    let plaintext = cipher
+        .decrypt(
+            nonce,
+            Payload {
+                msg: ciphertext.as_ref(),
+                aad: associated_data,
+            },
+        )
+        .map_err(|_| EncryptionError::Decryption)?;
+
+    serde_json::from_slice(&plaintext).map_err(|_| EncryptionError::Serialization)
+}
+
+pub fn encrypt_encryption_key(
+    encryption_key: &String,
+    key_storage: &KeyStorageKind,
+) -> Result<(String, String), EncryptionError> {
+    let wrapping_key = storage_for(key_storage).wrapping_key()?;
+    encrypt(encryption_key, &wrapping_key)
+}
+
+pub fn decrypt_encryption_key(
+    encrypted_key: &str,
+    nonce: &str,
+    key_storage: &KeyStorageKind,
+) -> Result<[u8; 32], EncryptionError> {
+    let wrapping_key = storage_for(key_storage).wrapping_key()?;
+    let key_string: String = decrypt(encrypted_key, nonce, &wrapping_key)?;
+    decode_sync_key(&key_string)
+}
+
+/// Turn a base64 sync key into its 32 bytes
+pub fn decode_sync_key(key_string: &str) -> Result<[u8; 32], EncryptionError> {
+    let key_bytes = URL_SAFE_NO_PAD
+        .decode(key_string)
+        .map_err(|_| EncryptionError::Serialization)?;
+
+    if key_bytes.len() != 32 {
+        return Err(EncryptionError::Serialization);
+    }
+
+    let mut result = [0u8; 32];
+    result.copy_from_slice(&key_bytes);
+
+    Ok(result)
+}
+
+pub fn create_canary(key: &[u8; 32]) -> Result<KeyCanary, EncryptionError> {
+    let (encrypted_data, nonce) =
+        encrypt_with_associated_data(&CANARY_PLAINTEXT, CANARY_ASSOCIATED_DATA, key)?;
+    Ok(KeyCanary {
+        encrypted_data,
+        nonce,
+    })
+}
+
+pub fn key_matches_canary(key: &[u8; 32], canary: &KeyCanary) -> bool {
+    decrypt_with_associated_data::<String>(
+        &canary.encrypted_data,
+        &canary.nonce,
+        CANARY_ASSOCIATED_DATA,
+        key,
+    )
+    .is_ok_and(|plaintext| plaintext == CANARY_PLAINTEXT)
+}
+
+/// Short, one-way summary of a sync key for comparing devices, e.g. "3F9A-01C2-7B44"
+pub fn key_fingerprint(key: &[u8; 32]) -> String {
+    let hash = blake3::derive_key(FINGERPRINT_CONTEXT, key);
+    hash[..6]
+        .chunks(2)
+        .map(|pair| format!("{:02X}{:02X}", pair[0], pair[1]))
+        .collect::<Vec<String>>()
+        .join("-")
+}
+
+/// Move a stored sync key from one key storage to another
+pub fn rewrap_encryption_key(
+    encrypted_key: &str,
+    nonce: &str,
+    from: &KeyStorageKind,
+    new_wrapping_key: &[u8; 32],
+) -> Result<(String, String), EncryptionError> {
+    let old_wrapping_key = storage_for(from).wrapping_key()?;
+    let key_string: String = decrypt(encrypted_key, nonce, &old_wrapping_key)?;
+    encrypt(&key_string, new_wrapping_key)
+}
+
+pub fn generate_device_id() -> Result<String, EncryptionError> {
+    use std::fs;
+    use uuid::Uuid;
+
+    let mut id_file = get_data_path();
+    id_file.extend(&["device_id"]);
+
+    if id_file.exists() {
+        let id = fs::read_to_string(&id_file).map_err(|_| EncryptionError::DeviceId)?;
+        Ok(id)
+    } else {
+        let new_id = Uuid::new_v4().to_string();
+        fs::write(&id_file, &new_id).map_err(|_| EncryptionError::DeviceId)?;
+        Ok(new_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: [u8; 32] = [3; 32];
+    const UID: &str = "task-uid";
+    const LAST_UPDATED: i64 = 1_700_000_000;
+
+    fn identity(record_type: RecordType, uid: &str, last_updated: i64) -> RecordIdentity<'_> {
+        RecordIdentity {
+            record_type,
+            uid,
+            last_updated,
+        }
+    }
+
+    fn sealed_task() -> (String, String) {
+        encrypt_record(
+            &"Write tests".to_string(),
+            &identity(RecordType::Task, UID, LAST_UPDATED),
+            &KEY,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn records_decrypt_only_under_their_own_identity() {
+        let (ciphertext, nonce) = sealed_task();
+        assert!(ciphertext.starts_with(RECORD_PAYLOAD_V1_PREFIX));
+
+        let opened: String = decrypt_record(
+            &ciphertext,
+            &nonce,
+            &identity(RecordType::Task, UID, LAST_UPDATED),
+            &KEY,
+        )
+        .unwrap();
+        assert_eq!(opened, "Write tests");
+
+        // The server moved the blob to another record
+        for moved in [
+            identity(RecordType::Task, "other-uid", LAST_UPDATED),
+            identity(RecordType::Todo, UID, LAST_UPDATED),
+            identity(RecordType::Task, UID, LAST_UPDATED + 1),
+        ] {
+            let result: Result<String, _> = decrypt_record(&ciphertext, &nonce, &moved, &KEY);
+            assert!(matches!(result, Err(EncryptionError::Decryption)));
+        }
+
+        // Nor can the prefix be stripped to skip the check
+        let unprefixed = ciphertext.trim_start_matches(RECORD_PAYLOAD_V1_PREFIX);
+        let result: Result<String, _> = decrypt_record(
+            unprefixed,
+            &nonce,
+            &identity(RecordType::Task, UID, LAST_UPDATED),
+            &KEY,
+        );
+        assert!(matches!(result, Err(EncryptionError::Decryption)));
+    }
+
+    #[test]
+    fn records_from_before_associated_data_still_decrypt() {
+        let (ciphertext, nonce) = encrypt(&"Old task".to_string(), &KEY).unwrap();
+        assert!(!ciphertext.starts_with(RECORD_PAYLOAD_V1_PREFIX));
+
+        let opened: String = decrypt_record(
+            &ciphertext,
+            &nonce,
+            &identity(RecordType::Task, UID, LAST_UPDATED),
+            &KEY,
+        )
+        .unwrap();
+        assert_eq!(opened, "Old task");
+    }
+}
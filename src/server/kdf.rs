@@ -0,0 +1,117 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{
+    engine::general_purpose::{STANDARD as BASE64, URL_SAFE_NO_PAD},
+    Engine,
+};
+use rand::{thread_rng, RngCore};
+use serde::{Deserialize, Serialize};
+
+use super::encryption::EncryptionError;
+
+/// KDF version used when creating a new passphrase account
+pub const CURRENT_KDF_VERSION: u32 = 1;
+const SALT_LENGTH: usize = 16;
+
+/// Salt and parameter version the server stores for a passphrase account
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct KdfInfo {
+    pub salt: String,
+    pub version: u32,
+}
+
+impl KdfInfo {
+    pub fn generate() -> Self {
+        let mut salt = [0u8; SALT_LENGTH];
+        thread_rng().fill_bytes(&mut salt);
+        KdfInfo {
+            salt: BASE64.encode(salt),
+            version: CURRENT_KDF_VERSION,
+        }
+    }
+}
+
+/// Argon2id cost for each KDF version. Never change an existing version, add a new one.
+fn params_for_version(version: u32) -> Option<Params> {
+    match version {
+        // 64 MiB, 3 passes, 1 lane
+        1 => Params::new(65536, 3, 1, Some(32)).ok(),
+        _ => None,
+    }
+}
+
+/// Whether the key is a raw 32-byte URL-safe base64 sync key rather than a passphrase
+pub fn is_raw_key(key: &str) -> bool {
+    URL_SAFE_NO_PAD
+        .decode(key)
+        .is_ok_and(|bytes| bytes.len() == 32)
+}
+
+/// Derive a sync key from a passphrase, encoded the same way as a raw key
+pub fn derive_key(passphrase: &str, kdf: &KdfInfo) -> Result<String, EncryptionError> {
+    let params = params_for_version(kdf.version).ok_or(EncryptionError::KeyDerivation)?;
+    let salt = BASE64
+        .decode(&kdf.salt)
+        .map_err(|_| EncryptionError::KeyDerivation)?;
+
+    let mut key = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), &salt, &mut key)
+        .map_err(|_| EncryptionError::KeyDerivation)?;
+
+    Ok(URL_SAFE_NO_PAD.encode(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PASSPHRASE: &str = "correct horse battery staple";
+
+    fn stored_kdf(version: u32) -> KdfInfo {
+        KdfInfo {
+            salt: BASE64.encode(b"furtherance-salt"),
+            version,
+        }
+    }
+
+    #[test]
+    fn version_one_derives_the_known_key() {
+        // Argon2id, 64 MiB, 3 passes, 1 lane, computed with a separate implementation
+        let key = derive_key(PASSPHRASE, &stored_kdf(1)).unwrap();
+        assert_eq!(key, "fbEMf-QCu1J1Tipn6HQQi_Kxjn0Z07GafK8fPrPbSL8");
+        assert!(is_raw_key(&key));
+    }
+
+    #[test]
+    fn accounts_derive_with_their_stored_version() {
+        let kdf = KdfInfo::generate();
+        assert_eq!(kdf.version, CURRENT_KDF_VERSION);
+
+        // Versions this build doesn't know are refused rather than derived with other costs
+        let unknown = stored_kdf(CURRENT_KDF_VERSION + 1);
+        assert!(matches!(
+            derive_key(PASSPHRASE, &unknown),
+            Err(EncryptionError::KeyDerivation)
+        ));
+        assert!(matches!(
+            derive_key(PASSPHRASE, &stored_kdf(0)),
+            Err(EncryptionError::KeyDerivation)
+        ));
+    }
+}
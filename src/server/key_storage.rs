@@ -0,0 +1,431 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::sync::Mutex;
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use log::error;
+use rand::{thread_rng, RngCore};
+
+use crate::{database, models::fur_user::FurUser};
+
+use super::encryption::{self, generate_device_id, EncryptionError};
+
+/// Argon2id cost for turning a PIN into a wrapping key. PINs are short, so keep this high.
+const PIN_MEMORY_KIB: u32 = 65536;
+const PIN_ITERATIONS: u32 = 3;
+
+/// Entry holding the random wrapping key in the OS keystore
+#[cfg(any(
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "linux",
+    target_os = "windows"
+))]
+const KEYSTORE_SERVICE: &str = "io.unobserved.furtherance";
+#[cfg(any(
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "linux",
+    target_os = "windows"
+))]
+const KEYSTORE_ACCOUNT: &str = "sync-key-wrapping-key";
+
+/// PIN-derived wrapping key, kept in memory once unlocked for this session
+static UNLOCKED_PIN_KEY: Mutex<Option<[u8; 32]>> = Mutex::new(None);
+
+/// Where the key that wraps the stored sync key comes from
+#[derive(Clone, Debug, PartialEq)]
+pub enum KeyStorageKind {
+    /// Derived from the device_id file. Used where nothing stronger is available.
+    DeviceId,
+    /// A random key kept in the OS keystore
+    Keychain,
+    /// Derived from a PIN entered at unlock
+    Pin,
+}
+
+impl KeyStorageKind {
+    pub fn to_sqlite(&self) -> &str {
+        match self {
+            KeyStorageKind::DeviceId => "device",
+            KeyStorageKind::Keychain => "keychain",
+            KeyStorageKind::Pin => "pin",
+        }
+    }
+
+    pub fn from_sqlite(value: &str) -> Self {
+        match value {
+            "keychain" => KeyStorageKind::Keychain,
+            "pin" => KeyStorageKind::Pin,
+            _ => KeyStorageKind::DeviceId,
+        }
+    }
+}
+
+pub trait KeyStorage {
+    /// The 32-byte key that wraps the sync key in the user table
+    fn wrapping_key(&self) -> Result<[u8; 32], EncryptionError>;
+}
+
+struct DeviceIdStorage;
+
+impl KeyStorage for DeviceIdStorage {
+    fn wrapping_key(&self) -> Result<[u8; 32], EncryptionError> {
+        let device_id = generate_device_id()?;
+
+        let mut key = [0u8; 32];
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(device_id.as_bytes());
+        key.copy_from_slice(&hasher.finalize().as_bytes()[..32]);
+
+        Ok(key)
+    }
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+struct KeychainStorage;
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+impl KeyStorage for KeychainStorage {
+    fn wrapping_key(&self) -> Result<[u8; 32], EncryptionError> {
+        use security_framework::passwords::{get_generic_password, set_generic_password};
+
+        const ERR_SEC_ITEM_NOT_FOUND: i32 = -25300;
+
+        match get_generic_password(KEYSTORE_SERVICE, KEYSTORE_ACCOUNT) {
+            Ok(bytes) => bytes.try_into().map_err(|_| EncryptionError::KeyStorage),
+            // Only create a key when there is none, never replace one that can't be read
+            Err(e) if e.code() == ERR_SEC_ITEM_NOT_FOUND => {
+                let key = random_key();
+                set_generic_password(KEYSTORE_SERVICE, KEYSTORE_ACCOUNT, &key)
+                    .map_err(|_| EncryptionError::KeyStorage)?;
+                Ok(key)
+            }
+            Err(_) => Err(EncryptionError::KeyStorage),
+        }
+    }
+}
+
+/// Windows Credential Manager, or the Secret Service (GNOME Keyring, KWallet) on Linux
+#[cfg(any(target_os = "linux", target_os = "windows"))]
+struct KeyringStorage;
+
+#[cfg(any(target_os = "linux", target_os = "windows"))]
+impl KeyStorage for KeyringStorage {
+    fn wrapping_key(&self) -> Result<[u8; 32], EncryptionError> {
+        let entry = keyring::Entry::new(KEYSTORE_SERVICE, KEYSTORE_ACCOUNT)
+            .map_err(|_| EncryptionError::KeyStorage)?;
+
+        match entry.get_secret() {
+            Ok(bytes) => bytes.try_into().map_err(|_| EncryptionError::KeyStorage),
+            // Only create a key when there is none, never replace one that can't be read
+            Err(keyring::Error::NoEntry) => {
+                let key = random_key();
+                entry
+                    .set_secret(&key)
+                    .map_err(|_| EncryptionError::KeyStorage)?;
+                Ok(key)
+            }
+            Err(e) => {
+                error!("Error reading the OS keystore: {}", e);
+                Err(EncryptionError::KeyStorage)
+            }
+        }
+    }
+}
+
+/// Stands in for the keystore where this platform has none, so a key wrapped
+/// elsewhere fails to unwrap instead of being read with the wrong key
+#[cfg(not(any(
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "linux",
+    target_os = "windows"
+)))]
+struct UnavailableStorage;
+
+#[cfg(not(any(
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "linux",
+    target_os = "windows"
+)))]
+impl KeyStorage for UnavailableStorage {
+    fn wrapping_key(&self) -> Result<[u8; 32], EncryptionError> {
+        Err(EncryptionError::KeyStorage)
+    }
+}
+
+struct PinStorage;
+
+impl KeyStorage for PinStorage {
+    fn wrapping_key(&self) -> Result<[u8; 32], EncryptionError> {
+        UNLOCKED_PIN_KEY
+            .lock()
+            .map_err(|_| EncryptionError::KeyStorage)?
+            .ok_or(EncryptionError::Locked)
+    }
+}
+
+pub fn storage_for(kind: &KeyStorageKind) -> Box<dyn KeyStorage> {
+    match kind {
+        KeyStorageKind::DeviceId => Box::new(DeviceIdStorage),
+        #[cfg(any(target_os = "macos", target_os = "ios"))]
+        KeyStorageKind::Keychain => Box::new(KeychainStorage),
+        #[cfg(any(target_os = "linux", target_os = "windows"))]
+        KeyStorageKind::Keychain => Box::new(KeyringStorage),
+        #[cfg(not(any(
+            target_os = "macos",
+            target_os = "ios",
+            target_os = "linux",
+            target_os = "windows"
+        )))]
+        KeyStorageKind::Keychain => Box::new(UnavailableStorage),
+        KeyStorageKind::Pin => Box::new(PinStorage),
+    }
+}
+
+/// The strongest storage that works without user interaction on this platform.
+/// A keystore can be missing or locked (no Secret Service on a Linux desktop, a sandboxed or
+/// headless session elsewhere), so it's only used when it gives back the key it stores.
+pub fn default_kind() -> KeyStorageKind {
+    if keystore_usable() {
+        KeyStorageKind::Keychain
+    } else {
+        KeyStorageKind::DeviceId
+    }
+}
+
+/// Whether the OS keystore round-trips its wrapping key: the first read creates the key if
+/// there is none, and the second has to read the same key back
+fn keystore_usable() -> bool {
+    let storage = storage_for(&KeyStorageKind::Keychain);
+    match (storage.wrapping_key(), storage.wrapping_key()) {
+        (Ok(stored), Ok(read_back)) => stored == read_back,
+        _ => false,
+    }
+}
+
+pub fn pin_available() -> bool {
+    cfg!(target_os = "linux")
+}
+
+pub fn is_unlocked() -> bool {
+    UNLOCKED_PIN_KEY
+        .lock()
+        .map(|key| key.is_some())
+        .unwrap_or(false)
+}
+
+#[cfg(any(
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "linux",
+    target_os = "windows"
+))]
+fn random_key() -> [u8; 32] {
+    let mut key = [0u8; 32];
+    thread_rng().fill_bytes(&mut key);
+    key
+}
+
+pub fn generate_pin_salt() -> String {
+    let mut salt = [0u8; 16];
+    thread_rng().fill_bytes(&mut salt);
+    BASE64.encode(salt)
+}
+
+/// Derive the PIN wrapping key and keep it for this session
+pub fn unlock_with_pin(pin: &str, salt: &str) -> Result<(), EncryptionError> {
+    let key = derive_pin_key(pin, salt)?;
+    keep_unlocked(key)
+}
+
+fn derive_pin_key(pin: &str, salt: &str) -> Result<[u8; 32], EncryptionError> {
+    let salt = BASE64
+        .decode(salt)
+        .map_err(|_| EncryptionError::KeyDerivation)?;
+    let params = Params::new(PIN_MEMORY_KIB, PIN_ITERATIONS, 1, Some(32))
+        .map_err(|_| EncryptionError::KeyDerivation)?;
+
+    let mut key = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(pin.as_bytes(), &salt, &mut key)
+        .map_err(|_| EncryptionError::KeyDerivation)?;
+    Ok(key)
+}
+
+fn keep_unlocked(key: [u8; 32]) -> Result<(), EncryptionError> {
+    *UNLOCKED_PIN_KEY
+        .lock()
+        .map_err(|_| EncryptionError::KeyStorage)? = Some(key);
+    Ok(())
+}
+
+pub fn lock() {
+    if let Ok(mut key) = UNLOCKED_PIN_KEY.lock() {
+        *key = None;
+    }
+}
+
+/// Move keys stored with the legacy device_id wrapping to this platform's default storage
+pub fn migrate_stored_key() {
+    let user = match database::sync::retrieve_credentials() {
+        Ok(Some(user)) => user,
+        Ok(None) => return,
+        Err(e) => {
+            error!("Error retrieving credentials for key migration: {}", e);
+            return;
+        }
+    };
+
+    let target = default_kind();
+    if user.key_storage != KeyStorageKind::DeviceId || target == KeyStorageKind::DeviceId {
+        return;
+    }
+    if let Err(e) = change_key_storage(&user, &target, None) {
+        error!("Failed to migrate stored sync key: {:?}", e);
+    }
+}
+
+/// Re-wrap the stored sync key (and any in-progress rotation key) with another storage
+pub fn change_key_storage(
+    user: &FurUser,
+    target: &KeyStorageKind,
+    salt: Option<&str>,
+) -> Result<(), EncryptionError> {
+    let wrapping_key = storage_for(target).wrapping_key()?;
+    rewrap_stored_keys(user, &wrapping_key, target, salt)
+}
+
+/// Protect the stored sync key with a PIN, or with a new one when a PIN already protects it.
+/// The stored key is unwrapped with the current storage's key, so the new PIN key only
+/// replaces the unlocked one after the key has been re-wrapped.
+pub fn set_pin(user: &FurUser, pin: &str, salt: &str) -> Result<(), EncryptionError> {
+    let pin_key = derive_pin_key(pin, salt)?;
+    rewrap_stored_keys(user, &pin_key, &KeyStorageKind::Pin, Some(salt))?;
+    keep_unlocked(pin_key)
+}
+
+fn rewrap_stored_keys(
+    user: &FurUser,
+    wrapping_key: &[u8; 32],
+    target: &KeyStorageKind,
+    salt: Option<&str>,
+) -> Result<(), EncryptionError> {
+    let rotation = database::sync::retrieve_key_rotation().unwrap_or_default();
+
+    let (encrypted_key, key_nonce) = encryption::rewrap_encryption_key(
+        &user.encrypted_key,
+        &user.key_nonce,
+        &user.key_storage,
+        wrapping_key,
+    )?;
+    let rotation = match rotation {
+        Some(mut rotation) => {
+            let (encrypted_key, key_nonce) = encryption::rewrap_encryption_key(
+                &rotation.encrypted_key,
+                &rotation.key_nonce,
+                &user.key_storage,
+                wrapping_key,
+            )?;
+            rotation.encrypted_key = encrypted_key;
+            rotation.key_nonce = key_nonce;
+            Some(rotation)
+        }
+        None => None,
+    };
+
+    if let Err(e) = database::sync::update_key_storage(
+        &user.email,
+        &encrypted_key,
+        &key_nonce,
+        target,
+        salt,
+        rotation.as_ref(),
+    ) {
+        error!("Error storing re-wrapped key: {}", e);
+        return Err(EncryptionError::KeyStorage);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+
+    use super::*;
+    use crate::helpers::test_data;
+
+    const SYNC_KEY: [u8; 32] = [9; 32];
+
+    fn store_user() -> FurUser {
+        let (encrypted_key, key_nonce) = encryption::encrypt_encryption_key(
+            &URL_SAFE_NO_PAD.encode(SYNC_KEY),
+            &KeyStorageKind::DeviceId,
+        )
+        .unwrap();
+        let user = FurUser {
+            email: "test@example.com".to_string(),
+            encrypted_key,
+            key_nonce,
+            access_token: String::new(),
+            refresh_token: String::new(),
+            server: String::new(),
+            key_generation: 0,
+            key_storage: KeyStorageKind::DeviceId,
+            key_storage_salt: None,
+        };
+        database::sync::store_credentials(&user).unwrap();
+        user
+    }
+
+    fn stored_user() -> FurUser {
+        database::sync::retrieve_credentials().unwrap().unwrap()
+    }
+
+    fn stored_sync_key() -> Result<[u8; 32], EncryptionError> {
+        let user = stored_user();
+        encryption::decrypt_encryption_key(&user.encrypted_key, &user.key_nonce, &user.key_storage)
+    }
+
+    #[test]
+    fn pin_can_be_changed_while_a_pin_protects_the_key() {
+        let _data = test_data::fresh_database();
+        store_user();
+
+        let first_salt = generate_pin_salt();
+        set_pin(&stored_user(), "1234", &first_salt).unwrap();
+        assert_eq!(stored_user().key_storage, KeyStorageKind::Pin);
+        assert_eq!(stored_sync_key().unwrap(), SYNC_KEY);
+
+        let second_salt = generate_pin_salt();
+        set_pin(&stored_user(), "5678", &second_salt).unwrap();
+        assert_eq!(stored_user().key_storage_salt, Some(second_salt.clone()));
+        assert_eq!(stored_sync_key().unwrap(), SYNC_KEY);
+
+        lock();
+        assert!(matches!(stored_sync_key(), Err(EncryptionError::Locked)));
+        unlock_with_pin("1234", &second_salt).unwrap();
+        assert!(stored_sync_key().is_err());
+        unlock_with_pin("5678", &second_salt).unwrap();
+        assert_eq!(stored_sync_key().unwrap(), SYNC_KEY);
+        lock();
+    }
+}
@@ -0,0 +1,989 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{collections::HashSet, sync::Arc};
+
+use chrono::Utc;
+use log::{error, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    database,
+    models::{
+        fur_settings::FurSettings,
+        fur_shortcut::{EncryptedShortcut, FurShortcut},
+        fur_sync_log::FurSyncLogEntry,
+        fur_sync_progress::{FurSyncProgress, SyncWatermark},
+        fur_task::{EncryptedTask, FurTask},
+        fur_todo::{EncryptedTodo, FurTodo},
+        fur_user::FurUser,
+    },
+};
+
+use super::{
+    api::{post_authorized, response_error, ApiError},
+    encryption::{self, RecordIdentity, RecordType},
+};
+
+const SECONDS_PER_DAY: i64 = 86400;
+/// Most records sent or requested in a single sync request
+pub const SYNC_PAGE_SIZE: usize = 250;
+/// Records of each type tried when checking a key at login
+const KEY_CHECK_SAMPLE: usize = 5;
+#[derive(Serialize, Deserialize)]
+pub struct SyncRequest {
+    last_sync: i64,
+    device_id: String,
+    /// Friendly name and OS shown in the devices list
+    #[serde(default)]
+    device_name: String,
+    #[serde(default)]
+    platform: String,
+    tasks: Vec<EncryptedTask>,
+    shortcuts: Vec<EncryptedShortcut>,
+    todos: Vec<EncryptedTodo>,
+    /// Continuation token from the previous response, if it had more changes to send
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cursor: Option<String>,
+    #[serde(default)]
+    page_size: usize,
+    #[serde(default)]
+    key_generation: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SyncResponse {
+    pub server_timestamp: i64,
+    pub tasks: Vec<EncryptedTask>,
+    pub shortcuts: Vec<EncryptedShortcut>,
+    pub todos: Vec<EncryptedTodo>,
+    pub orphaned_tasks: Vec<String>,
+    pub orphaned_shortcuts: Vec<String>,
+    pub orphaned_todos: Vec<String>,
+    /// Oldest last sync among all of the account's devices. Only servers that track
+    /// per-device progress send it (so far just the mock server); without it, signed-in
+    /// devices never purge tombstones.
+    #[serde(default)]
+    pub devices_synced_through: Option<i64>,
+    /// Set when the server has more changes than fit in one page
+    #[serde(default)]
+    pub next_cursor: Option<String>,
+}
+
+/// Totals of a sync that may have been split into several pages
+pub struct SyncSummary {
+    pub server_timestamp: i64,
+    pub started_at: i64,
+    pub devices_synced_through: Option<i64>,
+}
+
+/// Local records that fit in the next upload page
+pub struct UploadPage {
+    pub tasks: Vec<FurTask>,
+    pub shortcuts: Vec<FurShortcut>,
+    pub todos: Vec<FurTodo>,
+}
+
+impl UploadPage {
+    pub fn len(&self) -> usize {
+        self.tasks.len() + self.shortcuts.len() + self.todos.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Uids the server reported it does not have
+#[derive(Default)]
+struct OrphanedUids {
+    tasks: Vec<String>,
+    shortcuts: Vec<String>,
+    todos: Vec<String>,
+    /// Every uid reported during this sync, so a server that keeps reporting
+    /// the same ones gets them once instead of keeping the sync going forever
+    reported: HashSet<String>,
+}
+
+impl OrphanedUids {
+    fn is_empty(&self) -> bool {
+        self.tasks.is_empty() && self.shortcuts.is_empty() && self.todos.is_empty()
+    }
+
+    fn extend(&mut self, response: &SyncResponse) {
+        let reported = &mut self.reported;
+        let mut newly_reported = |record_type: &str, uids: &[String]| {
+            uids.iter()
+                .filter(|uid| reported.insert(format!("{}:{}", record_type, uid)))
+                .cloned()
+                .collect::<Vec<String>>()
+        };
+        self.tasks
+            .extend(newly_reported("task", &response.orphaned_tasks));
+        self.shortcuts
+            .extend(newly_reported("shortcut", &response.orphaned_shortcuts));
+        self.todos
+            .extend(newly_reported("todo", &response.orphaned_todos));
+    }
+
+    /// Remove and return up to `limit` uids
+    fn take_page(&mut self, limit: usize) -> OrphanedUids {
+        let tasks = drain_up_to(&mut self.tasks, limit);
+        let shortcuts = drain_up_to(&mut self.shortcuts, limit - tasks.len());
+        let todos = drain_up_to(&mut self.todos, limit - tasks.len() - shortcuts.len());
+        OrphanedUids {
+            tasks,
+            shortcuts,
+            todos,
+            reported: HashSet::new(),
+        }
+    }
+}
+
+fn drain_up_to(uids: &mut Vec<String>, limit: usize) -> Vec<String> {
+    let count = uids.len().min(limit);
+    uids.drain(..count).collect()
+}
+
+/// Upload local changes and download server changes one page at a time.
+/// Progress is stored after every page so an interrupted sync can resume.
+pub async fn sync_in_pages(
+    user: &mut FurUser,
+    mut progress: FurSyncProgress,
+    encryption_key: &[u8; 32],
+    device_name: &str,
+    log: &mut FurSyncLogEntry,
+) -> Result<SyncSummary, ApiError> {
+    let mut devices_synced_through = None;
+    let mut orphans = OrphanedUids::default();
+    // Records downloaded during this sync don't need to be sent back
+    let mut downloaded_uids: HashSet<String> = HashSet::new();
+
+    loop {
+        let mut page = retrieve_upload_page(
+            progress.upload_since(),
+            progress.full_sync,
+            &progress.tasks_uploaded_through,
+            &progress.shortcuts_uploaded_through,
+            &progress.todos_uploaded_through,
+        );
+        let page_is_full = page.len() >= SYNC_PAGE_SIZE;
+
+        let tasks_through = page.tasks.last().map(|task| SyncWatermark {
+            last_updated: task.last_updated,
+            uid: task.uid.clone(),
+        });
+        let shortcuts_through = page.shortcuts.last().map(|shortcut| SyncWatermark {
+            last_updated: shortcut.last_updated,
+            uid: shortcut.uid.clone(),
+        });
+        let todos_through = page.todos.last().map(|todo| SyncWatermark {
+            last_updated: todo.last_updated,
+            uid: todo.uid.clone(),
+        });
+
+        page.tasks
+            .retain(|task| !downloaded_uids.contains(&task.uid));
+        page.shortcuts
+            .retain(|shortcut| !downloaded_uids.contains(&shortcut.uid));
+        page.todos
+            .retain(|todo| !downloaded_uids.contains(&todo.uid));
+        log.uploaded.tasks += page.tasks.len() as i64;
+        log.uploaded.shortcuts += page.shortcuts.len() as i64;
+        log.uploaded.todos += page.todos.len() as i64;
+
+        let response = sync_with_server(
+            user,
+            device_name,
+            progress.download_since,
+            progress.download_cursor.clone(),
+            encrypt_tasks(page.tasks, encryption_key),
+            encrypt_shortcuts(page.shortcuts, encryption_key),
+            encrypt_todos(page.todos, encryption_key),
+        )
+        .await?;
+
+        apply_server_changes(&response, encryption_key, &mut downloaded_uids, log);
+        orphans.extend(&response);
+        if response.devices_synced_through.is_some() {
+            devices_synced_through = response.devices_synced_through;
+        }
+
+        if let Some(watermark) = tasks_through {
+            progress.tasks_uploaded_through = watermark;
+        }
+        if let Some(watermark) = shortcuts_through {
+            progress.shortcuts_uploaded_through = watermark;
+        }
+        if let Some(watermark) = todos_through {
+            progress.todos_uploaded_through = watermark;
+        }
+
+        // Changes made on the server after the first page are picked up by the next download
+        let download_started_at = *progress
+            .download_started_at
+            .get_or_insert(response.server_timestamp);
+        progress.download_cursor = response.next_cursor;
+        if progress.download_cursor.is_none() {
+            progress.download_since = download_started_at;
+            progress.download_started_at = None;
+        }
+
+        if let Err(e) = database::sync::store_sync_progress(&progress) {
+            error!("Error storing sync progress: {}", e);
+        }
+
+        if !page_is_full && progress.download_cursor.is_none() {
+            break;
+        }
+    }
+
+    // Re-send anything the server reported missing
+    while !orphans.is_empty() {
+        let uids = orphans.take_page(SYNC_PAGE_SIZE);
+
+        let orphaned_tasks = if !uids.tasks.is_empty() {
+            database::tasks::retrieve_orphaned_tasks(uids.tasks).unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+
+        let orphaned_shortcuts = if !uids.shortcuts.is_empty() {
+            database::shortcuts::retrieve_orphaned_shortcuts(uids.shortcuts).unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+
+        let orphaned_todos = if !uids.todos.is_empty() {
+            database::todos::retrieve_orphaned_todos(uids.todos).unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+
+        if orphaned_tasks.is_empty() && orphaned_shortcuts.is_empty() && orphaned_todos.is_empty() {
+            continue;
+        }
+
+        log.orphans_resent +=
+            (orphaned_tasks.len() + orphaned_shortcuts.len() + orphaned_todos.len()) as i64;
+
+        let response = sync_with_server(
+            user,
+            device_name,
+            progress.download_since,
+            None,
+            encrypt_tasks(orphaned_tasks, encryption_key),
+            encrypt_shortcuts(orphaned_shortcuts, encryption_key),
+            encrypt_todos(orphaned_todos, encryption_key),
+        )
+        .await?;
+
+        apply_server_changes(&response, encryption_key, &mut downloaded_uids, log);
+        orphans.extend(&response);
+    }
+
+    if let Err(e) = database::sync::delete_sync_progress() {
+        error!("Error clearing sync progress: {}", e);
+    }
+
+    Ok(SyncSummary {
+        server_timestamp: progress.download_since,
+        started_at: progress.started_at,
+        devices_synced_through,
+    })
+}
+
+/// Fill the next page with tasks, then shortcuts, then todos after each watermark
+pub fn retrieve_upload_page(
+    since: i64,
+    skip_acked_tombstones: bool,
+    tasks_through: &SyncWatermark,
+    shortcuts_through: &SyncWatermark,
+    todos_through: &SyncWatermark,
+) -> UploadPage {
+    let tasks = database::tasks::retrieve_tasks_for_sync(
+        since,
+        skip_acked_tombstones,
+        tasks_through,
+        SYNC_PAGE_SIZE,
+    )
+    .unwrap_or_default();
+
+    let shortcuts = database::shortcuts::retrieve_shortcuts_for_sync(
+        since,
+        skip_acked_tombstones,
+        shortcuts_through,
+        SYNC_PAGE_SIZE - tasks.len(),
+    )
+    .unwrap_or_default();
+
+    let todos = database::todos::retrieve_todos_for_sync(
+        since,
+        skip_acked_tombstones,
+        todos_through,
+        SYNC_PAGE_SIZE - tasks.len() - shortcuts.len(),
+    )
+    .unwrap_or_default();
+
+    UploadPage {
+        tasks,
+        shortcuts,
+        todos,
+    }
+}
+
+pub fn encrypt_tasks(tasks: Vec<FurTask>, encryption_key: &[u8; 32]) -> Vec<EncryptedTask> {
+    tasks
+        .into_iter()
+        .filter_map(|task| {
+            match encryption::encrypt_record(
+                &task,
+                &RecordIdentity {
+                    record_type: RecordType::Task,
+                    uid: &task.uid,
+                    last_updated: task.last_updated,
+                },
+                encryption_key,
+            ) {
+                Ok((encrypted_data, nonce)) => Some(EncryptedTask {
+                    encrypted_data,
+                    nonce,
+                    uid: task.uid,
+                    last_updated: task.last_updated,
+                }),
+                Err(e) => {
+                    error!("Failed to encrypt task: {:?}", e);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+pub fn encrypt_shortcuts(
+    shortcuts: Vec<FurShortcut>,
+    encryption_key: &[u8; 32],
+) -> Vec<EncryptedShortcut> {
+    shortcuts
+        .into_iter()
+        .filter_map(|shortcut| {
+            match encryption::encrypt_record(
+                &shortcut,
+                &RecordIdentity {
+                    record_type: RecordType::Shortcut,
+                    uid: &shortcut.uid,
+                    last_updated: shortcut.last_updated,
+                },
+                encryption_key,
+            ) {
+                Ok((encrypted_data, nonce)) => Some(EncryptedShortcut {
+                    encrypted_data,
+                    nonce,
+                    uid: shortcut.uid,
+                    last_updated: shortcut.last_updated,
+                }),
+                Err(e) => {
+                    error!("Failed to encrypt shortcut: {:?}", e);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+pub fn encrypt_todos(todos: Vec<FurTodo>, encryption_key: &[u8; 32]) -> Vec<EncryptedTodo> {
+    todos
+        .into_iter()
+        .filter_map(|todo| {
+            match encryption::encrypt_record(
+                &todo,
+                &RecordIdentity {
+                    record_type: RecordType::Todo,
+                    uid: &todo.uid,
+                    last_updated: todo.last_updated,
+                },
+                encryption_key,
+            ) {
+                Ok((encrypted_data, nonce)) => Some(EncryptedTodo {
+                    encrypted_data,
+                    nonce,
+                    uid: todo.uid,
+                    last_updated: todo.last_updated,
+                }),
+                Err(e) => {
+                    error!("Failed to encrypt todo: {:?}", e);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+pub async fn sync_with_server(
+    user: &mut FurUser,
+    device_name: &str,
+    last_sync: i64,
+    cursor: Option<String>,
+    tasks: Vec<EncryptedTask>,
+    shortcuts: Vec<EncryptedShortcut>,
+    todos: Vec<EncryptedTodo>,
+) -> Result<SyncResponse, ApiError> {
+    let device_id = encryption::generate_device_id().map_err(|e| {
+        error!("Failed to create device id for logout: {:?}", e);
+        ApiError::Device("Failed to generate device ID".to_string())
+    })?;
+
+    let sync_request = SyncRequest {
+        last_sync,
+        device_id,
+        device_name: device_name.to_string(),
+        platform: std::env::consts::OS.to_string(),
+        tasks,
+        shortcuts,
+        todos,
+        cursor,
+        page_size: SYNC_PAGE_SIZE,
+        key_generation: user.key_generation,
+    };
+
+    let response = post_authorized(user, "/api/sync", &sync_request).await?;
+
+    if response.status().is_success() {
+        response
+            .json()
+            .await
+            .map_err(|e| ApiError::Network(Arc::new(e)))
+    } else {
+        Err(response_error(response, "Sync failed").await)
+    }
+}
+
+/// Whether `key` opens the account's records, judged by the first few the server sends.
+/// None if the account has no records to check against.
+pub async fn key_opens_records(
+    user: &mut FurUser,
+    device_name: &str,
+    key: &[u8; 32],
+) -> Result<Option<bool>, ApiError> {
+    let response = sync_with_server(
+        user,
+        device_name,
+        0,
+        None,
+        Vec::new(),
+        Vec::new(),
+        Vec::new(),
+    )
+    .await?;
+
+    Ok(key_opens_any(&response, key))
+}
+
+/// True once any sampled record decrypts, so one corrupt or foreign record
+/// doesn't get the right key rejected
+fn key_opens_any(response: &SyncResponse, key: &[u8; 32]) -> Option<bool> {
+    let tasks = response.tasks.iter().take(KEY_CHECK_SAMPLE).map(|task| {
+        encryption::decrypt_record::<FurTask>(
+            &task.encrypted_data,
+            &task.nonce,
+            &RecordIdentity {
+                record_type: RecordType::Task,
+                uid: &task.uid,
+                last_updated: task.last_updated,
+            },
+            key,
+        )
+        .is_ok()
+    });
+    let shortcuts = response
+        .shortcuts
+        .iter()
+        .take(KEY_CHECK_SAMPLE)
+        .map(|shortcut| {
+            encryption::decrypt_record::<FurShortcut>(
+                &shortcut.encrypted_data,
+                &shortcut.nonce,
+                &RecordIdentity {
+                    record_type: RecordType::Shortcut,
+                    uid: &shortcut.uid,
+                    last_updated: shortcut.last_updated,
+                },
+                key,
+            )
+            .is_ok()
+        });
+    let todos = response.todos.iter().take(KEY_CHECK_SAMPLE).map(|todo| {
+        encryption::decrypt_record::<FurTodo>(
+            &todo.encrypted_data,
+            &todo.nonce,
+            &RecordIdentity {
+                record_type: RecordType::Todo,
+                uid: &todo.uid,
+                last_updated: todo.last_updated,
+            },
+            key,
+        )
+        .is_ok()
+    });
+
+    let mut checked = false;
+    for opened in tasks.chain(shortcuts).chain(todos) {
+        if opened {
+            return Some(true);
+        }
+        checked = true;
+    }
+    checked.then_some(false)
+}
+
+/// Save server changes that are newer than the local copies, counting them in the sync log
+fn apply_server_changes(
+    response: &SyncResponse,
+    encryption_key: &[u8; 32],
+    downloaded_uids: &mut HashSet<String>,
+    log: &mut FurSyncLogEntry,
+) {
+    // Decrypt and process server tasks
+    for encrypted_task in &response.tasks {
+        match encryption::decrypt_record::<FurTask>(
+            &encrypted_task.encrypted_data,
+            &encrypted_task.nonce,
+            &RecordIdentity {
+                record_type: RecordType::Task,
+                uid: &encrypted_task.uid,
+                last_updated: encrypted_task.last_updated,
+            },
+            encryption_key,
+        ) {
+            // Records from before associated data was added aren't bound to their uid
+            Ok(server_task)
+                if server_task.uid != encrypted_task.uid
+                    || server_task.last_updated != encrypted_task.last_updated =>
+            {
+                warn!(
+                    "Task does not match its sync record: {}",
+                    encrypted_task.uid
+                );
+                log.decrypt_failures
+                    .push(format!("task:{}", encrypted_task.uid));
+            }
+            Ok(server_task) => {
+                match database::tasks::retrieve_task_by_id(&server_task.uid) {
+                    Ok(Some(client_task)) => {
+                        // Task exists - update it if it changed
+                        if server_task.last_updated > client_task.last_updated {
+                            if let Err(e) = database::tasks::update_task(&server_task) {
+                                error!("Error updating task from server: {}", e);
+                            } else {
+                                downloaded_uids.insert(server_task.uid.clone());
+                                log.downloaded.tasks += 1;
+                            }
+                        }
+                    }
+                    Ok(None) => {
+                        // Task does not exist - insert it
+                        if let Err(e) = database::tasks::insert_task(&server_task) {
+                            error!("Error writing new task from server: {}", e);
+                        } else {
+                            downloaded_uids.insert(server_task.uid.clone());
+                            log.downloaded.tasks += 1;
+                        }
+                    }
+                    Err(e) => {
+                        error!("Error checking for existing task from server: {}", e)
+                    }
+                }
+            }
+            Err(e) => {
+                warn!("Failed to decrypt task: {:?}", e);
+                log.decrypt_failures
+                    .push(format!("task:{}", encrypted_task.uid));
+            }
+        }
+    }
+
+    // Decrypt and process server shortcuts
+    for encrypted_shortcut in &response.shortcuts {
+        match encryption::decrypt_record::<FurShortcut>(
+            &encrypted_shortcut.encrypted_data,
+            &encrypted_shortcut.nonce,
+            &RecordIdentity {
+                record_type: RecordType::Shortcut,
+                uid: &encrypted_shortcut.uid,
+                last_updated: encrypted_shortcut.last_updated,
+            },
+            encryption_key,
+        ) {
+            // Records from before associated data was added aren't bound to their uid
+            Ok(server_shortcut)
+                if server_shortcut.uid != encrypted_shortcut.uid
+                    || server_shortcut.last_updated != encrypted_shortcut.last_updated =>
+            {
+                warn!(
+                    "Shortcut does not match its sync record: {}",
+                    encrypted_shortcut.uid
+                );
+                log.decrypt_failures
+                    .push(format!("shortcut:{}", encrypted_shortcut.uid));
+            }
+            Ok(server_shortcut) => {
+                match database::shortcuts::retrieve_shortcut_by_id(&server_shortcut.uid) {
+                    Ok(Some(client_shortcut)) => {
+                        // Shortcut exists - update it if it changed
+                        if server_shortcut.last_updated > client_shortcut.last_updated {
+                            if let Err(e) = database::shortcuts::update_shortcut(&server_shortcut) {
+                                error!("Error updating shortcut from server: {}", e);
+                            } else {
+                                downloaded_uids.insert(server_shortcut.uid.clone());
+                                log.downloaded.shortcuts += 1;
+                            }
+                        }
+                    }
+                    Ok(None) => {
+                        // Shortcut does not exist - insert it
+                        if let Err(e) = database::shortcuts::insert_shortcut(&server_shortcut) {
+                            error!("Error writing new shortcut from server: {}", e);
+                        } else {
+                            downloaded_uids.insert(server_shortcut.uid.clone());
+                            log.downloaded.shortcuts += 1;
+                        }
+                    }
+                    Err(e) => {
+                        error!("Error checking for existing shortcut from server: {}", e)
+                    }
+                }
+            }
+            Err(e) => {
+                warn!("Failed to decrypt shortcut: {:?}", e);
+                log.decrypt_failures
+                    .push(format!("shortcut:{}", encrypted_shortcut.uid));
+            }
+        }
+    }
+
+    // Decrypt and process server todos
+    for encrypted_todo in &response.todos {
+        match encryption::decrypt_record::<FurTodo>(
+            &encrypted_todo.encrypted_data,
+            &encrypted_todo.nonce,
+            &RecordIdentity {
+                record_type: RecordType::Todo,
+                uid: &encrypted_todo.uid,
+                last_updated: encrypted_todo.last_updated,
+            },
+            encryption_key,
+        ) {
+            // Records from before associated data was added aren't bound to their uid
+            Ok(server_todo)
+                if server_todo.uid != encrypted_todo.uid
+                    || server_todo.last_updated != encrypted_todo.last_updated =>
+            {
+                warn!(
+                    "Todo does not match its sync record: {}",
+                    encrypted_todo.uid
+                );
+                log.decrypt_failures
+                    .push(format!("todo:{}", encrypted_todo.uid));
+            }
+            Ok(server_todo) => {
+                match database::todos::retrieve_todo_by_id(&server_todo.uid) {
+                    Ok(Some(client_todo)) => {
+                        // Todo exists - update it if it changed
+                        if server_todo.last_updated > client_todo.last_updated {
+                            if let Err(e) = database::todos::update_todo(&server_todo) {
+                                error!("Error updating todo from server: {}", e);
+                            } else {
+                                downloaded_uids.insert(server_todo.uid.clone());
+                                log.downloaded.todos += 1;
+                            }
+                        }
+                    }
+                    Ok(None) => {
+                        // Todo does not exist - insert it
+                        if let Err(e) = database::todos::insert_todo(&server_todo) {
+                            error!("Error writing new todo from server: {}", e);
+                        } else {
+                            downloaded_uids.insert(server_todo.uid.clone());
+                            log.downloaded.todos += 1;
+                        }
+                    }
+                    Err(e) => {
+                        error!("Error checking for existing todo from server: {}", e)
+                    }
+                }
+            }
+            Err(e) => {
+                warn!("Failed to decrypt todo: {:?}", e);
+                log.decrypt_failures
+                    .push(format!("todo:{}", encrypted_todo.uid));
+            }
+        }
+    }
+}
+
+/// Permanently remove tombstones older than the retention period.
+/// When signed in, only tombstones the server has received and every device has synced past are removed.
+/// If the server has never reported `devices_synced_through`, signed-in devices keep every tombstone,
+/// since another device may not have seen the deletion yet.
+pub fn purge_expired_tombstones(settings: &FurSettings, signed_in: bool) {
+    let retention_cutoff =
+        Utc::now().timestamp() - settings.tombstone_retention_days * SECONDS_PER_DAY;
+
+    let purge_result = if signed_in {
+        // Without knowing how far all devices have synced, nothing is safe to remove
+        if settings.devices_synced_through <= 0 {
+            return;
+        }
+        database::maintenance::purge_tombstones(
+            retention_cutoff.min(settings.devices_synced_through),
+            true,
+        )
+    } else {
+        database::maintenance::purge_tombstones(retention_cutoff, false)
+    };
+
+    if let Err(e) = purge_result {
+        error!("Error purging deleted items: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Local;
+
+    use super::*;
+    use crate::helpers::test_data;
+
+    /// A task deleted `days_ago` that the server has already received
+    fn add_synced_tombstone(days_ago: i64) -> String {
+        let mut task = FurTask::new(
+            "Deleted".to_string(),
+            Local::now(),
+            Local::now(),
+            String::new(),
+            String::new(),
+            0.0,
+            String::new(),
+        );
+        task.is_deleted = true;
+        task.last_updated = Utc::now().timestamp() - days_ago * SECONDS_PER_DAY;
+        database::tasks::insert_task(&task).unwrap();
+        database::maintenance::acknowledge_tombstones(Utc::now().timestamp()).unwrap();
+        task.uid
+    }
+
+    fn is_stored(uid: &String) -> bool {
+        database::tasks::retrieve_task_by_id(uid).unwrap().is_some()
+    }
+
+    fn add_task_at(uid: &str, last_updated: i64) {
+        let mut task = FurTask::new(
+            uid.to_string(),
+            Local::now(),
+            Local::now(),
+            String::new(),
+            String::new(),
+            0.0,
+            String::new(),
+        );
+        task.uid = uid.to_string();
+        task.last_updated = last_updated;
+        database::tasks::insert_task(&task).unwrap();
+    }
+
+    fn task_uids(page: &UploadPage) -> Vec<&str> {
+        page.tasks.iter().map(|task| task.uid.as_str()).collect()
+    }
+
+    fn response_with_orphans(tasks: &[&str], todos: &[&str]) -> SyncResponse {
+        serde_json::from_value(serde_json::json!({
+            "server_timestamp": 100,
+            "tasks": [],
+            "shortcuts": [],
+            "todos": [],
+            "orphaned_tasks": tasks,
+            "orphaned_shortcuts": [],
+            "orphaned_todos": todos,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn a_key_is_accepted_if_any_sampled_record_opens() {
+        let key = [1; 32];
+        let task = |name: &str| {
+            FurTask::new(
+                name.to_string(),
+                Local::now(),
+                Local::now(),
+                String::new(),
+                String::new(),
+                0.0,
+                String::new(),
+            )
+        };
+        let foreign = encrypt_tasks(vec![task("Foreign"), task("Corrupt")], &[2; 32]);
+        let own = encrypt_tasks(vec![task("Own")], &key);
+        let response = |tasks: Vec<EncryptedTask>| {
+            serde_json::from_value::<SyncResponse>(serde_json::json!({
+                "server_timestamp": 100,
+                "tasks": tasks,
+                "shortcuts": [],
+                "todos": [],
+                "orphaned_tasks": [],
+                "orphaned_shortcuts": [],
+                "orphaned_todos": [],
+            }))
+            .unwrap()
+        };
+
+        let mixed = response(foreign.iter().chain(own.iter()).cloned().collect());
+        assert_eq!(key_opens_any(&mixed, &key), Some(true));
+        assert_eq!(key_opens_any(&response(foreign), &key), Some(false));
+        assert_eq!(key_opens_any(&response(Vec::new()), &key), None);
+    }
+
+    #[test]
+    fn orphans_are_resent_once_per_sync() {
+        let mut orphans = OrphanedUids::default();
+        orphans.extend(&response_with_orphans(&["a", "b", "c"], &["a", "d"]));
+
+        let page = orphans.take_page(4);
+        assert_eq!(page.tasks, ["a", "b", "c"]);
+        assert_eq!(page.todos, ["a"]);
+        let page = orphans.take_page(4);
+        assert_eq!(page.todos, ["d"]);
+        assert!(orphans.is_empty());
+
+        // The server still reports them after they were sent again
+        orphans.extend(&response_with_orphans(&["a", "b"], &["d"]));
+        assert!(orphans.is_empty());
+        orphans.extend(&response_with_orphans(&["e"], &[]));
+        assert_eq!(orphans.take_page(4).tasks, ["e"]);
+    }
+
+    #[test]
+    fn upload_pages_resume_after_their_watermarks() {
+        let _data = test_data::fresh_database();
+        add_task_at("a", 10);
+        add_task_at("c", 10);
+        add_task_at("b", 20);
+        add_task_at("old", 5);
+        let start = SyncWatermark::start();
+
+        let page = retrieve_upload_page(10, false, &start, &start, &start);
+        assert_eq!(task_uids(&page), ["a", "c", "b"]);
+
+        // Records sharing a timestamp are told apart by uid
+        let through_a = SyncWatermark {
+            last_updated: 10,
+            uid: "a".to_string(),
+        };
+        let page = retrieve_upload_page(10, false, &through_a, &start, &start);
+        assert_eq!(task_uids(&page), ["c", "b"]);
+
+        // A full sync uploads from the beginning, whatever the last sync time
+        let mut progress = FurSyncProgress::new(10, true, 30);
+        assert_eq!(progress.upload_since(), i64::MIN);
+        let page = retrieve_upload_page(
+            progress.upload_since(),
+            progress.full_sync,
+            &start,
+            &start,
+            &start,
+        );
+        assert_eq!(task_uids(&page), ["old", "a", "c", "b"]);
+
+        // An interrupted sync picks up where its stored progress left off
+        progress.tasks_uploaded_through = SyncWatermark {
+            last_updated: 10,
+            uid: "c".to_string(),
+        };
+        progress.download_cursor = Some("page-2".to_string());
+        progress.download_started_at = Some(25);
+        database::sync::store_sync_progress(&progress).unwrap();
+        let resumed = database::sync::retrieve_sync_progress().unwrap().unwrap();
+        assert_eq!(resumed, progress);
+        let page = retrieve_upload_page(
+            resumed.upload_since(),
+            resumed.full_sync,
+            &resumed.tasks_uploaded_through,
+            &resumed.shortcuts_uploaded_through,
+            &resumed.todos_uploaded_through,
+        );
+        assert_eq!(task_uids(&page), ["b"]);
+    }
+
+    #[test]
+    fn responses_without_devices_synced_through_leave_it_unknown() {
+        let response: SyncResponse = serde_json::from_value(serde_json::json!({
+            "server_timestamp": 100,
+            "tasks": [],
+            "shortcuts": [],
+            "todos": [],
+            "orphaned_tasks": [],
+            "orphaned_shortcuts": [],
+            "orphaned_todos": [],
+        }))
+        .unwrap();
+        assert_eq!(response.devices_synced_through, None);
+        assert_eq!(response.next_cursor, None);
+    }
+
+    #[test]
+    fn signed_in_devices_keep_tombstones_until_the_server_reports_every_device() {
+        let _data = test_data::fresh_database();
+        let old = add_synced_tombstone(100);
+        let settings = FurSettings {
+            tombstone_retention_days: 30,
+            ..FurSettings::default()
+        };
+
+        // A server that never sent devices_synced_through
+        purge_expired_tombstones(&settings, true);
+        assert!(is_stored(&old));
+
+        // A device last synced before the deletion still needs it
+        let lagging = FurSettings {
+            devices_synced_through: Utc::now().timestamp() - 200 * SECONDS_PER_DAY,
+            ..settings.clone()
+        };
+        purge_expired_tombstones(&lagging, true);
+        assert!(is_stored(&old));
+
+        let caught_up = FurSettings {
+            devices_synced_through: Utc::now().timestamp(),
+            ..settings.clone()
+        };
+        purge_expired_tombstones(&caught_up, true);
+        assert!(!is_stored(&old));
+    }
+
+    #[test]
+    fn signed_out_devices_purge_by_retention_alone() {
+        let _data = test_data::fresh_database();
+        let old = add_synced_tombstone(100);
+        let recent = add_synced_tombstone(5);
+        let settings = FurSettings {
+            tombstone_retention_days: 30,
+            ..FurSettings::default()
+        };
+
+        purge_expired_tombstones(&settings, false);
+        assert!(!is_stored(&old));
+        assert!(is_stored(&recent));
+    }
+}
@@ -0,0 +1,381 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! A lossless JSON archive of tasks, todos, shortcuts and settings. Importing merges records
+//! by uid the same way sync applies server changes: new records are added, and existing ones
+//! are only replaced by a copy that was updated more recently.
+
+use std::{fs, io, path::Path};
+
+use chrono::Local;
+use rusqlite::Connection;
+
+use crate::{
+    database::{
+        self,
+        tasks::{SortBy, SortOrder},
+    },
+    events::{self, CoreEvent},
+    models::{
+        fur_archive::{FurArchive, ARCHIVE_FORMAT_VERSION},
+        fur_error::{ErrorContext, FurErrorContext, FurResult},
+        fur_settings::FurSettings,
+    },
+};
+
+use super::settings;
+
+/// How an import changed one kind of record
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MergeCount {
+    pub added: usize,
+    pub updated: usize,
+    /// Already saved with the same or a newer update
+    pub unchanged: usize,
+}
+
+impl MergeCount {
+    fn add(&mut self, other: MergeCount) {
+        self.added += other.added;
+        self.updated += other.updated;
+        self.unchanged += other.unchanged;
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ArchiveImport {
+    pub tasks: MergeCount,
+    pub todos: MergeCount,
+    pub shortcuts: MergeCount,
+    pub settings_imported: bool,
+}
+
+impl ArchiveImport {
+    pub fn total(&self) -> MergeCount {
+        let mut total = MergeCount::default();
+        total.add(self.tasks);
+        total.add(self.todos);
+        total.add(self.shortcuts);
+        total
+    }
+}
+
+/// Every record, deleted ones included so deletions carry over, and the current settings
+pub fn create(settings: &FurSettings) -> FurResult<FurArchive> {
+    let tasks = database::tasks::retrieve_all_tasks(SortBy::StartTime, SortOrder::Ascending)
+        .in_context(FurErrorContext::ExportData)?;
+    let todos = database::todos::retrieve_all_todos().in_context(FurErrorContext::ExportData)?;
+    let shortcuts =
+        database::shortcuts::retrieve_all_shortcuts().in_context(FurErrorContext::ExportData)?;
+
+    let mut settings = settings.clone();
+    // The token unlocks this device's local API, and hooks run commands or reach URLs on this
+    // device, so neither should travel with the archive
+    settings.local_api_token = String::new();
+    settings.hook_pomodoro_ended = String::new();
+    settings.hook_sync_finished = String::new();
+    settings.hook_timer_started = String::new();
+    settings.hook_timer_stopped = String::new();
+    settings.hook_todo_completed = String::new();
+
+    Ok(FurArchive {
+        format_version: ARCHIVE_FORMAT_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        exported_at: Local::now(),
+        tasks,
+        todos,
+        shortcuts,
+        settings: Some(settings),
+    })
+}
+
+pub fn to_json(archive: &FurArchive) -> FurResult<String> {
+    serde_json::to_string_pretty(archive).in_context(FurErrorContext::ExportData)
+}
+
+pub fn read(path: &Path) -> FurResult<FurArchive> {
+    let contents = fs::read_to_string(path).in_context(FurErrorContext::ImportData)?;
+    parse(&contents)
+}
+
+pub fn parse(contents: &str) -> FurResult<FurArchive> {
+    let archive: FurArchive =
+        serde_json::from_str(contents).in_context(FurErrorContext::ImportData)?;
+    if archive.format_version > ARCHIVE_FORMAT_VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "archive format {} is newer than {}",
+                archive.format_version, ARCHIVE_FORMAT_VERSION
+            ),
+        ))
+        .in_context(FurErrorContext::ImportData);
+    }
+    Ok(archive)
+}
+
+/// Merge the archive's records into the database and, with `include_settings`, replace the
+/// settings except for those that belong to this device
+pub fn import(archive: &FurArchive, include_settings: bool) -> FurResult<ArchiveImport> {
+    let mut conn = database::init::open_connection().in_context(FurErrorContext::ImportData)?;
+    // All or nothing, so a failed import doesn't leave half of the archive merged
+    let tx = conn.transaction().in_context(FurErrorContext::ImportData)?;
+    let tasks = merge(
+        &tx,
+        &archive.tasks,
+        |task| (&task.uid, task.last_updated),
+        database::tasks::retrieve_task_by_id_with,
+        database::tasks::insert_task_with,
+        database::tasks::update_task_with,
+    )?;
+    let todos = merge(
+        &tx,
+        &archive.todos,
+        |todo| (&todo.uid, todo.last_updated),
+        database::todos::retrieve_todo_by_id_with,
+        database::todos::insert_todo_with,
+        database::todos::update_todo_with,
+    )?;
+    let shortcuts = merge(
+        &tx,
+        &archive.shortcuts,
+        |shortcut| (&shortcut.uid, shortcut.last_updated),
+        database::shortcuts::retrieve_shortcut_by_id_with,
+        database::shortcuts::insert_shortcut_with,
+        database::shortcuts::update_shortcut_with,
+    )?;
+    tx.commit().in_context(FurErrorContext::ImportData)?;
+
+    let import = ArchiveImport {
+        tasks,
+        todos,
+        shortcuts,
+        settings_imported: include_settings && archive.settings.is_some(),
+    };
+    let changed = import.total().added + import.total().updated > 0;
+
+    settings::update(|settings| {
+        if let Some(archived) = archive.settings.as_ref().filter(|_| include_settings) {
+            *settings = with_device_settings(archived.clone(), settings);
+        }
+        // Merged records may be older than the last sync, so upload everything next time
+        if changed {
+            settings.needs_full_sync = true;
+        }
+        settings.save()
+    })?;
+
+    if tasks.added + tasks.updated > 0 {
+        events::emit(CoreEvent::TasksChanged);
+    }
+    if todos.added + todos.updated > 0 {
+        events::emit(CoreEvent::TodosChanged);
+    }
+    if shortcuts.added + shortcuts.updated > 0 {
+        events::emit(CoreEvent::ShortcutsChanged);
+    }
+    Ok(import)
+}
+
+/// Add records that aren't saved yet and replace saved ones with newer copies
+fn merge<T>(
+    conn: &Connection,
+    records: &[T],
+    identity: impl Fn(&T) -> (&String, i64),
+    retrieve: impl Fn(&Connection, &String) -> rusqlite::Result<Option<T>>,
+    insert: impl Fn(&Connection, &T) -> rusqlite::Result<()>,
+    update: impl Fn(&Connection, &T) -> rusqlite::Result<()>,
+) -> FurResult<MergeCount> {
+    let mut count = MergeCount::default();
+    for record in records {
+        let (uid, last_updated) = identity(record);
+        match retrieve(conn, uid).in_context(FurErrorContext::ImportData)? {
+            Some(saved) if last_updated > identity(&saved).1 => {
+                update(conn, record).in_context(FurErrorContext::ImportData)?;
+                count.updated += 1;
+            }
+            Some(_) => count.unchanged += 1,
+            None => {
+                insert(conn, record).in_context(FurErrorContext::ImportData)?;
+                count.added += 1;
+            }
+        }
+    }
+    Ok(count)
+}
+
+/// Archived settings, keeping this device's name, sync progress, hooks and local API token
+fn with_device_settings(mut archived: FurSettings, current: &FurSettings) -> FurSettings {
+    archived.device_name = current.device_name.clone();
+    archived.devices_synced_through = current.devices_synced_through;
+    archived.first_run = current.first_run;
+    archived.hook_pomodoro_ended = current.hook_pomodoro_ended.clone();
+    archived.hook_sync_finished = current.hook_sync_finished.clone();
+    archived.hook_timer_started = current.hook_timer_started.clone();
+    archived.hook_timer_stopped = current.hook_timer_stopped.clone();
+    archived.hook_todo_completed = current.hook_todo_completed.clone();
+    archived.last_sync = current.last_sync;
+    archived.local_api_token = current.local_api_token.clone();
+    archived.needs_full_sync = current.needs_full_sync;
+    archived
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::{
+        helpers::test_data,
+        models::{fur_hook::FurHookEvent, fur_task::FurTask, fur_todo::FurTodo},
+    };
+
+    fn task(name: &str, last_updated: i64) -> FurTask {
+        let start = Local.with_ymd_and_hms(2025, 3, 4, 9, 0, 0).unwrap();
+        FurTask::new_with_last_updated(
+            name.to_string(),
+            start,
+            start + chrono::TimeDelta::hours(1),
+            String::new(),
+            String::new(),
+            0.0,
+            String::new(),
+            last_updated,
+        )
+    }
+
+    fn archive(tasks: Vec<FurTask>, todos: Vec<FurTodo>) -> FurArchive {
+        FurArchive {
+            format_version: ARCHIVE_FORMAT_VERSION,
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            exported_at: Local::now(),
+            tasks,
+            todos,
+            shortcuts: Vec::new(),
+            settings: None,
+        }
+    }
+
+    fn saved_task(uid: &str) -> FurTask {
+        database::tasks::retrieve_task_by_id(&uid.to_string())
+            .unwrap()
+            .unwrap()
+    }
+
+    #[test]
+    fn only_new_records_and_newer_copies_are_saved() {
+        let _data = test_data::fresh_database();
+        let edited = task("Draft", 100);
+        let stale = task("Outline", 100);
+        for saved in [&edited, &stale] {
+            database::tasks::insert_task(saved).unwrap();
+        }
+
+        let mut newer = edited.clone();
+        newer.name = "Final draft".to_string();
+        newer.last_updated = 200;
+        let mut older = stale.clone();
+        older.name = "Old outline".to_string();
+        older.last_updated = 50;
+        let added = task("Review", 100);
+        let mut deleted = task("Scrapped", 100);
+        deleted.is_deleted = true;
+        let todo = FurTodo::new(
+            "Send".to_string(),
+            String::new(),
+            String::new(),
+            0.0,
+            Local::now(),
+        );
+
+        let merged = import(
+            &archive(
+                vec![newer, older, stale.clone(), added.clone(), deleted.clone()],
+                vec![todo.clone()],
+            ),
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(
+            merged.tasks,
+            MergeCount {
+                added: 2,
+                updated: 1,
+                unchanged: 2,
+            }
+        );
+        assert_eq!(merged.todos.added, 1);
+        assert_eq!(merged.total().added, 3);
+        assert_eq!(saved_task(&edited.uid).name, "Final draft");
+        assert_eq!(saved_task(&stale.uid).name, "Outline");
+        assert_eq!(saved_task(&added.uid).name, "Review");
+        // Deletions carry over so the record stays deleted on every device
+        assert!(saved_task(&deleted.uid).is_deleted);
+        assert!(settings::load().needs_full_sync);
+
+        // Importing again changes nothing
+        let again = import(&archive(vec![added], vec![todo]), false).unwrap();
+        assert_eq!(again.total().unchanged, 2);
+        assert_eq!(again.total().added + again.total().updated, 0);
+    }
+
+    #[test]
+    fn imported_settings_keep_this_devices_own() {
+        let _data = test_data::fresh_database();
+        settings::update(|settings| {
+            settings.device_name = "Laptop".to_string();
+            settings.local_api_token = "laptop-token".to_string();
+            settings.hook_timer_stopped = "notify-send stopped".to_string();
+            settings.save()
+        })
+        .unwrap();
+        let mut archived = settings::load();
+        archived.device_name = "Desktop".to_string();
+        archived.local_api_token = "desktop-token".to_string();
+        archived.hook_timer_stopped = "notify-send desktop".to_string();
+        archived.hook_sync_finished = "https://example.com/hook".to_string();
+        archived.pomodoro_length = 50;
+        let mut with_settings = archive(Vec::new(), Vec::new());
+        with_settings.settings = Some(archived);
+
+        assert!(import(&with_settings, true).unwrap().settings_imported);
+
+        let settings = settings::load();
+        assert_eq!(settings.pomodoro_length, 50);
+        assert_eq!(settings.device_name, "Laptop");
+        assert_eq!(settings.local_api_token, "laptop-token");
+        assert_eq!(settings.hook_timer_stopped, "notify-send stopped");
+        assert!(settings.hook_sync_finished.is_empty());
+    }
+
+    #[test]
+    fn exported_settings_leave_out_token_and_hooks() {
+        let _data = test_data::fresh_database();
+        let mut current = settings::load();
+        current.local_api_token = "laptop-token".to_string();
+        current.hook_timer_started = "notify-send started".to_string();
+        current.hook_todo_completed = "https://example.com/hook".to_string();
+        current.pomodoro_length = 50;
+
+        let exported = create(&current).unwrap().settings.unwrap();
+        assert_eq!(exported.pomodoro_length, 50);
+        assert!(exported.local_api_token.is_empty());
+        assert!(FurHookEvent::ALL
+            .iter()
+            .all(|event| exported.hook(*event).is_empty()));
+    }
+}
@@ -0,0 +1,698 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! iCalendar (.ics) files, so tracked time can be laid over a calendar
+//! and calendar events can become tasks or todos
+
+use std::{collections::HashSet, fs, path::Path};
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, TimeZone, Utc};
+use chrono_tz::Tz;
+
+use crate::{
+    database,
+    helpers::{formatters, uid},
+    loc,
+    localization::Localization,
+    models::{
+        fur_calendar_event::FurCalendarEvent,
+        fur_error::{ErrorContext, FurErrorContext, FurResult},
+        fur_settings::FurSettings,
+        fur_task::FurTask,
+        fur_task_group::FurTaskGroup,
+        fur_todo::FurTodo,
+    },
+};
+
+use super::{importers::ImportCount, tasks, todos};
+
+/// Longest gap between two tasks of a group that still merges them into one event
+const MERGE_GAP_SECONDS: i64 = 60;
+/// Lines longer than this many bytes are folded, as RFC 5545 requires
+const MAX_LINE_BYTES: usize = 75;
+const PRODUCT_ID: &str = "-//Unobserved//Furtherance//EN";
+const ICS_DATE_TIME_FORMAT: &str = "%Y%m%dT%H%M%S";
+
+/// Tasks as a calendar with one VEVENT each. With `merge_contiguous`, back-to-back tasks
+/// that would be grouped together in the task history become a single event.
+pub fn tasks_ics(tasks: &[FurTask], merge_contiguous: bool) -> String {
+    let mut sorted = tasks.to_vec();
+    sorted.sort_by_key(|task| task.start_time);
+
+    let mut events: Vec<FurTaskGroup> = Vec::new();
+    for task in sorted {
+        match events.last_mut() {
+            Some(event) if merge_contiguous && is_contiguous(event, &task) => event.add(task),
+            _ => events.push(FurTaskGroup::new_from(task)),
+        }
+    }
+
+    let stamp = ics_time(&Utc::now());
+    let mut ics = String::new();
+    for line in ["BEGIN:VCALENDAR", "VERSION:2.0", "CALSCALE:GREGORIAN"] {
+        push_line(&mut ics, line);
+    }
+    push_line(&mut ics, &format!("PRODID:{}", PRODUCT_ID));
+    for event in &events {
+        push_event(&mut ics, event, &stamp);
+    }
+    push_line(&mut ics, "END:VCALENDAR");
+    ics
+}
+
+fn is_contiguous(event: &FurTaskGroup, task: &FurTask) -> bool {
+    let Some(last) = event.tasks.last() else {
+        return false;
+    };
+    event.is_equal_to(task)
+        && last.start_time.date_naive() == task.start_time.date_naive()
+        && task.start_time - event_stop(event) <= TimeDelta::seconds(MERGE_GAP_SECONDS)
+}
+
+fn event_stop(event: &FurTaskGroup) -> DateTime<Local> {
+    event
+        .tasks
+        .iter()
+        .map(|task| task.stop_time)
+        .max()
+        .unwrap_or_default()
+}
+
+fn push_event(ics: &mut String, event: &FurTaskGroup, stamp: &str) {
+    let start = event
+        .tasks
+        .first()
+        .map(|task| task.start_time)
+        .unwrap_or_default();
+    push_line(ics, "BEGIN:VEVENT");
+    push_line(ics, &format!("UID:{}@furtherance", event.uid));
+    push_line(ics, &format!("DTSTAMP:{}", stamp));
+    push_line(ics, &format!("DTSTART:{}", ics_time(&start)));
+    push_line(ics, &format!("DTEND:{}", ics_time(&event_stop(event))));
+    push_line(ics, &format!("SUMMARY:{}", escape_text(&event.name)));
+    if !event.tags.is_empty() {
+        let categories = event
+            .tags
+            .split(" #")
+            .map(escape_text)
+            .collect::<Vec<String>>()
+            .join(",");
+        push_line(ics, &format!("CATEGORIES:{}", categories));
+    }
+    if !event.project.is_empty() {
+        push_line(
+            ics,
+            &format!(
+                "DESCRIPTION:{}",
+                escape_text(&format!("@{}", event.project))
+            ),
+        );
+    }
+    push_line(ics, "END:VEVENT");
+}
+
+fn ics_time<Tz: TimeZone>(time: &DateTime<Tz>) -> String {
+    time.with_timezone(&Utc)
+        .format("%Y%m%dT%H%M%SZ")
+        .to_string()
+}
+
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Add a content line, folding it onto continuation lines that start with a space
+fn push_line(ics: &mut String, line: &str) {
+    let mut line_bytes = 0;
+    for character in line.chars() {
+        if line_bytes + character.len_utf8() > MAX_LINE_BYTES {
+            ics.push_str("\r\n ");
+            line_bytes = 1;
+        }
+        ics.push(character);
+        line_bytes += character.len_utf8();
+    }
+    ics.push_str("\r\n");
+}
+
+pub fn read_ics(path: &Path) -> FurResult<Vec<FurCalendarEvent>> {
+    let contents = fs::read_to_string(path).in_context(FurErrorContext::ImportData)?;
+    Ok(parse_ics(&contents))
+}
+
+/// The events in an .ics file, leaving out cancelled ones. A recurring event is read once,
+/// at its first occurrence. Times with a TZID are converted from that zone, or read as local
+/// time if the zone isn't known.
+pub fn parse_ics(contents: &str) -> Vec<FurCalendarEvent> {
+    let mut events = Vec::new();
+    let mut properties: Option<Vec<Property>> = None;
+    // Components inside an event, such as alarms, whose properties aren't the event's
+    let mut nested_depth = 0;
+
+    for line in unfold(contents) {
+        let Some(property) = Property::parse(&line) else {
+            continue;
+        };
+        let is_event = property.value.eq_ignore_ascii_case("VEVENT");
+        match property.name.as_str() {
+            "BEGIN" if is_event && properties.is_none() => properties = Some(Vec::new()),
+            "BEGIN" if properties.is_some() => nested_depth += 1,
+            "END" if nested_depth > 0 => nested_depth -= 1,
+            "END" if is_event => {
+                if let Some(event) = properties.take().and_then(|p| event_from(&p)) {
+                    events.push(event);
+                }
+            }
+            _ => {
+                if let Some(properties) = properties.as_mut() {
+                    properties.push(property);
+                }
+            }
+        }
+    }
+    events
+}
+
+/// The task input an event imports as: its summary, then the remembered project and tags
+/// for each of its categories. Categories without a mapping become tags.
+pub fn event_task_input(event: &FurCalendarEvent, settings: &FurSettings) -> String {
+    let mut task_input = strip_task_symbols(&event.summary);
+    if task_input.is_empty() {
+        task_input = loc!("calendar-event");
+    }
+    for category in &event.categories {
+        match settings.category_mapping(category) {
+            Some(mapping) if !mapping.trim().is_empty() => {
+                task_input = format!("{} {}", task_input, mapping.trim())
+            }
+            _ => {
+                let tag = strip_task_symbols(category);
+                if !tag.is_empty() {
+                    task_input = format!("{} #{}", task_input, tag);
+                }
+            }
+        }
+    }
+    task_input
+}
+
+/// Whether an earlier import made a task or todo from this event, even if it was deleted since
+pub fn is_imported(event: &FurCalendarEvent) -> FurResult<bool> {
+    let uid = record_uid(event);
+    let task = database::tasks::retrieve_task_by_id(&uid).in_context(FurErrorContext::LoadTasks)?;
+    let todo = database::todos::retrieve_todo_by_id(&uid).in_context(FurErrorContext::LoadTodos)?;
+    Ok(task.is_some() || todo.is_some())
+}
+
+/// Save events as tasks, leaving out all-day events and ones imported before
+pub fn import_as_tasks(
+    events: &[FurCalendarEvent],
+    settings: &FurSettings,
+) -> FurResult<ImportCount> {
+    let mut tasks = Vec::new();
+    for event in new_events(events)? {
+        if event.all_day || event.end <= event.start {
+            continue;
+        }
+        let (name, project, tags, rate) =
+            formatters::split_task_input(&event_task_input(event, settings));
+        let mut task = FurTask::new(
+            name,
+            event.start,
+            event.end,
+            tags,
+            project,
+            rate,
+            String::new(),
+        );
+        task.uid = record_uid(event);
+        tasks.push(task);
+    }
+
+    if !tasks.is_empty() {
+        tasks::add_many(&tasks)?;
+    }
+    Ok(ImportCount {
+        imported: tasks.len(),
+        skipped: events.len() - tasks.len(),
+    })
+}
+
+/// Save events as todos on the day they start, leaving out ones imported before
+pub fn import_as_todos(
+    events: &[FurCalendarEvent],
+    settings: &FurSettings,
+) -> FurResult<ImportCount> {
+    let mut todos = Vec::new();
+    for event in new_events(events)? {
+        let (name, project, tags, rate) =
+            formatters::split_task_input(&event_task_input(event, settings));
+        let date = start_of_day(event.start.date_naive()).unwrap_or(event.start);
+        let mut todo = FurTodo::new(name, project, tags, rate, date);
+        todo.uid = record_uid(event);
+        todos.push(todo);
+    }
+
+    if !todos.is_empty() {
+        todos::add_many(&todos)?;
+    }
+    Ok(ImportCount {
+        imported: todos.len(),
+        skipped: events.len() - todos.len(),
+    })
+}
+
+/// Events that weren't imported before and aren't repeated earlier in `events`
+fn new_events(events: &[FurCalendarEvent]) -> FurResult<Vec<&FurCalendarEvent>> {
+    let mut seen = HashSet::new();
+    let mut new_events = Vec::new();
+    for event in events {
+        if seen.insert(event.uid.as_str()) && !is_imported(event)? {
+            new_events.push(event);
+        }
+    }
+    Ok(new_events)
+}
+
+/// The same event always imports with the same uid, which is how duplicates are found
+fn record_uid(event: &FurCalendarEvent) -> String {
+    uid::imported_uid(&format!("ics:{}", event.uid))
+}
+
+/// Remove the characters that start a project, tag or rate in task input
+fn strip_task_symbols(text: &str) -> String {
+    text.replace(['@', '#', '$'], "")
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// A content line such as `DTSTART;TZID=Europe/Berlin:20250102T090000`
+struct Property {
+    name: String,
+    parameters: Vec<(String, String)>,
+    value: String,
+}
+
+impl Property {
+    fn parse(line: &str) -> Option<Self> {
+        // The value starts at the first colon that isn't inside a quoted parameter value
+        let mut in_quotes = false;
+        let mut value_start = None;
+        for (index, character) in line.char_indices() {
+            match character {
+                '"' => in_quotes = !in_quotes,
+                ':' if !in_quotes => {
+                    value_start = Some(index);
+                    break;
+                }
+                _ => {}
+            }
+        }
+        let value_start = value_start?;
+
+        let mut parts = line[..value_start].split(';');
+        let name = parts.next()?.trim().to_ascii_uppercase();
+        let parameters = parts
+            .filter_map(|parameter| parameter.split_once('='))
+            .map(|(key, value)| {
+                (
+                    key.to_ascii_uppercase(),
+                    value.trim_matches('"').to_string(),
+                )
+            })
+            .collect();
+        Some(Property {
+            name,
+            parameters,
+            value: line[value_start + 1..].to_string(),
+        })
+    }
+
+    fn parameter(&self, name: &str) -> Option<&str> {
+        self.parameters
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Join folded lines back into one content line each
+fn unfold(contents: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in contents.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+fn event_from(properties: &[Property]) -> Option<FurCalendarEvent> {
+    let find = |name: &str| properties.iter().find(|property| property.name == name);
+    if find("STATUS").is_some_and(|status| status.value.eq_ignore_ascii_case("CANCELLED")) {
+        return None;
+    }
+
+    let (start, all_day, start_zone) = parse_time(find("DTSTART")?)?;
+    let mut unknown_time_zone = start_zone;
+    let end = match (find("DTEND"), find("DURATION")) {
+        (Some(end), _) => {
+            let (end, _, end_zone) = parse_time(end)?;
+            unknown_time_zone = unknown_time_zone.or(end_zone);
+            end
+        }
+        // Durations are untrusted input, so one too large to add skips the event
+        (None, Some(duration)) => start.checked_add_signed(parse_duration(&duration.value)?)?,
+        (None, None) if all_day => start.checked_add_signed(TimeDelta::days(1))?,
+        (None, None) => start,
+    };
+    let summary = find("SUMMARY")
+        .map(|summary| unescape_text(&summary.value))
+        .unwrap_or_default();
+
+    // Events without a UID break the standard, but still need to be told apart
+    let mut uid = match find("UID") {
+        Some(uid) if !uid.value.trim().is_empty() => uid.value.trim().to_string(),
+        _ => format!("{}/{}", summary, start.to_rfc3339()),
+    };
+    if let Some(recurrence_id) = find("RECURRENCE-ID") {
+        uid = format!("{}/{}", uid, recurrence_id.value.trim());
+    }
+
+    let categories = properties
+        .iter()
+        .filter(|property| property.name == "CATEGORIES")
+        .flat_map(|property| split_list(&property.value))
+        .map(|category| unescape_text(&category).trim().to_string())
+        .filter(|category| !category.is_empty())
+        .collect();
+
+    Some(FurCalendarEvent {
+        uid,
+        summary,
+        start,
+        end,
+        all_day,
+        categories,
+        unknown_time_zone,
+    })
+}
+
+/// A DTSTART or DTEND, whether it's a date without a time,
+/// and its TZID if that isn't a known time zone
+fn parse_time(property: &Property) -> Option<(DateTime<Local>, bool, Option<String>)> {
+    let value = property.value.trim();
+    let is_date = property
+        .parameter("VALUE")
+        .is_some_and(|kind| kind.eq_ignore_ascii_case("DATE"))
+        || value.len() == 8;
+    if is_date {
+        let date = NaiveDate::parse_from_str(value, "%Y%m%d").ok()?;
+        return Some((start_of_day(date)?, true, None));
+    }
+
+    if let Some(utc) = value.strip_suffix('Z') {
+        let naive = NaiveDateTime::parse_from_str(utc, ICS_DATE_TIME_FORMAT).ok()?;
+        return Some((
+            Utc.from_utc_datetime(&naive).with_timezone(&Local),
+            false,
+            None,
+        ));
+    }
+    let naive = NaiveDateTime::parse_from_str(value, ICS_DATE_TIME_FORMAT).ok()?;
+    match property.parameter("TZID") {
+        Some(tzid) => match time_zone(tzid) {
+            Some(zone) => {
+                let time = zone.from_local_datetime(&naive).earliest()?;
+                Some((time.with_timezone(&Local), false, None))
+            }
+            None => {
+                let time = Local.from_local_datetime(&naive).earliest()?;
+                Some((time, false, Some(tzid.to_string())))
+            }
+        },
+        // Times without a zone are meant to be read as local time
+        None => Some((Local.from_local_datetime(&naive).earliest()?, false, None)),
+    }
+}
+
+/// The IANA zone a TZID names. Some calendars put a path in front of the name,
+/// such as "/mozilla.org/20050126_1/Europe/Berlin".
+fn time_zone(tzid: &str) -> Option<Tz> {
+    let tzid = tzid.trim();
+    std::iter::once(tzid)
+        .chain(tzid.match_indices('/').map(|(index, _)| &tzid[index + 1..]))
+        .find_map(|name| name.parse::<Tz>().ok())
+}
+
+/// A DURATION such as `PT1H30M` or `P1D`
+fn parse_duration(value: &str) -> Option<TimeDelta> {
+    let value = value.trim();
+    let (negative, value) = match value.strip_prefix('-') {
+        Some(value) => (true, value),
+        None => (false, value.trim_start_matches('+')),
+    };
+
+    let mut seconds: i64 = 0;
+    let mut number = String::new();
+    let mut in_time = false;
+    for character in value.strip_prefix('P')?.chars() {
+        match character {
+            'T' => in_time = true,
+            '0'..='9' => number.push(character),
+            unit => {
+                let amount: i64 = number.parse().ok()?;
+                number.clear();
+                let unit_seconds = match (unit, in_time) {
+                    ('W', false) => 7 * 24 * 60 * 60,
+                    ('D', false) => 24 * 60 * 60,
+                    ('H', true) => 60 * 60,
+                    ('M', true) => 60,
+                    ('S', true) => 1,
+                    _ => return None,
+                };
+                seconds = seconds.checked_add(amount.checked_mul(unit_seconds)?)?;
+            }
+        }
+    }
+
+    let duration = TimeDelta::try_seconds(seconds)?;
+    Some(if negative { -duration } else { duration })
+}
+
+fn start_of_day(date: NaiveDate) -> Option<DateTime<Local>> {
+    Local
+        .from_local_datetime(&date.and_time(NaiveTime::MIN))
+        .earliest()
+}
+
+/// Split a comma-separated value, leaving escaped commas in place
+fn split_list(value: &str) -> Vec<String> {
+    let mut items = vec![String::new()];
+    let mut escaped = false;
+    for character in value.chars() {
+        match character {
+            ',' if !escaped => items.push(String::new()),
+            _ => {
+                if let Some(item) = items.last_mut() {
+                    item.push(character);
+                }
+            }
+        }
+        escaped = character == '\\' && !escaped;
+    }
+    items
+}
+
+fn unescape_text(text: &str) -> String {
+    let mut unescaped = String::new();
+    let mut characters = text.chars();
+    while let Some(character) = characters.next() {
+        if character != '\\' {
+            unescaped.push(character);
+            continue;
+        }
+        match characters.next() {
+            Some('n') | Some('N') => unescaped.push('\n'),
+            Some(escaped) => unescaped.push(escaped),
+            None => {}
+        }
+    }
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(name: &str, start: (u32, u32), stop: (u32, u32)) -> FurTask {
+        let at = |(hour, minute)| Local.with_ymd_and_hms(2025, 3, 4, hour, minute, 0).unwrap();
+        FurTask::new(
+            name.to_string(),
+            at(start),
+            at(stop),
+            "writing #deep work".to_string(),
+            "Book".to_string(),
+            0.0,
+            String::new(),
+        )
+    }
+
+    #[test]
+    fn exported_tasks_read_back_as_the_same_events() {
+        let tasks = vec![
+            task("Chapter, part 1", (9, 0), (10, 30)),
+            task("Edits; notes", (11, 0), (11, 45)),
+        ];
+
+        let events = parse_ics(&tasks_ics(&tasks, false));
+
+        assert_eq!(events.len(), 2);
+        for (event, task) in events.iter().zip(&tasks) {
+            assert_eq!(event.summary, task.name);
+            assert_eq!(event.start, task.start_time);
+            assert_eq!(event.end, task.stop_time);
+            assert!(!event.all_day);
+            assert_eq!(event.categories, vec!["writing", "deep work"]);
+        }
+    }
+
+    #[test]
+    fn contiguous_tasks_merge_into_one_event() {
+        let tasks = vec![
+            task("Chapter", (9, 0), (10, 0)),
+            task("Chapter", (10, 0), (10, 30)),
+            task("Chapter", (12, 0), (13, 0)),
+        ];
+
+        let events = parse_ics(&tasks_ics(&tasks, true));
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].start, tasks[0].start_time);
+        assert_eq!(events[0].end, tasks[1].stop_time);
+        assert_eq!(events[1].start, tasks[2].start_time);
+    }
+
+    #[test]
+    fn long_lines_are_folded_and_unfolded() {
+        let name = "Réécriture du chapitre ".repeat(8);
+        let ics = tasks_ics(&[task(&name, (9, 0), (10, 0))], false);
+
+        for line in ics.split("\r\n") {
+            assert!(line.len() <= MAX_LINE_BYTES, "{:?} is too long", line);
+        }
+        assert!(ics.contains("\r\n "));
+        assert_eq!(parse_ics(&ics)[0].summary, name);
+    }
+
+    #[test]
+    fn events_read_durations_dates_and_escapes() {
+        let ics = "BEGIN:VCALENDAR\r\n\
+            BEGIN:VEVENT\r\n\
+            UID:timed\r\n\
+            DTSTART:20250304T090000Z\r\n\
+            DURATION:PT1H30M\r\n\
+            SUMMARY:Plan\\, then\r\n\
+            \tbuild\r\n\
+            BEGIN:VALARM\r\n\
+            SUMMARY:Reminder\r\n\
+            END:VALARM\r\n\
+            END:VEVENT\r\n\
+            BEGIN:VEVENT\r\n\
+            UID:holiday\r\n\
+            DTSTART;VALUE=DATE:20250305\r\n\
+            SUMMARY:Holiday\r\n\
+            END:VEVENT\r\n\
+            BEGIN:VEVENT\r\n\
+            UID:cancelled\r\n\
+            STATUS:CANCELLED\r\n\
+            DTSTART:20250306T090000Z\r\n\
+            END:VEVENT\r\n\
+            END:VCALENDAR\r\n";
+
+        let events = parse_ics(ics);
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].summary, "Plan, thenbuild");
+        assert_eq!(events[0].end - events[0].start, TimeDelta::minutes(90));
+        assert!(events[1].all_day);
+        assert_eq!(events[1].end - events[1].start, TimeDelta::days(1));
+    }
+
+    #[test]
+    fn zoned_times_are_converted_from_their_zone() {
+        let event = |start: &str| {
+            let ics = format!(
+                "BEGIN:VEVENT\r\nUID:zoned\r\nDTSTART;{}\r\nDURATION:PT1H\r\nEND:VEVENT\r\n",
+                start
+            );
+            parse_ics(&ics).remove(0)
+        };
+        let nine_in_new_york = Utc.with_ymd_and_hms(2025, 3, 4, 14, 0, 0).unwrap();
+
+        let zoned = event("TZID=America/New_York:20250304T090000");
+        assert_eq!(zoned.start, nine_in_new_york);
+        assert_eq!(zoned.unknown_time_zone, None);
+
+        let prefixed = event("TZID=/mozilla.org/20050126_1/America/New_York:20250304T090000");
+        assert_eq!(prefixed.start, nine_in_new_york);
+
+        let unknown = event("TZID=Eastern Standard Time:20250304T090000");
+        let nine_here = Local.with_ymd_and_hms(2025, 3, 4, 9, 0, 0).unwrap();
+        assert_eq!(unknown.start, nine_here);
+        assert_eq!(
+            unknown.unknown_time_zone.as_deref(),
+            Some("Eastern Standard Time")
+        );
+    }
+
+    #[test]
+    fn durations_parse_weeks_days_and_signs() {
+        assert_eq!(parse_duration("P1W"), Some(TimeDelta::weeks(1)));
+        assert_eq!(parse_duration("P1DT2H"), Some(TimeDelta::hours(26)));
+        assert_eq!(parse_duration("-PT15M"), Some(TimeDelta::minutes(-15)));
+        assert_eq!(parse_duration("PT1D"), None);
+        assert_eq!(parse_duration("1H"), None);
+    }
+
+    #[test]
+    fn durations_too_long_to_add_skip_the_event() {
+        assert_eq!(parse_duration("P99999999999999W"), None);
+        assert_eq!(parse_duration("P99999999999999999999D"), None);
+        assert_eq!(parse_duration("P9999999999999D"), None);
+
+        let ics = "BEGIN:VCALENDAR\r\n\
+            BEGIN:VEVENT\r\n\
+            UID:endless\r\n\
+            DTSTART:20250304T090000Z\r\n\
+            DURATION:P99999999W\r\n\
+            END:VEVENT\r\n\
+            BEGIN:VEVENT\r\n\
+            UID:overflow\r\n\
+            DTSTART:20250304T090000Z\r\n\
+            DURATION:P99999999999999W\r\n\
+            END:VEVENT\r\n\
+            END:VCALENDAR\r\n";
+
+        assert!(parse_ics(ics).is_empty());
+    }
+}
@@ -0,0 +1,88 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::models::{
+    fur_error::{ErrorContext, FurErrorContext, FurResult},
+    fur_settings::get_export_path,
+    fur_task::FurTask,
+};
+
+const CSV_HEADER: [&str; 9] = [
+    "Name",
+    "Project",
+    "Tags",
+    "Rate",
+    "Currency",
+    "Start Time",
+    "Stop Time",
+    "Total Seconds",
+    "UID",
+];
+
+/// Tasks as CSV, one row per task with RFC 3339 times
+pub fn tasks_csv(tasks: &[FurTask]) -> String {
+    let mut csv = csv_row(CSV_HEADER.iter().map(|field| field.to_string()));
+    for task in tasks {
+        csv += &csv_row([
+            task.name.clone(),
+            task.project.clone(),
+            task.tags.clone(),
+            format!("{:.2}", task.rate),
+            task.currency.clone(),
+            task.start_time.to_rfc3339(),
+            task.stop_time.to_rfc3339(),
+            task.total_time_in_seconds().to_string(),
+            task.uid.clone(),
+        ]);
+    }
+    csv
+}
+
+pub fn write(path: &Path, contents: impl AsRef<[u8]>) -> FurResult<()> {
+    fs::write(path, contents).in_context(FurErrorContext::ExportData)
+}
+
+/// Save `contents` as `file_name` in the export folder and return where it went
+pub fn save(file_name: &str, contents: impl AsRef<[u8]>) -> FurResult<PathBuf> {
+    let path = get_export_path().join(file_name);
+    write(&path, contents)?;
+    Ok(path)
+}
+
+/// One CSV line, ending in CRLF
+pub fn csv_row(fields: impl IntoIterator<Item = String>) -> String {
+    let mut row = fields
+        .into_iter()
+        .map(|field| csv_field(&field))
+        .collect::<Vec<String>>()
+        .join(",");
+    row.push_str("\r\n");
+    row
+}
+
+/// Quote a field if it contains a separator, quote or line break
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
@@ -0,0 +1,876 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Importing time entries exported from other trackers: Toggl, Clockify and Harvest CSV,
+//! and Timewarrior's JSON. Each file is read into a table, its columns are mapped onto task
+//! fields, and the resulting tasks are previewed before anything is saved.
+
+use std::{collections::HashMap, fs, path::Path};
+
+use chrono::{
+    DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, TimeZone, Timelike, Utc,
+};
+use serde_json::Value;
+
+use crate::{
+    helpers::formatters,
+    loc,
+    localization::Localization,
+    models::{
+        fur_error::{ErrorContext, FurErrorContext, FurResult},
+        fur_task::FurTask,
+    },
+};
+
+use super::tasks;
+
+/// Entries with a date but no times are laid out back to back from this hour
+const UNTIMED_DAY_START_HOUR: u32 = 9;
+/// Slash dates are left out, since whether they start with the month depends on the file
+const DATE_FORMATS: [&str; 3] = ["%Y-%m-%d", "%d.%m.%Y", "%Y/%m/%d"];
+const TIME_FORMATS: [&str; 5] = ["%H:%M:%S", "%H:%M", "%I:%M:%S %p", "%I:%M %p", "%I:%M%p"];
+const DATE_TIME_FORMATS: [&str; 3] = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S"];
+/// Timewarrior's interval times, always in UTC
+const TIMEWARRIOR_TIME_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// How many of the records given to an import were saved and how many were left out
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ImportCount {
+    pub imported: usize,
+    pub skipped: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImportSource {
+    Toggl,
+    Clockify,
+    Harvest,
+    Timewarrior,
+}
+
+impl ImportSource {
+    pub const ALL: [ImportSource; 4] = [
+        ImportSource::Toggl,
+        ImportSource::Clockify,
+        ImportSource::Harvest,
+        ImportSource::Timewarrior,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ImportSource::Toggl => "Toggl (CSV)",
+            ImportSource::Clockify => "Clockify (CSV)",
+            ImportSource::Harvest => "Harvest (CSV)",
+            ImportSource::Timewarrior => "Timewarrior (JSON)",
+        }
+    }
+
+    /// Headers this tracker uses for a field, in order of preference
+    fn columns(&self, field: ImportField) -> &'static [&'static str] {
+        match (self, field) {
+            (ImportSource::Timewarrior, ImportField::Description) => &["annotation"],
+            (ImportSource::Timewarrior, ImportField::Tags) => &["tags"],
+            (ImportSource::Timewarrior, ImportField::StartDate) => &["start"],
+            (ImportSource::Timewarrior, ImportField::EndDate) => &["end"],
+            (ImportSource::Timewarrior, _) => &[],
+
+            (ImportSource::Harvest, ImportField::Description) => &["Notes"],
+            (ImportSource::Harvest, ImportField::Tags) => &[],
+            (ImportSource::Harvest, ImportField::Billable) => &["Billable?", "Billable"],
+            (ImportSource::Harvest, ImportField::Currency) => &["Currency"],
+            (ImportSource::Harvest, ImportField::StartDate) => &["Date", "Spent Date"],
+            (ImportSource::Harvest, ImportField::StartTime) => &["Started At", "Start Time"],
+            (ImportSource::Harvest, ImportField::EndDate) => &[],
+            (ImportSource::Harvest, ImportField::EndTime) => &["Ended At", "End Time"],
+            (ImportSource::Harvest, ImportField::Duration) => &["Hours"],
+
+            (ImportSource::Clockify, ImportField::Duration) => {
+                &["Duration (h)", "Duration (decimal)", "Duration"]
+            }
+
+            (_, ImportField::Description) => &["Description"],
+            (_, ImportField::Client) => &["Client"],
+            (_, ImportField::Project) => &["Project"],
+            (_, ImportField::Task) => &["Task"],
+            (_, ImportField::Tags) => &["Tags"],
+            (_, ImportField::Billable) => &["Billable"],
+            (_, ImportField::Rate) => &["Billable Rate", "Hourly Rate", "Rate"],
+            (_, ImportField::Amount) => &["Billable Amount", "Amount"],
+            (_, ImportField::Currency) => &[],
+            (_, ImportField::StartDate) => &["Start Date"],
+            (_, ImportField::StartTime) => &["Start Time"],
+            (_, ImportField::EndDate) => &["End Date"],
+            (_, ImportField::EndTime) => &["End Time"],
+            (_, ImportField::Duration) => &["Duration"],
+        }
+    }
+}
+
+/// A piece of a task that a column can be read into
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ImportField {
+    Description,
+    Client,
+    Project,
+    /// Becomes the name when there's no description, and a tag otherwise
+    Task,
+    Tags,
+    Billable,
+    Rate,
+    /// Used to work out the rate when there's no rate column
+    Amount,
+    Currency,
+    /// A date, or a date and time when there's no start time column
+    StartDate,
+    StartTime,
+    EndDate,
+    EndTime,
+    Duration,
+}
+
+impl ImportField {
+    pub const ALL: [ImportField; 14] = [
+        ImportField::Description,
+        ImportField::Client,
+        ImportField::Project,
+        ImportField::Task,
+        ImportField::Tags,
+        ImportField::Billable,
+        ImportField::Rate,
+        ImportField::Amount,
+        ImportField::Currency,
+        ImportField::StartDate,
+        ImportField::StartTime,
+        ImportField::EndDate,
+        ImportField::EndTime,
+        ImportField::Duration,
+    ];
+}
+
+/// Which number comes first in dates such as 03/04/2025
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum DateOrder {
+    MonthFirst,
+    DayFirst,
+}
+
+impl DateOrder {
+    fn slash_format(&self) -> &'static str {
+        match self {
+            DateOrder::MonthFirst => "%m/%d/%Y",
+            DateOrder::DayFirst => "%d/%m/%Y",
+        }
+    }
+}
+
+/// The index of the column each field is read from
+pub type ColumnMapping = HashMap<ImportField, usize>;
+
+/// A file from another tracker, read but not imported yet
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportTable {
+    pub source: ImportSource,
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl ImportTable {
+    /// The columns the tracker normally exports each field in, found by their headers
+    pub fn default_mapping(&self) -> ColumnMapping {
+        let mut mapping = ColumnMapping::new();
+        for field in ImportField::ALL {
+            let column = self.source.columns(field).iter().find_map(|name| {
+                self.headers
+                    .iter()
+                    .position(|header| header_matches(header, name))
+            });
+            if let Some(column) = column {
+                mapping.insert(field, column);
+            }
+        }
+        mapping
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PreviewStatus {
+    New,
+    /// The same task is already saved, or appears earlier in the file
+    Duplicate,
+    /// The row doesn't say when the entry happened
+    Invalid,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PreviewRow {
+    pub task: Option<FurTask>,
+    pub status: PreviewStatus,
+}
+
+pub fn read(path: &Path, source: ImportSource) -> FurResult<ImportTable> {
+    let contents = fs::read_to_string(path).in_context(FurErrorContext::ImportData)?;
+    parse(&contents, source)
+}
+
+pub fn parse(contents: &str, source: ImportSource) -> FurResult<ImportTable> {
+    let contents = contents.trim_start_matches('\u{feff}');
+    match source {
+        ImportSource::Timewarrior => parse_timewarrior(contents),
+        _ => {
+            let mut rows = parse_csv(contents);
+            let headers = if rows.is_empty() {
+                Vec::new()
+            } else {
+                rows.remove(0)
+            };
+            Ok(ImportTable {
+                source,
+                headers: headers
+                    .iter()
+                    .map(|header| header.trim().to_string())
+                    .collect(),
+                rows,
+            })
+        }
+    }
+}
+
+/// The task each row would become and whether it would be imported
+pub fn preview(table: &ImportTable, mapping: &ColumnMapping) -> FurResult<Vec<PreviewRow>> {
+    let currency = header_currency(table, mapping);
+    let date_order = date_order(table, mapping);
+    let mut untimed_ends: HashMap<NaiveDate, DateTime<Local>> = HashMap::new();
+    let tasks: Vec<Option<FurTask>> = table
+        .rows
+        .iter()
+        .map(|row| task_from_row(row, mapping, &currency, date_order, &mut untimed_ends))
+        .collect();
+
+    // Compare against saved tasks once rather than querying the database for every row
+    let saved = match (
+        tasks.iter().flatten().map(|task| task.start_time).min(),
+        tasks.iter().flatten().map(|task| task.start_time).max(),
+    ) {
+        (Some(first), Some(last)) => tasks::between(first, last)?,
+        _ => Vec::new(),
+    };
+
+    let mut seen: Vec<&FurTask> = Vec::new();
+    let mut rows = Vec::new();
+    for task in &tasks {
+        let status = match task {
+            None => PreviewStatus::Invalid,
+            Some(task)
+                if saved
+                    .iter()
+                    .chain(seen.iter().copied())
+                    .any(|other| is_same(task, other)) =>
+            {
+                PreviewStatus::Duplicate
+            }
+            Some(task) => {
+                seen.push(task);
+                PreviewStatus::New
+            }
+        };
+        rows.push(PreviewRow {
+            task: task.clone(),
+            status,
+        });
+    }
+    Ok(rows)
+}
+
+/// Save the previewed tasks that aren't duplicates or invalid
+pub fn import(rows: &[PreviewRow]) -> FurResult<ImportCount> {
+    let tasks: Vec<FurTask> = rows
+        .iter()
+        .filter(|row| row.status == PreviewStatus::New)
+        .filter_map(|row| row.task.clone())
+        .collect();
+    if !tasks.is_empty() {
+        tasks::add_many(&tasks)?;
+    }
+    Ok(ImportCount {
+        imported: tasks.len(),
+        skipped: rows.len() - tasks.len(),
+    })
+}
+
+/// The same fields `database::tasks::task_exists` compares
+fn is_same(task: &FurTask, other: &FurTask) -> bool {
+    task.name == other.name
+        && task.start_time == other.start_time
+        && task.stop_time == other.stop_time
+        && task.tags == other.tags
+        && task.project == other.project
+        && task.rate == other.rate
+        && task.currency == other.currency
+}
+
+fn task_from_row(
+    row: &[String],
+    mapping: &ColumnMapping,
+    header_currency: &str,
+    date_order: DateOrder,
+    untimed_ends: &mut HashMap<NaiveDate, DateTime<Local>>,
+) -> Option<FurTask> {
+    let get = |field: ImportField| {
+        mapping
+            .get(&field)
+            .and_then(|&column| row.get(column))
+            .map(|value| value.trim())
+            .filter(|value| !value.is_empty())
+    };
+
+    let duration = get(ImportField::Duration).and_then(parse_duration);
+    let (start_time, stop_time) = match (
+        parse_moment(
+            get(ImportField::StartDate),
+            get(ImportField::StartTime),
+            date_order,
+        )?,
+        get(ImportField::EndDate).or(get(ImportField::StartDate)),
+    ) {
+        (Moment::Time(start), end_date) => {
+            let stop = match parse_moment(end_date, get(ImportField::EndTime), date_order) {
+                Some(Moment::Time(stop))
+                    if get(ImportField::EndTime).is_some()
+                        || get(ImportField::EndDate).is_some() =>
+                {
+                    // An end time alone is on the start's day, or the next if it's earlier
+                    if stop < start {
+                        stop.checked_add_signed(TimeDelta::days(1))?
+                    } else {
+                        stop
+                    }
+                }
+                // Durations too long to add make the row invalid, so it's skipped
+                _ => start.checked_add_signed(duration?)?,
+            };
+            (start, stop)
+        }
+        (Moment::Date(date), _) => {
+            let start = match untimed_ends.get(&date) {
+                Some(end) => *end,
+                None => Local
+                    .from_local_datetime(&date.and_hms_opt(UNTIMED_DAY_START_HOUR, 0, 0)?)
+                    .earliest()?,
+            };
+            let stop = start.checked_add_signed(duration?)?;
+            untimed_ends.insert(date, stop);
+            (start, stop)
+        }
+    };
+    if stop_time <= start_time {
+        return None;
+    }
+
+    let mut tags: Vec<String> = get(ImportField::Tags)
+        .map(|tags| {
+            tags.split(',')
+                .map(|tag| tag.trim().to_string())
+                .filter(|tag| !tag.is_empty())
+                .collect()
+        })
+        .unwrap_or_default();
+    let name = match (get(ImportField::Description), get(ImportField::Task)) {
+        (Some(description), task) => {
+            tags.extend(task.map(str::to_string));
+            description.to_string()
+        }
+        (None, Some(task)) => task.to_string(),
+        // Timewarrior intervals are often only tags, so the first one names the task
+        (None, None) if !tags.is_empty() => tags.remove(0),
+        (None, None) => loc!("imported-task"),
+    };
+    let project = match (get(ImportField::Client), get(ImportField::Project)) {
+        (Some(client), Some(project)) => format!("{} / {}", client, project),
+        (client, project) => client.or(project).unwrap_or_default().to_string(),
+    };
+
+    let billable = get(ImportField::Billable).is_none_or(parse_bool);
+    let hours = (stop_time - start_time).num_seconds() as f32 / 3600.0;
+    let rate = match (get(ImportField::Rate), get(ImportField::Amount)) {
+        _ if !billable => 0.0,
+        (Some(rate), _) => parse_number(rate).unwrap_or(0.0),
+        (None, Some(amount)) => parse_number(amount).map_or(0.0, |amount| amount / hours),
+        (None, None) => 0.0,
+    };
+    let currency = match get(ImportField::Currency) {
+        _ if rate <= 0.0 => String::new(),
+        Some(currency) => currency_code(currency),
+        None => header_currency.to_string(),
+    };
+
+    // Build task input so the name, project and tags are cleaned up like typed ones
+    let mut task_input = strip_task_symbols(&name);
+    if !project.is_empty() {
+        task_input += &format!(" @{}", strip_task_symbols(&project));
+    }
+    for tag in tags {
+        task_input += &format!(" #{}", strip_task_symbols(&tag));
+    }
+    let (name, project, tags, _) = formatters::split_task_input(&task_input);
+
+    Some(FurTask::new(
+        name,
+        start_time,
+        stop_time,
+        tags,
+        project,
+        (rate * 100.0).round() / 100.0,
+        currency,
+    ))
+}
+
+enum Moment {
+    Time(DateTime<Local>),
+    /// A day without a time, as Harvest exports
+    Date(NaiveDate),
+}
+
+fn parse_moment(date: Option<&str>, time: Option<&str>, order: DateOrder) -> Option<Moment> {
+    let date_value = date?;
+    if let Some(time) = time {
+        let date = parse_date(date_value, order)?;
+        let time = parse_time(time)?;
+        return Local
+            .from_local_datetime(&date.and_time(time))
+            .earliest()
+            .map(Moment::Time);
+    }
+    if let Some(time) = parse_date_time(date_value) {
+        return Some(Moment::Time(time));
+    }
+    parse_date(date_value, order).map(Moment::Date)
+}
+
+fn parse_date(value: &str, order: DateOrder) -> Option<NaiveDate> {
+    DATE_FORMATS
+        .iter()
+        .chain([order.slash_format()].iter())
+        .find_map(|format| NaiveDate::parse_from_str(value, format).ok())
+}
+
+/// US exports put the month first, so slash dates are read that way unless one of the file's
+/// dates starts with a number that can only be a day
+fn date_order(table: &ImportTable, mapping: &ColumnMapping) -> DateOrder {
+    let day_first = [ImportField::StartDate, ImportField::EndDate]
+        .iter()
+        .filter_map(|field| mapping.get(field))
+        .flat_map(|&column| table.rows.iter().filter_map(move |row| row.get(column)))
+        .any(|value| {
+            let mut parts = value.trim().split('/');
+            let first = parts.next().filter(|first| first.len() <= 2);
+            parts.count() == 2 && first.and_then(|first| first.parse::<u32>().ok()) > Some(12)
+        });
+    if day_first {
+        DateOrder::DayFirst
+    } else {
+        DateOrder::MonthFirst
+    }
+}
+
+fn parse_time(value: &str) -> Option<NaiveTime> {
+    let value = value.to_uppercase();
+    TIME_FORMATS
+        .iter()
+        .find_map(|format| NaiveTime::parse_from_str(&value, format).ok())
+        .map(|time| time.with_nanosecond(0).unwrap_or(time))
+}
+
+fn parse_date_time(value: &str) -> Option<DateTime<Local>> {
+    if let Ok(time) = NaiveDateTime::parse_from_str(value, TIMEWARRIOR_TIME_FORMAT) {
+        return Some(Utc.from_utc_datetime(&time).with_timezone(&Local));
+    }
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Some(time.with_timezone(&Local));
+    }
+    DATE_TIME_FORMATS.iter().find_map(|format| {
+        NaiveDateTime::parse_from_str(value, format)
+            .ok()
+            .and_then(|time| Local.from_local_datetime(&time).earliest())
+    })
+}
+
+/// "1:30:00", "1:30" or decimal hours such as "1.5"
+fn parse_duration(value: &str) -> Option<TimeDelta> {
+    let parts: Vec<&str> = value.split(':').collect();
+    let clock = |hours: &str, minutes: &str, seconds: &str| {
+        hours
+            .parse::<i64>()
+            .ok()?
+            .checked_mul(3600)?
+            .checked_add(minutes.parse::<i64>().ok()?.checked_mul(60)?)?
+            .checked_add(seconds.parse::<i64>().ok()?)
+    };
+    let seconds = match parts.as_slice() {
+        [hours, minutes, seconds] => clock(hours, minutes, seconds)?,
+        [hours, minutes] => clock(hours, minutes, "0")?,
+        [hours] => {
+            let seconds = (parse_number(hours)? * 3600.0).round();
+            // `as` saturates, which would turn a huge number into a valid looking one
+            if !seconds.is_finite() || seconds >= i64::MAX as f32 {
+                return None;
+            }
+            seconds as i64
+        }
+        _ => return None,
+    };
+    TimeDelta::try_seconds(seconds).filter(|_| seconds > 0)
+}
+
+/// A number that may use a decimal comma or carry a currency symbol
+fn parse_number(value: &str) -> Option<f32> {
+    let cleaned: String = value
+        .chars()
+        .filter(|character| character.is_ascii_digit() || matches!(character, '.' | ',' | '-'))
+        .collect();
+    let cleaned = if cleaned.contains('.') {
+        cleaned.replace(',', "")
+    } else {
+        cleaned.replace(',', ".")
+    };
+    cleaned.parse().ok()
+}
+
+fn parse_bool(value: &str) -> bool {
+    !matches!(
+        value.to_lowercase().as_str(),
+        "no" | "false" | "0" | "non-billable"
+    )
+}
+
+/// "USD", or the code at the end of a name such as Harvest's "US Dollar - USD"
+fn currency_code(value: &str) -> String {
+    value
+        .rsplit(" - ")
+        .next()
+        .unwrap_or(value)
+        .trim()
+        .to_string()
+}
+
+/// The currency in the header of the rate or amount column, e.g. "Billable Rate (USD)"
+fn header_currency(table: &ImportTable, mapping: &ColumnMapping) -> String {
+    [ImportField::Rate, ImportField::Amount]
+        .iter()
+        .filter_map(|field| mapping.get(field))
+        .filter_map(|&column| table.headers.get(column))
+        .find_map(|header| {
+            let (_, suffix) = header.rsplit_once('(')?;
+            let code = suffix.strip_suffix(')')?.trim();
+            (!code.is_empty()).then(|| code.to_string())
+        })
+        .unwrap_or_default()
+}
+
+/// Headers match ignoring case, and may add a unit such as " (USD)" after the name
+fn header_matches(header: &str, name: &str) -> bool {
+    let header = header.trim().to_lowercase();
+    let name = name.to_lowercase();
+    header == name || header.starts_with(&format!("{} (", name))
+}
+
+/// Remove the characters that start a project, tag or rate in task input
+fn strip_task_symbols(text: &str) -> String {
+    text.replace(['@', '#', '$'], "")
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// Rows of quoted or unquoted fields, separated by commas or, failing that, semicolons
+fn parse_csv(contents: &str) -> Vec<Vec<String>> {
+    let first_line = contents.lines().next().unwrap_or_default();
+    let separator = if first_line.matches(';').count() > first_line.matches(',').count() {
+        ';'
+    } else {
+        ','
+    };
+
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut characters = contents.chars().peekable();
+    while let Some(character) = characters.next() {
+        match character {
+            '"' if in_quotes && characters.peek() == Some(&'"') => {
+                field.push('"');
+                characters.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            _ if in_quotes => field.push(character),
+            '\r' => {}
+            '\n' => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            _ if character == separator => row.push(std::mem::take(&mut field)),
+            _ => field.push(character),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+
+    rows.retain(|row| row.iter().any(|field| !field.trim().is_empty()));
+    rows
+}
+
+/// `timew export` prints a JSON array of intervals with start, end, tags and an annotation
+fn parse_timewarrior(contents: &str) -> FurResult<ImportTable> {
+    let intervals: Vec<Value> =
+        serde_json::from_str(contents).in_context(FurErrorContext::ImportData)?;
+    let text = |interval: &Value, key: &str| {
+        interval
+            .get(key)
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string()
+    };
+
+    let rows = intervals
+        .iter()
+        .map(|interval| {
+            let tags = interval
+                .get("tags")
+                .and_then(Value::as_array)
+                .map(|tags| {
+                    tags.iter()
+                        .filter_map(Value::as_str)
+                        .collect::<Vec<&str>>()
+                        .join(", ")
+                })
+                .unwrap_or_default();
+            vec![
+                text(interval, "start"),
+                text(interval, "end"),
+                tags,
+                text(interval, "annotation"),
+            ]
+        })
+        .collect();
+
+    Ok(ImportTable {
+        source: ImportSource::Timewarrior,
+        headers: ["start", "end", "tags", "annotation"]
+            .iter()
+            .map(|header| header.to_string())
+            .collect(),
+        rows,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Datelike;
+
+    use super::*;
+    use crate::helpers::test_data;
+
+    const TOGGL_CSV: &str = "User,Email,Client,Project,Task,Description,Billable,Start date,\
+        Start time,End date,End time,Duration,Tags,Amount (USD)\r\n\
+        Jo,jo@example.com,Acme,Website,Design,\"Homepage, hero\",Yes,2025-03-04,09:00:00,\
+        2025-03-04,10:30:00,01:30:00,\"ux, web\",150.00\r\n\
+        Jo,jo@example.com,,,,Late call,No,2025-03-04,23:30:00,,00:15:00,,,\r\n";
+
+    fn preview_csv(contents: &str, source: ImportSource) -> Vec<PreviewRow> {
+        let table = parse(contents, source).unwrap();
+        preview(&table, &table.default_mapping()).unwrap()
+    }
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+        Local
+            .with_ymd_and_hms(2025, 3, day, hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn csv_fields_keep_quoted_separators_quotes_and_newlines() {
+        let rows = parse_csv("a,\"b, c\",\"say \"\"hi\"\"\"\r\n\r\n\"two\nlines\",,x");
+
+        assert_eq!(
+            rows,
+            vec![vec!["a", "b, c", "say \"hi\""], vec!["two\nlines", "", "x"],]
+        );
+    }
+
+    #[test]
+    fn csv_uses_semicolons_when_the_header_does() {
+        let rows = parse_csv("Description;Amount\nLunch;12,50\n");
+
+        assert_eq!(
+            rows,
+            vec![vec!["Description", "Amount"], vec!["Lunch", "12,50"]]
+        );
+    }
+
+    #[test]
+    fn durations_read_clock_and_decimal_hours() {
+        assert_eq!(parse_duration("1:30:15"), Some(TimeDelta::seconds(5415)));
+        assert_eq!(parse_duration("2:05"), Some(TimeDelta::minutes(125)));
+        assert_eq!(parse_duration("1.5"), Some(TimeDelta::minutes(90)));
+        assert_eq!(parse_duration("0,25"), Some(TimeDelta::minutes(15)));
+        assert_eq!(parse_duration("0:00"), None);
+        assert_eq!(parse_duration("soon"), None);
+    }
+
+    #[test]
+    fn durations_too_long_to_add_skip_the_row() {
+        assert_eq!(parse_duration("9999999999999999:00:00"), None);
+        assert_eq!(parse_duration("99999999999999999999"), None);
+        assert_eq!(parse_duration("9999999999999"), None);
+
+        let _data = test_data::fresh_database();
+        let csv = "Date,Client,Project,Task,Notes,Hours,Billable?\n\
+            2025-03-04,,Site,,Forever,99999999999,No\n\
+            2025-03-04,,Site,,Copy,2,No\n";
+        let rows = preview_csv(csv, ImportSource::Harvest);
+
+        assert_eq!(rows[0].status, PreviewStatus::Invalid);
+        assert_eq!(rows[1].status, PreviewStatus::New);
+        assert_eq!(
+            import(&rows).unwrap(),
+            ImportCount {
+                imported: 1,
+                skipped: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn slash_dates_read_the_files_day_and_month_order() {
+        let _data = test_data::fresh_database();
+        let csv = |dates: [&str; 2]| {
+            format!(
+                "Description,Start date,Start time,End time\n\
+                First,{},09:00,10:00\n\
+                Second,{},09:00,10:00\n",
+                dates[0], dates[1]
+            )
+        };
+        let days = |contents: &str| -> Vec<(u32, u32)> {
+            preview_csv(contents, ImportSource::Clockify)
+                .iter()
+                .map(|row| {
+                    let start = row.task.as_ref().unwrap().start_time;
+                    (start.month(), start.day())
+                })
+                .collect()
+        };
+
+        // A US export, where only the second date shows the month comes first
+        assert_eq!(
+            days(&csv(["03/04/2025", "03/14/2025"])),
+            vec![(3, 4), (3, 14)]
+        );
+        // A European export, where the second date shows the day comes first
+        assert_eq!(
+            days(&csv(["03/04/2025", "14/03/2025"])),
+            vec![(4, 3), (3, 14)]
+        );
+    }
+
+    #[test]
+    fn toggl_rows_become_tasks() {
+        let _data = test_data::fresh_database();
+        let rows = preview_csv(TOGGL_CSV, ImportSource::Toggl);
+
+        let task = rows[0].task.as_ref().unwrap();
+        assert_eq!(task.name, "Homepage, hero");
+        assert_eq!(task.project, "Acme / Website");
+        assert_eq!(task.tags, "design #ux #web");
+        assert_eq!(
+            (task.start_time, task.stop_time),
+            (at(4, 9, 0), at(4, 10, 30))
+        );
+        assert_eq!(task.rate, 100.0);
+        assert_eq!(task.currency, "USD");
+
+        // An end time earlier than the start is on the next day, and unbillable means no rate
+        let late = rows[1].task.as_ref().unwrap();
+        assert_eq!(
+            (late.start_time, late.stop_time),
+            (at(4, 23, 30), at(5, 0, 15))
+        );
+        assert_eq!(late.rate, 0.0);
+        assert!(late.currency.is_empty());
+    }
+
+    #[test]
+    fn untimed_rows_are_laid_out_back_to_back() {
+        let _data = test_data::fresh_database();
+        let csv = "Date,Client,Project,Task,Notes,Hours,Billable?\n\
+            2025-03-04,,Site,,Copy,2,No\n\
+            2025-03-04,,Site,,Review,1.5,No\n\
+            ,,Site,,Undated,1,No\n";
+
+        let rows = preview_csv(csv, ImportSource::Harvest);
+
+        let times: Vec<_> = rows
+            .iter()
+            .map(|row| {
+                row.task
+                    .as_ref()
+                    .map(|task| (task.start_time, task.stop_time))
+            })
+            .collect();
+        assert_eq!(
+            times,
+            vec![
+                Some((at(4, 9, 0), at(4, 11, 0))),
+                Some((at(4, 11, 0), at(4, 12, 30))),
+                None,
+            ]
+        );
+        assert_eq!(rows[2].status, PreviewStatus::Invalid);
+    }
+
+    #[test]
+    fn saved_and_repeated_tasks_are_duplicates() {
+        let _data = test_data::fresh_database();
+        let first_row = TOGGL_CSV.lines().nth(1).unwrap();
+        let csv = format!("{}\r\n{}", TOGGL_CSV, first_row);
+
+        let rows = preview_csv(&csv, ImportSource::Toggl);
+        let statuses: Vec<_> = rows.iter().map(|row| row.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                PreviewStatus::New,
+                PreviewStatus::New,
+                PreviewStatus::Duplicate,
+            ]
+        );
+        assert_eq!(
+            import(&rows).unwrap(),
+            ImportCount {
+                imported: 2,
+                skipped: 1,
+            }
+        );
+
+        // Importing the same file again finds everything already saved
+        let rows = preview_csv(&csv, ImportSource::Toggl);
+        assert!(rows
+            .iter()
+            .all(|row| row.status == PreviewStatus::Duplicate));
+    }
+}
@@ -0,0 +1,611 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Invoices built from a project's billable tasks. Issuing one marks the tasks it billed with
+//! its number, so the marks sync and travel in archives and later invoices leave them out.
+
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{Local, NaiveDate};
+
+use crate::{
+    database,
+    helpers::{
+        formatters::escape_html,
+        pdf::{self, PdfDocument, PdfFont, PAGE_HEIGHT, PAGE_WIDTH},
+    },
+    loc,
+    localization::Localization,
+    models::{
+        fur_error::{ErrorContext, FurErrorContext, FurResult},
+        fur_invoice::{FurInvoice, FurInvoiceLine, FurInvoiceTax, FurTaxLine, InvoiceGrouping},
+        fur_settings::FurSettings,
+        fur_task::FurTask,
+    },
+};
+
+use super::{settings, tasks};
+
+const PDF_MARGIN: f32 = 50.0;
+/// Where the hours, rate and amount columns end on a PDF page
+const PDF_HOURS_RIGHT: f32 = 360.0;
+const PDF_RATE_RIGHT: f32 = 450.0;
+const PDF_AMOUNT_RIGHT: f32 = PAGE_WIDTH - PDF_MARGIN;
+const PDF_TEXT_SIZE: f32 = 10.0;
+const PDF_LINE_HEIGHT: f32 = 14.0;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct InvoiceOptions {
+    pub project: String,
+    pub first_day: NaiveDate,
+    pub last_day: NaiveDate,
+    pub grouping: InvoiceGrouping,
+    /// Each line's time is rounded up to a multiple of this many minutes, or not at all if 0
+    pub rounding_minutes: i64,
+    /// Only tasks in this currency, or without one, are billed. Empty picks the most used one.
+    pub currency: String,
+    pub client: String,
+    pub tax_lines: Vec<FurTaxLine>,
+}
+
+/// The invoice the options would make, numbered with the next unused invoice number
+pub fn build(options: &InvoiceOptions, settings: &FurSettings) -> FurResult<FurInvoice> {
+    let invoiced =
+        database::invoices::retrieve_invoiced_task_uids().in_context(FurErrorContext::LoadTasks)?;
+    let project_tasks: Vec<FurTask> = tasks::on_dates(options.first_day, options.last_day)?
+        .into_iter()
+        .filter(|task| task.project.eq_ignore_ascii_case(&options.project) && task.rate > 0.0)
+        .collect();
+    let currency = if options.currency.trim().is_empty() {
+        most_used_currency(&project_tasks)
+    } else {
+        options.currency.trim().to_string()
+    };
+    let (billable, already_invoiced): (Vec<FurTask>, Vec<FurTask>) = project_tasks
+        .into_iter()
+        .filter(|task| task.currency.is_empty() || task.currency.eq_ignore_ascii_case(&currency))
+        .partition(|task| !invoiced.contains(&task.uid));
+
+    // Tasks with the same description but different rates become separate lines
+    let mut groups: BTreeMap<(String, i64), (f32, i64)> = BTreeMap::new();
+    for task in &billable {
+        let description = match options.grouping {
+            InvoiceGrouping::TaskName => task.name.clone(),
+            InvoiceGrouping::Tag => task
+                .tags
+                .split(" #")
+                .find(|tag| !tag.is_empty())
+                .map(str::to_string)
+                .unwrap_or_else(|| loc!("untagged")),
+        };
+        let rate_cents = (task.rate * 100.0).round() as i64;
+        let group = groups
+            .entry((description, rate_cents))
+            .or_insert((task.rate, 0));
+        group.1 += task.total_time_in_seconds();
+    }
+
+    let lines: Vec<FurInvoiceLine> = groups
+        .into_iter()
+        .map(|((description, _), (rate, seconds))| {
+            let hours = round_seconds(seconds, options.rounding_minutes) as f64 / 3600.0;
+            FurInvoiceLine {
+                description,
+                hours,
+                rate,
+                amount: round_cents(hours * rate as f64),
+            }
+        })
+        .collect();
+    let subtotal = round_cents(lines.iter().map(|line| line.amount).sum());
+    let taxes: Vec<FurInvoiceTax> = options
+        .tax_lines
+        .iter()
+        .map(|tax| FurInvoiceTax {
+            name: tax.name.clone(),
+            percent: tax.percent,
+            amount: round_cents(subtotal * tax.percent as f64 / 100.0),
+        })
+        .collect();
+    let total = round_cents(subtotal + taxes.iter().map(|tax| tax.amount).sum::<f64>());
+
+    let (sequence, number) = next_number(settings)?;
+    Ok(FurInvoice {
+        number,
+        sequence,
+        issued: Local::now().date_naive(),
+        sender: settings.invoice_sender.clone(),
+        client: options.client.clone(),
+        project: options.project.clone(),
+        first_day: options.first_day,
+        last_day: options.last_day,
+        currency,
+        lines,
+        subtotal,
+        taxes,
+        total,
+        task_uids: billable.into_iter().map(|task| task.uid).collect(),
+        already_invoiced: already_invoiced.len(),
+    })
+}
+
+/// Mark the invoice's tasks as invoiced, move the number sequence past it and remember the
+/// client's details for the project's next invoice
+pub fn issue(invoice: &FurInvoice) -> FurResult<()> {
+    database::invoices::insert_invoice(invoice).in_context(FurErrorContext::ExportData)?;
+    settings::update(|settings| {
+        settings.change_invoice_next_number(&(invoice.sequence + 1))?;
+        settings.change_invoice_client(&invoice.project, &invoice.client)
+    })?;
+    Ok(())
+}
+
+/// Projects with billable tasks from `first_day` through `last_day`, sorted by name
+pub fn billable_projects(first_day: NaiveDate, last_day: NaiveDate) -> FurResult<Vec<String>> {
+    let mut projects: Vec<String> = tasks::on_dates(first_day, last_day)?
+        .into_iter()
+        .filter(|task| task.rate > 0.0 && !task.project.is_empty())
+        .map(|task| task.project)
+        .collect();
+    projects.sort_by_key(|project| project.to_lowercase());
+    projects.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
+    Ok(projects)
+}
+
+pub fn file_name(invoice: &FurInvoice, extension: &str) -> String {
+    let number: String = invoice
+        .number
+        .chars()
+        .map(|character| {
+            if character.is_alphanumeric() || character == '-' {
+                character
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("invoice-{}.{}", number, extension)
+}
+
+pub fn to_html(invoice: &FurInvoice) -> String {
+    let mut rows = String::new();
+    for line in &invoice.lines {
+        rows += &format!(
+            "<tr><td>{}</td><td class=\"number\">{}</td><td class=\"number\">{}</td><td class=\"number\">{}</td></tr>\n",
+            escape_html(&line.description),
+            format_hours(line.hours),
+            escape_html(&format_money(line.rate as f64, &invoice.currency)),
+            escape_html(&format_money(line.amount, &invoice.currency)),
+        );
+    }
+    let mut totals = total_row(
+        &loc!("subtotal"),
+        invoice.subtotal,
+        &invoice.currency,
+        false,
+    );
+    for tax in &invoice.taxes {
+        totals += &total_row(&tax_label(tax), tax.amount, &invoice.currency, false);
+    }
+    totals += &total_row(&loc!("total"), invoice.total, &invoice.currency, true);
+
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{title} {number}</title>
+<style>
+body {{ font-family: Helvetica, Arial, sans-serif; color: #222; max-width: 50rem; margin: 2rem auto; padding: 0 1rem; }}
+.parties {{ display: flex; justify-content: space-between; gap: 2rem; margin: 2rem 0; }}
+.parties p {{ white-space: pre-line; margin: 0.25rem 0 0; }}
+table {{ width: 100%; border-collapse: collapse; }}
+th, td {{ padding: 0.4rem 0; text-align: left; border-bottom: 1px solid #ddd; }}
+.number {{ text-align: right; }}
+.total td {{ font-weight: bold; border-bottom: none; }}
+</style>
+</head>
+<body>
+<h1>{title} {number}</h1>
+<p>{issued_label}: {issued}<br>{period_label}: {first_day} – {last_day}<br>{project_label}: {project}</p>
+<div class="parties">
+<div><strong>{from_label}</strong><p>{sender}</p></div>
+<div><strong>{bill_to_label}</strong><p>{client}</p></div>
+</div>
+<table>
+<tr><th>{description_label}</th><th class="number">{hours_label}</th><th class="number">{rate_label}</th><th class="number">{amount_label}</th></tr>
+{rows}{totals}</table>
+</body>
+</html>
+"#,
+        title = escape_html(&loc!("invoice")),
+        number = escape_html(&invoice.number),
+        issued_label = escape_html(&loc!("invoice-date")),
+        issued = invoice.issued,
+        period_label = escape_html(&loc!("invoice-period")),
+        first_day = invoice.first_day,
+        last_day = invoice.last_day,
+        project_label = escape_html(&loc!("project")),
+        project = escape_html(&invoice.project),
+        from_label = escape_html(&loc!("from")),
+        sender = escape_html(&invoice.sender),
+        bill_to_label = escape_html(&loc!("bill-to")),
+        client = escape_html(&invoice.client),
+        description_label = escape_html(&loc!("description")),
+        hours_label = escape_html(&loc!("hours")),
+        rate_label = escape_html(&loc!("rate")),
+        amount_label = escape_html(&loc!("amount")),
+        rows = rows,
+        totals = totals,
+    )
+}
+
+pub fn to_pdf(invoice: &FurInvoice) -> Vec<u8> {
+    let mut document = PdfDocument::new();
+    let mut y = PAGE_HEIGHT - PDF_MARGIN - 10.0;
+
+    document.text(
+        PDF_MARGIN,
+        y,
+        22.0,
+        PdfFont::Bold,
+        &format!("{} {}", loc!("invoice"), invoice.number),
+    );
+    y -= 30.0;
+    for line in [
+        format!("{}: {}", loc!("invoice-date"), invoice.issued),
+        format!(
+            "{}: {} – {}",
+            loc!("invoice-period"),
+            invoice.first_day,
+            invoice.last_day
+        ),
+        format!("{}: {}", loc!("project"), invoice.project),
+    ] {
+        document.text(PDF_MARGIN, y, PDF_TEXT_SIZE, PdfFont::Regular, &line);
+        y -= PDF_LINE_HEIGHT;
+    }
+
+    // Sender and client side by side
+    y -= PDF_LINE_HEIGHT;
+    let column_width = (PAGE_WIDTH - PDF_MARGIN * 2.0) / 2.0 - 10.0;
+    let client_x = PAGE_WIDTH / 2.0;
+    document.text(PDF_MARGIN, y, PDF_TEXT_SIZE, PdfFont::Bold, &loc!("from"));
+    document.text(client_x, y, PDF_TEXT_SIZE, PdfFont::Bold, &loc!("bill-to"));
+    let sender = pdf::wrap(
+        &invoice.sender,
+        PDF_TEXT_SIZE,
+        PdfFont::Regular,
+        column_width,
+    );
+    let client = pdf::wrap(
+        &invoice.client,
+        PDF_TEXT_SIZE,
+        PdfFont::Regular,
+        column_width,
+    );
+    for index in 0..sender.len().max(client.len()) {
+        y -= PDF_LINE_HEIGHT;
+        if let Some(line) = sender.get(index) {
+            document.text(PDF_MARGIN, y, PDF_TEXT_SIZE, PdfFont::Regular, line);
+        }
+        if let Some(line) = client.get(index) {
+            document.text(client_x, y, PDF_TEXT_SIZE, PdfFont::Regular, line);
+        }
+    }
+
+    y -= PDF_LINE_HEIGHT * 2.5;
+    pdf_table_header(&mut document, y);
+    y -= PDF_LINE_HEIGHT;
+    let description_width = PDF_HOURS_RIGHT - PDF_MARGIN - 60.0;
+    for line in &invoice.lines {
+        let description = pdf::wrap(
+            &line.description,
+            PDF_TEXT_SIZE,
+            PdfFont::Regular,
+            description_width,
+        );
+        if y - PDF_LINE_HEIGHT * (description.len() as f32) < PDF_MARGIN {
+            document.new_page();
+            y = PAGE_HEIGHT - PDF_MARGIN;
+            pdf_table_header(&mut document, y);
+            y -= PDF_LINE_HEIGHT;
+        }
+        y -= PDF_LINE_HEIGHT;
+        document.text_right(
+            PDF_HOURS_RIGHT,
+            y,
+            PDF_TEXT_SIZE,
+            PdfFont::Regular,
+            &format_hours(line.hours),
+        );
+        document.text_right(
+            PDF_RATE_RIGHT,
+            y,
+            PDF_TEXT_SIZE,
+            PdfFont::Regular,
+            &format_money(line.rate as f64, &invoice.currency),
+        );
+        document.text_right(
+            PDF_AMOUNT_RIGHT,
+            y,
+            PDF_TEXT_SIZE,
+            PdfFont::Regular,
+            &format_money(line.amount, &invoice.currency),
+        );
+        for (index, text) in description.iter().enumerate() {
+            if index > 0 {
+                y -= PDF_LINE_HEIGHT;
+            }
+            document.text(PDF_MARGIN, y, PDF_TEXT_SIZE, PdfFont::Regular, text);
+        }
+    }
+
+    let mut totals: Vec<(String, f64, PdfFont)> =
+        vec![(loc!("subtotal"), invoice.subtotal, PdfFont::Regular)];
+    totals.extend(
+        invoice
+            .taxes
+            .iter()
+            .map(|tax| (tax_label(tax), tax.amount, PdfFont::Regular)),
+    );
+    totals.push((loc!("total"), invoice.total, PdfFont::Bold));
+    if y - PDF_LINE_HEIGHT * (totals.len() as f32 + 1.0) < PDF_MARGIN {
+        document.new_page();
+        y = PAGE_HEIGHT - PDF_MARGIN;
+    }
+    y -= PDF_LINE_HEIGHT / 2.0;
+    document.line(PDF_RATE_RIGHT - 140.0, y, PDF_AMOUNT_RIGHT, y);
+    for (label, amount, font) in totals {
+        y -= PDF_LINE_HEIGHT;
+        document.text_right(PDF_RATE_RIGHT, y, PDF_TEXT_SIZE, font, &label);
+        document.text_right(
+            PDF_AMOUNT_RIGHT,
+            y,
+            PDF_TEXT_SIZE,
+            font,
+            &format_money(amount, &invoice.currency),
+        );
+    }
+
+    document.finish()
+}
+
+pub fn format_money(amount: f64, currency: &str) -> String {
+    format!("{:.2} {}", amount, currency).trim_end().to_string()
+}
+
+pub fn format_hours(hours: f64) -> String {
+    format!("{:.2}", hours)
+}
+
+pub fn tax_label(tax: &FurInvoiceTax) -> String {
+    format!("{} {}%", tax.name, tax.percent).trim().to_string()
+}
+
+fn pdf_table_header(document: &mut PdfDocument, y: f32) {
+    document.text(
+        PDF_MARGIN,
+        y,
+        PDF_TEXT_SIZE,
+        PdfFont::Bold,
+        &loc!("description"),
+    );
+    document.text_right(
+        PDF_HOURS_RIGHT,
+        y,
+        PDF_TEXT_SIZE,
+        PdfFont::Bold,
+        &loc!("hours"),
+    );
+    document.text_right(
+        PDF_RATE_RIGHT,
+        y,
+        PDF_TEXT_SIZE,
+        PdfFont::Bold,
+        &loc!("rate"),
+    );
+    document.text_right(
+        PDF_AMOUNT_RIGHT,
+        y,
+        PDF_TEXT_SIZE,
+        PdfFont::Bold,
+        &loc!("amount"),
+    );
+    document.line(PDF_MARGIN, y - 5.0, PDF_AMOUNT_RIGHT, y - 5.0);
+}
+
+fn total_row(label: &str, amount: f64, currency: &str, is_total: bool) -> String {
+    format!(
+        "<tr{}><td colspan=\"3\" class=\"number\">{}</td><td class=\"number\">{}</td></tr>\n",
+        if is_total { " class=\"total\"" } else { "" },
+        escape_html(label),
+        escape_html(&format_money(amount, currency)),
+    )
+}
+
+/// The first invoice number from the sequence that no issued invoice or billed task uses
+fn next_number(settings: &FurSettings) -> FurResult<(i64, String)> {
+    let mut sequence = settings.invoice_next_number.max(1);
+    loop {
+        let number = format!("{}{:04}", settings.invoice_number_prefix, sequence);
+        if !database::invoices::invoice_exists(&number).in_context(FurErrorContext::LoadTasks)? {
+            return Ok((sequence, number));
+        }
+        sequence += 1;
+    }
+}
+
+fn most_used_currency(tasks: &[FurTask]) -> String {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for task in tasks.iter().filter(|task| !task.currency.is_empty()) {
+        *counts.entry(task.currency.as_str()).or_default() += 1;
+    }
+    counts
+        .into_iter()
+        .max_by_key(|(currency, count)| (*count, std::cmp::Reverse(*currency)))
+        .map(|(currency, _)| currency.to_string())
+        .unwrap_or_default()
+}
+
+/// Round up to a whole number of `minutes`, leaving the time as it is if `minutes` is 0
+fn round_seconds(seconds: i64, minutes: i64) -> i64 {
+    if minutes <= 0 {
+        return seconds;
+    }
+    let step = minutes * 60;
+    (seconds + step - 1) / step * step
+}
+
+fn round_cents(amount: f64) -> f64 {
+    (amount * 100.0).round() / 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, TimeZone};
+
+    use super::*;
+    use crate::{helpers::test_data, services::archive};
+
+    fn day() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 3, 4).unwrap()
+    }
+
+    fn at(hour: u32, minute: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2025, 3, 4, hour, minute, 0).unwrap()
+    }
+
+    fn add_task(
+        name: &str,
+        project: &str,
+        start: (u32, u32),
+        minutes: i64,
+        rate: f32,
+        currency: &str,
+    ) {
+        let start = at(start.0, start.1);
+        tasks::add(&FurTask::new(
+            name.to_string(),
+            start,
+            start + chrono::TimeDelta::minutes(minutes),
+            String::new(),
+            project.to_string(),
+            rate,
+            currency.to_string(),
+        ))
+        .unwrap();
+    }
+
+    fn add_acme_tasks() {
+        add_task("Design", "Acme", (9, 0), 60, 80.0, "USD");
+        add_task("Design", "acme", (10, 0), 30, 80.0, "USD");
+        add_task("Design", "Acme", (11, 0), 60, 100.0, "");
+        add_task("Support", "Acme", (13, 0), 20, 60.0, "USD");
+        add_task("Design", "Acme", (14, 0), 60, 90.0, "EUR");
+        add_task("Design", "Other", (15, 0), 60, 80.0, "USD");
+        add_task("Unbilled", "Acme", (16, 0), 60, 0.0, "");
+    }
+
+    fn options() -> InvoiceOptions {
+        InvoiceOptions {
+            project: "Acme".to_string(),
+            first_day: day(),
+            last_day: day(),
+            grouping: InvoiceGrouping::TaskName,
+            rounding_minutes: 15,
+            currency: String::new(),
+            client: "Acme Inc.".to_string(),
+            tax_lines: vec![
+                FurTaxLine {
+                    name: "VAT".to_string(),
+                    percent: 20.0,
+                },
+                FurTaxLine {
+                    name: "City".to_string(),
+                    percent: 1.5,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn seconds_round_up_to_whole_steps() {
+        assert_eq!(round_seconds(61, 0), 61);
+        assert_eq!(round_seconds(61, 15), 900);
+        assert_eq!(round_seconds(900, 15), 900);
+        assert_eq!(round_seconds(901, 15), 1800);
+    }
+
+    #[test]
+    fn lines_group_by_name_and_rate_then_add_taxes() {
+        let _data = test_data::fresh_database();
+        add_acme_tasks();
+
+        let invoice = build(&options(), &settings::load()).unwrap();
+
+        assert_eq!(invoice.currency, "USD");
+        let lines: Vec<(&str, f64, f64)> = invoice
+            .lines
+            .iter()
+            .map(|line| (line.description.as_str(), line.hours, line.amount))
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                ("Design", 1.5, 120.0),
+                ("Design", 1.0, 100.0),
+                ("Support", 0.5, 30.0),
+            ]
+        );
+        assert_eq!(invoice.subtotal, 250.0);
+        let taxes: Vec<f64> = invoice.taxes.iter().map(|tax| tax.amount).collect();
+        assert_eq!(taxes, vec![50.0, 3.75]);
+        assert_eq!(invoice.total, 303.75);
+        assert_eq!(invoice.task_uids.len(), 4);
+    }
+
+    #[test]
+    fn issued_invoices_use_up_their_number_and_tasks() {
+        let data = test_data::fresh_database();
+        add_acme_tasks();
+        let first = build(&options(), &settings::load()).unwrap();
+        assert_eq!(first.number, "INV-0001");
+
+        issue(&first).unwrap();
+        let second = build(&options(), &settings::load()).unwrap();
+        assert_eq!(second.number, "INV-0002");
+        assert!(second.lines.is_empty());
+        assert_eq!(second.already_invoiced, 4);
+
+        // A sequence that fell behind, e.g. from older settings, skips numbers already issued
+        let mut behind = settings::load();
+        behind.invoice_next_number = 1;
+        assert_eq!(next_number(&behind).unwrap(), (2, "INV-0002".to_string()));
+
+        // The marks are saved on the tasks, so they travel with synced tasks and archives
+        let json = archive::to_json(&archive::create(&settings::load()).unwrap()).unwrap();
+        drop(data);
+        let _data = test_data::fresh_database();
+        archive::import(&archive::parse(&json).unwrap(), false).unwrap();
+        let after_import = build(&options(), &settings::load()).unwrap();
+        assert!(after_import.lines.is_empty());
+        assert_eq!(after_import.already_invoiced, 4);
+
+        // Only the tasks know about the invoice now, and that still keeps its number taken
+        assert_eq!(next_number(&behind).unwrap(), (2, "INV-0002".to_string()));
+    }
+}
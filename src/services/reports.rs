@@ -0,0 +1,90 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::fur_task::FurTask;
+
+/// What the rows of a summary are split by
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SummaryGrouping {
+    #[default]
+    Project,
+    /// A task with several tags counts toward each of them
+    Tag,
+    Day,
+    Task,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SummaryRow {
+    /// Project, tag, YYYY-MM-DD date or task name. Empty for tasks without one.
+    pub label: String,
+    pub seconds: i64,
+    pub earnings: f32,
+    pub task_count: usize,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Summary {
+    pub total_seconds: i64,
+    pub total_earnings: f32,
+    pub task_count: usize,
+    pub rows: Vec<SummaryRow>,
+}
+
+/// Time and earnings per group. Days are in date order, everything else longest first.
+pub fn summarize(tasks: &[FurTask], grouping: SummaryGrouping) -> Summary {
+    let mut rows: BTreeMap<String, SummaryRow> = BTreeMap::new();
+    for task in tasks {
+        for label in labels(task, grouping) {
+            let row = rows.entry(label.clone()).or_insert(SummaryRow {
+                label,
+                seconds: 0,
+                earnings: 0.0,
+                task_count: 0,
+            });
+            row.seconds += task.total_time_in_seconds();
+            row.earnings += task.total_earnings();
+            row.task_count += 1;
+        }
+    }
+
+    let mut rows: Vec<SummaryRow> = rows.into_values().collect();
+    if grouping != SummaryGrouping::Day {
+        rows.sort_by_key(|row| std::cmp::Reverse(row.seconds));
+    }
+
+    Summary {
+        total_seconds: tasks.iter().map(|task| task.total_time_in_seconds()).sum(),
+        total_earnings: tasks.iter().map(|task| task.total_earnings()).sum(),
+        task_count: tasks.len(),
+        rows,
+    }
+}
+
+fn labels(task: &FurTask, grouping: SummaryGrouping) -> Vec<String> {
+    match grouping {
+        SummaryGrouping::Project => vec![task.project.clone()],
+        SummaryGrouping::Tag if task.tags.is_empty() => vec![String::new()],
+        SummaryGrouping::Tag => task.tags.split(" #").map(str::to_string).collect(),
+        SummaryGrouping::Day => vec![task.start_time.date_naive().to_string()],
+        SummaryGrouping::Task => vec![task.name.clone()],
+    }
+}
@@ -0,0 +1,46 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use log::error;
+
+use crate::{
+    events::{self, CoreEvent},
+    models::{
+        fur_error::{ErrorContext, FurErrorContext, FurResult},
+        fur_settings::FurSettings,
+    },
+};
+
+/// Settings as saved on disk, or the defaults if they can't be read
+pub fn load() -> FurSettings {
+    match FurSettings::new() {
+        Ok(settings) => settings,
+        Err(e) => {
+            error!("Error loading settings, using defaults: {}", e);
+            FurSettings::default()
+        }
+    }
+}
+
+/// Change the saved settings with FurSettings' change methods and tell subscribers
+pub fn update(
+    change: impl FnOnce(&mut FurSettings) -> Result<(), std::io::Error>,
+) -> FurResult<FurSettings> {
+    let mut settings = load();
+    change(&mut settings).in_context(FurErrorContext::SaveSettings)?;
+    events::emit(CoreEvent::SettingsChanged(Box::new(settings.clone())));
+    Ok(settings)
+}
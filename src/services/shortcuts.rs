@@ -0,0 +1,62 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use log::error;
+
+use crate::{
+    database,
+    events::{self, CoreEvent},
+    models::{
+        fur_error::{ErrorContext, FurErrorContext, FurResult},
+        fur_shortcut::FurShortcut,
+    },
+};
+
+pub fn add(shortcut: &FurShortcut) -> FurResult<()> {
+    database::shortcuts::insert_shortcut(shortcut).in_context(FurErrorContext::SaveShortcut)?;
+    events::emit(CoreEvent::ShortcutsChanged);
+    Ok(())
+}
+
+pub fn update(shortcut: &FurShortcut) -> FurResult<()> {
+    database::shortcuts::update_shortcut(shortcut).in_context(FurErrorContext::SaveShortcut)?;
+    events::emit(CoreEvent::ShortcutsChanged);
+    Ok(())
+}
+
+pub fn delete(uid: &str) -> FurResult<()> {
+    database::shortcuts::delete_shortcut_by_id(uid).in_context(FurErrorContext::DeleteShortcut)?;
+    events::emit(CoreEvent::ShortcutsChanged);
+    Ok(())
+}
+
+/// The shortcut with this uid, unless it has been deleted
+pub fn find(uid: &str) -> FurResult<Option<FurShortcut>> {
+    let shortcut = database::shortcuts::retrieve_shortcut_by_id(&uid.to_string())
+        .in_context(FurErrorContext::LoadShortcuts)?;
+    Ok(shortcut.filter(|shortcut| !shortcut.is_deleted))
+}
+
+/// Every shortcut that hasn't been deleted
+pub fn all() -> Vec<FurShortcut> {
+    match database::shortcuts::retrieve_existing_shortcuts() {
+        Ok(shortcuts) => shortcuts,
+        Err(e) => {
+            error!("Error reading shortcuts from database: {}", e);
+            vec![]
+        }
+    }
+}
@@ -0,0 +1,134 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::time::Instant;
+
+use chrono::{Local, Utc};
+use log::{error, info};
+
+use crate::{
+    database,
+    events::{self, CoreEvent},
+    models::{
+        fur_sync_log::FurSyncLogEntry, fur_sync_progress::FurSyncProgress, fur_user::FurUser,
+    },
+    server::{
+        api::ApiError,
+        encryption::{self, EncryptionError},
+        sync::{purge_expired_tombstones, sync_in_pages, SyncSummary},
+    },
+};
+
+use super::settings;
+
+/// Most sync log entries kept in the database
+pub const SYNC_LOG_LENGTH: usize = 200;
+
+#[derive(Debug)]
+pub enum SyncError {
+    /// The stored sync key couldn't be unwrapped, e.g. because its PIN hasn't been entered
+    Key(EncryptionError),
+    Api(ApiError),
+}
+
+/// Sync with the user's server, resuming an interrupted sync where it stopped.
+/// Returns the sync log entry, which has already been stored.
+pub async fn run(mut user: FurUser) -> Result<FurSyncLogEntry, SyncError> {
+    info!("Syncing");
+    let settings = settings::load();
+
+    let progress = match database::sync::retrieve_sync_progress() {
+        Ok(Some(progress)) => progress,
+        Ok(None) => FurSyncProgress::new(
+            settings.last_sync,
+            settings.needs_full_sync,
+            Utc::now().timestamp(),
+        ),
+        Err(e) => {
+            error!("Error retrieving sync progress: {}", e);
+            FurSyncProgress::new(
+                settings.last_sync,
+                settings.needs_full_sync,
+                Utc::now().timestamp(),
+            )
+        }
+    };
+
+    sync_from(&mut user, progress).await
+}
+
+/// Upload and download every record, e.g. so a key rotation re-encrypts everything
+/// the account has stored and not only what this device already has.
+pub async fn run_full(user: &mut FurUser) -> Result<FurSyncLogEntry, SyncError> {
+    info!("Running a full sync");
+    sync_from(user, FurSyncProgress::new(0, true, Utc::now().timestamp())).await
+}
+
+async fn sync_from(
+    user: &mut FurUser,
+    progress: FurSyncProgress,
+) -> Result<FurSyncLogEntry, SyncError> {
+    let settings = settings::load();
+    let encryption_key =
+        encryption::decrypt_encryption_key(&user.encrypted_key, &user.key_nonce, &user.key_storage)
+            .map_err(SyncError::Key)?;
+
+    events::emit(CoreEvent::SyncStarted);
+    let mut log = FurSyncLogEntry::new(Local::now().timestamp(), progress.full_sync);
+    let sync_timer = Instant::now();
+    let sync_result = sync_in_pages(
+        user,
+        progress,
+        &encryption_key,
+        &settings.device_name,
+        &mut log,
+    )
+    .await;
+    log.duration_ms = sync_timer.elapsed().as_millis() as i64;
+
+    match &sync_result {
+        Ok(summary) => finish_sync(summary),
+        Err(e) => log.error = Some(format!("{:?}", e)),
+    }
+    if let Err(e) = database::sync::insert_sync_log_entry(&log, SYNC_LOG_LENGTH) {
+        error!("Error storing sync log entry: {}", e);
+    }
+    events::emit(CoreEvent::SyncFinished(log.clone()));
+
+    sync_result.map(|_| log).map_err(SyncError::Api)
+}
+
+/// Remember how far this sync got and remove tombstones every device has received
+fn finish_sync(summary: &SyncSummary) {
+    let settings = settings::update(|settings| {
+        settings.change_last_sync(&summary.server_timestamp)?;
+        settings.change_needs_full_sync(&false)?;
+        if let Some(devices_synced_through) = summary.devices_synced_through {
+            settings.change_devices_synced_through(&devices_synced_through)?;
+        }
+        Ok(())
+    });
+
+    // Everything deleted before this sync started has now reached the server
+    if let Err(e) = database::maintenance::acknowledge_tombstones(summary.started_at) {
+        error!("Error acknowledging synced tombstones: {}", e);
+    }
+
+    match settings {
+        Ok(settings) => purge_expired_tombstones(&settings, true),
+        Err(e) => error!("{}", e),
+    }
+}
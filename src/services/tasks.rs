@@ -0,0 +1,147 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Local, NaiveDate, NaiveTime, TimeDelta, TimeZone};
+use log::error;
+
+use crate::{
+    database::{
+        self,
+        tasks::{SortBy, SortOrder},
+    },
+    events::{self, CoreEvent},
+    models::{
+        fur_error::{ErrorContext, FurErrorContext, FurResult},
+        fur_task::FurTask,
+        fur_task_group::FurTaskGroup,
+    },
+};
+
+pub fn add(task: &FurTask) -> FurResult<()> {
+    database::tasks::insert_task(task).in_context(FurErrorContext::SaveTask)?;
+    events::emit(CoreEvent::TasksChanged);
+    Ok(())
+}
+
+/// Save several tasks in one transaction, e.g. from an import
+pub fn add_many(tasks: &[FurTask]) -> FurResult<()> {
+    database::tasks::insert_tasks(tasks).in_context(FurErrorContext::SaveTask)?;
+    events::emit(CoreEvent::TasksChanged);
+    Ok(())
+}
+
+pub fn update(task: &FurTask) -> FurResult<()> {
+    database::tasks::update_task(task).in_context(FurErrorContext::SaveTask)?;
+    events::emit(CoreEvent::TasksChanged);
+    Ok(())
+}
+
+/// Apply a group's name, project, tags and rate to every task in it
+pub fn update_group(group: &FurTaskGroup) -> FurResult<()> {
+    database::tasks::update_group_of_tasks(group).in_context(FurErrorContext::SaveTask)?;
+    events::emit(CoreEvent::TasksChanged);
+    Ok(())
+}
+
+pub fn delete(uids: &[String]) -> FurResult<()> {
+    database::tasks::delete_tasks_by_ids(uids).in_context(FurErrorContext::DeleteTask)?;
+    events::emit(CoreEvent::TasksChanged);
+    Ok(())
+}
+
+/// The task with this uid, unless it has been deleted
+pub fn find(uid: &str) -> FurResult<Option<FurTask>> {
+    let task = database::tasks::retrieve_task_by_id(&uid.to_string())
+        .in_context(FurErrorContext::LoadTasks)?;
+    Ok(task.filter(|task| !task.is_deleted))
+}
+
+/// The task that stopped most recently
+pub fn most_recent() -> FurResult<Option<FurTask>> {
+    let tasks =
+        database::tasks::retrieve_all_existing_tasks(SortBy::StopTime, SortOrder::Descending)
+            .in_context(FurErrorContext::LoadTasks)?;
+    Ok(tasks.into_iter().next())
+}
+
+/// Tasks that started between `start` and `end`, oldest first
+pub fn between(start: DateTime<Local>, end: DateTime<Local>) -> FurResult<Vec<FurTask>> {
+    database::tasks::retrieve_tasks_by_date_range(start.to_rfc3339(), end.to_rfc3339())
+        .in_context(FurErrorContext::LoadTasks)
+}
+
+/// Tasks that started on any day from `first_day` through `last_day`
+pub fn on_dates(first_day: NaiveDate, last_day: NaiveDate) -> FurResult<Vec<FurTask>> {
+    between(
+        start_of_day(first_day),
+        start_of_day(last_day + TimeDelta::days(1)) - TimeDelta::milliseconds(1),
+    )
+}
+
+/// Local midnight, or the first moment of the day if midnight was skipped by a clock change
+fn start_of_day(date: NaiveDate) -> DateTime<Local> {
+    let midnight = date.and_time(NaiveTime::MIN);
+    Local
+        .from_local_datetime(&midnight)
+        .earliest()
+        .unwrap_or_else(|| Local.from_utc_datetime(&midnight))
+}
+
+/// Tasks from the last `days` days, grouped by start date and then by matching task
+pub fn history(days: i64) -> BTreeMap<NaiveDate, Vec<FurTaskGroup>> {
+    let mut grouped_tasks_by_date: BTreeMap<NaiveDate, Vec<FurTaskGroup>> = BTreeMap::new();
+
+    match database::tasks::retrieve_tasks_with_day_limit(
+        days,
+        SortBy::StopTime,
+        SortOrder::Descending,
+    ) {
+        Ok(all_tasks) => {
+            let tasks_by_date = group_tasks_by_date(all_tasks);
+
+            for (date, tasks) in tasks_by_date {
+                let mut all_groups: Vec<FurTaskGroup> = vec![];
+                for task in tasks {
+                    if let Some(matching_group) =
+                        all_groups.iter_mut().find(|x| x.is_equal_to(&task))
+                    {
+                        matching_group.add(task);
+                    } else {
+                        all_groups.push(FurTaskGroup::new_from(task));
+                    }
+                }
+                grouped_tasks_by_date.insert(date, all_groups);
+            }
+        }
+        Err(e) => {
+            error!("Error retrieving tasks from database: {}", e);
+        }
+    }
+    grouped_tasks_by_date
+}
+
+fn group_tasks_by_date(tasks: Vec<FurTask>) -> BTreeMap<NaiveDate, Vec<FurTask>> {
+    let mut grouped_tasks: BTreeMap<NaiveDate, Vec<FurTask>> = BTreeMap::new();
+
+    for task in tasks {
+        let date = task.start_time.date_naive(); // Extract the date part
+        grouped_tasks.entry(date).or_default().push(task);
+    }
+
+    grouped_tasks
+}
@@ -0,0 +1,381 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use chrono::{DateTime, Local, TimeDelta};
+use log::error;
+
+use crate::{
+    database,
+    events::{self, CoreEvent},
+    helpers::formatters,
+    models::{
+        fur_error::{ErrorContext, FurError, FurErrorContext, FurErrorKind, FurResult},
+        fur_persist::FurPersist,
+        fur_pomodoro::FurPomodoro,
+        fur_settings::FurSettings,
+        fur_task::FurTask,
+    },
+};
+
+use super::tasks;
+
+/// The timer saved in the database, if one is running
+pub fn running() -> FurResult<Option<FurPersist>> {
+    let timer = database::persistence::retrieve_persisting_timer()
+        .in_context(FurErrorContext::LoadTimer)?;
+    Ok(timer.is_running.then_some(timer))
+}
+
+/// The saved pomodoro progress
+pub fn pomodoro() -> FurResult<FurPomodoro> {
+    database::persistence::retrieve_pomodoro().in_context(FurErrorContext::LoadTimer)
+}
+
+/// Start the timer and save it so other front ends see it running.
+/// Starting outside a break begins a new pomodoro session.
+pub fn start(
+    task_input: &str,
+    start_time: DateTime<Local>,
+    settings: &FurSettings,
+) -> FurResult<FurPersist> {
+    begin(task_input, start_time, pomodoro()?, settings)
+}
+
+pub fn update_task_input(task_input: &str) -> FurResult<()> {
+    database::persistence::update_persisting_timer_task_input(task_input)
+        .in_context(FurErrorContext::SaveTimer)
+}
+
+/// Save the running timer as a task ending at `stop_time` and end the pomodoro cycle.
+/// Nothing changes if the task can't be saved, so the timer keeps running.
+pub fn stop(timer: &FurPersist, stop_time: DateTime<Local>) -> FurResult<FurTask> {
+    let task = save_task(timer, stop_time)?;
+    reset();
+    events::emit(CoreEvent::TimerStopped(task.clone()));
+    Ok(task)
+}
+
+/// Stop the timer without saving a task
+pub fn cancel() {
+    reset();
+    events::emit(CoreEvent::TimerCancelled);
+}
+
+/// Save the finished pomodoro as a task and start the break with the same task input
+pub fn start_break(timer: &FurPersist, settings: &FurSettings) -> FurResult<FurPersist> {
+    let now = Local::now();
+    let task = save_task(timer, now)?;
+    events::emit(CoreEvent::TimerStopped(task));
+
+    let mut pomodoro = pomodoro()?;
+    pomodoro.on_break = true;
+    pomodoro.snoozed = false;
+    begin(&timer.task_input, now, pomodoro, settings)
+}
+
+/// End the break without saving it and start the next pomodoro
+pub fn continue_after_break(timer: &FurPersist, settings: &FurSettings) -> FurResult<FurPersist> {
+    let mut pomodoro = pomodoro()?;
+    events::emit(CoreEvent::TimerCancelled);
+    pomodoro.on_break = false;
+    pomodoro.snoozed = false;
+    begin(&timer.task_input, Local::now(), pomodoro, settings)
+}
+
+/// Whether the break after the current session is an extended one
+pub fn is_long_break(pomodoro: &FurPomodoro, settings: &FurSettings) -> bool {
+    settings.pomodoro_extended_breaks
+        && pomodoro.sessions % settings.pomodoro_extended_break_interval == 0
+}
+
+/// Full length in seconds of the current pomodoro, snooze or break
+pub fn pomodoro_length_seconds(pomodoro: &FurPomodoro, settings: &FurSettings) -> i64 {
+    let minutes = if pomodoro.on_break {
+        if is_long_break(pomodoro, settings) {
+            settings.pomodoro_extended_break_length
+        } else {
+            settings.pomodoro_break_length
+        }
+    } else if pomodoro.snoozed {
+        settings.pomodoro_snooze_length
+    } else {
+        settings.pomodoro_length
+    };
+    minutes * 60
+}
+
+/// Seconds left in the running pomodoro or break, never below zero
+pub fn pomodoro_seconds_left(
+    timer: &FurPersist,
+    pomodoro: &FurPomodoro,
+    settings: &FurSettings,
+    now: DateTime<Local>,
+) -> i64 {
+    let counting_from = if pomodoro.snoozed && !pomodoro.on_break {
+        pomodoro.snoozed_at
+    } else {
+        timer.start_time
+    };
+    let end = counting_from + TimeDelta::seconds(pomodoro_length_seconds(pomodoro, settings));
+    (end - now).num_seconds().max(0)
+}
+
+/// The pomodoro progress once the running pomodoro or break has counted down to zero.
+/// PomodoroEnded is emitted by whichever front end notices first, so hooks run once.
+pub fn check_pomodoro(settings: &FurSettings) -> FurResult<Option<FurPomodoro>> {
+    if !settings.pomodoro {
+        return Ok(None);
+    }
+    let Some(timer) = running()? else {
+        return Ok(None);
+    };
+    let pomodoro = pomodoro()?;
+    if pomodoro_seconds_left(&timer, &pomodoro, settings, Local::now()) > 0 {
+        return Ok(None);
+    }
+
+    if database::persistence::claim_pomodoro_end().in_context(FurErrorContext::SaveTimer)? {
+        events::emit(CoreEvent::PomodoroEnded {
+            on_break: pomodoro.on_break,
+        });
+    }
+    Ok(Some(pomodoro))
+}
+
+/// Seconds the timer has been running, or 0 when it is stopped
+pub fn elapsed_seconds(timer: &FurPersist) -> i64 {
+    if timer.is_running {
+        Local::now()
+            .signed_duration_since(timer.start_time)
+            .num_seconds()
+            .max(0)
+    } else {
+        0
+    }
+}
+
+fn begin(
+    task_input: &str,
+    start_time: DateTime<Local>,
+    mut pomodoro: FurPomodoro,
+    settings: &FurSettings,
+) -> FurResult<FurPersist> {
+    let timer = FurPersist {
+        is_running: true,
+        task_input: task_input.to_string(),
+        start_time,
+    };
+    if settings.pomodoro && !pomodoro.on_break {
+        pomodoro.sessions += 1;
+    }
+    pomodoro.end_announced = false;
+    database::persistence::update_persisting_timer_and_pomodoro(&timer, &pomodoro)
+        .in_context(FurErrorContext::SaveTimer)?;
+    events::emit(CoreEvent::TimerStarted(timer.clone()));
+    Ok(timer)
+}
+
+/// Only the timer that is still saved as running becomes a task,
+/// so stopping it from the app and the CLI at once doesn't save it twice
+fn save_task(timer: &FurPersist, stop_time: DateTime<Local>) -> FurResult<FurTask> {
+    let claimed = database::persistence::stop_persisting_timer_started_at(timer.start_time)
+        .in_context(FurErrorContext::SaveTask)?;
+    if !claimed {
+        return Err(FurError::new(
+            FurErrorContext::SaveTask,
+            FurErrorKind::TimerChanged,
+        ));
+    }
+
+    let (name, project, tags, rate) = formatters::split_task_input(&timer.task_input);
+    let task = FurTask::new(
+        name,
+        timer.start_time,
+        stop_time,
+        tags,
+        project,
+        rate,
+        String::new(),
+    );
+    if let Err(e) = tasks::add(&task) {
+        // Keep the timer running so the task can be saved again
+        if let Err(e) = database::persistence::update_persisting_timer(timer) {
+            error!("Error restoring persisting timer: {}", e);
+        }
+        return Err(e);
+    }
+    Ok(task)
+}
+
+/// Stop the timer and start the next pomodoro cycle from scratch
+fn reset() {
+    if let Err(e) = database::persistence::update_persisting_timer_and_pomodoro(
+        &FurPersist {
+            is_running: false,
+            task_input: String::new(),
+            start_time: Local::now(),
+        },
+        &FurPomodoro::new(),
+    ) {
+        error!("Error updating persisting timer: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::test_data;
+
+    fn pomodoro_settings() -> FurSettings {
+        FurSettings {
+            pomodoro: true,
+            pomodoro_length: 25,
+            pomodoro_break_length: 5,
+            pomodoro_extended_breaks: true,
+            pomodoro_extended_break_interval: 2,
+            pomodoro_extended_break_length: 20,
+            ..FurSettings::default()
+        }
+    }
+
+    fn saved_tasks() -> Vec<FurTask> {
+        tasks::between(
+            Local::now() - TimeDelta::days(1),
+            Local::now() + TimeDelta::days(1),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn stopping_saves_the_running_task() {
+        let _data = test_data::fresh_database();
+        let start_time = Local::now() - TimeDelta::minutes(30);
+
+        let timer = start(
+            "Write @Book #draft $20",
+            start_time,
+            &FurSettings::default(),
+        )
+        .unwrap();
+        assert_eq!(running().unwrap(), Some(timer.clone()));
+
+        let task = stop(&timer, Local::now()).unwrap();
+        assert_eq!(task.name, "Write");
+        assert_eq!(task.project, "Book");
+        assert_eq!(task.tags, "draft");
+        assert_eq!(task.rate, 20.0);
+        assert_eq!(task.start_time, start_time);
+        assert_eq!(saved_tasks(), vec![task]);
+        assert_eq!(running().unwrap(), None);
+    }
+
+    #[test]
+    fn a_timer_stopped_elsewhere_isnt_saved_again() {
+        let _data = test_data::fresh_database();
+        let settings = FurSettings::default();
+
+        let timer = start("Write", Local::now() - TimeDelta::minutes(5), &settings).unwrap();
+        stop(&timer, Local::now()).unwrap();
+        let again = stop(&timer, Local::now()).unwrap_err();
+        assert!(matches!(again.kind, FurErrorKind::TimerChanged));
+
+        // Nor is one that was restarted since
+        start("Edit", Local::now(), &settings).unwrap();
+        let restarted = stop(&timer, Local::now()).unwrap_err();
+        assert!(matches!(restarted.kind, FurErrorKind::TimerChanged));
+        assert!(running().unwrap().is_some());
+        assert_eq!(saved_tasks().len(), 1);
+    }
+
+    #[test]
+    fn cancelling_saves_nothing() {
+        let _data = test_data::fresh_database();
+
+        start("Write", Local::now(), &FurSettings::default()).unwrap();
+        cancel();
+
+        assert_eq!(running().unwrap(), None);
+        assert!(saved_tasks().is_empty());
+    }
+
+    #[test]
+    fn task_input_changes_are_saved() {
+        let _data = test_data::fresh_database();
+
+        start("Write", Local::now(), &FurSettings::default()).unwrap();
+        update_task_input("Edit @Book").unwrap();
+
+        assert_eq!(running().unwrap().unwrap().task_input, "Edit @Book");
+    }
+
+    #[test]
+    fn pomodoros_count_sessions_through_breaks() {
+        let _data = test_data::fresh_database();
+        let settings = pomodoro_settings();
+
+        let timer = start("Write", Local::now() - TimeDelta::minutes(25), &settings).unwrap();
+        assert_eq!(pomodoro().unwrap().sessions, 1);
+        assert!(!is_long_break(&pomodoro().unwrap(), &settings));
+
+        let break_timer = start_break(&timer, &settings).unwrap();
+        let on_break = pomodoro().unwrap();
+        assert!(on_break.on_break);
+        assert_eq!(on_break.sessions, 1);
+        assert_eq!(break_timer.task_input, "Write");
+        assert_eq!(pomodoro_length_seconds(&on_break, &settings), 5 * 60);
+        assert_eq!(saved_tasks().len(), 1);
+
+        let timer = continue_after_break(&break_timer, &settings).unwrap();
+        let second = pomodoro().unwrap();
+        assert!(!second.on_break);
+        assert_eq!(second.sessions, 2);
+        assert!(is_long_break(&second, &settings));
+        // The break itself isn't saved as a task
+        assert_eq!(saved_tasks().len(), 1);
+
+        // Stopping ends the cycle, so the next pomodoro is the first again
+        stop(&timer, Local::now()).unwrap();
+        let stopped = pomodoro().unwrap();
+        assert_eq!((stopped.on_break, stopped.sessions), (false, 0));
+    }
+
+    #[test]
+    fn an_ended_pomodoro_is_announced_once() {
+        let _data = test_data::fresh_database();
+        let settings = pomodoro_settings();
+        let mut events = events::subscribe();
+
+        let timer = start("Write", Local::now() - TimeDelta::minutes(20), &settings).unwrap();
+        let progress = pomodoro().unwrap();
+        let seconds_left = pomodoro_seconds_left(&timer, &progress, &settings, Local::now());
+        assert!((299..=300).contains(&seconds_left));
+        assert_eq!(check_pomodoro(&settings).unwrap(), None);
+
+        start("Write", Local::now() - TimeDelta::minutes(26), &settings).unwrap();
+        let ended = check_pomodoro(&settings).unwrap().unwrap();
+        assert!(!ended.on_break);
+        assert!(check_pomodoro(&settings).unwrap().is_some());
+        assert!(pomodoro().unwrap().end_announced);
+
+        let mut announcements = 0;
+        while let Ok(event) = events.try_recv() {
+            if matches!(event, CoreEvent::PomodoroEnded { on_break: false }) {
+                announcements += 1;
+            }
+        }
+        assert_eq!(announcements, 1);
+    }
+}
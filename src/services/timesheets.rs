@@ -0,0 +1,437 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Weekly timesheets: one row per task or project, one column per day, with totals for
+//! both, as CSV, Markdown or HTML.
+
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{Datelike, NaiveDate, TimeDelta};
+use fluent::FluentValue;
+
+use crate::{
+    helpers::formatters::{escape_html, seconds_to_formatted_duration},
+    loc,
+    localization::Localization,
+    models::{fur_error::FurResult, fur_task::FurTask},
+};
+
+use super::{export::csv_row, tasks};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum TimesheetRows {
+    #[default]
+    Task,
+    Project,
+}
+
+impl TimesheetRows {
+    pub const ALL: [TimesheetRows; 2] = [TimesheetRows::Task, TimesheetRows::Project];
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum TimesheetFormat {
+    #[default]
+    Csv,
+    Markdown,
+    Html,
+}
+
+impl TimesheetFormat {
+    pub const ALL: [TimesheetFormat; 3] = [
+        TimesheetFormat::Csv,
+        TimesheetFormat::Markdown,
+        TimesheetFormat::Html,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TimesheetFormat::Csv => "CSV",
+            TimesheetFormat::Markdown => "Markdown",
+            TimesheetFormat::Html => "HTML",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            TimesheetFormat::Csv => "csv",
+            TimesheetFormat::Markdown => "md",
+            TimesheetFormat::Html => "html",
+        }
+    }
+}
+
+/// Which tasks a timesheet includes. An empty list doesn't filter anything.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TimesheetFilter {
+    pub projects: Vec<String>,
+    /// A task is included if it has any of these tags
+    pub tags: Vec<String>,
+}
+
+impl TimesheetFilter {
+    /// Projects and tags as typed, e.g. "Work, Home" and "#docs #ui"
+    pub fn parse(projects: &str, tags: &str) -> Self {
+        TimesheetFilter {
+            projects: projects
+                .split(',')
+                .map(|project| project.trim().to_string())
+                .filter(|project| !project.is_empty())
+                .collect(),
+            tags: tags
+                .split([',', '#'])
+                .map(|tag| tag.trim().to_lowercase())
+                .filter(|tag| !tag.is_empty())
+                .collect(),
+        }
+    }
+
+    fn includes(&self, task: &FurTask) -> bool {
+        let project_matches = self.projects.is_empty()
+            || self
+                .projects
+                .iter()
+                .any(|project| project.eq_ignore_ascii_case(&task.project));
+        let tag_matches = self.tags.is_empty()
+            || task.tags.split(" #").any(|tag| {
+                self.tags
+                    .iter()
+                    .any(|wanted| wanted.eq_ignore_ascii_case(tag))
+            });
+        project_matches && tag_matches
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimesheetRow {
+    pub label: String,
+    /// Seconds tracked on each day of the week
+    pub seconds: [i64; 7],
+    pub total: i64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Timesheet {
+    /// Monday of the week
+    pub first_day: NaiveDate,
+    pub rows: Vec<TimesheetRow>,
+    pub day_totals: [i64; 7],
+    pub total: i64,
+}
+
+impl Timesheet {
+    pub fn days(&self) -> [NaiveDate; 7] {
+        std::array::from_fn(|index| self.first_day + TimeDelta::days(index as i64))
+    }
+}
+
+/// The timesheet for the Monday-to-Sunday week that contains `date`
+pub fn for_week(
+    date: NaiveDate,
+    rows: TimesheetRows,
+    filter: &TimesheetFilter,
+) -> FurResult<Timesheet> {
+    let first_day = week_start(date);
+    let tasks = tasks::on_dates(first_day, first_day + TimeDelta::days(6))?;
+    Ok(build(first_day, &tasks, rows, filter))
+}
+
+pub fn week_start(date: NaiveDate) -> NaiveDate {
+    date - TimeDelta::days(date.weekday().num_days_from_monday() as i64)
+}
+
+/// Tasks count toward the day they started on, like in the task history
+pub fn build(
+    first_day: NaiveDate,
+    tasks: &[FurTask],
+    rows: TimesheetRows,
+    filter: &TimesheetFilter,
+) -> Timesheet {
+    let mut grouped: BTreeMap<String, TimesheetRow> = BTreeMap::new();
+    let mut day_totals = [0; 7];
+    for task in tasks.iter().filter(|task| filter.includes(task)) {
+        let day = (task.start_time.date_naive() - first_day).num_days();
+        if !(0..7).contains(&day) {
+            continue;
+        }
+        let label = match rows {
+            TimesheetRows::Task => task.name.clone(),
+            TimesheetRows::Project if task.project.is_empty() => loc!("no-project"),
+            TimesheetRows::Project => task.project.clone(),
+        };
+        let seconds = task.total_time_in_seconds();
+        let row = grouped
+            .entry(label.to_lowercase())
+            .or_insert_with(|| TimesheetRow {
+                label,
+                seconds: [0; 7],
+                total: 0,
+            });
+        row.seconds[day as usize] += seconds;
+        row.total += seconds;
+        day_totals[day as usize] += seconds;
+    }
+
+    Timesheet {
+        first_day,
+        rows: grouped.into_values().collect(),
+        day_totals,
+        total: day_totals.iter().sum(),
+    }
+}
+
+pub fn file_name(timesheet: &Timesheet, format: TimesheetFormat) -> String {
+    format!(
+        "timesheet-{}.{}",
+        timesheet.first_day.format("%Y-%m-%d"),
+        format.extension()
+    )
+}
+
+pub fn render(timesheet: &Timesheet, format: TimesheetFormat, show_seconds: bool) -> String {
+    match format {
+        TimesheetFormat::Csv => to_csv(timesheet, show_seconds),
+        TimesheetFormat::Markdown => to_markdown(timesheet, show_seconds),
+        TimesheetFormat::Html => to_html(timesheet, show_seconds),
+    }
+}
+
+pub fn to_csv(timesheet: &Timesheet, show_seconds: bool) -> String {
+    table(timesheet, show_seconds)
+        .into_iter()
+        .map(csv_row)
+        .collect()
+}
+
+pub fn to_markdown(timesheet: &Timesheet, show_seconds: bool) -> String {
+    let mut markdown = format!("# {}\n\n", title(timesheet));
+    for (index, cells) in table(timesheet, show_seconds).into_iter().enumerate() {
+        let cells: Vec<String> = cells.iter().map(|cell| cell.replace('|', "\\|")).collect();
+        markdown += &format!("| {} |\n", cells.join(" | "));
+        if index == 0 {
+            // The label column is left aligned and the times right aligned
+            let alignment: Vec<&str> = (0..cells.len())
+                .map(|column| if column == 0 { ":---" } else { "---:" })
+                .collect();
+            markdown += &format!("| {} |\n", alignment.join(" | "));
+        }
+    }
+    markdown
+}
+
+pub fn to_html(timesheet: &Timesheet, show_seconds: bool) -> String {
+    let table = table(timesheet, show_seconds);
+    let last = table.len() - 1;
+    let mut rows = String::new();
+    for (index, cells) in table.into_iter().enumerate() {
+        let cell_tag = if index == 0 || index == last {
+            "th"
+        } else {
+            "td"
+        };
+        rows += "<tr>";
+        for (column, cell) in cells.iter().enumerate() {
+            let class = if column == 0 { "" } else { " class=\"time\"" };
+            rows += &format!("<{0}{1}>{2}</{0}>", cell_tag, class, escape_html(cell));
+        }
+        rows += "</tr>\n";
+    }
+
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+<style>
+body {{ font-family: Helvetica, Arial, sans-serif; color: #222; margin: 2rem; }}
+table {{ border-collapse: collapse; }}
+th, td {{ padding: 0.4rem 0.75rem; text-align: left; border-bottom: 1px solid #ddd; }}
+.time {{ text-align: right; font-variant-numeric: tabular-nums; }}
+</style>
+</head>
+<body>
+<h1>{title}</h1>
+<table>
+{rows}</table>
+</body>
+</html>
+"#,
+        title = escape_html(&title(timesheet)),
+        rows = rows,
+    )
+}
+
+/// Fluent wraps arguments in bidi isolation marks, which only clutter a file
+fn title(timesheet: &Timesheet) -> String {
+    loc!(
+        "timesheet-week-of",
+        &HashMap::from([("date", FluentValue::from(timesheet.first_day.to_string()))])
+    )
+    .replace(['\u{2068}', '\u{2069}'], "")
+}
+
+/// The grid as text cells: a header, a row per task or project, then the day totals
+fn table(timesheet: &Timesheet, show_seconds: bool) -> Vec<Vec<String>> {
+    let duration = |seconds: i64| seconds_to_formatted_duration(seconds, show_seconds);
+
+    let mut header = vec![String::new()];
+    header.extend(
+        timesheet
+            .days()
+            .iter()
+            .map(|day| day.format("%a %Y-%m-%d").to_string()),
+    );
+    header.push(loc!("total"));
+
+    let mut table = vec![header];
+    for row in &timesheet.rows {
+        let mut cells = vec![row.label.clone()];
+        cells.extend(row.seconds.iter().map(|&seconds| {
+            if seconds == 0 {
+                String::new()
+            } else {
+                duration(seconds)
+            }
+        }));
+        cells.push(duration(row.total));
+        table.push(cells);
+    }
+
+    let mut totals = vec![loc!("total")];
+    totals.extend(
+        timesheet
+            .day_totals
+            .iter()
+            .map(|&seconds| duration(seconds)),
+    );
+    totals.push(duration(timesheet.total));
+    table.push(totals);
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Local, TimeZone};
+
+    use super::*;
+
+    /// Monday
+    fn first_day() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 3, 3).unwrap()
+    }
+
+    fn task(name: &str, project: &str, tags: &str, day: u32, minutes: i64) -> FurTask {
+        let start = Local.with_ymd_and_hms(2025, 3, day, 9, 0, 0).unwrap();
+        FurTask::new(
+            name.to_string(),
+            start,
+            start + TimeDelta::minutes(minutes),
+            tags.to_string(),
+            project.to_string(),
+            0.0,
+            String::new(),
+        )
+    }
+
+    fn tasks() -> Vec<FurTask> {
+        vec![
+            task("Write", "Book", "draft", 3, 60),
+            task("write", "Book", "draft #edit", 3, 30),
+            task("Write", "Book", "", 5, 45),
+            task("Plan", "Garden", "outside", 9, 20),
+            // The weeks before and after
+            task("Write", "Book", "draft", 2, 60),
+            task("Write", "Book", "draft", 10, 60),
+        ]
+    }
+
+    #[test]
+    fn rows_and_days_add_up() {
+        let timesheet = build(
+            first_day(),
+            &tasks(),
+            TimesheetRows::Task,
+            &TimesheetFilter::default(),
+        );
+
+        let rows: Vec<(&str, [i64; 7], i64)> = timesheet
+            .rows
+            .iter()
+            .map(|row| (row.label.as_str(), row.seconds, row.total))
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                ("Plan", [0, 0, 0, 0, 0, 0, 1200], 1200),
+                ("Write", [5400, 0, 2700, 0, 0, 0, 0], 8100),
+            ]
+        );
+        assert_eq!(timesheet.day_totals, [5400, 0, 2700, 0, 0, 0, 1200]);
+        assert_eq!(timesheet.total, 9300);
+    }
+
+    #[test]
+    fn project_rows_combine_tasks() {
+        let timesheet = build(
+            first_day(),
+            &tasks(),
+            TimesheetRows::Project,
+            &TimesheetFilter::default(),
+        );
+
+        let totals: Vec<(&str, i64)> = timesheet
+            .rows
+            .iter()
+            .map(|row| (row.label.as_str(), row.total))
+            .collect();
+        assert_eq!(totals, vec![("Book", 8100), ("Garden", 1200)]);
+    }
+
+    #[test]
+    fn filters_match_projects_or_any_tag() {
+        let by_project = build(
+            first_day(),
+            &tasks(),
+            TimesheetRows::Task,
+            &TimesheetFilter::parse("garden, Other", ""),
+        );
+        assert_eq!(by_project.total, 1200);
+
+        let by_tag = build(
+            first_day(),
+            &tasks(),
+            TimesheetRows::Task,
+            &TimesheetFilter::parse("", "#Edit #outside"),
+        );
+        assert_eq!(by_tag.total, 1800 + 1200);
+
+        let both = build(
+            first_day(),
+            &tasks(),
+            TimesheetRows::Task,
+            &TimesheetFilter::parse("Book", "draft"),
+        );
+        assert_eq!(both.total, 5400);
+    }
+
+    #[test]
+    fn weeks_start_on_monday() {
+        let sunday = NaiveDate::from_ymd_opt(2025, 3, 9).unwrap();
+        assert_eq!(week_start(sunday), first_day());
+        assert_eq!(week_start(first_day()), first_day());
+    }
+}
@@ -0,0 +1,142 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+
+use chrono::{Local, NaiveDate, TimeDelta};
+use log::error;
+
+use crate::{
+    database,
+    events::{self, CoreEvent},
+    models::{
+        fur_error::{ErrorContext, FurErrorContext, FurResult},
+        fur_task_group::FurTaskGroup,
+        fur_todo::FurTodo,
+    },
+};
+
+pub fn add(todo: &FurTodo) -> FurResult<()> {
+    database::todos::insert_todo(todo).in_context(FurErrorContext::SaveTodo)?;
+    events::emit(CoreEvent::TodosChanged);
+    Ok(())
+}
+
+/// Save several todos, e.g. from an import, telling subscribers once
+pub fn add_many(todos: &[FurTodo]) -> FurResult<()> {
+    for todo in todos {
+        database::todos::insert_todo(todo).in_context(FurErrorContext::SaveTodo)?;
+    }
+    events::emit(CoreEvent::TodosChanged);
+    Ok(())
+}
+
+pub fn update(todo: &FurTodo) -> FurResult<()> {
+    database::todos::update_todo(todo).in_context(FurErrorContext::SaveTodo)?;
+    events::emit(CoreEvent::TodosChanged);
+    Ok(())
+}
+
+pub fn delete(uid: &str) -> FurResult<()> {
+    database::todos::delete_todo_by_id(uid).in_context(FurErrorContext::DeleteTodo)?;
+    events::emit(CoreEvent::TodosChanged);
+    Ok(())
+}
+
+pub fn toggle_completed(uid: &str) -> FurResult<()> {
+    database::todos::toggle_todo_completed(uid).in_context(FurErrorContext::SaveTodo)?;
+    match database::todos::retrieve_todo_by_id(&uid.to_string()) {
+        Ok(Some(todo)) if todo.is_completed => events::emit(CoreEvent::TodoCompleted(todo)),
+        Ok(_) => {}
+        Err(e) => error!("Error retrieving toggled todo {}: {}", uid, e),
+    }
+    events::emit(CoreEvent::TodosChanged);
+    Ok(())
+}
+
+/// The todo with this uid, unless it has been deleted
+pub fn find(uid: &str) -> FurResult<Option<FurTodo>> {
+    let todo = database::todos::retrieve_todo_by_id(&uid.to_string())
+        .in_context(FurErrorContext::LoadTodos)?;
+    Ok(todo.filter(|todo| !todo.is_deleted))
+}
+
+/// Todos from 60 days ago through 3 days from now, grouped by date
+pub fn all() -> BTreeMap<NaiveDate, Vec<FurTodo>> {
+    let future_limit = Local::now() + TimeDelta::days(3);
+    let past_limit = Local::now() - TimeDelta::days(60);
+    let mut todos_by_date: BTreeMap<NaiveDate, Vec<FurTodo>> = BTreeMap::new();
+
+    match database::todos::retrieve_todos_between_dates(
+        past_limit.to_string(),
+        future_limit.to_string(),
+    ) {
+        Ok(all_todos) => {
+            todos_by_date = group_todos_by_date(all_todos);
+        }
+        Err(e) => {
+            error!("Error retrieving todos from database: {}", e);
+        }
+    }
+
+    todos_by_date
+}
+
+/// Mark today's todos as completed when a task matching them was recorded today
+pub fn complete_todos_done_today(task_history: &BTreeMap<NaiveDate, Vec<FurTaskGroup>>) {
+    let today = Local::now().date_naive();
+    let Some(todays_tasks) = task_history.get(&today) else {
+        return;
+    };
+    let Some(todays_todos) = all().remove(&today) else {
+        return;
+    };
+
+    let mut completed_any = false;
+    for mut todo in todays_todos {
+        if todo.is_completed
+            || !todays_tasks
+                .iter()
+                .any(|task_group| task_group.to_string() == todo.to_string())
+        {
+            continue;
+        }
+
+        match database::todos::set_todo_completed(&todo.uid) {
+            Ok(_) => {
+                todo.is_completed = true;
+                completed_any = true;
+                events::emit(CoreEvent::TodoCompleted(todo));
+            }
+            Err(e) => error!("Error while marking todo {} as completed: {}", todo.uid, e),
+        }
+    }
+
+    if completed_any {
+        events::emit(CoreEvent::TodosChanged);
+    }
+}
+
+fn group_todos_by_date(todos: Vec<FurTodo>) -> BTreeMap<NaiveDate, Vec<FurTodo>> {
+    let mut grouped_todos: BTreeMap<NaiveDate, Vec<FurTodo>> = BTreeMap::new();
+
+    for todo in todos {
+        let date = todo.date.date_naive();
+        grouped_todos.entry(date).or_default().push(todo);
+    }
+
+    grouped_todos
+}
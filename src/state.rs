@@ -21,36 +21,54 @@ use dioxus::{
     hooks::{use_context_provider, use_signal},
     signals::{Global, GlobalSignal, Signal},
 };
+use log::error;
 
 use crate::{
-    helpers::{
-        server,
-        views::{shortcuts, task_history, todos},
-    },
+    database,
+    helpers::server,
     models::{
-        fur_alert::FurAlert,
-        fur_pomodoro::FurPomodoro,
-        fur_settings::FurSettings,
-        fur_sheet::FurSheet,
-        fur_shortcut::FurShortcut,
-        fur_task_group::FurTaskGroup,
-        fur_todo::FurTodo,
-        fur_user::{FurUser, FurUserFields},
+        fur_alert::FurAlert, fur_device::FurDevice, fur_error::FurError,
+        fur_key_rotation::FurKeyRotation, fur_pomodoro::FurPomodoro, fur_settings::FurSettings,
+        fur_sheet::FurSheet, fur_shortcut::FurShortcut, fur_sync_log::FurSyncLogEntry,
+        fur_sync_status::FurSyncStatus, fur_task_group::FurTaskGroup, fur_todo::FurTodo,
+        fur_user::FurUser, fur_user_fields::FurUserFields,
     },
-    NavTab,
+    services, NavTab,
 };
 
-pub static SETTINGS: GlobalSignal<FurSettings> =
-    Global::new(|| FurSettings::new().expect("Failed to load settings"));
-pub static SHORTCUTS: GlobalSignal<Vec<FurShortcut>> =
-    Global::new(|| shortcuts::get_all_shortcuts());
+pub static DATABASE_ENCRYPTED: GlobalSignal<bool> =
+    Global::new(|| database::encryption::is_encrypted());
+/// True until an encrypted database has been unlocked with its passphrase
+pub static DATABASE_LOCKED: GlobalSignal<bool> =
+    Global::new(|| database::encryption::is_encrypted());
+pub static SETTINGS: GlobalSignal<FurSettings> = Global::new(|| services::settings::load());
+/// Why the database couldn't be prepared at launch. An encrypted database is
+/// prepared by the unlock view instead.
+pub static STARTUP_ERROR: GlobalSignal<Option<FurError>> = Global::new(|| {
+    if database::encryption::is_encrypted() {
+        None
+    } else {
+        crate::prepare_database()
+            .inspect_err(|e| error!("{}", e))
+            .err()
+    }
+});
+pub static SHORTCUTS: GlobalSignal<Vec<FurShortcut>> = Global::new(|| services::shortcuts::all());
 pub static TASKS: GlobalSignal<BTreeMap<NaiveDate, Vec<FurTaskGroup>>> =
-    Global::new(|| task_history::get_task_history(365));
+    Global::new(|| services::tasks::history(365));
 pub static TODOS: GlobalSignal<BTreeMap<NaiveDate, Vec<FurTodo>>> =
-    Global::new(|| todos::get_all_todos());
+    Global::new(|| services::todos::all());
 pub static USER: GlobalSignal<Option<FurUser>> = Global::new(|| server::sync::get_user());
-pub static SYNC_MESSAGE: GlobalSignal<Result<String, Box<dyn std::error::Error>>> =
-    Global::new(|| Ok(String::new()));
+/// Latest sync message, shown as an error when Err
+pub static SYNC_MESSAGE: GlobalSignal<Result<String, String>> = Global::new(|| Ok(String::new()));
+pub static KEY_ROTATION: GlobalSignal<Option<FurKeyRotation>> =
+    Global::new(|| server::key_rotation::get_key_rotation());
+/// Devices on the sync account, once loaded from the settings view
+pub static DEVICES: GlobalSignal<Option<Vec<FurDevice>>> = Global::new(|| None);
+/// Recent sync outcomes, newest first
+pub static SYNC_LOG: GlobalSignal<Vec<FurSyncLogEntry>> =
+    Global::new(|| server::sync_log::get_sync_log());
+pub static SYNC_STATUS: GlobalSignal<FurSyncStatus> = Global::new(|| FurSyncStatus::new());
 pub static USER_FIELDS: GlobalSignal<FurUserFields> =
     Global::new(|| server::sync::get_user_fields());
 pub static ALERT: GlobalSignal<FurAlert> = Global::new(|| FurAlert::new());
@@ -65,3 +83,4 @@ pub static TIMER_START_TIME: GlobalSignal<DateTime<Local>> = Global::new(|| Loca
 pub static TASK_IDS_TO_DELETE: GlobalSignal<Option<Vec<String>>> = Global::new(|| None);
 pub static SHORTCUT_ID_TO_DELETE: GlobalSignal<Option<String>> = Global::new(|| None);
 pub static TODO_ID_TO_DELETE: GlobalSignal<Option<String>> = Global::new(|| None);
+pub static DEVICE_TO_REVOKE: GlobalSignal<Option<String>> = Global::new(|| None);
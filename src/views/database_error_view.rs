@@ -0,0 +1,53 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use dioxus::prelude::*;
+use log::error;
+
+use crate::{constants::SETTINGS_CSS, loc, localization::Localization, state};
+
+/// Shown at launch in place of the app when the database can't be opened
+#[component]
+pub fn DatabaseErrorView() -> Element {
+    let (title, message) = match state::STARTUP_ERROR.read().as_ref() {
+        Some(error) => (error.title(), error.localized_message()),
+        None => (String::new(), String::new()),
+    };
+
+    rsx! {
+        document::Stylesheet { href: SETTINGS_CSS }
+
+        div { id: "settings",
+            div { class: "settings-header", "{title}" }
+            div { class: "settings-group",
+                div { class: "settings-item",
+                    div { class: "settings-value dangerous", "{message}" }
+                }
+                div { class: "settings-item",
+                    button {
+                        class: "settings-button",
+                        onclick: move |_| {
+                            *state::STARTUP_ERROR.write() = crate::prepare_database()
+                                .inspect_err(|e| error!("{}", e))
+                                .err();
+                        },
+                        "{loc!(\"try-again\")}"
+                    }
+                }
+            }
+        }
+    }
+}
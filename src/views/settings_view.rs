@@ -14,19 +14,73 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use std::collections::{HashMap, HashSet};
+
+use chrono::{Datelike, Local};
 use dioxus::prelude::*;
+use fluent::FluentValue;
+use log::error;
 
 use crate::{
-    constants::{OFFICIAL_SERVER, SETTINGS_CSS},
+    constants::{OFFICIAL_SERVER, SETTINGS_CSS, SHEET_CSS},
     helpers::{
-        server::{self, login::login_button_pressed, logout::logout_button_pressed},
-        views::{settings::ServerChoices, timer},
+        logger,
+        server::{
+            self,
+            devices::{device_details, is_this_device, load_devices, revoke_device_pressed},
+            key_rotation::{cancel_key_rotation, resume_key_rotation, rotate_key_button_pressed},
+            key_storage::{
+                self, remove_pin_button_pressed, set_pin_button_pressed, unlock_button_pressed,
+                KeyStorageKind,
+            },
+            login::login_button_pressed,
+            logout::logout_button_pressed,
+            sync_log::{clear_sync_log, entry_summary, entry_time, export_diagnostics_pressed},
+        },
+        view_enums::FurLogLevel,
+        views::{
+            invoices::{
+                change_invoice_next_number, close_invoice_sheet, create_invoice, grouping_label,
+                preview_invoice, rounding_label, set_default_tax_lines, set_invoice_number_prefix,
+                set_invoice_sender, show_invoice_sheet, InvoiceFormat, ROUNDING_CHOICES,
+            },
+            settings::{
+                calendar_event_time, close_import_calendar_sheet, close_import_tasks_sheet,
+                compact_database, decrypt_database, encrypt_database, export_archive,
+                export_calendar, export_timesheet, hook_label, import_archive,
+                import_calendar_events, import_field_label, import_tracker_rows,
+                import_tracker_title, key_fingerprint_text, load_calendar_file, load_tracker_file,
+                parse_date_range, preview_import, regenerate_local_api_token, set_category_mapping,
+                set_hook, set_local_api_enabled, set_local_api_port, sync_status_text,
+                timesheet_rows_label, ServerChoices,
+            },
+            timer,
+        },
     },
     loc,
     localization::Localization,
+    models::{
+        fur_calendar_event::FurCalendarEvent,
+        fur_device::FurDevice,
+        fur_hook::FurHookEvent,
+        fur_invoice::{FurTaxLine, InvoiceGrouping},
+    },
+    services::{
+        self,
+        importers::{ImportField, ImportSource, ImportTable, PreviewStatus},
+        invoices::{format_hours, format_money, tax_label, InvoiceOptions},
+        timesheets::{TimesheetFormat, TimesheetRows},
+    },
     state,
 };
 
+/// Most recent syncs listed in the sync history section
+const SYNC_HISTORY_SHOWN: usize = 10;
+/// Lines of the log file shown in the log viewer
+const LOG_LINES_SHOWN: usize = 200;
+/// Entries listed in the import preview, which still imports every row
+const IMPORT_PREVIEW_ROWS_SHOWN: usize = 50;
+
 #[component]
 pub fn SettingsView() -> Element {
     let pomodoro = state::SETTINGS.read().pomodoro;
@@ -51,6 +105,82 @@ pub fn SettingsView() -> Element {
     let show_todo_tags = state::SETTINGS.read().show_todo_tags;
     let show_todo_rate = state::SETTINGS.read().show_todo_rate;
 
+    let hook_timeout_seconds = state::SETTINGS.read().hook_timeout_seconds;
+
+    let local_api_enabled = state::SETTINGS.read().local_api_enabled;
+    let local_api_port = state::SETTINGS.read().local_api_port;
+    let mut local_api_port_input = use_signal(|| local_api_port.to_string());
+    let local_api_token = state::SETTINGS.read().local_api_token.clone();
+
+    let tombstone_retention_days = state::SETTINGS.read().tombstone_retention_days;
+    let database_is_encrypted = state::DATABASE_ENCRYPTED.cloned();
+    let mut database_passphrase = use_signal(|| String::new());
+    let mut database_passphrase_confirmation = use_signal(|| String::new());
+
+    let sheets = state::SHEETS.cloned();
+    let mut calendar_path = use_signal(|| String::new());
+    let mut tracker_path = use_signal(|| String::new());
+    let mut tracker_source = use_signal(|| ImportSource::Toggl);
+    let mut archive_path = use_signal(|| String::new());
+    let mut archive_include_settings = use_signal(|| true);
+
+    let invoice_sender = state::SETTINGS.read().invoice_sender.clone();
+    let invoice_number_prefix = state::SETTINGS.read().invoice_number_prefix.clone();
+    let invoice_next_number = state::SETTINGS.read().invoice_next_number;
+    let mut default_taxes =
+        use_signal(|| FurTaxLine::format_list(&state::SETTINGS.read().invoice_tax_lines));
+
+    let mut export_first_day = use_signal(|| {
+        let today = Local::now().date_naive();
+        today
+            .with_day(1)
+            .unwrap_or(today)
+            .format("%Y-%m-%d")
+            .to_string()
+    });
+    let mut export_last_day =
+        use_signal(|| Local::now().date_naive().format("%Y-%m-%d").to_string());
+    let mut export_merge_contiguous = use_signal(|| false);
+
+    let mut timesheet_week =
+        use_signal(|| Local::now().date_naive().format("%Y-%m-%d").to_string());
+    let mut timesheet_rows = use_signal(|| TimesheetRows::Task);
+    let mut timesheet_format = use_signal(|| TimesheetFormat::Csv);
+    let mut timesheet_projects = use_signal(|| String::new());
+    let mut timesheet_tags = use_signal(|| String::new());
+
+    let key_rotation_progress = state::KEY_ROTATION.read().as_ref().map(|rotation| {
+        loc!(
+            "records-reencrypted",
+            &HashMap::from([("count", FluentValue::from(rotation.uploaded_count))])
+        )
+    });
+    let key_fingerprint = use_memo(|| {
+        state::USER
+            .read()
+            .as_ref()
+            .map(|user| key_fingerprint_text(user))
+    });
+    let mut device_name = use_signal(|| state::SETTINGS.read().device_name.clone());
+    let devices = state::DEVICES.cloned();
+    let log_level = state::SETTINGS.read().log_level;
+    let mut log_is_shown = use_signal(|| false);
+    let log_text = if log_is_shown() {
+        logger::recent_lines(LOG_LINES_SHOWN).join("\n")
+    } else {
+        String::new()
+    };
+    let recent_syncs: Vec<(String, String)> = state::SYNC_LOG
+        .read()
+        .iter()
+        .take(SYNC_HISTORY_SHOWN)
+        .map(|entry| (entry_time(entry), entry_summary(entry)))
+        .collect();
+    let pin_protected = state::USER
+        .read()
+        .as_ref()
+        .is_some_and(|user| user.key_storage == KeyStorageKind::Pin);
+
     // sync_server_col = sync_server_col.push(sync_button_row);
     // sync_server_col = sync_server_col.push_maybe(match &self.login_message {
     //     Ok(msg) => {
@@ -65,6 +195,7 @@ pub fn SettingsView() -> Element {
 
     rsx! {
         document::Stylesheet { href: SETTINGS_CSS }
+        document::Stylesheet { href: SHEET_CSS }
 
         div { id: "settings",
             SettingsTitleRow { title: loc!("sync") }
@@ -117,7 +248,7 @@ pub fn SettingsView() -> Element {
                     label: loc!("encryption-key"),
                     input_type: "password".to_string(),
                     value: state::USER_FIELDS.read().encryption_key.clone(),
-                    placeholder: loc!("key"),
+                    placeholder: loc!("key-or-passphrase"),
                     oninput: move |event: Event<FormData>| {
                         let mut user_fields_clone = state::USER_FIELDS.cloned();
                         user_fields_clone.encryption_key = event.value();
@@ -125,6 +256,13 @@ pub fn SettingsView() -> Element {
                     },
                 }
                 if state::USER.read().is_some() {
+                    SettingsTextRow {
+                        label: loc!("sync-status"),
+                        value: sync_status_text(&state::SYNC_STATUS.read()),
+                    }
+                    if let Some(fingerprint) = key_fingerprint() {
+                        SettingsTextRow { label: loc!("key-fingerprint"), value: fingerprint }
+                    }
                     SettingsButtonRow {
                         label: loc!("sync"),
                         dangerous: false,
@@ -139,13 +277,86 @@ pub fn SettingsView() -> Element {
                             }
                         },
                     }
+                    if let Some(progress) = key_rotation_progress {
+                        SettingsTextRow { label: loc!("key-rotation"), value: progress }
+                        SettingsButtonRow {
+                            label: loc!("resume-key-rotation"),
+                            dangerous: false,
+                            onclick: move |_| resume_key_rotation(),
+                        }
+                        SettingsButtonRow {
+                            label: loc!("cancel-key-rotation"),
+                            dangerous: true,
+                            onclick: move |_| cancel_key_rotation(),
+                        }
+                    } else {
+                        SettingsInputRow {
+                            label: loc!("new-encryption-key"),
+                            input_type: "password".to_string(),
+                            value: state::USER_FIELDS.read().new_encryption_key.clone(),
+                            placeholder: loc!("key-or-passphrase"),
+                            oninput: move |event: Event<FormData>| {
+                                let mut user_fields_clone = state::USER_FIELDS.cloned();
+                                user_fields_clone.new_encryption_key = event.value();
+                                *state::USER_FIELDS.write() = user_fields_clone;
+                            },
+                        }
+                        SettingsButtonRow {
+                            label: loc!("rotate-key"),
+                            dangerous: true,
+                            onclick: move |_| rotate_key_button_pressed(),
+                        }
+                    }
+                    if key_storage::pin_available() {
+                        if pin_protected && !key_storage::is_unlocked() {
+                            SettingsInputRow {
+                                label: loc!("pin"),
+                                input_type: "password".to_string(),
+                                value: state::USER_FIELDS.read().pin.clone(),
+                                placeholder: loc!("pin"),
+                                oninput: move |event: Event<FormData>| {
+                                    let mut user_fields_clone = state::USER_FIELDS.cloned();
+                                    user_fields_clone.pin = event.value();
+                                    *state::USER_FIELDS.write() = user_fields_clone;
+                                },
+                            }
+                            SettingsButtonRow {
+                                label: loc!("unlock-sync-key"),
+                                dangerous: false,
+                                onclick: move |_| unlock_button_pressed(),
+                            }
+                        } else if pin_protected {
+                            SettingsButtonRow {
+                                label: loc!("remove-pin"),
+                                dangerous: true,
+                                onclick: move |_| remove_pin_button_pressed(),
+                            }
+                        } else {
+                            SettingsInputRow {
+                                label: loc!("pin"),
+                                input_type: "password".to_string(),
+                                value: state::USER_FIELDS.read().pin.clone(),
+                                placeholder: loc!("new-pin"),
+                                oninput: move |event: Event<FormData>| {
+                                    let mut user_fields_clone = state::USER_FIELDS.cloned();
+                                    user_fields_clone.pin = event.value();
+                                    *state::USER_FIELDS.write() = user_fields_clone;
+                                },
+                            }
+                            SettingsButtonRow {
+                                label: loc!("protect-with-pin"),
+                                dangerous: false,
+                                onclick: move |_| set_pin_button_pressed(),
+                            }
+                        }
+                    }
                 } else {
                     SettingsButtonRow {
                         label: loc!("sign-up"),
                         dangerous: false,
                         onclick: move |_| {
                             if let Err(e) = webbrowser::open("https://furtherance.app/sync") {
-                                eprintln!("Failed to open URL in browser: {}", e);
+                                error!("Failed to open URL in browser: {}", e);
                             }
                         },
                     }
@@ -188,7 +399,7 @@ pub fn SettingsView() -> Element {
                         let mut settings_clone = state::SETTINGS.cloned();
                         match settings_clone.change_pomodoro_length(&(pomodoro_length + delta)) {
                             Ok(_) => *state::SETTINGS.write() = settings_clone,
-                            Err(e) => eprintln!("Error: {}", e),
+                            Err(e) => error!("Error: {}", e),
                         }
                     },
                 }
@@ -201,7 +412,7 @@ pub fn SettingsView() -> Element {
                             .change_pomodoro_break_length(&(pomodoro_break_length + delta))
                         {
                             Ok(_) => *state::SETTINGS.write() = settings_clone,
-                            Err(e) => eprintln!("Error: {}", e),
+                            Err(e) => error!("Error: {}", e),
                         }
                     },
                 }
@@ -214,7 +425,7 @@ pub fn SettingsView() -> Element {
                             .change_pomodoro_snooze_length(&(pomodoro_snooze_length + delta))
                         {
                             Ok(_) => *state::SETTINGS.write() = settings_clone,
-                            Err(e) => eprintln!("Error: {}", e),
+                            Err(e) => error!("Error: {}", e),
                         }
                     },
                 }
@@ -241,7 +452,7 @@ pub fn SettingsView() -> Element {
                             )
                         {
                             Ok(_) => *state::SETTINGS.write() = settings_clone,
-                            Err(e) => eprintln!("Error: {}", e),
+                            Err(e) => error!("Error: {}", e),
                         }
                     },
                 }
@@ -256,7 +467,7 @@ pub fn SettingsView() -> Element {
                             )
                         {
                             Ok(_) => *state::SETTINGS.write() = settings_clone,
-                            Err(e) => eprintln!("Error: {}", e),
+                            Err(e) => error!("Error: {}", e),
                         }
                     },
                 }
@@ -349,7 +560,7 @@ pub fn SettingsView() -> Element {
                         let mut settings_clone = state::SETTINGS.cloned();
                         match settings_clone.change_days_to_show(&(days_to_show + delta)) {
                             Ok(_) => *state::SETTINGS.write() = settings_clone,
-                            Err(e) => eprintln!("Error: {}", e),
+                            Err(e) => error!("Error: {}", e),
                         }
                     },
                 }
@@ -389,6 +600,423 @@ pub fn SettingsView() -> Element {
                 }
             }
 
+            if state::USER.read().is_some() {
+                SettingsTitleRow { title: loc!("devices") }
+                div { class: "settings-group",
+                    SettingsInputRow {
+                        label: loc!("device-name"),
+                        input_type: "text".to_string(),
+                        value: device_name(),
+                        placeholder: loc!("device-name"),
+                        oninput: move |event: Event<FormData>| device_name.set(event.value()),
+                        onchange: move |event: Event<FormData>| {
+                            let mut settings_clone = state::SETTINGS.cloned();
+                            match settings_clone.change_device_name(&event.value()) {
+                                Ok(_) => {
+                                    device_name.set(settings_clone.device_name.clone());
+                                    *state::SETTINGS.write() = settings_clone;
+                                }
+                                Err(e) => error!("Error changing device_name: {}", e),
+                            }
+                        },
+                    }
+                    if let Some(devices) = devices {
+                        for device in devices {
+                            SettingsDeviceRow { key: "{device.device_id}", device }
+                        }
+                    }
+                    SettingsButtonRow {
+                        label: if state::DEVICES.read().is_some() { loc!("refresh-devices") } else { loc!("show-devices") },
+                        dangerous: false,
+                        onclick: move |_| load_devices(),
+                    }
+                }
+            }
+
+            if !recent_syncs.is_empty() {
+                SettingsTitleRow { title: loc!("sync-history") }
+                div { class: "settings-group",
+                    for (time, summary) in recent_syncs {
+                        SettingsTextRow { label: time, value: summary }
+                    }
+                    SettingsButtonRow {
+                        label: loc!("export-diagnostics"),
+                        dangerous: false,
+                        onclick: move |_| export_diagnostics_pressed(),
+                    }
+                    SettingsButtonRow {
+                        label: loc!("clear-sync-history"),
+                        dangerous: true,
+                        onclick: move |_| clear_sync_log(),
+                    }
+                }
+            }
+
+            SettingsTitleRow { title: loc!("hooks") }
+            div { class: "settings-group",
+                for event in FurHookEvent::ALL {
+                    SettingsInputRow {
+                        key: "{event.name()}",
+                        label: hook_label(event),
+                        input_type: "text".to_string(),
+                        value: state::SETTINGS.read().hook(event).to_string(),
+                        placeholder: loc!("command-or-url"),
+                        oninput: move |event_data: Event<FormData>| {
+                            set_hook(event, &event_data.value())
+                        },
+                    }
+                }
+                SettingsNumberRow {
+                    label: loc!("hook-timeout"),
+                    value: hook_timeout_seconds,
+                    onupdate: move |(delta, _)| {
+                        let mut settings_clone = state::SETTINGS.cloned();
+                        match settings_clone
+                            .change_hook_timeout_seconds(&(hook_timeout_seconds + delta))
+                        {
+                            Ok(_) => *state::SETTINGS.write() = settings_clone,
+                            Err(e) => error!("Error changing hook_timeout_seconds: {}", e),
+                        }
+                    },
+                }
+            }
+
+            SettingsTitleRow { title: loc!("local-api") }
+            div { class: "settings-group",
+                SettingsToggleRow {
+                    label: loc!("enable-local-api"),
+                    toggled: local_api_enabled,
+                    onchange: move |_| set_local_api_enabled(!local_api_enabled),
+                }
+                if local_api_enabled {
+                    SettingsInputRow {
+                        label: loc!("port"),
+                        input_type: "number".to_string(),
+                        value: local_api_port_input(),
+                        placeholder: loc!("port"),
+                        oninput: move |event: Event<FormData>| local_api_port_input.set(event.value()),
+                        onchange: move |event: Event<FormData>| {
+                            set_local_api_port(&event.value());
+                            let saved_port = state::SETTINGS.read().local_api_port;
+                            local_api_port_input.set(saved_port.to_string());
+                        },
+                    }
+                    SettingsTextRow {
+                        label: loc!("address"),
+                        value: format!("http://127.0.0.1:{}", local_api_port),
+                    }
+                    SettingsTextRow { label: loc!("access-token"), value: local_api_token }
+                    SettingsButtonRow {
+                        label: loc!("regenerate-token"),
+                        dangerous: false,
+                        onclick: move |_| regenerate_local_api_token(),
+                    }
+                }
+            }
+
+            SettingsTitleRow { title: loc!("logs") }
+            div { class: "settings-group",
+                SettingsDropDownRow {
+                    label: loc!("log-level"),
+                    list_items: FurLogLevel::ALL.iter().map(|level| level.to_string()).collect(),
+                    selected_item: log_level.to_string(),
+                    onchange: move |event: Event<FormData>| {
+                        let Some(new_level) = FurLogLevel::ALL
+                            .into_iter()
+                            .find(|level| level.to_string() == event.value())
+                        else {
+                            return;
+                        };
+                        let mut settings_clone = state::SETTINGS.cloned();
+                        match settings_clone.change_log_level(&new_level) {
+                            Ok(_) => {
+                                logger::set_level(&new_level);
+                                *state::SETTINGS.write() = settings_clone;
+                            }
+                            Err(e) => error!("Error changing log_level: {}", e),
+                        }
+                    },
+                }
+                SettingsTextRow {
+                    label: loc!("log-file"),
+                    value: logger::log_path().display().to_string(),
+                }
+                if log_is_shown() {
+                    pre { class: "log-viewer", "{log_text}" }
+                }
+                SettingsButtonRow {
+                    label: if log_is_shown() { loc!("hide-log") } else { loc!("show-log") },
+                    dangerous: false,
+                    onclick: move |_| log_is_shown.set(!log_is_shown()),
+                }
+            }
+
+            SettingsTitleRow { title: loc!("local-database") }
+            div { class: "settings-group",
+                SettingsNumberRow {
+                    label: loc!("days-to-keep-deleted-items"),
+                    value: tombstone_retention_days,
+                    onupdate: move |(delta, _)| {
+                        let mut settings_clone = state::SETTINGS.cloned();
+                        match settings_clone
+                            .change_tombstone_retention_days(&(tombstone_retention_days + delta))
+                        {
+                            Ok(_) => *state::SETTINGS.write() = settings_clone,
+                            Err(e) => error!("Error: {}", e),
+                        }
+                    },
+                }
+                SettingsButtonRow {
+                    label: loc!("compact-database"),
+                    dangerous: false,
+                    onclick: move |_| compact_database(),
+                }
+                if database_is_encrypted {
+                    SettingsButtonRow {
+                        label: loc!("decrypt-database"),
+                        dangerous: true,
+                        onclick: move |_| decrypt_database(),
+                    }
+                } else {
+                    SettingsInputRow {
+                        label: loc!("database-passphrase"),
+                        input_type: "password".to_string(),
+                        value: database_passphrase(),
+                        placeholder: loc!("passphrase"),
+                        oninput: move |event: Event<FormData>| database_passphrase.set(event.value()),
+                    }
+                    SettingsInputRow {
+                        label: loc!("confirm-passphrase"),
+                        input_type: "password".to_string(),
+                        value: database_passphrase_confirmation(),
+                        placeholder: loc!("passphrase"),
+                        oninput: move |event: Event<FormData>| {
+                            database_passphrase_confirmation.set(event.value())
+                        },
+                    }
+                    SettingsButtonRow {
+                        label: loc!("encrypt-database"),
+                        dangerous: false,
+                        onclick: move |_| {
+                            if encrypt_database(
+                                &database_passphrase(),
+                                &database_passphrase_confirmation(),
+                            ) {
+                                database_passphrase.set(String::new());
+                                database_passphrase_confirmation.set(String::new());
+                            }
+                        },
+                    }
+                }
+            }
+
+            SettingsTitleRow { title: loc!("import") }
+            div { class: "settings-group",
+                SettingsInputRow {
+                    label: loc!("calendar-file"),
+                    input_type: "text".to_string(),
+                    value: calendar_path(),
+                    placeholder: loc!("calendar-file-placeholder"),
+                    oninput: move |event: Event<FormData>| calendar_path.set(event.value()),
+                }
+                SettingsButtonRow {
+                    label: loc!("import-calendar"),
+                    dangerous: false,
+                    onclick: move |_| load_calendar_file(&calendar_path()),
+                }
+                SettingsDropDownRow {
+                    label: loc!("tracker"),
+                    list_items: ImportSource::ALL
+                        .iter()
+                        .map(|source| source.name().to_string())
+                        .collect::<Vec<String>>(),
+                    selected_item: tracker_source().name().to_string(),
+                    onchange: move |event: Event<FormData>| {
+                        if let Some(source) = ImportSource::ALL
+                            .into_iter()
+                            .find(|source| source.name() == event.value())
+                        {
+                            tracker_source.set(source);
+                        }
+                    },
+                }
+                SettingsInputRow {
+                    label: loc!("tracker-file"),
+                    input_type: "text".to_string(),
+                    value: tracker_path(),
+                    placeholder: loc!("tracker-file-placeholder"),
+                    oninput: move |event: Event<FormData>| tracker_path.set(event.value()),
+                }
+                SettingsButtonRow {
+                    label: loc!("preview-import"),
+                    dangerous: false,
+                    onclick: move |_| load_tracker_file(&tracker_path(), tracker_source()),
+                }
+                SettingsInputRow {
+                    label: loc!("archive-file"),
+                    input_type: "text".to_string(),
+                    value: archive_path(),
+                    placeholder: loc!("archive-file-placeholder"),
+                    oninput: move |event: Event<FormData>| archive_path.set(event.value()),
+                }
+                SettingsToggleRow {
+                    label: loc!("import-settings"),
+                    toggled: archive_include_settings(),
+                    onchange: move |_| archive_include_settings.set(!archive_include_settings()),
+                }
+                SettingsButtonRow {
+                    label: loc!("import-archive"),
+                    dangerous: false,
+                    onclick: move |_| import_archive(&archive_path(), archive_include_settings()),
+                }
+            }
+
+            SettingsTitleRow { title: loc!("export") }
+            div { class: "settings-group",
+                SettingsInputRow {
+                    label: loc!("from"),
+                    input_type: "date".to_string(),
+                    value: export_first_day(),
+                    placeholder: "YYYY-MM-DD".to_string(),
+                    oninput: move |event: Event<FormData>| export_first_day.set(event.value()),
+                }
+                SettingsInputRow {
+                    label: loc!("to"),
+                    input_type: "date".to_string(),
+                    value: export_last_day(),
+                    placeholder: "YYYY-MM-DD".to_string(),
+                    oninput: move |event: Event<FormData>| export_last_day.set(event.value()),
+                }
+                SettingsToggleRow {
+                    label: loc!("merge-contiguous-tasks"),
+                    toggled: export_merge_contiguous(),
+                    onchange: move |_| export_merge_contiguous.set(!export_merge_contiguous()),
+                }
+                SettingsButtonRow {
+                    label: loc!("export-calendar"),
+                    dangerous: false,
+                    onclick: move |_| {
+                        export_calendar(
+                            &export_first_day(),
+                            &export_last_day(),
+                            export_merge_contiguous(),
+                        )
+                    },
+                }
+                SettingsButtonRow {
+                    label: loc!("export-archive"),
+                    dangerous: false,
+                    onclick: move |_| export_archive(),
+                }
+            }
+
+            SettingsTitleRow { title: loc!("timesheet") }
+            div { class: "settings-group",
+                SettingsInputRow {
+                    label: loc!("week"),
+                    input_type: "date".to_string(),
+                    value: timesheet_week(),
+                    placeholder: "YYYY-MM-DD".to_string(),
+                    oninput: move |event: Event<FormData>| timesheet_week.set(event.value()),
+                }
+                SettingsDropDownRow {
+                    label: loc!("timesheet-rows"),
+                    list_items: TimesheetRows::ALL
+                        .into_iter()
+                        .map(timesheet_rows_label)
+                        .collect::<Vec<String>>(),
+                    selected_item: timesheet_rows_label(timesheet_rows()),
+                    onchange: move |event: Event<FormData>| {
+                        if let Some(rows) = TimesheetRows::ALL
+                            .into_iter()
+                            .find(|rows| timesheet_rows_label(*rows) == event.value())
+                        {
+                            timesheet_rows.set(rows);
+                        }
+                    },
+                }
+                SettingsDropDownRow {
+                    label: loc!("format"),
+                    list_items: TimesheetFormat::ALL
+                        .iter()
+                        .map(|format| format.name().to_string())
+                        .collect::<Vec<String>>(),
+                    selected_item: timesheet_format().name().to_string(),
+                    onchange: move |event: Event<FormData>| {
+                        if let Some(format) = TimesheetFormat::ALL
+                            .into_iter()
+                            .find(|format| format.name() == event.value())
+                        {
+                            timesheet_format.set(format);
+                        }
+                    },
+                }
+                SettingsInputRow {
+                    label: loc!("only-projects"),
+                    input_type: "text".to_string(),
+                    value: timesheet_projects(),
+                    placeholder: loc!("only-projects-placeholder"),
+                    oninput: move |event: Event<FormData>| timesheet_projects.set(event.value()),
+                }
+                SettingsInputRow {
+                    label: loc!("only-tags"),
+                    input_type: "text".to_string(),
+                    value: timesheet_tags(),
+                    placeholder: loc!("only-tags-placeholder"),
+                    oninput: move |event: Event<FormData>| timesheet_tags.set(event.value()),
+                }
+                SettingsButtonRow {
+                    label: loc!("export-timesheet"),
+                    dangerous: false,
+                    onclick: move |_| {
+                        export_timesheet(
+                            &timesheet_week(),
+                            timesheet_rows(),
+                            timesheet_format(),
+                            &timesheet_projects(),
+                            &timesheet_tags(),
+                        )
+                    },
+                }
+            }
+
+            SettingsTitleRow { title: loc!("invoices") }
+            div { class: "settings-group",
+                SettingsTextAreaRow {
+                    label: loc!("invoice-sender"),
+                    value: invoice_sender,
+                    placeholder: loc!("invoice-sender-placeholder"),
+                    oninput: move |event: Event<FormData>| set_invoice_sender(&event.value()),
+                }
+                SettingsInputRow {
+                    label: loc!("invoice-number-prefix"),
+                    input_type: "text".to_string(),
+                    value: invoice_number_prefix,
+                    placeholder: "INV-".to_string(),
+                    oninput: move |event: Event<FormData>| set_invoice_number_prefix(&event.value()),
+                }
+                SettingsNumberRow {
+                    label: loc!("next-invoice-number"),
+                    value: invoice_next_number,
+                    onupdate: move |(delta, _)| change_invoice_next_number(delta),
+                }
+                SettingsInputRow {
+                    label: loc!("default-taxes"),
+                    input_type: "text".to_string(),
+                    value: default_taxes(),
+                    placeholder: loc!("taxes-placeholder"),
+                    oninput: move |event: Event<FormData>| {
+                        set_default_tax_lines(&event.value());
+                        default_taxes.set(event.value());
+                    },
+                }
+                SettingsButtonRow {
+                    label: loc!("create-invoice"),
+                    dangerous: false,
+                    onclick: move |_| show_invoice_sheet(),
+                }
+            }
+
             // Activate in a future release
             /*
             SettingsTitleRow { title: loc!("csv") }
@@ -414,6 +1042,530 @@ pub fn SettingsView() -> Element {
                 }
             }*/
         }
+
+        div { class: if sheets.import_calendar_sheet.is_some() { "overlay visible" } else { "overlay" },
+            ""
+        }
+        div { class: if sheets.import_calendar_sheet.is_some() { "sheet visible" } else { "sheet" },
+            if sheets.import_calendar_sheet.is_some() {
+                ImportCalendarSheet { events: sheets.import_calendar_sheet.clone().unwrap_or_default() }
+            }
+        }
+
+        div { class: if sheets.new_invoice_is_shown { "overlay visible" } else { "overlay" }, "" }
+        div { class: if sheets.new_invoice_is_shown { "sheet visible" } else { "sheet" },
+            if sheets.new_invoice_is_shown {
+                InvoiceSheet {}
+            }
+        }
+
+        div { class: if sheets.import_tasks_sheet.is_some() { "overlay visible" } else { "overlay" },
+            ""
+        }
+        div { class: if sheets.import_tasks_sheet.is_some() { "sheet visible" } else { "sheet" },
+            if let Some(table) = sheets.import_tasks_sheet.clone() {
+                ImportTasksSheet { table }
+            }
+        }
+    }
+}
+
+#[component]
+fn ImportCalendarSheet(events: Vec<FurCalendarEvent>) -> Element {
+    let imported_before = use_hook(|| {
+        events
+            .iter()
+            .filter(|event| services::calendar::is_imported(event).unwrap_or(false))
+            .map(|event| event.uid.clone())
+            .collect::<HashSet<String>>()
+    });
+    let selected = use_signal(|| {
+        events
+            .iter()
+            .map(|event| event.uid.clone())
+            .filter(|uid| !imported_before.contains(uid))
+            .collect::<HashSet<String>>()
+    });
+
+    let settings = state::SETTINGS.cloned();
+    let rows: Vec<(String, String, String, bool)> = events
+        .iter()
+        .map(|event| {
+            (
+                event.uid.clone(),
+                calendar_event_time(event),
+                services::calendar::event_task_input(event, &settings),
+                imported_before.contains(&event.uid),
+            )
+        })
+        .collect();
+    let mut categories: Vec<String> = events
+        .iter()
+        .flat_map(|event| event.categories.clone())
+        .collect();
+    categories.sort_by_key(|category| category.to_lowercase());
+    categories.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
+
+    let chosen: Vec<FurCalendarEvent> = events
+        .iter()
+        .filter(|event| selected.read().contains(&event.uid))
+        .cloned()
+        .collect();
+    let chosen_for_todos = chosen.clone();
+
+    rsx! {
+        div { class: "sheet-contents",
+            h2 { {loc!("import-calendar")} }
+            div { class: "import-list",
+                for (uid , time , task_input , imported) in rows {
+                    ImportEventRow {
+                        key: "{uid}",
+                        uid: uid.clone(),
+                        time,
+                        task_input,
+                        imported,
+                        selected,
+                    }
+                }
+            }
+
+            if !categories.is_empty() {
+                h3 { {loc!("calendar-categories")} }
+                div { class: "settings-group",
+                    for category in categories {
+                        CategoryMappingRow { key: "{category}", category: category.clone() }
+                    }
+                }
+            }
+
+            button {
+                class: "sheet-cancel-button",
+                onclick: move |_| close_import_calendar_sheet(),
+                {loc!("cancel")}
+            }
+            button {
+                class: "sheet-cancel-button",
+                disabled: chosen_for_todos.is_empty(),
+                onclick: move |_| import_calendar_events(&chosen_for_todos, true),
+                {loc!("import-as-todos")}
+            }
+            button {
+                class: "sheet-primary-button",
+                disabled: chosen.is_empty(),
+                onclick: move |_| import_calendar_events(&chosen, false),
+                {loc!("import-as-tasks")}
+            }
+        }
+    }
+}
+
+#[component]
+fn InvoiceSheet() -> Element {
+    let mut first_day = use_signal(|| {
+        let today = Local::now().date_naive();
+        today
+            .with_day(1)
+            .unwrap_or(today)
+            .format("%Y-%m-%d")
+            .to_string()
+    });
+    let mut last_day = use_signal(|| Local::now().date_naive().format("%Y-%m-%d").to_string());
+    let mut chosen_project = use_signal(|| String::new());
+    let mut grouping = use_signal(|| InvoiceGrouping::TaskName);
+    let mut rounding_minutes = use_signal(|| 0);
+    let mut currency = use_signal(|| String::new());
+    // Until it's edited, the client is whatever was remembered for the project
+    let mut edited_client = use_signal(|| None::<String>);
+    let mut taxes =
+        use_signal(|| FurTaxLine::format_list(&state::SETTINGS.read().invoice_tax_lines));
+    let mut format = use_signal(|| InvoiceFormat::Pdf);
+
+    let date_range = parse_date_range(&first_day(), &last_day());
+    let projects = match date_range {
+        Some((first, last)) => {
+            services::invoices::billable_projects(first, last).unwrap_or_else(|e| {
+                error!("Error loading billable projects: {}", e);
+                Vec::new()
+            })
+        }
+        None => Vec::new(),
+    };
+    let project = projects
+        .iter()
+        .find(|project| **project == chosen_project())
+        .or(projects.first())
+        .cloned()
+        .unwrap_or_default();
+    let client = edited_client().unwrap_or_else(|| {
+        state::SETTINGS
+            .read()
+            .invoice_client(&project)
+            .unwrap_or_default()
+            .to_string()
+    });
+
+    let options = date_range
+        .filter(|_| !project.is_empty())
+        .map(|(first, last)| InvoiceOptions {
+            project: project.clone(),
+            first_day: first,
+            last_day: last,
+            grouping: grouping(),
+            rounding_minutes: rounding_minutes(),
+            currency: currency(),
+            client: client.clone(),
+            tax_lines: FurTaxLine::parse_list(&taxes()).unwrap_or_default(),
+        });
+    let invoice = options.as_ref().and_then(preview_invoice);
+    let can_create = invoice
+        .as_ref()
+        .is_some_and(|invoice| !invoice.lines.is_empty());
+
+    rsx! {
+        div { class: "sheet-contents",
+            h2 {
+                match &invoice {
+                    Some(invoice) => format!("{} {}", loc!("invoice"), invoice.number),
+                    None => loc!("create-invoice"),
+                }
+            }
+            div { class: "settings-group",
+                SettingsInputRow {
+                    label: loc!("from"),
+                    input_type: "date".to_string(),
+                    value: first_day(),
+                    placeholder: "YYYY-MM-DD".to_string(),
+                    oninput: move |event: Event<FormData>| first_day.set(event.value()),
+                }
+                SettingsInputRow {
+                    label: loc!("to"),
+                    input_type: "date".to_string(),
+                    value: last_day(),
+                    placeholder: "YYYY-MM-DD".to_string(),
+                    oninput: move |event: Event<FormData>| last_day.set(event.value()),
+                }
+                SettingsDropDownRow {
+                    label: loc!("project"),
+                    list_items: projects.clone(),
+                    selected_item: project.clone(),
+                    onchange: move |event: Event<FormData>| {
+                        chosen_project.set(event.value());
+                        edited_client.set(None);
+                    },
+                }
+                SettingsDropDownRow {
+                    label: loc!("group-lines-by"),
+                    list_items: InvoiceGrouping::ALL.iter().map(|grouping| grouping_label(*grouping)).collect::<Vec<String>>(),
+                    selected_item: grouping_label(grouping()),
+                    onchange: move |event: Event<FormData>| {
+                        if let Some(chosen) = InvoiceGrouping::ALL
+                            .into_iter()
+                            .find(|grouping| grouping_label(*grouping) == event.value())
+                        {
+                            grouping.set(chosen);
+                        }
+                    },
+                }
+                SettingsDropDownRow {
+                    label: loc!("rounding"),
+                    list_items: ROUNDING_CHOICES.iter().map(|minutes| rounding_label(*minutes)).collect::<Vec<String>>(),
+                    selected_item: rounding_label(rounding_minutes()),
+                    onchange: move |event: Event<FormData>| {
+                        if let Some(minutes) = ROUNDING_CHOICES
+                            .into_iter()
+                            .find(|minutes| rounding_label(*minutes) == event.value())
+                        {
+                            rounding_minutes.set(minutes);
+                        }
+                    },
+                }
+                SettingsInputRow {
+                    label: loc!("currency"),
+                    input_type: "text".to_string(),
+                    value: currency(),
+                    placeholder: loc!("currency-automatic"),
+                    oninput: move |event: Event<FormData>| currency.set(event.value()),
+                }
+                SettingsTextAreaRow {
+                    label: loc!("bill-to"),
+                    value: client.clone(),
+                    placeholder: loc!("client-details-placeholder"),
+                    oninput: move |event: Event<FormData>| edited_client.set(Some(event.value())),
+                }
+                SettingsInputRow {
+                    label: loc!("taxes"),
+                    input_type: "text".to_string(),
+                    value: taxes(),
+                    placeholder: loc!("taxes-placeholder"),
+                    oninput: move |event: Event<FormData>| taxes.set(event.value()),
+                }
+                SettingsDropDownRow {
+                    label: loc!("invoice-format"),
+                    list_items: InvoiceFormat::ALL.iter().map(|format| format.label()).collect::<Vec<String>>(),
+                    selected_item: format().label(),
+                    onchange: move |event: Event<FormData>| {
+                        if let Some(chosen) = InvoiceFormat::ALL
+                            .into_iter()
+                            .find(|format| format.label() == event.value())
+                        {
+                            format.set(chosen);
+                        }
+                    },
+                }
+            }
+
+            if let Some(invoice) = &invoice {
+                div { class: "import-list",
+                    for (index , line) in invoice.lines.iter().enumerate() {
+                        div { key: "{index}", class: "import-row",
+                            div { class: "import-row-details",
+                                div { "{line.description}" }
+                                div { class: "import-row-note",
+                                    {
+                                        format!(
+                                            "{} × {} = {}",
+                                            format_hours(line.hours),
+                                            format_money(line.rate as f64, &invoice.currency),
+                                            format_money(line.amount, &invoice.currency),
+                                        )
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    if invoice.lines.is_empty() {
+                        div { class: "import-row-note", {loc!("no-billable-tasks")} }
+                    }
+                    div { class: "import-row-note",
+                        {format!("{}: {}", loc!("subtotal"), format_money(invoice.subtotal, &invoice.currency))}
+                    }
+                    for (index , tax) in invoice.taxes.iter().enumerate() {
+                        div { key: "tax-{index}", class: "import-row-note",
+                            {format!("{}: {}", tax_label(tax), format_money(tax.amount, &invoice.currency))}
+                        }
+                    }
+                    div { class: "import-row-details",
+                        strong {
+                            {format!("{}: {}", loc!("total"), format_money(invoice.total, &invoice.currency))}
+                        }
+                    }
+                    if invoice.already_invoiced > 0 {
+                        div { class: "import-row-note",
+                            {
+                                loc!(
+                                    "tasks-already-invoiced",
+                                    &HashMap::from([("count", FluentValue::from(invoice.already_invoiced))])
+                                )
+                            }
+                        }
+                    }
+                }
+            } else if projects.is_empty() {
+                p { {loc!("no-billable-tasks")} }
+            }
+
+            button {
+                class: "sheet-cancel-button",
+                onclick: move |_| close_invoice_sheet(),
+                {loc!("cancel")}
+            }
+            button {
+                class: "sheet-primary-button",
+                disabled: !can_create,
+                onclick: move |_| {
+                    if let Some(options) = &options {
+                        create_invoice(options, format());
+                    }
+                },
+                {loc!("create-invoice")}
+            }
+        }
+    }
+}
+
+#[component]
+fn ImportTasksSheet(table: ImportTable) -> Element {
+    let source = table.source;
+    let headers = table.headers.clone();
+    let mut mapping = use_signal(|| table.default_mapping());
+    let preview = use_memo(move || preview_import(&table, &mapping.read()));
+
+    let count = |status: PreviewStatus| {
+        preview
+            .read()
+            .iter()
+            .filter(|row| row.status == status)
+            .count()
+    };
+    let new_count = count(PreviewStatus::New);
+    let summary = loc!(
+        "import-preview-summary",
+        &HashMap::from([
+            ("new", FluentValue::from(new_count)),
+            (
+                "duplicates",
+                FluentValue::from(count(PreviewStatus::Duplicate))
+            ),
+            ("invalid", FluentValue::from(count(PreviewStatus::Invalid))),
+        ])
+    );
+    let hidden_count = preview
+        .read()
+        .len()
+        .saturating_sub(IMPORT_PREVIEW_ROWS_SHOWN);
+
+    let rows: Vec<(usize, String, String)> = preview
+        .read()
+        .iter()
+        .take(IMPORT_PREVIEW_ROWS_SHOWN)
+        .enumerate()
+        .map(|(index, row)| {
+            let Some(task) = &row.task else {
+                return (index, loc!("invalid-entry"), String::new());
+            };
+            let mut title = task.name.clone();
+            if !task.project.is_empty() {
+                title += &format!(" @{}", task.project);
+            }
+            if !task.tags.is_empty() {
+                title += &format!(" #{}", task.tags);
+            }
+            let mut note = format!(
+                "{} – {}",
+                task.start_time.format("%Y-%m-%d %H:%M"),
+                task.stop_time.format("%H:%M")
+            );
+            if task.rate > 0.0 {
+                note += &format!(" · {:.2} {}", task.rate, task.currency);
+            }
+            if row.status == PreviewStatus::Duplicate {
+                note += &format!(" · {}", loc!("duplicate"));
+            }
+            (index, title, note)
+        })
+        .collect();
+
+    let mut column_names = vec![loc!("column-not-used")];
+    column_names.extend(headers.iter().cloned());
+
+    rsx! {
+        div { class: "sheet-contents",
+            h2 { {import_tracker_title(source)} }
+            p { "{summary}" }
+            div { class: "import-list",
+                for (index , title , note) in rows {
+                    div { key: "{index}", class: "import-row",
+                        div { class: "import-row-details",
+                            div { "{title}" }
+                            div { class: "import-row-note", "{note}" }
+                        }
+                    }
+                }
+                if hidden_count > 0 {
+                    div { class: "import-row-note",
+                        {
+                            loc!(
+                                "import-preview-more",
+                                &HashMap::from([("count", FluentValue::from(hidden_count))])
+                            )
+                        }
+                    }
+                }
+            }
+
+            h3 { {loc!("import-columns")} }
+            div { class: "settings-group",
+                for field in ImportField::ALL {
+                    SettingsDropDownRow {
+                        key: "{field:?}",
+                        label: import_field_label(field),
+                        list_items: column_names.clone(),
+                        selected_item: mapping
+                            .read()
+                            .get(&field)
+                            .and_then(|&column| headers.get(column))
+                            .cloned()
+                            .unwrap_or_else(|| loc!("column-not-used")),
+                        onchange: {
+                            let headers = headers.clone();
+                            move |event: Event<FormData>| {
+                                match headers.iter().position(|header| *header == event.value()) {
+                                    Some(column) => mapping.write().insert(field, column),
+                                    None => mapping.write().remove(&field),
+                                };
+                            }
+                        },
+                    }
+                }
+            }
+
+            button {
+                class: "sheet-cancel-button",
+                onclick: move |_| close_import_tasks_sheet(),
+                {loc!("cancel")}
+            }
+            button {
+                class: "sheet-primary-button",
+                disabled: new_count == 0,
+                onclick: move |_| import_tracker_rows(source, &preview.read()),
+                {loc!("import")}
+            }
+        }
+    }
+}
+
+#[component]
+fn ImportEventRow(
+    uid: String,
+    time: String,
+    task_input: String,
+    imported: bool,
+    selected: Signal<HashSet<String>>,
+) -> Element {
+    let is_selected = selected.read().contains(&uid);
+    let note = if imported {
+        format!("{} · {}", time, loc!("already-imported"))
+    } else {
+        time
+    };
+
+    rsx! {
+        label { class: "import-row",
+            input {
+                r#type: "checkbox",
+                checked: is_selected,
+                disabled: imported,
+                onchange: move |_| {
+                    let mut selected = selected.write();
+                    if !selected.remove(&uid) {
+                        selected.insert(uid.clone());
+                    }
+                },
+            }
+            div { class: "import-row-details",
+                div { "{task_input}" }
+                div { class: "import-row-note", "{note}" }
+            }
+        }
+    }
+}
+
+#[component]
+fn CategoryMappingRow(category: String) -> Element {
+    let mapping = state::SETTINGS
+        .read()
+        .category_mapping(&category)
+        .unwrap_or_default()
+        .to_string();
+
+    rsx! {
+        SettingsInputRow {
+            label: category.clone(),
+            input_type: "text".to_string(),
+            value: mapping,
+            placeholder: loc!("project-and-tags-placeholder"),
+            oninput: move |event: Event<FormData>| set_category_mapping(&category, &event.value()),
+        }
     }
 }
 
@@ -501,6 +1653,27 @@ fn SettingsDropDownRow(
     }
 }
 
+#[component]
+fn SettingsTextAreaRow(
+    label: String,
+    value: String,
+    placeholder: String,
+    oninput: EventHandler<FormEvent>,
+) -> Element {
+    rsx! {
+        div { class: "settings-item",
+            div { class: "settings-label", "{label}" }
+            textarea {
+                class: "settings-textarea",
+                rows: 3,
+                value,
+                oninput,
+                placeholder,
+            }
+        }
+    }
+}
+
 #[component]
 fn SettingsInputRow(
     label: String,
@@ -509,6 +1682,8 @@ fn SettingsInputRow(
     value: String,
     placeholder: String,
     oninput: EventHandler<FormEvent>,
+    /// Called once editing is done (the field loses focus or Enter is pressed)
+    onchange: Option<EventHandler<FormEvent>>,
 ) -> Element {
     rsx! {
         div { class: "settings-item",
@@ -518,12 +1693,52 @@ fn SettingsInputRow(
                 r#type: input_type,
                 value,
                 oninput,
+                onchange: move |event| {
+                    if let Some(onchange) = onchange {
+                        onchange.call(event);
+                    }
+                },
                 placeholder,
             }
         }
     }
 }
 
+#[component]
+fn SettingsTextRow(label: String, value: String) -> Element {
+    rsx! {
+        div { class: "settings-item",
+            div { class: "settings-label", "{label}" }
+            div { class: "settings-value", "{value}" }
+        }
+    }
+}
+
+#[component]
+fn SettingsDeviceRow(device: FurDevice) -> Element {
+    let this_device = is_this_device(&device);
+    let name = if this_device {
+        format!("{} ({})", device.display_name(), loc!("this-device"))
+    } else {
+        device.display_name()
+    };
+    let details = device_details(&device);
+
+    rsx! {
+        div { class: "settings-item",
+            div { class: "settings-label", "{name}" }
+            div { class: "settings-value", "{details}" }
+            if !this_device {
+                button {
+                    class: "settings-button dangerous",
+                    onclick: move |_| revoke_device_pressed(&device),
+                    {loc!("revoke")}
+                }
+            }
+        }
+    }
+}
+
 #[component]
 fn SettingsButtonRow(label: String, dangerous: bool, onclick: EventHandler<MouseEvent>) -> Element {
     rsx! {
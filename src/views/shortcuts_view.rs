@@ -20,18 +20,17 @@ use rand::Rng;
 
 use crate::{
     constants::{SHEET_CSS, SHORTCUTS_CSS},
-    database,
     helpers::{
         actions,
         color_utils::FromHex,
         formatters,
         icons::{BsPencil, BsPencilFill, BsPlusLg, BsTrash3, Icon},
-        server::sync::sync_after_change,
-        views::{shortcuts::update_all_shortcuts, task_input::validate_task_input},
+        views::{alerts, task_input::validate_task_input},
     },
     loc,
     localization::Localization,
     models::fur_shortcut::FurShortcut,
+    services,
     state::{self, SHORTCUT_ID_TO_DELETE},
 };
 
@@ -191,24 +190,22 @@ fn NewShortcutSheet() -> Element {
                         let (name, project, tags, rate) = formatters::split_task_input(
                             &task_input.cloned(),
                         );
-                        database::shortcuts::insert_shortcut(
-                                &FurShortcut::new(
-                                    name,
-                                    tags,
-                                    project,
-                                    rate,
-                                    String::new(),
-                                    color_hex.cloned(),
-                                ),
-                            )
-                            .expect("Couldn't write task to database.");
+                        if let Err(e) = services::shortcuts::add(&FurShortcut::new(
+                            name,
+                            tags,
+                            project,
+                            rate,
+                            String::new(),
+                            color_hex.cloned(),
+                        )) {
+                            alerts::show_error(&e);
+                            return;
+                        }
                         task_input.set(String::new());
                         color_hex.set(random_color());
                         let mut new_sheets = state::SHEETS.cloned();
                         new_sheets.new_shortcut_is_shown = false;
                         *state::SHEETS.write() = new_sheets;
-                        update_all_shortcuts();
-                        sync_after_change();
                     }
                 },
                 "{save_text}"
@@ -235,10 +232,9 @@ fn EditShortcutSheet(shortcut: Option<FurShortcut>) -> Element {
                             onclick: move |_| {
                                 fn delete_shortcut() {
                                     if let Some(shortcut_id) = SHORTCUT_ID_TO_DELETE.cloned() {
-                                        if let Err(e) = database::shortcuts::delete_shortcut_by_id(
-                                            &shortcut_id,
-                                        ) {
-                                            eprintln!("Failed to delete shortcut: {}", e);
+                                        if let Err(e) = services::shortcuts::delete(&shortcut_id) {
+                                            alerts::show_error(&e);
+                                            return;
                                         }
                                     }
                                     let mut alert = state::ALERT.cloned();
@@ -248,8 +244,6 @@ fn EditShortcutSheet(shortcut: Option<FurShortcut>) -> Element {
                                     *SHORTCUT_ID_TO_DELETE.write() = None;
                                     alert.close();
                                     *state::ALERT.write() = alert.clone();
-                                    update_all_shortcuts();
-                                    sync_after_change();
                                 }
                                 fn close_alert() {
                                     let mut alert = state::ALERT.cloned();
@@ -268,16 +262,13 @@ fn EditShortcutSheet(shortcut: Option<FurShortcut>) -> Element {
                                     alert.cancel_button = Some((loc!("cancel"), || close_alert()));
                                     *state::ALERT.write() = alert.clone();
                                 } else {
-                                    if let Err(e) = database::shortcuts::delete_shortcut_by_id(
-                                        &shortcut_clone_two.uid,
-                                    ) {
-                                        eprintln!("Failed to delete shortcut: {}", e);
+                                    if let Err(e) = services::shortcuts::delete(&shortcut_clone_two.uid) {
+                                        alerts::show_error(&e);
+                                        return;
                                     }
                                     let mut new_sheets = state::SHEETS.cloned();
                                     new_sheets.edit_shortcut_sheet = None;
                                     *state::SHEETS.write() = new_sheets;
-                                    update_all_shortcuts();
-                                    sync_after_change();
                                 }
                             },
                             Icon { icon: BsTrash3, width: 25, height: 25 }
@@ -331,13 +322,13 @@ fn EditShortcutSheet(shortcut: Option<FurShortcut>) -> Element {
                             shortcut_clone.tags = tags.clone();
                             shortcut_clone.rate = rate.clone();
                             shortcut_clone.color_hex = color_hex.cloned();
-                            database::shortcuts::update_shortcut(&shortcut_clone)
-                                .expect("Couldn't write task to database.");
+                            if let Err(e) = services::shortcuts::update(&shortcut_clone) {
+                                alerts::show_error(&e);
+                                return;
+                            }
                             let mut new_sheets = state::SHEETS.cloned();
                             new_sheets.edit_shortcut_sheet = None;
                             *state::SHEETS.write() = new_sheets;
-                            update_all_shortcuts();
-                            sync_after_change();
                         }
                     },
                     {loc!("save")}
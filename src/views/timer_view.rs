@@ -22,7 +22,9 @@ use chrono::{
 };
 use dioxus::prelude::*;
 use fluent::FluentValue;
+use log::error;
 
+use crate::state::{self, TASK_IDS_TO_DELETE};
 use crate::{
     constants::SHEET_CSS,
     helpers::{
@@ -31,15 +33,11 @@ use crate::{
         views::{task_input::validate_task_input, timer::get_stopped_timer_text},
     },
 };
-use crate::{database, loc, models::fur_task::FurTask};
 use crate::{
     helpers::formatters::seconds_to_formatted_duration, models::fur_task_group::FurTaskGroup,
 };
-use crate::{
-    helpers::server::sync::sync_after_change,
-    state::{self, TASK_IDS_TO_DELETE},
-};
-use crate::{helpers::views::task_history, localization::Localization};
+use crate::{helpers::views::alerts, services};
+use crate::{loc, localization::Localization, models::fur_task::FurTask};
 
 static DATE_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M";
 static TIME_FORMAT: &str = "%H:%M";
@@ -152,10 +150,8 @@ pub fn TaskInput() -> Element {
                             *state::TASK_INPUT.write() = old_value.clone();
                         } else {
                             *state::TASK_INPUT.write() = new_value.clone();
-                            if let Err(e) = database::persistence::update_persisting_timer_task_input(
-                                &new_value,
-                            ) {
-                                eprintln!("Error updating persisting timer task input: {}", e);
+                            if let Err(e) = services::timer::update_task_input(&new_value) {
+                                error!("{}", e);
                             }
                         }
                     } else {
@@ -203,7 +199,8 @@ pub fn HistoryTitleRow(date: NaiveDate, task_groups: Vec<FurTaskGroup>) -> Eleme
             )
         },
     );
-    let total_time_str = formatters::seconds_to_formatted_duration(total_time);
+    let total_time_str =
+        formatters::seconds_to_formatted_duration(total_time, state::SETTINGS.read().show_seconds);
     let formatted_date = formatters::format_title_date(&date);
     let total_earnings_str = format!("${:.2}", total_earnings);
 
@@ -225,7 +222,10 @@ pub fn HistoryTitleRow(date: NaiveDate, task_groups: Vec<FurTaskGroup>) -> Eleme
 #[component]
 pub fn HistoryGroupContainer(task_group: FurTaskGroup) -> Element {
     let number_of_tasks = task_group.tasks.len();
-    let total_time_str = formatters::seconds_to_formatted_duration(task_group.total_time);
+    let total_time_str = formatters::seconds_to_formatted_duration(
+        task_group.total_time,
+        state::SETTINGS.read().show_seconds,
+    );
     let total_earnings = task_group.rate * (task_group.total_time as f32 / 3600.0);
     let total_earnings_str = format!("${:.2}", total_earnings);
 
@@ -366,28 +366,24 @@ fn NewTaskSheet() -> Element {
                                 let (name, project, tags, rate) = formatters::split_task_input(
                                     &task_input.cloned(),
                                 );
-                                database::tasks::insert_task(
-                                        &FurTask::new(
-                                            name,
-                                            parsed_start_time,
-                                            parsed_stop_time,
-                                            tags,
-                                            project,
-                                            rate,
-                                            String::new(),
-                                        ),
-                                    )
-                                    .expect("Couldn't write task to database.");
+                                if let Err(e) = services::tasks::add(&FurTask::new(
+                                    name,
+                                    parsed_start_time,
+                                    parsed_stop_time,
+                                    tags,
+                                    project,
+                                    rate,
+                                    String::new(),
+                                )) {
+                                    alerts::show_error(&e);
+                                    return;
+                                }
                                 task_input.set(String::new());
                                 start_time.set(one_hour_ago.format(DATE_TIME_FORMAT).to_string());
                                 stop_time.set(Local::now().format(DATE_TIME_FORMAT).to_string());
                                 let mut new_sheets = state::SHEETS.cloned();
                                 new_sheets.new_task_is_shown = false;
                                 *state::SHEETS.write() = new_sheets;
-                                task_history::update_task_history(
-                                    state::SETTINGS.read().days_to_show,
-                                );
-                                sync_after_change();
                             }
                         }
                     }
@@ -436,8 +432,9 @@ fn GroupDetailsSheet(task_group: Option<FurTaskGroup>) -> Element {
                             onclick: move |_| {
                                 fn delete_whole_group() {
                                     if let Some(task_ids) = TASK_IDS_TO_DELETE.cloned() {
-                                        if let Err(e) = database::tasks::delete_tasks_by_ids(&task_ids) {
-                                            eprintln!("Failed to delete tasks: {}", e);
+                                        if let Err(e) = services::tasks::delete(&task_ids) {
+                                            alerts::show_error(&e);
+                                            return;
                                         }
                                     }
                                     let mut alert = state::ALERT.cloned();
@@ -447,8 +444,6 @@ fn GroupDetailsSheet(task_group: Option<FurTaskGroup>) -> Element {
                                     *TASK_IDS_TO_DELETE.write() = None;
                                     alert.close();
                                     *state::ALERT.write() = alert.clone();
-                                    task_history::update_task_history(state::SETTINGS.read().days_to_show);
-                                    sync_after_change();
                                 }
                                 fn close_alert() {
                                     let mut alert = state::ALERT.cloned();
@@ -467,16 +462,13 @@ fn GroupDetailsSheet(task_group: Option<FurTaskGroup>) -> Element {
                                         alert.cancel_button = Some((loc!("cancel"), || close_alert()));
                                         *state::ALERT.write() = alert.clone();
                                     } else {
-                                        if let Err(e) = database::tasks::delete_tasks_by_ids(
-                                            &task_group.all_task_ids(),
-                                        ) {
-                                            eprintln!("Failed to delete tasks: {}", e);
+                                        if let Err(e) = services::tasks::delete(&task_group.all_task_ids()) {
+                                            alerts::show_error(&e);
+                                            return;
                                         }
                                         let mut new_sheets = state::SHEETS.cloned();
                                         new_sheets.group_details_sheet = None;
                                         *state::SHEETS.write() = new_sheets;
-                                        task_history::update_task_history(state::SETTINGS.read().days_to_show);
-                                        sync_after_change();
                                     }
                                 }
                             },
@@ -546,8 +538,9 @@ fn TaskEditSheet(task: Option<FurTask>) -> Element {
                         onclick: move |_| {
                             fn delete_task() {
                                 if let Some(tasks_id) = TASK_IDS_TO_DELETE.cloned() {
-                                    if let Err(e) = database::tasks::delete_tasks_by_ids(&tasks_id) {
-                                        eprintln!("Failed to delete task: {}", e);
+                                    if let Err(e) = services::tasks::delete(&tasks_id) {
+                                        alerts::show_error(&e);
+                                        return;
                                     }
                                 }
                                 let mut alert = state::ALERT.cloned();
@@ -558,8 +551,6 @@ fn TaskEditSheet(task: Option<FurTask>) -> Element {
                                 *TASK_IDS_TO_DELETE.write() = None;
                                 alert.close();
                                 *state::ALERT.write() = alert.clone();
-                                task_history::update_task_history(state::SETTINGS.read().days_to_show);
-                                sync_after_change();
                             }
                             fn close_alert() {
                                 let mut alert = state::ALERT.cloned();
@@ -578,15 +569,14 @@ fn TaskEditSheet(task: Option<FurTask>) -> Element {
                                 alert.cancel_button = Some((loc!("cancel"), || close_alert()));
                                 *state::ALERT.write() = alert.clone();
                             } else {
-                                if let Err(e) = database::tasks::delete_tasks_by_ids(&task_uid_vec) {
-                                    eprintln!("Failed to delete task: {}", e);
+                                if let Err(e) = services::tasks::delete(&task_uid_vec) {
+                                    alerts::show_error(&e);
+                                    return;
                                 }
                                 let mut new_sheets = state::SHEETS.cloned();
                                 new_sheets.task_edit_sheet = None;
                                 new_sheets.group_details_sheet = None;
                                 *state::SHEETS.write() = new_sheets;
-                                task_history::update_task_history(state::SETTINGS.read().days_to_show);
-                                sync_after_change();
                             }
                         },
                         Icon { icon: BsTrash3, width: 25, height: 25 }
@@ -681,26 +671,23 @@ fn TaskEditSheet(task: Option<FurTask>) -> Element {
                                         || rate != task.rate || parsed_start_time != task.start_time
                                         || parsed_stop_time != task.stop_time
                                     {
-                                        database::tasks::update_task(
-                                                &FurTask {
-                                                    name,
-                                                    start_time: parsed_start_time,
-                                                    stop_time: parsed_stop_time,
-                                                    tags,
-                                                    project,
-                                                    rate,
-                                                    currency: task_currency.clone(),
-                                                    uid: task_uid.clone(),
-                                                    is_deleted: task.is_deleted,
-                                                    last_updated: chrono::Utc::now().timestamp(),
-                                                },
-                                            )
-                                            .expect("Couldn't update task in database.");
+                                        if let Err(e) = services::tasks::update(&FurTask {
+                                            name,
+                                            start_time: parsed_start_time,
+                                            stop_time: parsed_stop_time,
+                                            tags,
+                                            project,
+                                            rate,
+                                            currency: task_currency.clone(),
+                                            uid: task_uid.clone(),
+                                            is_deleted: task.is_deleted,
+                                            last_updated: chrono::Utc::now().timestamp(),
+                                            invoice_number: task.invoice_number.clone(),
+                                        }) {
+                                            alerts::show_error(&e);
+                                            return;
+                                        }
                                     }
-                                    task_history::update_task_history(
-                                        state::SETTINGS.read().days_to_show,
-                                    );
-                                    sync_after_change();
                                     let mut new_sheets = state::SHEETS.cloned();
                                     new_sheets.group_details_sheet = None;
                                     new_sheets.task_edit_sheet = None;
@@ -812,25 +799,22 @@ fn AddToGroupSheet(group: Option<FurTaskGroup>) -> Element {
                                 let (name, project, tags, rate) = formatters::split_task_input(
                                     &group.to_string(),
                                 );
-                                if let Err(e) = database::tasks::insert_task(
-                                    &FurTask::new(
-                                        name,
-                                        parsed_start_time,
-                                        parsed_stop_time,
-                                        tags,
-                                        project,
-                                        rate,
-                                        String::new(),
-                                    ),
-                                ) {
-                                    eprintln!("Error inserting task into database: {}", e);
+                                if let Err(e) = services::tasks::add(&FurTask::new(
+                                    name,
+                                    parsed_start_time,
+                                    parsed_stop_time,
+                                    tags,
+                                    project,
+                                    rate,
+                                    String::new(),
+                                )) {
+                                    alerts::show_error(&e);
+                                    return;
                                 }
                                 let mut new_sheets = state::SHEETS.cloned();
                                 new_sheets.group_details_sheet = None;
                                 new_sheets.add_to_group_sheet = None;
                                 *state::SHEETS.write() = new_sheets;
-                                task_history::update_task_history(state::SETTINGS.read().days_to_show);
-                                sync_after_change();
                             }
                         }
                     },
@@ -883,15 +867,14 @@ fn EditGroupSheet(group: Option<FurTaskGroup>) -> Element {
                         new_group.project = project;
                         new_group.tags = tags;
                         new_group.rate = rate;
-                        if let Err(e) = database::tasks::update_group_of_tasks(&new_group) {
-                            eprintln!("Error updating task group in database: {}", e);
+                        if let Err(e) = services::tasks::update_group(&new_group) {
+                            alerts::show_error(&e);
+                            return;
                         }
                         let mut new_sheets = state::SHEETS.cloned();
                         new_sheets.group_details_sheet = None;
                         new_sheets.edit_group_sheet = None;
                         *state::SHEETS.write() = new_sheets;
-                        task_history::update_task_history(state::SETTINGS.read().days_to_show);
-                        sync_after_change();
                     },
                     {loc!("save")}
                 }
@@ -930,7 +913,10 @@ fn get_total_task_time_string(task: &FurTask) -> String {
         "total-time-dynamic",
         &HashMap::from([(
             "time",
-            FluentValue::from(seconds_to_formatted_duration(task.total_time_in_seconds()))
+            FluentValue::from(seconds_to_formatted_duration(
+                task.total_time_in_seconds(),
+                state::SETTINGS.read().show_seconds,
+            ))
         )])
     )
 }
@@ -17,23 +17,20 @@
 use chrono::{offset::LocalResult, Local, NaiveDate, TimeZone};
 use dioxus::prelude::*;
 
+use crate::helpers::actions;
 use crate::{
     constants::TODO_CSS,
     helpers::{
         icons::{BsCheckSquare, BsPlayFill, BsPlusLg, BsSquare, BsTrash3, Icon},
-        server::sync::sync_after_change,
-        views::todos::update_all_todos,
+        views::alerts,
     },
     loc,
     localization::Localization,
+    services,
     state::{self, TODO_ID_TO_DELETE},
 };
-use crate::{database, helpers::actions};
+use crate::{helpers::formatters, models::fur_todo::FurTodo};
 use crate::{helpers::views::task_input::validate_task_input, state::TIMER_IS_RUNNING};
-use crate::{
-    helpers::{self, formatters},
-    models::fur_todo::FurTodo,
-};
 
 static CHECK_BOX_SIZE: u32 = 14;
 
@@ -109,12 +106,8 @@ fn TodoListItem(todo: FurTodo) -> Element {
                 button {
                     class: "no-bg-button",
                     onclick: move |_| {
-                        match database::todos::toggle_todo_completed(&todo_uid) {
-                            Ok(_) => {
-                                helpers::views::todos::update_all_todos();
-                                sync_after_change();
-                            }
-                            Err(e) => eprintln!("Error updating todo: {}", e),
+                        if let Err(e) = services::todos::toggle_completed(&todo_uid) {
+                            alerts::show_error(&e);
                         }
                     },
                     if todo.is_completed {
@@ -232,17 +225,17 @@ fn NewTodoSheet() -> Element {
                                     let (name, project, tags, rate) = formatters::split_task_input(
                                         &task_input.cloned(),
                                     );
-                                    database::todos::insert_todo(
-                                            &FurTodo::new(name, project, tags, rate, parsed_datetime),
-                                        )
-                                        .expect("Couldn't write task to database.");
+                                    if let Err(e) = services::todos::add(
+                                        &FurTodo::new(name, project, tags, rate, parsed_datetime),
+                                    ) {
+                                        alerts::show_error(&e);
+                                        return;
+                                    }
                                     task_input.set(String::new());
                                     date.set(Local::now().date_naive().to_string());
                                     let mut new_sheets = state::SHEETS.cloned();
                                     new_sheets.new_todo_is_shown = false;
                                     *state::SHEETS.write() = new_sheets;
-                                    update_all_todos();
-                                    sync_after_change();
                                 }
                             }
                         }
@@ -270,8 +263,9 @@ fn EditTodoSheet(todo: Option<FurTodo>) -> Element {
                         onclick: move |_| {
                             fn delete_todo() {
                                 if let Some(todo_id) = TODO_ID_TO_DELETE.cloned() {
-                                    if let Err(e) = database::todos::delete_todo_by_id(&todo_id) {
-                                        eprintln!("Failed to delete todo: {}", e);
+                                    if let Err(e) = services::todos::delete(&todo_id) {
+                                        alerts::show_error(&e);
+                                        return;
                                     }
                                 }
                                 let mut alert = state::ALERT.cloned();
@@ -281,8 +275,6 @@ fn EditTodoSheet(todo: Option<FurTodo>) -> Element {
                                 *TODO_ID_TO_DELETE.write() = None;
                                 alert.close();
                                 *state::ALERT.write() = alert.clone();
-                                update_all_todos();
-                                sync_after_change();
                             }
                             fn close_alert() {
                                 let mut alert = state::ALERT.cloned();
@@ -301,14 +293,13 @@ fn EditTodoSheet(todo: Option<FurTodo>) -> Element {
                                 alert.cancel_button = Some((loc!("cancel"), || close_alert()));
                                 *state::ALERT.write() = alert.clone();
                             } else {
-                                if let Err(e) = database::todos::delete_todo_by_id(&todo_uid) {
-                                    eprintln!("Failed to delete todo: {}", e);
+                                if let Err(e) = services::todos::delete(&todo_uid) {
+                                    alerts::show_error(&e);
+                                    return;
                                 }
                                 let mut new_sheets = state::SHEETS.cloned();
                                 new_sheets.edit_todo_sheet = None;
                                 *state::SHEETS.write() = new_sheets;
-                                update_all_todos();
-                                sync_after_change();
                             }
                         },
                         Icon { icon: BsTrash3, width: 25, height: 25 }
@@ -369,14 +360,13 @@ fn EditTodoSheet(todo: Option<FurTodo>) -> Element {
                                         new_todo.rate = rate;
                                         new_todo.date = parsed_datetime;
                                         new_todo.last_updated = chrono::Utc::now().timestamp();
-                                        if let Err(e) = database::todos::update_todo(&new_todo) {
-                                            eprintln!("Error updating todo in database: {}", e);
+                                        if let Err(e) = services::todos::update(&new_todo) {
+                                            alerts::show_error(&e);
+                                            return;
                                         }
                                         let mut new_sheets = state::SHEETS.cloned();
                                         new_sheets.edit_todo_sheet = None;
                                         *state::SHEETS.write() = new_sheets;
-                                        update_all_todos();
-                                        sync_after_change();
                                     }
                                 }
                             }
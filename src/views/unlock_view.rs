@@ -0,0 +1,76 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use dioxus::prelude::*;
+use log::error;
+
+use crate::{constants::SETTINGS_CSS, database, loc, localization::Localization, state};
+
+/// Shown at launch in place of the app while the database is encrypted and locked
+#[component]
+pub fn UnlockView() -> Element {
+    let mut passphrase = use_signal(|| String::new());
+    let mut error = use_signal(|| Option::<String>::None);
+
+    let mut unlock = move || {
+        if let Err(e) = database::encryption::unlock(&passphrase()) {
+            error!("Error unlocking database: {}", e);
+            error.set(Some(loc!("incorrect-database-passphrase")));
+            return;
+        }
+        passphrase.set(String::new());
+        match crate::prepare_database() {
+            Ok(_) => *state::DATABASE_LOCKED.write() = false,
+            Err(e) => {
+                error!("Error preparing database: {}", e);
+                error.set(Some(loc!("error-opening-database")));
+            }
+        }
+    };
+
+    rsx! {
+        document::Stylesheet { href: SETTINGS_CSS }
+
+        div { id: "settings",
+            div { class: "settings-header", {loc!("database-locked")} }
+            div { class: "settings-group",
+                div { class: "settings-item",
+                    div { class: "settings-label", {loc!("database-passphrase")} }
+                    input {
+                        class: "settings-input",
+                        r#type: "password",
+                        value: passphrase(),
+                        placeholder: loc!("passphrase"),
+                        oninput: move |event| passphrase.set(event.value()),
+                        onkeydown: move |event| {
+                            if event.key() == Key::Enter {
+                                unlock();
+                            }
+                        },
+                    }
+                }
+                if let Some(message) = error() {
+                    div { class: "settings-item",
+                        div { class: "settings-value dangerous", "{message}" }
+                    }
+                }
+                div { class: "settings-item",
+                    button { class: "settings-button", onclick: move |_| unlock(), "{loc!(\"unlock\")}" }
+                }
+            }
+        }
+    }
+}
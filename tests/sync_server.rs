@@ -0,0 +1,280 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2025  Ricky Kresslein <rk@unobserved.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Login, token refresh and sync against the mock sync server on an ephemeral port
+
+#[allow(dead_code)]
+#[path = "../examples/mock_sync_server.rs"]
+mod mock_sync_server;
+
+use std::{env, fs};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{Local, Utc};
+use furtherance_core::{
+    database,
+    models::{
+        fur_settings::DATA_DIR_VARIABLE, fur_sync_log::FurSyncLogEntry,
+        fur_sync_progress::FurSyncProgress, fur_task::FurTask, fur_user::FurUser,
+    },
+    server::{
+        api::{self, ApiError},
+        encryption,
+        key_storage::KeyStorageKind,
+        sync::{self, SyncSummary},
+    },
+    services,
+};
+use serde_json::{json, Value};
+use tokio::sync::{Mutex, MutexGuard};
+
+const EMAIL: &str = "test@example.com";
+const SYNC_KEY: &str = "integration-test-sync-key";
+const ENCRYPTION_KEY: [u8; 32] = [7; 32];
+
+/// The tests share a data folder, so they take turns
+static DATA: Mutex<()> = Mutex::const_new(());
+
+async fn fresh_data() -> MutexGuard<'static, ()> {
+    let guard = DATA.lock().await;
+    wipe_data();
+    guard
+}
+
+/// Start over with an empty database and a new device id
+fn wipe_data() {
+    let path = env::temp_dir().join(format!("furtherance-sync-tests-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&path);
+    env::set_var(DATA_DIR_VARIABLE, &path);
+    database::init::db_init().unwrap();
+}
+
+async fn start_server() -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, mock_sync_server::app()).await });
+    format!("http://{}", address)
+}
+
+async fn configure(server: &str, config: Value) {
+    reqwest::Client::new()
+        .post(format!("{}/mock/config", server))
+        .json(&config)
+        .send()
+        .await
+        .unwrap();
+}
+
+/// Uids of the tasks the server has stored for the account
+async fn stored_task_uids(server: &str) -> Vec<String> {
+    let state: Value = reqwest::get(format!("{}/mock/state", server))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    state["accounts"][EMAIL]["records"]["tasks"]
+        .as_object()
+        .map(|tasks| tasks.keys().cloned().collect())
+        .unwrap_or_default()
+}
+
+async fn sign_in(server: &str) -> FurUser {
+    let response = api::login(
+        EMAIL.to_string(),
+        SYNC_KEY.to_string(),
+        None,
+        server.to_string(),
+    )
+    .await
+    .unwrap();
+    FurUser {
+        email: EMAIL.to_string(),
+        encrypted_key: String::new(),
+        key_nonce: String::new(),
+        access_token: response.access_token,
+        refresh_token: response.refresh_token,
+        server: server.to_string(),
+        key_generation: response.key_generation,
+        key_storage: KeyStorageKind::DeviceId,
+        key_storage_salt: None,
+    }
+}
+
+async fn sync_since(
+    user: &mut FurUser,
+    last_sync: i64,
+) -> (Result<SyncSummary, ApiError>, FurSyncLogEntry) {
+    let progress = FurSyncProgress::new(last_sync, last_sync == 0, Utc::now().timestamp());
+    let mut log = FurSyncLogEntry::new(Local::now().timestamp(), progress.full_sync);
+    let summary =
+        sync::sync_in_pages(user, progress, &ENCRYPTION_KEY, "Test device", &mut log).await;
+    (summary, log)
+}
+
+fn add_task(name: &str) -> FurTask {
+    let start = Local::now() - chrono::TimeDelta::hours(1);
+    let task = FurTask::new(
+        name.to_string(),
+        start,
+        Local::now(),
+        String::new(),
+        "Tests".to_string(),
+        0.0,
+        String::new(),
+    );
+    database::tasks::insert_task(&task).unwrap();
+    task
+}
+
+#[tokio::test]
+async fn login_checks_the_key_and_refresh_issues_new_tokens() {
+    let _data = fresh_data().await;
+    let server = start_server().await;
+
+    let user = sign_in(&server).await;
+    let wrong_key = api::login(
+        EMAIL.to_string(),
+        "another-key".to_string(),
+        None,
+        server.clone(),
+    )
+    .await;
+    assert!(matches!(wrong_key, Err(ApiError::Auth(_))));
+
+    let access_token = api::refresh_auth_token(user.refresh_token.clone(), &server)
+        .await
+        .unwrap();
+    assert_ne!(access_token, user.access_token);
+
+    configure(&server, json!({ "refresh_fails": true })).await;
+    let refused = api::refresh_auth_token(user.refresh_token, &server).await;
+    assert!(matches!(refused, Err(ApiError::TokenRefresh(_))));
+}
+
+#[tokio::test]
+async fn expired_tokens_are_refreshed_and_server_errors_reported() {
+    let _data = fresh_data().await;
+    let server = start_server().await;
+    let mut user = sign_in(&server).await;
+    let first_token = user.access_token.clone();
+
+    configure(&server, json!({ "unauthorized_responses": 1 })).await;
+    let response = sync::sync_with_server(
+        &mut user,
+        "Test device",
+        0,
+        None,
+        Vec::new(),
+        Vec::new(),
+        Vec::new(),
+    )
+    .await
+    .unwrap();
+    assert!(response.tasks.is_empty());
+    assert_ne!(user.access_token, first_token);
+
+    configure(&server, json!({ "inactive_subscription": true })).await;
+    let (summary, _) = sync_since(&mut user, 0).await;
+    assert!(matches!(summary, Err(ApiError::InactiveSubscription(_))));
+
+    configure(
+        &server,
+        json!({ "unauthorized_responses": 1, "refresh_fails": true }),
+    )
+    .await;
+    let (summary, _) = sync_since(&mut user, 0).await;
+    assert!(matches!(summary, Err(ApiError::TokenRefresh(_))));
+}
+
+#[tokio::test]
+async fn tasks_reach_a_new_device_through_the_server() {
+    let _data = fresh_data().await;
+    let server = start_server().await;
+    let mut user = sign_in(&server).await;
+    let task = add_task("Write tests");
+
+    let (summary, log) = sync_since(&mut user, 0).await;
+    summary.unwrap();
+    assert_eq!(log.uploaded.tasks, 1);
+    assert_eq!(stored_task_uids(&server).await, vec![task.uid.clone()]);
+
+    // A second device signs in with the same key and downloads the task
+    wipe_data();
+    let mut other_device = sign_in(&server).await;
+    let (summary, log) = sync_since(&mut other_device, 0).await;
+    summary.unwrap();
+    assert_eq!(log.downloaded.tasks, 1);
+    let downloaded = database::tasks::retrieve_task_by_id(&task.uid)
+        .unwrap()
+        .unwrap();
+    assert_eq!(downloaded.name, "Write tests");
+}
+
+#[tokio::test]
+async fn orphaned_records_are_uploaded_again() {
+    let _data = fresh_data().await;
+    let server = start_server().await;
+    let mut user = sign_in(&server).await;
+    let task = add_task("Keep me");
+    let (summary, _) = sync_since(&mut user, 0).await;
+    let last_sync = summary.unwrap().server_timestamp;
+
+    reqwest::Client::new()
+        .post(format!("{}/mock/orphans", server))
+        .json(&json!({ "tasks": [task.uid] }))
+        .send()
+        .await
+        .unwrap();
+    assert!(stored_task_uids(&server).await.is_empty());
+
+    let (summary, log) = sync_since(&mut user, last_sync).await;
+    summary.unwrap();
+    assert_eq!(log.orphans_resent, 1);
+    assert_eq!(stored_task_uids(&server).await, vec![task.uid]);
+}
+
+#[tokio::test]
+async fn a_full_sync_downloads_records_from_before_the_last_sync() {
+    let _data = fresh_data().await;
+    let server = start_server().await;
+    let mut user = sign_in(&server).await;
+    let task = add_task("Made elsewhere");
+    let (summary, _) = sync_since(&mut user, 0).await;
+    summary.unwrap();
+
+    // This device believes it is up to date but has never seen the task
+    wipe_data();
+    let mut other_device = sign_in(&server).await;
+    let (encrypted_key, key_nonce) = encryption::encrypt_encryption_key(
+        &URL_SAFE_NO_PAD.encode(ENCRYPTION_KEY),
+        &other_device.key_storage,
+    )
+    .unwrap();
+    other_device.encrypted_key = encrypted_key;
+    other_device.key_nonce = key_nonce;
+    services::settings::update(|settings| {
+        settings.change_last_sync(&(Utc::now().timestamp() + 60))
+    })
+    .unwrap();
+
+    let log = services::sync::run_full(&mut other_device).await.unwrap();
+    assert!(log.full_sync);
+    assert_eq!(log.downloaded.tasks, 1);
+    assert!(database::tasks::retrieve_task_by_id(&task.uid)
+        .unwrap()
+        .is_some());
+}